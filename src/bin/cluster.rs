@@ -1,9 +1,12 @@
 fn main() {
     use clap::Parser;
+    #[cfg(feature = "logger")]
+    stripe_update::init_logger();
     let args = Cli::parse();
+    let threads = args.threads;
     match args.cmd {
-        Commands::Coordinator { cmd, config } => launch_coordinator(cmd, config),
-        Commands::Worker { config, id } => launch_worker(id.get(), config),
+        Commands::Coordinator { cmd, config } => launch_coordinator(cmd, config, threads),
+        Commands::Worker { config, id } => launch_worker(id.get(), config, threads),
     };
 }
 
@@ -20,6 +23,9 @@ use stripe_update::{
 struct Cli {
     #[command(subcommand)]
     cmd: Commands,
+    /// number of threads for the global thread pool, overriding the configuration file
+    #[arg(short, long, global = true)]
+    threads: Option<NonZeroUsize>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -30,14 +36,14 @@ enum Commands {
         /// subcommand for coordinator
         #[command(subcommand)]
         cmd: CoordinatorCmds,
-        /// configuration file in toml format
+        /// configuration file in toml, json or yaml format
         #[arg(short, long)]
         config: std::path::PathBuf,
     },
     /// launch a worker to do requests
     #[command(arg_required_else_help = true)]
     Worker {
-        /// configuration file in toml format
+        /// configuration file in toml, json or yaml format
         #[arg(short, long)]
         config: std::path::PathBuf,
         /// worker id
@@ -52,18 +58,29 @@ enum CoordinatorCmds {
     BuildData,
     /// Purge all the existing data in the cluster
     Purge,
+    /// Persist the updates buffered in the workers, and re-encode the updated stripes
+    Persist,
     /// Benchmark stripe update
     BenchUpdate,
     /// Kill all workers
-    KillAll,
+    KillAll {
+        /// only report the alive workers without shutting them down
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Show the buffered updates and the stored blocks of each worker
+    Status,
 }
 
-fn launch_coordinator(cmd: CoordinatorCmds, config: PathBuf) {
-    config::init_config_toml(&config);
+fn launch_coordinator(cmd: CoordinatorCmds, config: PathBuf, threads: Option<NonZeroUsize>) {
+    config::init_config_auto(&config);
     config::validate_config();
     config::validate_cluster_config(None);
+    config::init_thread_pool(threads);
     let builder = crate::cluster::coordinator::CoordinatorBuilder::default()
         .redis_url(config::redis_url().expect("redis url not set in config file"))
+        .redis_password(config::redis_password())
+        .heartbeat_interval(config::heartbeat_interval())
         .block_size(NonZeroUsize::new(config::block_size()).unwrap())
         .block_num(NonZeroUsize::new(config::block_num()).unwrap())
         .worker_num(
@@ -81,21 +98,27 @@ fn launch_coordinator(cmd: CoordinatorCmds, config: PathBuf) {
             .map(Box::new)
             .and_then(Cmds::exec),
         CoordinatorCmds::BenchUpdate => todo!(),
-        CoordinatorCmds::KillAll => KillAll::try_from(builder)
-            .map(Box::new)
+        CoordinatorCmds::KillAll { dry_run } => KillAll::try_from(builder)
+            .map(|kill_all| Box::new(kill_all.dry_run(dry_run)))
             .and_then(Cmds::exec),
         CoordinatorCmds::Purge => Purge::try_from(builder).map(Box::new).and_then(Cmds::exec),
+        CoordinatorCmds::Persist => Persist::try_from(builder)
+            .map(Box::new)
+            .and_then(Cmds::exec),
+        CoordinatorCmds::Status => Status::try_from(builder).map(Box::new).and_then(Cmds::exec),
     }
     .unwrap_or_else(|e| panic!("FATAL ERROR in coordinator: {e}"));
 }
 
-fn launch_worker(id: usize, config: PathBuf) {
-    config::init_config_toml(&config);
+fn launch_worker(id: usize, config: PathBuf, threads: Option<NonZeroUsize>) {
+    config::init_config_auto(&config);
     config::validate_config();
     config::validate_cluster_config(Some(id));
+    config::init_thread_pool(threads);
     cluster::worker::WorkerBuilder::default()
         .id(id)
         .client(config::redis_url().expect("redis url not set in config file"))
+        .redis_password(config::redis_password())
         .ssd_dev_path(config::worker_ssd_dev_path(id).expect("ssd dev path not set in config file"))
         .hdd_dev_path(config::worker_hdd_dev_path(id).expect("hdd dev path not set in config file"))
         .block_size(NonZeroUsize::new(config::block_size()).unwrap())
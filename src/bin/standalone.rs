@@ -1,16 +1,46 @@
 fn main() {
     use clap::Parser;
+    #[cfg(feature = "logger")]
+    stripe_update::init_logger();
     let args = Cli::parse();
+    let threads = args.threads;
     match args.cmd {
-        Commands::BuildData { config, purge } => build_data(&config, purge),
-        Commands::Benchmark { config, manner } => benchmark(&config, manner),
-        Commands::Clean { config, ssd, hdd } => cleanup(&config, ssd, hdd),
+        Commands::BuildData {
+            config,
+            purge,
+            resume,
+            stripe_checksum,
+        } => build_data(&config, threads, purge, resume, stripe_checksum),
+        Commands::Benchmark {
+            config,
+            manner,
+            trace,
+            auto_clean,
+        } => benchmark(&config, threads, manner, trace, auto_clean),
+        Commands::BenchAll { config } => bench_all(&config, threads),
+        Commands::Clean {
+            config,
+            ssd,
+            hdd,
+            out,
+            force,
+        } => cleanup(&config, ssd, hdd, out, force),
+        Commands::ReencodeParity { config } => reencode_parity(&config),
+        Commands::Verify { config } => verify(&config),
+        Commands::Rebuild { config, block_id } => rebuild(&config, block_id),
     };
 }
 
-fn build_data(config_path: &std::path::Path, purge: bool) {
-    stripe_update::config::init_config_toml(config_path);
+fn build_data(
+    config_path: &std::path::Path,
+    threads: Option<NonZeroUsize>,
+    purge: bool,
+    resume: bool,
+    stripe_checksum: bool,
+) {
+    stripe_update::config::init_config_auto(config_path);
     stripe_update::config::validate_standalone_config();
+    stripe_update::config::init_thread_pool(threads);
     use stripe_update::config;
     stripe_update::standalone::data_builder::DataBuilder::new()
         .block_num(config::block_num())
@@ -18,17 +48,37 @@ fn build_data(config_path: &std::path::Path, purge: bool) {
         .hdd_dev_path(config::hdd_dev_path())
         .ssd_dev_path(config::ssd_dev_path())
         .purge(purge)
+        .resume(resume)
+        .stripe_checksum(stripe_checksum)
         .ssd_block_capacity(config::ssd_block_capacity())
         .k_p(config::ec_k(), config::ec_p())
+        .threads(threads.map_or_else(config::threads, NonZeroUsize::get))
         .build()
         .unwrap_or_else(|e| panic!("fail to benchmark, {e}"));
 }
 
-fn benchmark(config_path: &std::path::Path, manner: Manner) {
+fn benchmark(
+    config_path: &std::path::Path,
+    threads: Option<NonZeroUsize>,
+    manner: Manner,
+    trace: Option<std::path::PathBuf>,
+    auto_clean: bool,
+) {
     use stripe_update::config;
-    stripe_update::config::init_config_toml(config_path);
+    stripe_update::config::init_config_auto(config_path);
     stripe_update::config::validate_standalone_config();
-    stripe_update::standalone::bench::Bench::new()
+    stripe_update::config::init_thread_pool(threads);
+    let mut bench = stripe_update::standalone::bench::Bench::new();
+    if let Some(trace) = trace {
+        bench.trace_path(trace);
+    }
+    if let Some(warmup) = config::warmup() {
+        bench.warmup(warmup);
+    }
+    if let Some(ssd_low_watermark) = config::ssd_low_watermark() {
+        bench.ssd_low_watermark(ssd_low_watermark);
+    }
+    bench
         .block_num(config::block_num())
         .block_size(config::block_size())
         .hdd_dev_path(config::hdd_dev_path())
@@ -38,14 +88,60 @@ fn benchmark(config_path: &std::path::Path, manner: Manner) {
         .ssd_block_capacity(config::ssd_block_capacity())
         .k_p(config::ec_k(), config::ec_p())
         .out_dir_path(config::out_dir_path())
+        .ssd_op_latency(config::ssd_op_latency())
+        .hdd_op_latency(config::hdd_op_latency())
+        .auto_clean(auto_clean)
         .manner(manner)
         .run()
+        .unwrap_or_else(|e| {
+            eprintln!("fail to benchmark, {e}");
+            std::process::exit(1);
+        });
+}
+
+fn bench_all(config_path: &std::path::Path, threads: Option<NonZeroUsize>) {
+    use stripe_update::config;
+    stripe_update::config::init_config_auto(config_path);
+    stripe_update::config::validate_standalone_config();
+    stripe_update::config::init_thread_pool(threads);
+    stripe_update::standalone::data_builder::DataBuilder::new()
+        .block_num(config::block_num())
+        .block_size(config::block_size())
+        .hdd_dev_path(config::hdd_dev_path())
+        .ssd_dev_path(config::ssd_dev_path())
+        .purge(true)
+        .ssd_block_capacity(config::ssd_block_capacity())
+        .k_p(config::ec_k(), config::ec_p())
+        .threads(threads.map_or_else(config::threads, NonZeroUsize::get))
+        .build()
+        .unwrap_or_else(|e| panic!("fail to build data, {e}"));
+    let mut bench = stripe_update::standalone::bench::Bench::new();
+    if let Some(warmup) = config::warmup() {
+        bench.warmup(warmup);
+    }
+    if let Some(ssd_low_watermark) = config::ssd_low_watermark() {
+        bench.ssd_low_watermark(ssd_low_watermark);
+    }
+    let rows = bench
+        .block_num(config::block_num())
+        .block_size(config::block_size())
+        .hdd_dev_path(config::hdd_dev_path())
+        .ssd_dev_path(config::ssd_dev_path())
+        .slice_size(config::slice_size())
+        .test_load(config::test_load())
+        .ssd_block_capacity(config::ssd_block_capacity())
+        .k_p(config::ec_k(), config::ec_p())
+        .out_dir_path(config::out_dir_path())
+        .ssd_op_latency(config::ssd_op_latency())
+        .hdd_op_latency(config::hdd_op_latency())
+        .compare(&[Manner::Baseline, Manner::MergeStripe, Manner::DegradedRead])
         .unwrap_or_else(|e| panic!("fail to benchmark, {e}"));
+    stripe_update::standalone::bench::print_comparison(&rows);
 }
 
-fn cleanup(config_path: &std::path::Path, ssd: bool, hdd: bool) {
+fn cleanup(config_path: &std::path::Path, ssd: bool, hdd: bool, out: bool, force: bool) {
     use stripe_update::config;
-    stripe_update::config::init_config_toml(config_path);
+    stripe_update::config::init_config_auto(config_path);
     stripe_update::config::validate_standalone_config();
     let mut cleaner = stripe_update::standalone::clean::Cleaner::new();
     if ssd {
@@ -54,11 +150,71 @@ fn cleanup(config_path: &std::path::Path, ssd: bool, hdd: bool) {
     if hdd {
         cleaner.hdd_dev_path(config::hdd_dev_path());
     }
-    cleaner
+    if out {
+        cleaner.out_dir_path(config::out_dir_path());
+    }
+    let report = cleaner
+        .force(force)
         .run()
-        .unwrap_or_else(|e| panic!("fail to benchmark, {e}"));
+        .unwrap_or_else(|e| panic!("fail to clean, {e}"));
+    println!(
+        "removed {} ssd files, {} hdd files and {} result files, {} freed",
+        report.ssd_files,
+        report.hdd_files,
+        report.out_files,
+        bytesize::ByteSize(report.bytes_freed)
+    );
 }
 
+fn reencode_parity(config_path: &std::path::Path) {
+    use stripe_update::config;
+    stripe_update::config::init_config_auto(config_path);
+    stripe_update::config::validate_standalone_config();
+    stripe_update::standalone::reencode::Reencoder::new()
+        .block_num(config::block_num())
+        .block_size(config::block_size())
+        .hdd_dev_path(config::hdd_dev_path())
+        .k_p(config::ec_k(), config::ec_p())
+        .run()
+        .unwrap_or_else(|e| panic!("fail to re-encode parity, {e}"));
+}
+
+fn verify(config_path: &std::path::Path) {
+    use stripe_update::config;
+    stripe_update::config::init_config_auto(config_path);
+    stripe_update::config::validate_standalone_config();
+    let inconsistent = stripe_update::standalone::verify::Verifier::new()
+        .block_size(config::block_size())
+        .hdd_dev_path(config::hdd_dev_path())
+        .k_p(config::ec_k(), config::ec_p())
+        .run()
+        .unwrap_or_else(|e| panic!("fail to verify, {e}"));
+    if inconsistent.is_empty() {
+        println!("all stripes are consistent");
+        return;
+    }
+    println!("{} inconsistent stripes:", inconsistent.len());
+    inconsistent
+        .iter()
+        .for_each(|stripe_id| println!("\tstripe {stripe_id}"));
+    std::process::exit(1);
+}
+
+fn rebuild(config_path: &std::path::Path, block_id: usize) {
+    use stripe_update::config;
+    stripe_update::config::init_config_auto(config_path);
+    stripe_update::config::validate_standalone_config();
+    stripe_update::standalone::rebuild::Rebuilder::new()
+        .block_size(config::block_size())
+        .hdd_dev_path(config::hdd_dev_path())
+        .k_p(config::ec_k(), config::ec_p())
+        .block_id(block_id)
+        .run()
+        .unwrap_or_else(|e| panic!("fail to rebuild block {block_id}, {e}"));
+}
+
+use std::num::NonZeroUsize;
+
 use clap::Subcommand;
 use stripe_update::standalone::bench::Manner;
 
@@ -67,6 +223,9 @@ use stripe_update::standalone::bench::Manner;
 struct Cli {
     #[command(subcommand)]
     cmd: Commands,
+    /// number of threads for the global thread pool, overriding the configuration file
+    #[arg(short, long, global = true)]
+    threads: Option<NonZeroUsize>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -74,32 +233,82 @@ enum Commands {
     /// Build data set
     #[command(arg_required_else_help = true)]
     BuildData {
-        /// configuration file in toml format
+        /// configuration file in toml, json or yaml format
         #[arg(short, long)]
         config: std::path::PathBuf,
         /// purge the existing dev directory
         #[arg(short, long)]
         purge: bool,
+        /// skip the stripes already built by an interrupted build
+        #[arg(short, long)]
+        resume: bool,
+        /// store the checksum of each stripe to detect the mis-decoded stripes
+        #[arg(long)]
+        stripe_checksum: bool,
     },
     /// Benchmark
     #[command(arg_required_else_help = true)]
     Benchmark {
-        /// configuration file in toml format
+        /// configuration file in toml, json or yaml format
         #[arg(short, long)]
         config: std::path::PathBuf,
         /// bench mark manners
         #[arg(short, long, default_value_t = Manner::Baseline)]
         manner: Manner,
+        /// trace file to replay, required by the trace-replay manner
+        #[arg(short, long)]
+        trace: Option<std::path::PathBuf>,
+        /// purge the ssd device first if it is not empty, instead of failing
+        #[arg(long)]
+        auto_clean: bool,
+    },
+    /// Build the data once, then run the baseline, merge-stripe and degraded-read benchmarks
+    /// on it, and compare their results
+    #[command(arg_required_else_help = true)]
+    BenchAll {
+        /// configuration file in toml, json or yaml format
+        #[arg(short, long)]
+        config: std::path::PathBuf,
     },
     /// Clean up the dev directory
     #[command(arg_required_else_help = true)]
     Clean {
-        /// configuration file in toml format
+        /// configuration file in toml, json or yaml format
         #[arg(short, long)]
         config: std::path::PathBuf,
         #[arg(short, long, default_value_t = false)]
         ssd: bool,
         #[arg(short, long, default_value_t = false)]
         hdd: bool,
+        /// remove the benchmark results in the output directory, keeping the directory itself
+        #[arg(short, long, default_value_t = false)]
+        out: bool,
+        /// purge the dev directories even if they contain files other than the blocks
+        #[arg(long, default_value_t = false)]
+        force: bool,
+    },
+    /// Verify the consistency of every stripe, exit with non-zero status if any is inconsistent
+    #[command(arg_required_else_help = true)]
+    Verify {
+        /// configuration file in toml, json or yaml format
+        #[arg(short, long)]
+        config: std::path::PathBuf,
+    },
+    /// Rebuild a block from the surviving blocks of its stripe
+    #[command(arg_required_else_help = true)]
+    Rebuild {
+        /// configuration file in toml, json or yaml format
+        #[arg(short, long)]
+        config: std::path::PathBuf,
+        /// id of the block to rebuild
+        #[arg(short, long)]
+        block_id: usize,
+    },
+    /// Regenerate the parity blocks from the existing source blocks
+    #[command(arg_required_else_help = true)]
+    ReencodeParity {
+        /// configuration file in toml, json or yaml format
+        #[arg(short, long)]
+        config: std::path::PathBuf,
     },
 }
@@ -92,7 +92,7 @@ impl super::CoordinatorCmds for BenchUpdate {
         // send requests
         let request_sender = move || -> SUResult<()> {
             while let Ok((id, request)) = request_consumer.recv() {
-                let key = &request_queue_list[id.0 - 1];
+                let key = &request_queue_list[usize::from(id.0) - 1];
                 request.try_push_to_redis(&mut send_conn, key)?;
                 ack_notifier
                     .send(())
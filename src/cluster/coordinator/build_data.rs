@@ -7,19 +7,23 @@ use crate::{
     cluster::{
         messages::{
             coordinator_request::Request,
-            worker_response::{Ack, Response},
+            worker_response::{Ack, Nak, Response},
         },
-        progress_style_template, MessageQueueKey, WorkerID,
+        progress_style_template,
+        transport::MessageTransport,
+        MessageQueueKey, WorkerID,
     },
     erasure_code::{ErasureCode, ReedSolomon, Stripe},
     SUError, SUResult,
 };
 
 pub struct BuildData {
-    recv_conn: redis::Connection,
-    send_conn: redis::Connection,
+    recv_conn: Box<dyn MessageTransport + Send>,
+    send_conn: Box<dyn MessageTransport + Send>,
     request_queue_list: Vec<MessageQueueKey>,
     response_queue: MessageQueueKey,
+    heartbeat_interval: std::time::Duration,
+    channel_capacity: usize,
     block_size: usize,
     block_num: usize,
     k_p: (usize, usize),
@@ -29,9 +33,6 @@ impl TryFrom<super::CoordinatorBuilder> for BuildData {
     type Error = SUError;
 
     fn try_from(value: super::CoordinatorBuilder) -> Result<Self, Self::Error> {
-        let redis_url = value
-            .redis_url
-            .ok_or_else(|| SUError::Other("redis url not set".into()))?;
         let worker_num = value
             .worker_num
             .ok_or_else(|| SUError::Other("worker number not set".into()))?;
@@ -44,7 +45,6 @@ impl TryFrom<super::CoordinatorBuilder> for BuildData {
         let k_p = value
             .k_p
             .ok_or_else(|| SUError::Other("k and p not set".into()))?;
-        let client = redis::Client::open(redis_url)?;
         let request_queue_list = (1..=worker_num)
             .map(|i| i.try_into().unwrap())
             .map(WorkerID)
@@ -52,10 +52,12 @@ impl TryFrom<super::CoordinatorBuilder> for BuildData {
             .collect();
         let response_queue = crate::cluster::format_response_queue_key();
         Ok(Self {
-            recv_conn: client.get_connection()?,
-            send_conn: client.get_connection()?,
+            recv_conn: value.connect()?,
+            send_conn: value.connect()?,
             request_queue_list,
             response_queue,
+            heartbeat_interval: value.heartbeat_interval_or_default(),
+            channel_capacity: value.channel_capacity_or_default(),
             block_size,
             block_num,
             k_p,
@@ -65,11 +67,11 @@ impl TryFrom<super::CoordinatorBuilder> for BuildData {
 
 impl super::CoordinatorCmds for BuildData {
     fn exec(self: Box<Self>) -> SUResult<()> {
-        const CH_SIZE: usize = 32;
         let request_queue_list = self.request_queue_list;
         let response_queue = self.response_queue.clone();
         let worker_id_range = 1_u8..u8::try_from(request_queue_list.len()).unwrap() + 1;
         let block_size = self.block_size;
+        let heartbeat_interval = self.heartbeat_interval;
         let mut recv_conn = self.recv_conn;
         let mut send_conn = self.send_conn;
         let mut block_num = self.block_num;
@@ -93,13 +95,15 @@ impl super::CoordinatorCmds for BuildData {
         );
 
         // make sure redis is clean
-        let _: () = redis::cmd("FLUSHALL")
-            .query(&mut send_conn)
-            .expect("fail to flush redis");
+        send_conn.flush_all().expect("fail to flush redis");
 
         // make sure workers are alive
-        let alive_workers =
-            super::broadcast_heartbeat(&request_queue_list, &response_queue, &mut recv_conn)?;
+        let alive_workers = super::broadcast_heartbeat(
+            &request_queue_list,
+            &response_queue,
+            recv_conn.as_mut(),
+            heartbeat_interval,
+        )?;
         if alive_workers != worker_id_range.clone().map(WorkerID).collect::<Vec<_>>() {
             let offline_workers = worker_id_range
                 .clone()
@@ -118,7 +122,7 @@ impl super::CoordinatorCmds for BuildData {
 
         type StripeItem = Vec<Request>;
         let (stripe_producer, stripe_consumer) =
-            std::sync::mpsc::sync_channel::<StripeItem>(CH_SIZE);
+            std::sync::mpsc::sync_channel::<StripeItem>(self.channel_capacity);
 
         let stripe_maker_handle = std::thread::spawn(move || {
             use rand::Rng;
@@ -148,7 +152,7 @@ impl super::CoordinatorCmds for BuildData {
         let dispatcher_handle = std::thread::spawn(move || {
             while let Ok(item) = stripe_consumer.recv() {
                 std::iter::zip(item, request_queue_list.iter().cycle())
-                    .try_for_each(|(request, key)| request.push_to_redis(&mut send_conn, key))
+                    .try_for_each(|(request, key)| request.push_to_redis(send_conn.as_mut(), key))
                     .expect("fail to dispatch stripe");
             }
         });
@@ -157,13 +161,10 @@ impl super::CoordinatorCmds for BuildData {
             (0..block_num)
                 .progress_with_style(progress_style_template(Some("block stored")))
                 .try_for_each(|_| {
-                    let response = Response::fetch_from_redis(&mut recv_conn, &response_queue)?;
+                    let response = Response::fetch_from_redis(recv_conn.as_mut(), &response_queue)?;
                     match &response.head {
                         Ok(Ack::StoreBlock) => Ok(()),
-                        Err(_) => Err(SUError::other(format!(
-                            "nak: {}",
-                            String::from_utf8(response.payload.unwrap().into()).unwrap()
-                        ))),
+                        Err(Nak(e)) => Err(SUError::other(format!("nak: {e}"))),
                         _ => unreachable!("unexpected response"),
                     }
                 })
@@ -1,90 +1,153 @@
-use std::collections::BTreeMap;
-
-use indicatif::ProgressIterator;
+use std::io::Write;
 
 use crate::{
     cluster::{
-        messages::{coordinator_request::Request, worker_response::Response},
-        progress_style_template, MessageQueueKey, WorkerID,
+        messages::{coordinator_request::Request, worker_response::Ack},
+        transport::MessageTransport,
+        MessageQueueKey, WorkerID,
     },
     SUError, SUResult,
 };
 
 pub struct KillAll {
-    conn: redis::Connection,
-    request_queue_list: Vec<MessageQueueKey>,
+    conn: Box<dyn MessageTransport + Send>,
+    workers: Vec<WorkerID>,
     response_queue: MessageQueueKey,
+    heartbeat_interval: std::time::Duration,
+    dry_run: bool,
+}
+
+/// Summary of the workers shut down by [`KillAll`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KillAllSummary {
+    /// ids of the workers responding to the heartbeat, which are asked to shut down
+    /// unless in dry run
+    pub alive: Vec<usize>,
+    /// ids of the workers acknowledging the shutdown within the heartbeat interval
+    pub acked: Vec<usize>,
+    /// ids of the workers not acknowledging the shutdown, including the ones not alive
+    pub not_acked: Vec<usize>,
 }
 
 impl TryFrom<super::CoordinatorBuilder> for KillAll {
     type Error = SUError;
 
     fn try_from(value: super::CoordinatorBuilder) -> Result<Self, Self::Error> {
-        let redis_url = value
-            .redis_url
-            .ok_or_else(|| SUError::Other("redis url not set".into()))?;
         let worker_num = value
             .worker_num
             .ok_or_else(|| SUError::Other("worker number not set".into()))?;
         Ok(KillAll {
-            conn: redis::Client::open(redis_url)?.get_connection()?,
-            request_queue_list: (1..=worker_num)
+            conn: value.connect()?,
+            workers: (1..=worker_num)
                 .map(|i| i.try_into().unwrap())
                 .map(WorkerID)
-                .map(crate::cluster::format_request_queue_key)
                 .collect(),
             response_queue: crate::cluster::format_response_queue_key(),
+            heartbeat_interval: value.heartbeat_interval_or_default(),
+            dry_run: false,
         })
     }
 }
 
-impl super::CoordinatorCmds for KillAll {
-    fn exec(mut self: Box<Self>) -> SUResult<()> {
-        redis::cmd("FLUSHALL").query(&mut self.conn)?;
-        println!("broadcasting heartbeat...");
-        std::io::stdout().flush().unwrap();
+impl KillAll {
+    /// Only find out the alive workers, without shutting them down or flushing redis.
+    /// Disabled by default.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Shut down all the alive workers, and wait for their acknowledgements
+    /// within the heartbeat interval.
+    ///
+    /// # Return
+    /// - [`Ok`] with the workers acknowledged and not
+    /// - [`Err`] on any error occurring on the transport
+    pub fn run(&mut self) -> SUResult<KillAllSummary> {
+        if !self.dry_run {
+            self.conn.flush_all()?;
+        }
+        let request_queue_list = self
+            .workers
+            .iter()
+            .cloned()
+            .map(crate::cluster::format_request_queue_key)
+            .collect::<Vec<_>>();
         let alive_workers = super::broadcast_heartbeat(
-            &self.request_queue_list,
+            &request_queue_list,
             &self.response_queue,
-            &mut self.conn,
+            self.conn.as_mut(),
+            self.heartbeat_interval,
         )?;
-        if alive_workers.is_empty() {
-            println!("no worker is alive");
-            return Ok(());
+        let acked = if self.dry_run || alive_workers.is_empty() {
+            Vec::new()
+        } else {
+            let alive_queue_list = alive_workers
+                .iter()
+                .cloned()
+                .map(crate::cluster::format_request_queue_key)
+                .collect::<Vec<_>>();
+            super::broadcast(
+                &alive_queue_list,
+                &self.response_queue,
+                self.conn.as_mut(),
+                self.heartbeat_interval,
+                Request::shutdown,
+                |ack| match ack {
+                    Ack::Shutdown { worker_id } => Some(*worker_id),
+                    _ => None,
+                },
+            )?
+        };
+        if !self.dry_run {
+            self.conn.flush_all()?;
         }
-        print!("alive workers:");
-        alive_workers.iter().for_each(|&id| print!(" {id}"));
-        use std::io::Write;
-        std::io::stdout().flush().unwrap();
-        let mut task_map = alive_workers
+        let not_acked = self
+            .workers
             .iter()
+            .filter(|id| !acked.contains(id))
             .cloned()
-            .map(crate::cluster::format_request_queue_key)
-            .map(|key| {
-                let request = Request::shutdown();
-                let id = request.id;
-                request
-                    .push_to_redis(&mut self.conn, key.as_str())
-                    .map(|_| (id, None))
-            })
-            .collect::<SUResult<BTreeMap<_, _>>>()?;
-        println!("\nwaiting for workers to shutdown...");
-        (0..alive_workers.len())
-            .progress_with_style(progress_style_template(Some("shutting down workers")))
-            .try_for_each(|_| {
-                let res = Response::fetch_from_redis(&mut self.conn, &self.response_queue)?;
-                task_map
-                    .get_mut(&res.id)
-                    .expect("unexpected response")
-                    .replace(Some(res));
-                Ok::<(), SUError>(())
-            })
-            .unwrap_or_else(|e| eprintln!("shutdown fails: {e}"));
-        println!("done!");
-        print!("flushing redis...");
+            .collect::<Vec<_>>();
+        let to_ids = |workers: Vec<WorkerID>| {
+            workers
+                .into_iter()
+                .map(|id| usize::from(id.0))
+                .collect::<Vec<_>>()
+        };
+        Ok(KillAllSummary {
+            alive: to_ids(alive_workers),
+            acked: to_ids(acked),
+            not_acked: to_ids(not_acked),
+        })
+    }
+}
+
+impl super::CoordinatorCmds for KillAll {
+    fn exec(mut self: Box<Self>) -> SUResult<()> {
+        if self.dry_run {
+            println!("dry run, no worker will be shut down");
+        }
+        print!("shutting down workers...");
         std::io::stdout().flush().unwrap();
-        redis::cmd("FLUSHALL").query(&mut self.conn)?;
+        let summary = self.run()?;
         println!("done!");
+        let display = |ids: &[usize]| {
+            ids.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        if summary.alive.is_empty() {
+            println!("no worker is alive");
+        } else {
+            println!("alive workers: {}", display(&summary.alive));
+        }
+        if !self.dry_run {
+            println!("acked workers: {}", display(&summary.acked));
+            if !summary.not_acked.is_empty() {
+                println!("workers not acked: {}", display(&summary.not_acked));
+            }
+        }
         Ok(())
     }
 }
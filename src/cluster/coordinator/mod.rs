@@ -1,15 +1,19 @@
-use std::{collections::BTreeMap, num::NonZeroUsize};
+use std::{collections::BTreeMap, num::NonZeroUsize, time::Duration};
 
 use crate::{config, SUError, SUResult};
 
 // mod bench_update;
 mod build_data;
 mod kill_all;
+mod persist;
 mod purge;
+mod status;
 pub mod cmds {
     pub use super::build_data::BuildData;
-    pub use super::kill_all::KillAll;
+    pub use super::kill_all::{KillAll, KillAllSummary};
+    pub use super::persist::Persist;
     pub use super::purge::Purge;
+    pub use super::status::{Status, WorkerStat};
 }
 
 use super::{
@@ -18,25 +22,40 @@ use super::{
         worker_response::{Ack, Response},
         TaskID,
     },
+    transport::{InProcessTransport, MessageTransport},
     WorkerID,
 };
 
 #[derive(Debug, Clone, Default)]
 pub struct CoordinatorBuilder {
     redis_url: Option<String>,
+    redis_password: Option<String>,
     block_size: Option<usize>,
     slice_size: Option<usize>,
     block_num: Option<usize>,
     worker_num: Option<usize>,
     k_p: Option<(usize, usize)>,
+    in_process: Option<InProcessTransport>,
+    heartbeat_interval: Option<Duration>,
+    channel_capacity: Option<usize>,
 }
 
+/// Capacity of the channels between the pipeline stages of a command if not set.
+const DEFAULT_CHANNEL_CAPACITY: usize = 32;
+
 impl CoordinatorBuilder {
+    /// Set the url of redis, a `rediss://` url connects over TLS.
     pub fn redis_url(mut self, url: impl Into<String>) -> Self {
         self.redis_url = Some(url.into());
         self
     }
 
+    /// Set the password of redis, which overrides the password in the url if [`Some`].
+    pub fn redis_password(mut self, password: Option<String>) -> Self {
+        self.redis_password = password;
+        self
+    }
+
     pub fn block_size(mut self, size: NonZeroUsize) -> Self {
         self.block_size = Some(size.get());
         self
@@ -61,6 +80,50 @@ impl CoordinatorBuilder {
         self.k_p = Some((k.get(), p.get()));
         self
     }
+
+    /// Talk to the workers over an in-process transport instead of redis.
+    /// The redis url is not required if this is set.
+    pub fn in_process_transport(mut self, transport: InProcessTransport) -> Self {
+        self.in_process = Some(transport);
+        self
+    }
+
+    /// Set the time to wait for the workers to respond to a heartbeat,
+    /// [`config::DEFAULT_HEARTBEAT_INTERVAL`] if not set.
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = Some(interval);
+        self
+    }
+
+    fn heartbeat_interval_or_default(&self) -> Duration {
+        self.heartbeat_interval
+            .unwrap_or(config::DEFAULT_HEARTBEAT_INTERVAL)
+    }
+
+    /// Set the capacity of the channels between the pipeline stages of a command,
+    /// [`DEFAULT_CHANNEL_CAPACITY`] if not set.
+    ///
+    /// A small capacity serializes the pipeline, while a large one hides the memory growth.
+    pub fn channel_capacity(mut self, capacity: NonZeroUsize) -> Self {
+        self.channel_capacity = Some(capacity.get());
+        self
+    }
+
+    fn channel_capacity_or_default(&self) -> usize {
+        self.channel_capacity.unwrap_or(DEFAULT_CHANNEL_CAPACITY)
+    }
+
+    /// Make a connection to the message transport.
+    fn connect(&self) -> SUResult<Box<dyn MessageTransport + Send>> {
+        let client = match (&self.in_process, &self.redis_url) {
+            (None, None) => return Err(SUError::Other("redis url not set".into())),
+            (None, Some(url)) => Some(redis::Client::open(
+                super::transport::redis_connection_info(url.as_str(), self.redis_password.clone())?,
+            )?),
+            (Some(_), _) => None,
+        };
+        super::transport::connect(self.in_process.as_ref(), client.as_ref())
+    }
 }
 
 pub trait CoordinatorCmds {
@@ -68,44 +131,464 @@ pub trait CoordinatorCmds {
     fn exec(self: Box<Self>) -> SUResult<()>;
 }
 
-/// Broadcasts a heartbeat message to all workers and waits for their responses.
+/// Broadcasts a heartbeat message to all workers and waits `interval` for their responses.
 ///
 /// # Returns
-/// The alive workers' IDs.
+/// The alive workers' IDs, in ascending order.
 fn broadcast_heartbeat(
     request_queue_list: &[impl AsRef<str>],
     response_queue: &impl AsRef<str>,
-    conn: &mut redis::Connection,
+    conn: &mut dyn MessageTransport,
+    interval: Duration,
 ) -> SUResult<Vec<WorkerID>> {
+    broadcast(
+        request_queue_list,
+        response_queue,
+        conn,
+        interval,
+        Request::heartbeat,
+        |ack| match ack {
+            Ack::HeartBeat { worker_id } => Some(*worker_id),
+            _ => None,
+        },
+    )
+}
+
+/// Broadcasts a request made by `make_request` to all workers,
+/// and collects their responses until all of them respond or `interval` elapses.
+/// The responses to other requests, e.g. the late ones of a previous broadcast, are dropped.
+///
+/// # Returns
+/// The values taken by `ack_of` from the acknowledgements, e.g. the IDs of the workers,
+/// in ascending order.
+fn broadcast<T: Ord>(
+    request_queue_list: &[impl AsRef<str>],
+    response_queue: &impl AsRef<str>,
+    conn: &mut dyn MessageTransport,
+    interval: Duration,
+    make_request: impl Fn() -> Request,
+    ack_of: impl Fn(&Ack) -> Option<T>,
+) -> SUResult<Vec<T>> {
     let mut response_map = request_queue_list
         .iter()
         .map(|key| -> Result<TaskID, SUError> {
-            let request = Request::heartbeat();
+            let request = make_request();
             let id = request.id;
             request.push_to_redis(conn, key.as_ref()).map(|_| id)
         })
         .map(|t_id| t_id.map(|id| (id, None)))
         .collect::<SUResult<BTreeMap<_, _>>>()?;
-    std::thread::sleep(config::heartbeat_interval());
-    let worker_num = request_queue_list.len();
-    for _ in 0..worker_num {
-        let response = Response::fetch_from_redis_timeout(conn, response_queue.as_ref(), None)?;
-        if response.is_none() {
+    let deadline = std::time::Instant::now() + interval;
+    let mut pending = response_map.len();
+    while pending > 0 {
+        let timeout = deadline.saturating_duration_since(std::time::Instant::now());
+        let Some(response) =
+            Response::fetch_from_redis_timeout(conn, response_queue.as_ref(), Some(timeout))?
+        else {
             // timeout
             break;
+        };
+        if let Some(val @ None) = response_map.get_mut(&response.id) {
+            *val = Some(response);
+            pending -= 1;
         }
-        let response = response.unwrap();
-        let id = response.id;
-        let val = response_map.get_mut(&id).expect("bad response id");
-        *val = Some(response);
-    }
-    let res = response_map
-        .into_iter()
-        .filter_map(|(_, v)| v)
-        .filter_map(|response| match &response.head {
-            Ok(Ack::HeartBeat { worker_id }) => Some(*worker_id),
-            _ => None,
-        })
-        .collect();
+    }
+    let mut res = response_map
+        .into_values()
+        .flatten()
+        .filter_map(|response| response.head.as_ref().ok().and_then(&ack_of))
+        .collect::<Vec<_>>();
+    // task ids assigned within the same millisecond are not ordered
+    res.sort_unstable();
     Ok(res)
 }
+
+#[cfg(test)]
+mod test {
+    use std::{num::NonZeroUsize, path::Path, thread::JoinHandle};
+
+    use bytes::{Bytes, BytesMut};
+    use range_collections::RangeSet2;
+
+    use crate::{
+        cluster::{
+            messages::{
+                coordinator_request::Request,
+                worker_response::{Ack, Response},
+            },
+            transport::InProcessTransport,
+            worker::{EvictKind, WorkerBuilder},
+            Ranges, WorkerID,
+        },
+        erasure_code::{ErasureCode, ReedSolomon, Stripe},
+        storage::{BlockStorage, HDDStorage},
+        SUResult,
+    };
+
+    use super::{cmds, CoordinatorBuilder, CoordinatorCmds};
+
+    /// Spawn the workers of `ids` on `transport`, with their devices at `dir/hdd-{id}` and `dir/ssd-{id}`.
+    fn spawn_workers(
+        transport: &InProcessTransport,
+        dir: &Path,
+        ids: impl IntoIterator<Item = usize>,
+        block_size: NonZeroUsize,
+    ) -> Vec<JoinHandle<SUResult<()>>> {
+        spawn_workers_with(transport, dir, ids, block_size, |_| ())
+    }
+
+    /// [`spawn_workers`] with each worker builder further set by `configure`.
+    fn spawn_workers_with(
+        transport: &InProcessTransport,
+        dir: &Path,
+        ids: impl IntoIterator<Item = usize>,
+        block_size: NonZeroUsize,
+        configure: impl Fn(&mut WorkerBuilder),
+    ) -> Vec<JoinHandle<SUResult<()>>> {
+        ids.into_iter()
+            .map(|id| {
+                let hdd_path = dir.join(format!("hdd-{id}"));
+                let ssd_path = dir.join(format!("ssd-{id}"));
+                std::fs::create_dir_all(&hdd_path).unwrap();
+                std::fs::create_dir_all(&ssd_path).unwrap();
+                let mut builder = WorkerBuilder::default();
+                builder
+                    .id(id)
+                    .in_process_transport(transport.clone())
+                    .hdd_dev_path(hdd_path)
+                    .ssd_dev_path(ssd_path)
+                    .block_size(block_size);
+                configure(&mut builder);
+                std::thread::spawn(move || builder.work())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn in_process_cluster() {
+        const WORKER_NUM: usize = 2;
+        const BLOCK_SIZE: NonZeroUsize = unsafe { NonZeroUsize::new_unchecked(4 << 10) };
+        const BLOCK_NUM: usize = 9;
+        const K: NonZeroUsize = unsafe { NonZeroUsize::new_unchecked(2) };
+        const P: NonZeroUsize = unsafe { NonZeroUsize::new_unchecked(1) };
+        let transport = InProcessTransport::new();
+        let tempdir = tempfile::tempdir().unwrap();
+        let hdd_path = |id: usize| tempdir.path().join(format!("hdd-{id}"));
+        let worker_handles = spawn_workers(&transport, tempdir.path(), 1..=WORKER_NUM, BLOCK_SIZE);
+
+        let builder = CoordinatorBuilder::default()
+            .in_process_transport(transport)
+            .worker_num(NonZeroUsize::new(WORKER_NUM).unwrap())
+            .block_size(BLOCK_SIZE)
+            .block_num(NonZeroUsize::new(BLOCK_NUM).unwrap())
+            .k_p(K, P)
+            // the pipeline works even if fully serialized
+            .channel_capacity(NonZeroUsize::new(1).unwrap());
+        Box::new(cmds::BuildData::try_from(builder.clone()).unwrap())
+            .exec()
+            .unwrap();
+
+        // blocks of a stripe are dispatched to the workers in turn
+        let n = K.get() + P.get();
+        let hdd_stores = (1..=WORKER_NUM)
+            .map(|id| HDDStorage::connect_to_dev(hdd_path(id), BLOCK_SIZE).unwrap())
+            .collect::<Vec<_>>();
+        let rs = ReedSolomon::from_k_p(K, P);
+        (0..BLOCK_NUM / n).for_each(|stripe_id| {
+            let blocks = (stripe_id * n..stripe_id * n + n)
+                .map(|block_id| {
+                    let mut data = BytesMut::zeroed(BLOCK_SIZE.get());
+                    hdd_stores[(block_id % n) % WORKER_NUM]
+                        .get_block(block_id, &mut data)
+                        .unwrap()
+                        .expect("block not stored");
+                    data.into()
+                })
+                .collect();
+            assert!(rs.verify(&Stripe::from_vec(blocks, K, P)).unwrap());
+        });
+
+        Box::new(cmds::KillAll::try_from(builder).unwrap())
+            .exec()
+            .unwrap();
+        worker_handles
+            .into_iter()
+            .for_each(|handle| handle.join().unwrap().unwrap());
+    }
+
+    /// Size of the segments updated by the persisting tests
+    const SEG_SIZE: usize = 4 << 10;
+
+    /// Buffer the `updates` of (block id, ranges, byte) in the workers set by `configure`,
+    /// persist them by the coordinator, and check that the updates are applied
+    /// with the parity of every stripe consistent.
+    /// If `restart`, the workers are shut down and spawned again before persisting.
+    ///
+    /// # Return
+    /// The number of the stripes updated by persisting
+    fn persist_updates(
+        configure: impl Fn(&mut WorkerBuilder),
+        updates: &[(usize, RangeSet2<usize>, u8)],
+        restart: bool,
+    ) -> usize {
+        const WORKER_NUM: usize = 2;
+        const BLOCK_SIZE: NonZeroUsize = unsafe { NonZeroUsize::new_unchecked(SEG_SIZE * 4) };
+        const BLOCK_NUM: usize = 9;
+        const K: NonZeroUsize = unsafe { NonZeroUsize::new_unchecked(2) };
+        const P: NonZeroUsize = unsafe { NonZeroUsize::new_unchecked(1) };
+        let n = K.get() + P.get();
+        let transport = InProcessTransport::new();
+        let tempdir = tempfile::tempdir().unwrap();
+        let hdd_path = |id: usize| tempdir.path().join(format!("hdd-{id}"));
+        let spawn = || {
+            spawn_workers_with(
+                &transport,
+                tempdir.path(),
+                1..=WORKER_NUM,
+                BLOCK_SIZE,
+                // the buffered updates are not persisted by the shutdown unless configured
+                |builder| {
+                    builder.flush_on_shutdown(false);
+                    configure(builder);
+                },
+            )
+        };
+        let mut worker_handles = spawn();
+        let builder = CoordinatorBuilder::default()
+            .in_process_transport(transport.clone())
+            .worker_num(NonZeroUsize::new(WORKER_NUM).unwrap())
+            .block_size(BLOCK_SIZE)
+            .block_num(NonZeroUsize::new(BLOCK_NUM).unwrap())
+            .k_p(K, P);
+        Box::new(cmds::BuildData::try_from(builder.clone()).unwrap())
+            .exec()
+            .unwrap();
+
+        let mut conn = crate::cluster::transport::connect(Some(&transport), None).unwrap();
+        let queue_of = |block_id: usize| {
+            crate::cluster::format_request_queue_key(WorkerID(
+                ((block_id % n) % WORKER_NUM + 1).try_into().unwrap(),
+            ))
+        };
+        // one by one, so that the evictions are in the order of the updates
+        updates.iter().for_each(|(block_id, ranges, byte)| {
+            let ranges = Ranges(ranges.clone());
+            let payload = Bytes::from(vec![*byte; ranges.len()]);
+            Request::buffer_update_data(*block_id, ranges, payload)
+                .push_to_redis(conn.as_mut(), &queue_of(*block_id))
+                .unwrap();
+            let response = Response::fetch_from_redis(
+                conn.as_mut(),
+                &crate::cluster::format_response_queue_key(),
+            )
+            .unwrap();
+            assert!(matches!(response.head, Ok(Ack::BufferUpdateData)));
+        });
+        if restart {
+            Box::new(cmds::KillAll::try_from(builder.clone()).unwrap())
+                .exec()
+                .unwrap();
+            worker_handles
+                .into_iter()
+                .for_each(|handle| handle.join().unwrap().unwrap());
+            worker_handles = spawn();
+        }
+
+        let request_queue_list = (1..=WORKER_NUM)
+            .map(|id| crate::cluster::format_request_queue_key(WorkerID(id.try_into().unwrap())))
+            .collect::<Vec<_>>();
+        let updated = super::persist::persist_buffered(
+            conn.as_mut(),
+            &request_queue_list,
+            &crate::cluster::format_response_queue_key(),
+            BLOCK_SIZE.get(),
+            BLOCK_NUM,
+            (K.get(), P.get()),
+        )
+        .unwrap();
+        // nothing is left buffered
+        Box::new(cmds::Persist::try_from(builder.clone()).unwrap())
+            .exec()
+            .unwrap();
+        Box::new(cmds::KillAll::try_from(builder).unwrap())
+            .exec()
+            .unwrap();
+        worker_handles
+            .into_iter()
+            .for_each(|handle| handle.join().unwrap().unwrap());
+
+        let hdd_stores = (1..=WORKER_NUM)
+            .map(|id| HDDStorage::connect_to_dev(hdd_path(id), BLOCK_SIZE).unwrap())
+            .collect::<Vec<_>>();
+        let get_block = |block_id: usize| {
+            let mut data = BytesMut::zeroed(BLOCK_SIZE.get());
+            hdd_stores[(block_id % n) % WORKER_NUM]
+                .get_block(block_id, &mut data)
+                .unwrap()
+                .expect("block not stored");
+            data
+        };
+        let rs = ReedSolomon::from_k_p(K, P);
+        (0..BLOCK_NUM / n).for_each(|stripe_id| {
+            let blocks = (stripe_id * n..stripe_id * n + n)
+                .map(|block_id| get_block(block_id).into())
+                .collect();
+            assert!(rs.verify(&Stripe::from_vec(blocks, K, P)).unwrap());
+        });
+        updates.iter().for_each(|(block_id, ranges, byte)| {
+            let block = get_block(*block_id);
+            Ranges(ranges.clone())
+                .to_ranges()
+                .into_iter()
+                .for_each(|range| assert!(block[range].iter().all(|b| b == byte)));
+        });
+        updated
+    }
+
+    #[test]
+    fn persist_buffered_updates() {
+        // a source block in stripe 0 and stripe 2
+        let mut ranges_7 = RangeSet2::from(SEG_SIZE..SEG_SIZE * 2);
+        ranges_7.union_with(&RangeSet2::from(SEG_SIZE * 3..SEG_SIZE * 4));
+        let updates = [
+            (0, RangeSet2::from(0..SEG_SIZE), 0xaa_u8),
+            (7, ranges_7, 0x55_u8),
+        ];
+        assert_eq!(persist_updates(|_| (), &updates, false), 2);
+    }
+
+    #[test]
+    fn persist_evicted_updates() {
+        // each worker buffers a segment at most, and evicts it on its own on the next update
+        let updates = [0, 3, 1, 4, 7]
+            .map(|block_id| (block_id, RangeSet2::from(0..SEG_SIZE), block_id as u8 + 1));
+        let updated = persist_updates(
+            |builder| {
+                builder
+                    .evict_strategy(EvictKind::MostModifiedBlock)
+                    .buf_capacity(NonZeroUsize::new(SEG_SIZE).unwrap());
+            },
+            &updates,
+            false,
+        );
+        assert_eq!(updated, 3);
+    }
+
+    #[test]
+    fn persist_flushed_updates() {
+        // the updates flushed on shutdown are persisted by the restarted workers
+        let updates = [
+            (0, RangeSet2::from(0..SEG_SIZE), 0xaa_u8),
+            (4, RangeSet2::from(SEG_SIZE * 2..SEG_SIZE * 3), 0x55_u8),
+            (7, RangeSet2::from(SEG_SIZE..SEG_SIZE * 4), 0x5a_u8),
+        ];
+        let updated = persist_updates(
+            |builder| {
+                builder.flush_on_shutdown(true);
+            },
+            &updates,
+            true,
+        );
+        assert_eq!(updated, 3);
+    }
+
+    #[test]
+    fn status_round_trip() {
+        const WORKER_NUM: usize = 3;
+        const BLOCK_SIZE: NonZeroUsize = unsafe { NonZeroUsize::new_unchecked(4 << 10) };
+        const BLOCK_NUM: usize = 9;
+        const K: NonZeroUsize = unsafe { NonZeroUsize::new_unchecked(2) };
+        const P: NonZeroUsize = unsafe { NonZeroUsize::new_unchecked(1) };
+        let transport = InProcessTransport::new();
+        let tempdir = tempfile::tempdir().unwrap();
+        let builder = CoordinatorBuilder::default()
+            .in_process_transport(transport.clone())
+            .worker_num(NonZeroUsize::new(WORKER_NUM).unwrap())
+            .block_size(BLOCK_SIZE)
+            .block_num(NonZeroUsize::new(BLOCK_NUM).unwrap())
+            .k_p(K, P)
+            .heartbeat_interval(std::time::Duration::from_secs(1));
+        let worker_handles = spawn_workers(&transport, tempdir.path(), 1..=WORKER_NUM, BLOCK_SIZE);
+        Box::new(cmds::BuildData::try_from(builder.clone()).unwrap())
+            .exec()
+            .unwrap();
+        Box::new(cmds::KillAll::try_from(builder.clone()).unwrap())
+            .exec()
+            .unwrap();
+        worker_handles
+            .into_iter()
+            .for_each(|handle| handle.join().unwrap().unwrap());
+
+        // worker 2 is not relaunched, so it is not reported
+        let worker_handles = spawn_workers(&transport, tempdir.path(), [1, 3], BLOCK_SIZE);
+        let stats = cmds::Status::try_from(builder.clone())
+            .unwrap()
+            .run()
+            .unwrap();
+        // blocks of a stripe are dispatched to the workers in turn
+        assert_eq!(
+            stats,
+            vec![
+                cmds::WorkerStat {
+                    id: 1,
+                    buffer_len: 0,
+                    block_num: BLOCK_NUM / WORKER_NUM,
+                },
+                cmds::WorkerStat {
+                    id: 3,
+                    buffer_len: 0,
+                    block_num: BLOCK_NUM / WORKER_NUM,
+                },
+            ]
+        );
+        Box::new(cmds::KillAll::try_from(builder).unwrap())
+            .exec()
+            .unwrap();
+        worker_handles
+            .into_iter()
+            .for_each(|handle| handle.join().unwrap().unwrap());
+    }
+
+    #[test]
+    fn kill_all_summary() {
+        const WORKER_NUM: usize = 3;
+        const BLOCK_SIZE: NonZeroUsize = unsafe { NonZeroUsize::new_unchecked(4 << 10) };
+        let transport = InProcessTransport::new();
+        let tempdir = tempfile::tempdir().unwrap();
+        // worker 2 is never launched, so it is unresponsive
+        let worker_handles = spawn_workers(&transport, tempdir.path(), [1, 3], BLOCK_SIZE);
+        let builder = CoordinatorBuilder::default()
+            .in_process_transport(transport)
+            .worker_num(NonZeroUsize::new(WORKER_NUM).unwrap())
+            .heartbeat_interval(std::time::Duration::from_secs(1));
+
+        // dry run shuts nothing down
+        let summary = cmds::KillAll::try_from(builder.clone())
+            .unwrap()
+            .dry_run(true)
+            .run()
+            .unwrap();
+        assert_eq!(
+            summary,
+            cmds::KillAllSummary {
+                alive: vec![1, 3],
+                acked: vec![],
+                not_acked: vec![1, 2, 3],
+            }
+        );
+        assert!(worker_handles.iter().all(|handle| !handle.is_finished()));
+
+        let summary = cmds::KillAll::try_from(builder).unwrap().run().unwrap();
+        assert_eq!(
+            summary,
+            cmds::KillAllSummary {
+                alive: vec![1, 3],
+                acked: vec![1, 3],
+                not_acked: vec![2],
+            }
+        );
+        worker_handles
+            .into_iter()
+            .for_each(|handle| handle.join().unwrap().unwrap());
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    num::NonZeroUsize,
+};
+
+use indicatif::ProgressIterator;
+
+use crate::{
+    cluster::{
+        messages::{
+            coordinator_request::Request,
+            worker_response::{Ack, Nak, Response},
+            TaskID,
+        },
+        progress_style_template,
+        transport::MessageTransport,
+        MessageQueueKey, Ranges, WorkerID,
+    },
+    erasure_code::{ErasureCode, ReedSolomon, Stripe},
+    storage::BlockId,
+    SUError, SUResult,
+};
+
+pub struct Persist {
+    conn: Box<dyn MessageTransport + Send>,
+    request_queue_list: Vec<MessageQueueKey>,
+    response_queue: MessageQueueKey,
+    heartbeat_interval: std::time::Duration,
+    block_size: usize,
+    block_num: usize,
+    k_p: (usize, usize),
+}
+
+impl TryFrom<super::CoordinatorBuilder> for Persist {
+    type Error = SUError;
+
+    fn try_from(value: super::CoordinatorBuilder) -> Result<Self, Self::Error> {
+        let worker_num = value
+            .worker_num
+            .ok_or_else(|| SUError::Other("worker number not set".into()))?;
+        Ok(Persist {
+            conn: value.connect()?,
+            request_queue_list: (1..=worker_num)
+                .map(|i| i.try_into().unwrap())
+                .map(WorkerID)
+                .map(crate::cluster::format_request_queue_key)
+                .collect(),
+            response_queue: crate::cluster::format_response_queue_key(),
+            heartbeat_interval: value.heartbeat_interval_or_default(),
+            block_size: value
+                .block_size
+                .ok_or_else(|| SUError::Other("block size not set".into()))?,
+            block_num: value
+                .block_num
+                .ok_or_else(|| SUError::Other("block number not set".into()))?,
+            k_p: value
+                .k_p
+                .ok_or_else(|| SUError::Other("k and p not set".into()))?,
+        })
+    }
+}
+
+impl super::CoordinatorCmds for Persist {
+    fn exec(mut self: Box<Self>) -> SUResult<()> {
+        // make sure workers are alive
+        let alive_workers = super::broadcast_heartbeat(
+            &self.request_queue_list,
+            &self.response_queue,
+            self.conn.as_mut(),
+            self.heartbeat_interval,
+        )?;
+        if alive_workers.len() != self.request_queue_list.len() {
+            return Err(SUError::Other(format!(
+                "only workers [{}] of {} are alive",
+                alive_workers
+                    .iter()
+                    .map(WorkerID::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                self.request_queue_list.len()
+            )));
+        }
+        let updated = persist_buffered(
+            self.conn.as_mut(),
+            &self.request_queue_list,
+            &self.response_queue,
+            self.block_size,
+            self.block_num,
+            self.k_p,
+        )?;
+        println!("{updated} stripes updated");
+        Ok(())
+    }
+}
+
+/// Persist the updates buffered in the workers, and re-encode the parity of the updated stripes,
+/// so that the cluster ends in a consistent state with nothing buffered,
+/// like the standalone benchmarks draining their slice buffers.
+/// The stripes of the blocks persisted by the workers on their own,
+/// i.e., evicted locally or flushed on shutdown, are re-encoded as well,
+/// as such blocks are acknowledged with no slices.
+///
+/// The blocks of a stripe are dispatched to the workers in turn, as [`super::cmds::BuildData`] does.
+///
+/// # Return
+/// - [`Ok`] with the number of the stripes updated
+/// - [`Err`] on any error occurring on the transport, or reported by a worker
+pub(super) fn persist_buffered(
+    conn: &mut dyn MessageTransport,
+    request_queue_list: &[impl AsRef<str>],
+    response_queue: &impl AsRef<str>,
+    block_size: usize,
+    block_num: usize,
+    (k, p): (usize, usize),
+) -> SUResult<usize> {
+    let n = k + p;
+    let stripe_num = block_num.div_ceil(n);
+    let queue_of =
+        |block_id: BlockId| request_queue_list[(block_id % n) % request_queue_list.len()].as_ref();
+    let request = |conn: &mut dyn MessageTransport, request: Request, block_id: BlockId| {
+        let task_id = request.id;
+        request
+            .push_to_redis(conn, queue_of(block_id))
+            .map(|()| (task_id, block_id))
+    };
+    let block_of = |tasks: &BTreeMap<TaskID, BlockId>, response: &Response| {
+        tasks
+            .get(&response.id)
+            .copied()
+            .ok_or_else(|| SUError::other(format!("unexpected response {}", response.id)))
+    };
+    let unexpected_ack = |ack: Ack| SUError::other(format!("unexpected ack {ack:?}"));
+
+    // persist the buffered updates of all the source blocks
+    let tasks = (0..stripe_num)
+        .flat_map(|stripe_id| stripe_id * n..stripe_id * n + k)
+        .map(|block_id| request(conn, Request::persist_update(block_id), block_id))
+        .collect::<SUResult<BTreeMap<_, _>>>()?;
+    let mut updated_stripes = BTreeSet::new();
+    for _ in (0..tasks.len()).progress_with_style(progress_style_template(Some("block persisted")))
+    {
+        let response = Response::fetch_from_redis(conn, response_queue.as_ref())?;
+        let block_id = block_of(&tasks, &response)?;
+        match response.head {
+            Ok(Ack::PersistUpdate { .. }) => {
+                updated_stripes.insert(block_id / n);
+            }
+            // nothing buffered
+            Err(Nak(SUError::Io(e))) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(Nak(e)) => {
+                return Err(SUError::other(format!(
+                    "fail to persist block {block_id}: {e}"
+                )))
+            }
+            Ok(ack) => return Err(unexpected_ack(ack)),
+        }
+    }
+
+    // re-encode the parity of the updated stripes
+    let (k, p) = (NonZeroUsize::new(k).unwrap(), NonZeroUsize::new(p).unwrap());
+    let rs = ReedSolomon::from_k_p(k, p);
+    updated_stripes
+        .iter()
+        .progress_with_style(progress_style_template(Some("stripe re-encoded")))
+        .try_for_each(|&stripe_id| {
+            let mut stripe = Stripe::zero(k, p, NonZeroUsize::new(block_size).unwrap());
+            let first_block_id = stripe_id * n;
+            let tasks = (first_block_id..first_block_id + k.get())
+                .map(|block_id| {
+                    let ranges = Ranges(range_collections::RangeSet2::from(0..block_size));
+                    request(conn, Request::retrieve_data(block_id, ranges), block_id)
+                })
+                .collect::<SUResult<BTreeMap<_, _>>>()?;
+            for _ in 0..tasks.len() {
+                let response = Response::fetch_from_redis(conn, response_queue.as_ref())?;
+                let block_id = block_of(&tasks, &response)?;
+                match response.head {
+                    Ok(Ack::RetrieveSlice { .. }) if response.payload.len() == block_size => {
+                        stripe.as_mut_source()[block_id - first_block_id]
+                            .copy_from_slice(&response.payload.unwrap())
+                    }
+                    Ok(Ack::RetrieveSlice { .. }) => {
+                        return Err(SUError::other(format!(
+                            "block {block_id} retrieved with {} bytes of payload, expected {block_size}",
+                            response.payload.len()
+                        )))
+                    }
+                    Err(Nak(e)) => {
+                        return Err(SUError::other(format!(
+                            "fail to retrieve block {block_id}: {e}"
+                        )))
+                    }
+                    Ok(ack) => return Err(unexpected_ack(ack)),
+                }
+            }
+            rs.encode_stripe(&mut stripe)?;
+            let tasks = stripe
+                .into_blocks()
+                .into_iter()
+                .enumerate()
+                .skip(k.get())
+                .map(|(idx, block)| {
+                    let block_id = first_block_id + idx;
+                    request(conn, Request::store_block(block_id, block.into()), block_id)
+                })
+                .collect::<SUResult<BTreeMap<_, _>>>()?;
+            for _ in 0..tasks.len() {
+                let response = Response::fetch_from_redis(conn, response_queue.as_ref())?;
+                let block_id = block_of(&tasks, &response)?;
+                match response.head {
+                    Ok(Ack::StoreBlock) => (),
+                    Err(Nak(e)) => {
+                        return Err(SUError::other(format!(
+                            "fail to store parity block {block_id}: {e}"
+                        )))
+                    }
+                    Ok(ack) => return Err(unexpected_ack(ack)),
+                }
+            }
+            Ok(())
+        })?;
+    Ok(updated_stripes.len())
+}
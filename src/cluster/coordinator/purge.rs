@@ -7,9 +7,11 @@ use crate::{
         format_request_queue_key,
         messages::{
             coordinator_request::Request,
-            worker_response::{Ack, Response},
+            worker_response::{Ack, Nak, Response},
         },
-        progress_style_template, MessageQueueKey, WorkerID,
+        progress_style_template,
+        transport::MessageTransport,
+        MessageQueueKey, WorkerID,
     },
     SUError, SUResult,
 };
@@ -17,29 +19,28 @@ use crate::{
 use super::CoordinatorCmds;
 
 pub struct Purge {
-    conn: redis::Connection,
+    conn: Box<dyn MessageTransport + Send>,
     request_queue_list: Vec<MessageQueueKey>,
     response_queue: MessageQueueKey,
+    heartbeat_interval: std::time::Duration,
 }
 
 impl TryFrom<super::CoordinatorBuilder> for Purge {
     type Error = SUError;
 
     fn try_from(value: super::CoordinatorBuilder) -> Result<Self, Self::Error> {
-        let redis_url = value
-            .redis_url
-            .ok_or_else(|| SUError::Other("redis url not set".into()))?;
         let worker_num = value
             .worker_num
             .ok_or_else(|| SUError::Other("worker number not set".into()))?;
         Ok(Purge {
-            conn: redis::Client::open(redis_url)?.get_connection()?,
+            conn: value.connect()?,
             request_queue_list: (1..=worker_num)
                 .map(|i| i.try_into().unwrap())
                 .map(WorkerID)
                 .map(format_request_queue_key)
                 .collect(),
             response_queue: crate::cluster::format_response_queue_key(),
+            heartbeat_interval: value.heartbeat_interval_or_default(),
         })
     }
 }
@@ -48,13 +49,14 @@ impl CoordinatorCmds for Purge {
     fn exec(mut self: Box<Self>) -> SUResult<()> {
         let worker_num = self.request_queue_list.len();
 
-        redis::cmd("FLUSHALL").query(&mut self.conn)?;
+        self.conn.flush_all()?;
 
         // get alive workers
         let alive_workers = super::broadcast_heartbeat(
             &self.request_queue_list,
             &self.response_queue,
-            &mut self.conn,
+            self.conn.as_mut(),
+            self.heartbeat_interval,
         )?;
         println!(
             "alive workers: {}",
@@ -73,7 +75,7 @@ impl CoordinatorCmds for Purge {
                 let request = Request::flush_buf();
                 let id = request.id;
                 request
-                    .push_to_redis(&mut self.conn, key)
+                    .push_to_redis(self.conn.as_mut(), key)
                     .map(|_| (id, None::<Response>))
             })
             .collect::<SUResult<BTreeMap<_, _>>>()?;
@@ -84,7 +86,7 @@ impl CoordinatorCmds for Purge {
                 let request = Request::drop_store();
                 let id = request.id;
                 request
-                    .push_to_redis(&mut self.conn, key)
+                    .push_to_redis(self.conn.as_mut(), key)
                     .map(|_| (id, None::<Response>))
             })
             .collect::<SUResult<BTreeMap<_, _>>>()?;
@@ -92,7 +94,8 @@ impl CoordinatorCmds for Purge {
         (0..worker_num * 2)
             .progress_with_style(progress_style_template(Some("purging worker data")))
             .try_for_each(|_| -> SUResult<()> {
-                let response = Response::fetch_from_redis(&mut self.conn, &self.response_queue)?;
+                let response =
+                    Response::fetch_from_redis(self.conn.as_mut(), &self.response_queue)?;
                 let task_id = response.id;
                 match &response.head {
                     Ok(Ack::FlushBuf { .. }) => {
@@ -124,20 +127,18 @@ impl CoordinatorCmds for Purge {
             .all(|(_, response)| { response.is_some() }));
         flush_tasks
             .into_iter()
-            .filter(|(_, response)| response.as_ref().unwrap().head.is_err())
-            .for_each(|(task_id, response)| {
-                let response = response.unwrap();
-                let err_str = String::from_utf8(response.payload.unwrap().into()).unwrap();
-                eprintln!("flush task {} failed: {}", task_id, err_str);
-            });
+            .filter_map(|(task_id, response)| match response.unwrap().head {
+                Err(Nak(e)) => Some((task_id, e)),
+                Ok(_) => None,
+            })
+            .for_each(|(task_id, e)| log::error!("flush task {task_id} failed: {e}"));
         drop_tasks
             .into_iter()
-            .filter(|(_, response)| response.as_ref().unwrap().head.is_err())
-            .for_each(|(task_id, response)| {
-                let response = response.unwrap();
-                let err_str = String::from_utf8(response.payload.unwrap().into()).unwrap();
-                eprintln!("drop task {} failed: {}", task_id, err_str);
-            });
+            .filter_map(|(task_id, response)| match response.unwrap().head {
+                Err(Nak(e)) => Some((task_id, e)),
+                Ok(_) => None,
+            })
+            .for_each(|(task_id, e)| log::error!("drop task {task_id} failed: {e}"));
         println!("done");
         Ok(())
     }
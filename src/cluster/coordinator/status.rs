@@ -0,0 +1,115 @@
+use crate::{
+    cluster::{
+        messages::{coordinator_request::Request, worker_response::Ack},
+        transport::MessageTransport,
+        MessageQueueKey, WorkerID,
+    },
+    SUError, SUResult,
+};
+
+pub struct Status {
+    conn: Box<dyn MessageTransport + Send>,
+    workers: Vec<WorkerID>,
+    response_queue: MessageQueueKey,
+    heartbeat_interval: std::time::Duration,
+}
+
+/// Status of an alive worker reported to [`Status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WorkerStat {
+    /// id of the worker
+    pub id: usize,
+    /// size of the updates buffered in the worker, in bytes
+    pub buffer_len: usize,
+    /// number of the blocks stored in the worker
+    pub block_num: usize,
+}
+
+impl TryFrom<super::CoordinatorBuilder> for Status {
+    type Error = SUError;
+
+    fn try_from(value: super::CoordinatorBuilder) -> Result<Self, Self::Error> {
+        let worker_num = value
+            .worker_num
+            .ok_or_else(|| SUError::Other("worker number not set".into()))?;
+        Ok(Status {
+            conn: value.connect()?,
+            workers: (1..=worker_num)
+                .map(|i| i.try_into().unwrap())
+                .map(WorkerID)
+                .collect(),
+            response_queue: crate::cluster::format_response_queue_key(),
+            heartbeat_interval: value.heartbeat_interval_or_default(),
+        })
+    }
+}
+
+impl Status {
+    /// Query the status of all the alive workers,
+    /// each of which responds within the heartbeat interval.
+    ///
+    /// # Return
+    /// - [`Ok`] with the status of the workers responding, in ascending order of the ids
+    /// - [`Err`] on any error occurring on the transport
+    pub fn run(&mut self) -> SUResult<Vec<WorkerStat>> {
+        let request_queue_list = self
+            .workers
+            .iter()
+            .cloned()
+            .map(crate::cluster::format_request_queue_key)
+            .collect::<Vec<_>>();
+        let alive_workers = super::broadcast_heartbeat(
+            &request_queue_list,
+            &self.response_queue,
+            self.conn.as_mut(),
+            self.heartbeat_interval,
+        )?;
+        if alive_workers.is_empty() {
+            return Ok(Vec::new());
+        }
+        let alive_queue_list = alive_workers
+            .into_iter()
+            .map(crate::cluster::format_request_queue_key)
+            .collect::<Vec<_>>();
+        super::broadcast(
+            &alive_queue_list,
+            &self.response_queue,
+            self.conn.as_mut(),
+            self.heartbeat_interval,
+            Request::stat,
+            |ack| match ack {
+                Ack::Stat {
+                    worker_id,
+                    buffer_len,
+                    block_num,
+                } => Some(WorkerStat {
+                    id: worker_id.0.into(),
+                    buffer_len: *buffer_len,
+                    block_num: *block_num,
+                }),
+                _ => None,
+            },
+        )
+    }
+}
+
+impl super::CoordinatorCmds for Status {
+    fn exec(mut self: Box<Self>) -> SUResult<()> {
+        let stats = self.run()?;
+        println!(
+            "{:>8} {:>16} {:>16}",
+            "worker", "buffered bytes", "stored blocks"
+        );
+        for id in self.workers.iter().map(|id| usize::from(id.0)) {
+            match stats.iter().find(|stat| stat.id == id) {
+                Some(stat) => println!(
+                    "{:>8} {:>16} {:>16}",
+                    stat.id, stat.buffer_len, stat.block_num
+                ),
+                None => println!("{id:>8} {:>16} {:>16}", "-", "-"),
+            }
+        }
+        println!("{} of {} workers alive", stats.len(), self.workers.len());
+        Ok(())
+    }
+}
@@ -1,10 +1,13 @@
 use bytes::Bytes;
-use redis::Commands;
 use serde::{Deserialize, Serialize};
 
-use crate::{cluster::Ranges, storage::BlockId, SUResult};
+use crate::{
+    cluster::{transport::MessageTransport, Ranges},
+    storage::BlockId,
+    SUError, SUResult,
+};
 
-use super::{PayloadData, PayloadID, TaskID};
+use super::{envelope, PayloadData, PayloadID, TaskID};
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Request {
@@ -23,6 +26,10 @@ impl Request {
         Self::assemble(Head::Shutdown, None)
     }
 
+    pub fn stat() -> Self {
+        Self::assemble(Head::Stat, None)
+    }
+
     pub fn flush_buf() -> Self {
         Self::assemble(Head::FlushBuf, None)
     }
@@ -31,11 +38,32 @@ impl Request {
         Self::assemble(Head::DropStore, None)
     }
 
+    pub fn retrieve_data(id: BlockId, ranges: Ranges) -> Self {
+        Self::assemble(Head::RetrieveData { id, ranges }, None)
+    }
+
+    pub fn persist_update(id: BlockId) -> Self {
+        Self::assemble(Head::PersistUpdate { id }, None)
+    }
+
+    // used by the update benchmark, which is not enabled yet
+    #[allow(dead_code)]
+    pub fn buffer_update_data(id: BlockId, ranges: Ranges, payload: Bytes) -> Self {
+        Self::assemble(
+            Head::BufferUpdateData {
+                id,
+                ranges,
+                payload: PayloadID::assign(&payload),
+            },
+            Some(payload),
+        )
+    }
+
     pub fn store_block(id: BlockId, payload: Bytes) -> Self {
         Self::assemble(
             Head::StoreBlock {
                 id,
-                payload: PayloadID::assign(),
+                payload: PayloadID::assign(&payload),
             },
             Some(payload),
         )
@@ -54,57 +82,76 @@ impl Request {
         }
     }
 
-    pub fn push_to_redis(&self, conn: &mut redis::Connection, key: &str) -> SUResult<()> {
+    pub fn push_to_redis(&self, conn: &mut dyn MessageTransport, key: &str) -> SUResult<()> {
         // push payload
         if let Some(id) = self.head.get_payload_id() {
             self.payload.push_to_redis(id, conn)?;
         }
-        let bin_ser = bincode::serialize(self).expect("serde error");
-        Ok(conn.rpush(key, bin_ser)?)
-    }
-
-    pub fn fetch_from_redis(conn: &mut redis::Connection, key: &str) -> SUResult<Self> {
-        let value: redis::Value = conn.blpop(key, 0_f64)?;
-        if let redis::Value::Bulk(value) = value {
-            let value = value.get(1).expect("bad redis value");
-            if let redis::Value::Data(value) = value {
-                let mut request: Request = bincode::deserialize(value).expect("serde error");
-                if let Some(id) = request.head.get_payload_id() {
-                    request.payload = PayloadData::fetch_from_redis(id, conn)?;
-                }
-                return Ok(request);
-            }
-        }
-        unreachable!("bad redis value")
+        conn.push(key, &envelope::seal(self))
     }
 
-    /// Fetch a request from redis with timeout
+    /// Fetch a request with its payload from the transport.
+    ///
+    /// The workers fetch with [`Self::open`] and [`Self::fetch_payload`] instead,
+    /// to tell an unopenable message from a request whose payload fails to be fetched.
+    #[allow(dead_code)]
+    pub fn fetch_from_redis(conn: &mut dyn MessageTransport, key: &str) -> SUResult<Self> {
+        let bin_ser = conn.fetch(key)?;
+        Self::deserialize_with_payload(&bin_ser, conn)
+    }
+
+    /// Fetch a request from the transport with timeout
     ///
     /// If timeout is None, it will never be blocked and return `None` when there is no request.
     #[allow(dead_code)]
     pub fn fetch_from_redis_timeout(
-        conn: &mut redis::Connection,
+        conn: &mut dyn MessageTransport,
         key: &str,
         timeout: Option<std::time::Duration>,
     ) -> SUResult<Option<Self>> {
-        let value: redis::Value = if let Some(timeout) = timeout {
-            let timeout = timeout.as_secs_f64();
-            conn.blpop(key, timeout)?
-        } else {
-            conn.lpop(key, None)?
+        conn.fetch_timeout(key, timeout)?
+            .map(|bin_ser| Self::deserialize_with_payload(&bin_ser, conn))
+            .transpose()
+    }
+
+    /// Open a request sealed in an envelope, as fetched by [`MessageTransport::fetch`],
+    /// without fetching its payload, see [`Self::fetch_payload`].
+    pub fn open(envelope: &[u8]) -> SUResult<Self> {
+        envelope::open(envelope)
+    }
+
+    /// Fetch the payload of an opened request if it has one, no larger than `max_payload_bytes` if set.
+    ///
+    /// The size of the payload is checked before it is fetched,
+    /// and an oversized payload is dropped from the transport without being fetched.
+    ///
+    /// # Error
+    /// - [`SUError::InvalidArg`] if the payload is oversized
+    /// - [`SUError::Other`] if the payload is not found
+    /// - any error occurring on the transport
+    pub fn fetch_payload(
+        mut self,
+        conn: &mut dyn MessageTransport,
+        max_payload_bytes: Option<usize>,
+    ) -> SUResult<Self> {
+        let Some(id) = self.head.get_payload_id() else {
+            return Ok(self);
         };
-        match value {
-            // timeout
-            redis::Value::Nil => Ok(None),
-            redis::Value::Data(value) => {
-                let mut request: Request = bincode::deserialize(&value).expect("serde error");
-                if let Some(id) = request.head.get_payload_id() {
-                    request.payload = PayloadData::fetch_from_redis(id, conn)?;
-                }
-                Ok(Some(request))
+        if let Some(max) = max_payload_bytes {
+            let payload_len = conn.payload_len(id.as_bytes())?;
+            if payload_len > max {
+                conn.drop_payload(id.as_bytes())?;
+                return Err(SUError::invalid_arg(format!(
+                    "payload of {payload_len} bytes exceeds the limit of {max} bytes"
+                )));
             }
-            _ => unreachable!("bad redis value"),
         }
+        self.payload = PayloadData::fetch_from_redis(id, conn)?;
+        Ok(self)
+    }
+
+    fn deserialize_with_payload(bin_ser: &[u8], conn: &mut dyn MessageTransport) -> SUResult<Self> {
+        Self::open(bin_ser)?.fetch_payload(conn, None)
     }
 }
 
@@ -141,6 +188,8 @@ pub enum Head {
     HeartBeat,
     /// Shutdown the worker
     Shutdown,
+    /// Query the size of the buffered updates and the number of the stored blocks
+    Stat,
 }
 
 impl Head {
@@ -151,6 +200,20 @@ impl Head {
         )
     }
 
+    /// Whether the request modifies the stored blocks or the buffered updates,
+    /// so that it must not be applied twice if it is replayed.
+    pub fn is_mutation(&self) -> bool {
+        matches!(
+            self,
+            Self::StoreBlock { .. }
+                | Self::PersistUpdate { .. }
+                | Self::BufferUpdateData { .. }
+                | Self::UpdateParity { .. }
+                | Self::FlushBuf
+                | Self::DropStore
+        )
+    }
+
     pub fn get_payload_id(&self) -> Option<PayloadID> {
         match self {
             Self::StoreBlock { payload, .. } => Some(*payload),
@@ -0,0 +1,122 @@
+//! The envelope wrapping a serialized message on the transport:
+//! the protocol version byte, the CRC32 of the body in little endian, then the body.
+//!
+//! The payload of a message is stored aside on the transport, out of the envelope,
+//! and is checked against its own CRC32 carried in the [`super::PayloadID`] of the body.
+
+use crate::{SUError, SUResult};
+
+/// Version of the message protocol, bumped on any change of the message schema,
+/// so that the coordinator and the workers of different builds never mis-deserialize the messages.
+pub(super) const PROTOCOL_VERSION: u8 = 3;
+
+const HEADER_LEN: usize = 1 + std::mem::size_of::<u32>();
+
+/// Serialize `msg` and wrap it in an envelope.
+pub(super) fn seal<T: serde::Serialize>(msg: &T) -> Vec<u8> {
+    let body = bincode::serialize(msg).expect("serde error");
+    let mut envelope = Vec::with_capacity(HEADER_LEN + body.len());
+    envelope.push(PROTOCOL_VERSION);
+    envelope.extend_from_slice(&crc32fast::hash(&body).to_le_bytes());
+    envelope.extend_from_slice(&body);
+    envelope
+}
+
+/// Check the envelope made by [`seal`] and deserialize the message in it.
+///
+/// # Error
+/// - [`SUError::Other`] if the envelope is truncated, of another protocol version,
+///   or its body is not a valid message
+/// - [`SUError::Checksum`] if the body is corrupted
+pub(super) fn open<T: serde::de::DeserializeOwned>(envelope: &[u8]) -> SUResult<T> {
+    if envelope.len() < HEADER_LEN {
+        return Err(SUError::other(format!(
+            "message of {} bytes is shorter than the envelope header",
+            envelope.len()
+        )));
+    }
+    let (header, body) = envelope.split_at(HEADER_LEN);
+    let version = header[0];
+    if version != PROTOCOL_VERSION {
+        return Err(SUError::other(format!(
+            "message protocol version {version} does not match {PROTOCOL_VERSION}, \
+            the coordinator and the workers must run the same version"
+        )));
+    }
+    let expect = u32::from_le_bytes(header[1..].try_into().unwrap());
+    let actual = crc32fast::hash(body);
+    if expect != actual {
+        return Err(SUError::Checksum(format!(
+            "message is corrupted, checksum expected: {expect:#010x}, found: {actual:#010x}"
+        )));
+    }
+    bincode::deserialize(body).map_err(|e| SUError::other(format!("malformed message: {e}")))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        cluster::{
+            messages::{coordinator_request::Request, worker_response::Response},
+            transport::{InProcessTransport, MessageTransport},
+            WorkerID,
+        },
+        SUError,
+    };
+
+    use super::{open, seal, PROTOCOL_VERSION};
+
+    #[test]
+    fn round_trip() {
+        let request = Request::persist_update(3);
+        assert_eq!(seal(&request)[0], PROTOCOL_VERSION);
+        assert_eq!(open::<Request>(&seal(&request)).unwrap(), request);
+    }
+
+    #[test]
+    fn reject_bad_envelope() {
+        let envelope = seal(&Request::persist_update(3));
+
+        let mut wrong_version = envelope.clone();
+        wrong_version[0] = PROTOCOL_VERSION + 1;
+        assert!(matches!(
+            open::<Request>(&wrong_version),
+            Err(SUError::Other(e)) if e.contains("version")
+        ));
+
+        let mut corrupted = envelope.clone();
+        *corrupted.last_mut().unwrap() ^= 0xFF;
+        assert!(matches!(
+            open::<Request>(&corrupted),
+            Err(SUError::Checksum(_))
+        ));
+
+        assert!(matches!(
+            open::<Request>(&envelope[..3]),
+            Err(SUError::Other(_))
+        ));
+
+        // a message without an envelope, as sent by an older build
+        let bare = bincode::serialize(&Request::persist_update(3)).unwrap();
+        assert!(open::<Request>(&bare).is_err());
+
+        // the error surfaces on fetching instead of a garbage message
+        let mut conn = InProcessTransport::new();
+        conn.push("bad-envelope", &corrupted).unwrap();
+        assert!(matches!(
+            Request::fetch_from_redis(&mut conn, "bad-envelope"),
+            Err(SUError::Checksum(_))
+        ));
+        conn.push("bad-envelope", &wrong_version).unwrap();
+        assert!(Response::fetch_from_redis(&mut conn, "bad-envelope").is_err());
+
+        let response = Response::heartbeat(Request::heartbeat().id, WorkerID(1));
+        response.push_to_redis(&mut conn, "bad-envelope").unwrap();
+        assert_eq!(
+            Response::fetch_from_redis(&mut conn, "bad-envelope")
+                .unwrap()
+                .id,
+            response.id
+        );
+    }
+}
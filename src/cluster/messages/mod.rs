@@ -1,13 +1,13 @@
 use bytes::Bytes;
-use redis::Commands;
 
-use crate::SUResult;
+use crate::{cluster::transport::MessageTransport, SUError, SUResult};
 
 pub mod coordinator_request;
+mod envelope;
 pub mod worker_response;
 
 #[derive(
-    Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy,
+    Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy,
 )]
 struct Uuid(#[serde(with = "uuid::serde::compact")] uuid::Uuid);
 
@@ -17,15 +17,6 @@ impl std::fmt::Display for Uuid {
     }
 }
 
-impl redis::ToRedisArgs for Uuid {
-    fn write_redis_args<W>(&self, out: &mut W)
-    where
-        W: ?Sized + redis::RedisWrite,
-    {
-        self.0.as_bytes().write_redis_args(out)
-    }
-}
-
 impl Uuid {
     fn new() -> Self {
         Self(uuid::Uuid::now_v7())
@@ -33,7 +24,7 @@ impl Uuid {
 }
 
 #[derive(
-    Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy,
+    Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy,
 )]
 pub struct TaskID(Uuid);
 
@@ -49,21 +40,25 @@ impl std::fmt::Display for TaskID {
     }
 }
 
+/// Reference to a payload stored aside on the transport, carried in the message head.
 #[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Clone, Copy)]
-pub struct PayloadID(Uuid);
-
-impl redis::ToRedisArgs for PayloadID {
-    fn write_redis_args<W>(&self, out: &mut W)
-    where
-        W: ?Sized + redis::RedisWrite,
-    {
-        self.0.write_redis_args(out)
-    }
+pub struct PayloadID {
+    uuid: Uuid,
+    /// CRC32 of the payload, checked on fetching,
+    /// since the payload is not covered by the checksum of the envelope
+    checksum: u32,
 }
 
 impl PayloadID {
-    pub(crate) fn assign() -> PayloadID {
-        PayloadID(Uuid::new())
+    pub(crate) fn assign(data: &[u8]) -> PayloadID {
+        PayloadID {
+            uuid: Uuid::new(),
+            checksum: crc32fast::hash(data),
+        }
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        self.uuid.0.as_bytes()
     }
 }
 
@@ -79,29 +74,90 @@ impl PayloadData {
         self.0.unwrap()
     }
 
-    pub fn fetch_from_redis(id: PayloadID, conn: &mut redis::Connection) -> SUResult<Self> {
-        let value: redis::Value = conn.get_del(id)?;
-        let data = match value {
-            redis::Value::Nil => {
-                return Err(crate::SUError::other(format!(
-                    "payload id: {} not found",
-                    id.0
-                )))
-            }
-            redis::Value::Data(data) => data,
-            _ => unreachable!("bad redis value"),
-        };
-        Ok(Self::new(data.into()))
-    }
-
-    pub fn push_to_redis(&self, id: PayloadID, conn: &mut redis::Connection) -> SUResult<()> {
-        let data = self.0.as_ref().unwrap().as_ref();
-        // TODO: performance issue: redis makes a copy of the data
-        conn.set_options(
-            id,
-            data,
-            redis::SetOptions::default().conditional_set(redis::ExistenceCheck::NX),
-        )?;
-        Ok(())
+    /// Size of the payload in bytes, `0` if there is no payload.
+    pub fn len(&self) -> usize {
+        self.0.as_ref().map_or(0, Bytes::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Fetch the payload of `id`, and check it against the checksum in `id`.
+    ///
+    /// # Error
+    /// - [`SUError::Other`] if the payload is not found
+    /// - [`SUError::Checksum`] if the payload is corrupted
+    /// - any error occurring on the transport
+    pub fn fetch_from_redis(id: PayloadID, conn: &mut dyn MessageTransport) -> SUResult<Self> {
+        let data = conn
+            .take_payload(id.as_bytes())?
+            .ok_or_else(|| SUError::other(format!("payload id: {} not found", id.uuid)))?;
+        let actual = crc32fast::hash(&data);
+        if actual != id.checksum {
+            return Err(SUError::Checksum(format!(
+                "payload id: {} is corrupted, checksum expected: {:#010x}, found: {actual:#010x}",
+                id.uuid, id.checksum
+            )));
+        }
+        Ok(Self::new(data))
+    }
+
+    pub fn push_to_redis(&self, id: PayloadID, conn: &mut dyn MessageTransport) -> SUResult<()> {
+        conn.put_payload(id.as_bytes(), self.0.as_ref().unwrap().as_ref())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use crate::{
+        cluster::transport::{InProcessTransport, MessageTransport, PAYLOAD_CHUNK_SIZE},
+        SUError,
+    };
+
+    use super::coordinator_request::Request;
+
+    fn payload_round_trip(conn: &mut dyn MessageTransport) {
+        const KEY: &str = "payload-round-trip";
+        // span several chunks, with a partial chunk at the end
+        let payload = (0..PAYLOAD_CHUNK_SIZE * 3 + 17)
+            .map(|i| (i % 251) as u8)
+            .collect::<Bytes>();
+        let request = Request::store_block(0, payload);
+        request.push_to_redis(conn, KEY).unwrap();
+        let fetched = Request::fetch_from_redis(conn, KEY).unwrap();
+        assert_eq!(fetched, request);
+    }
+
+    #[test]
+    fn in_process_payload_round_trip() {
+        payload_round_trip(&mut InProcessTransport::new());
+    }
+
+    #[test]
+    fn corrupted_payload() {
+        const KEY: &str = "corrupted-payload";
+        let mut conn = InProcessTransport::new();
+        let request = Request::store_block(0, Bytes::from_static(b"payload"));
+        request.push_to_redis(&mut conn, KEY).unwrap();
+        let id = request.head.get_payload_id().unwrap();
+        conn.take_payload(id.as_bytes()).unwrap().unwrap();
+        conn.put_payload(id.as_bytes(), b"corrupt").unwrap();
+        assert!(matches!(
+            Request::fetch_from_redis(&mut conn, KEY),
+            Err(SUError::Checksum(_))
+        ));
+    }
+
+    #[ignore = "requires a redis server at redis://127.0.0.1"]
+    #[test]
+    fn redis_payload_round_trip() {
+        let mut conn = redis::Client::open("redis://127.0.0.1")
+            .unwrap()
+            .get_connection()
+            .unwrap();
+        payload_round_trip(&mut conn);
     }
 }
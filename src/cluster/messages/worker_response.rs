@@ -1,18 +1,18 @@
 use bytes::Bytes;
-use redis::Commands;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    cluster::{Ranges, WorkerID},
-    SUResult,
+    cluster::{transport::MessageTransport, Ranges, WorkerID},
+    SUError, SUResult,
 };
 
-use super::{PayloadData, PayloadID, TaskID};
+use super::{envelope, PayloadData, PayloadID, TaskID};
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Clone, Copy)]
-pub struct Nak(PayloadID);
+/// The error reported by a worker, which is carried in the response head.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Nak(pub SUError);
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Clone)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct Response {
     pub id: TaskID,
     pub head: Result<Ack, Nak>,
@@ -21,8 +21,8 @@ pub struct Response {
 }
 
 impl Response {
-    pub fn nak(task_id: TaskID, err: impl ToString) -> Self {
-        Self::assemble_nak(task_id, err.to_string())
+    pub fn nak(task_id: TaskID, err: SUError) -> Self {
+        Self::assemble_nak(task_id, err)
     }
 
     pub fn store_block(task_id: TaskID) -> Self {
@@ -33,7 +33,7 @@ impl Response {
         Self::assemble_ack(
             task_id,
             Ack::RetrieveSlice {
-                payload: PayloadID::assign(),
+                payload: PayloadID::assign(&payload),
             },
             Some(payload),
         )
@@ -44,7 +44,7 @@ impl Response {
             task_id,
             Ack::PersistUpdate {
                 ranges,
-                payload: PayloadID::assign(),
+                payload: PayloadID::assign(&payload),
             },
             Some(payload),
         )
@@ -73,6 +73,18 @@ impl Response {
     pub fn shutdown(task_id: TaskID, worker_id: WorkerID) -> Self {
         Self::assemble_ack(task_id, Ack::Shutdown { worker_id }, None)
     }
+
+    pub fn stat(task_id: TaskID, worker_id: WorkerID, buffer_len: usize, block_num: usize) -> Self {
+        Self::assemble_ack(
+            task_id,
+            Ack::Stat {
+                worker_id,
+                buffer_len,
+                block_num,
+            },
+            None,
+        )
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
@@ -95,10 +107,16 @@ pub enum Ack {
     HeartBeat { worker_id: WorkerID },
     /// Shutdown the worker
     Shutdown { worker_id: WorkerID },
+    /// Ack for Stat, with the size of the buffered updates and the number of the stored blocks
+    Stat {
+        worker_id: WorkerID,
+        buffer_len: usize,
+        block_num: usize,
+    },
 }
 
 impl Ack {
-    fn has_payload(&self) -> bool {
+    pub(crate) fn has_payload(&self) -> bool {
         matches!(
             self,
             Self::RetrieveSlice { .. } | Self::PersistUpdate { .. }
@@ -126,62 +144,44 @@ impl Response {
         }
     }
 
-    fn assemble_nak(task_id: TaskID, err_str: impl Into<String>) -> Self {
+    fn assemble_nak(task_id: TaskID, err: SUError) -> Self {
         Self {
             id: task_id,
-            head: Err(Nak(PayloadID::assign())),
-            payload: PayloadData::new(err_str.into().into()),
+            head: Err(Nak(err)),
+            payload: PayloadData(None),
         }
     }
 
-    pub fn push_to_redis(&self, conn: &mut redis::Connection, key: &str) -> SUResult<()> {
+    pub fn push_to_redis(&self, conn: &mut dyn MessageTransport, key: &str) -> SUResult<()> {
         if let Some(payload) = self.head.as_ref().ok().and_then(Ack::get_payload_id) {
             self.payload.push_to_redis(payload, conn)?;
         }
-        let bin_ser = bincode::serialize(self).expect("serde error");
-        Ok(conn.rpush(key, bin_ser)?)
-    }
-
-    pub fn fetch_from_redis(conn: &mut redis::Connection, key: &str) -> SUResult<Self> {
-        let value: redis::Value = conn.blpop(key, 0_f64)?;
-        if let redis::Value::Bulk(value) = value {
-            let value = value.get(1).expect("bad redis value");
-            if let redis::Value::Data(bin_ser) = value {
-                let mut request: Response = bincode::deserialize(bin_ser).expect("serde error");
-                if let Some(id) = request.head.as_ref().ok().and_then(Ack::get_payload_id) {
-                    request.payload = PayloadData::fetch_from_redis(id, conn)?;
-                }
-                return Ok(request);
-            }
-        }
-        unreachable!("bad redis value")
+        conn.push(key, &envelope::seal(self))
+    }
+
+    pub fn fetch_from_redis(conn: &mut dyn MessageTransport, key: &str) -> SUResult<Self> {
+        let bin_ser = conn.fetch(key)?;
+        Self::deserialize_with_payload(&bin_ser, conn)
     }
 
-    /// Fetch a request from redis with timeout
+    /// Fetch a request from the transport with timeout
     ///
     /// If timeout is None, it will never be blocked and return `None` when there is no request.
     pub fn fetch_from_redis_timeout(
-        conn: &mut redis::Connection,
+        conn: &mut dyn MessageTransport,
         key: &str,
         timeout: Option<std::time::Duration>,
     ) -> SUResult<Option<Self>> {
-        let value: redis::Value = if let Some(timeout) = timeout {
-            let timeout = timeout.as_secs_f64();
-            conn.blpop(key, timeout)?
-        } else {
-            conn.lpop(key, None)?
-        };
-        match value {
-            // timeout
-            redis::Value::Nil => Ok(None),
-            redis::Value::Data(bin_ser) => {
-                let mut request: Response = bincode::deserialize(&bin_ser).expect("serde error");
-                if let Some(id) = request.head.as_ref().ok().and_then(Ack::get_payload_id) {
-                    request.payload = PayloadData::fetch_from_redis(id, conn)?;
-                }
-                Ok(Some(request))
-            }
-            _ => unreachable!("bad redis value"),
+        conn.fetch_timeout(key, timeout)?
+            .map(|bin_ser| Self::deserialize_with_payload(&bin_ser, conn))
+            .transpose()
+    }
+
+    fn deserialize_with_payload(bin_ser: &[u8], conn: &mut dyn MessageTransport) -> SUResult<Self> {
+        let mut request: Response = envelope::open(bin_ser)?;
+        if let Some(id) = request.head.as_ref().ok().and_then(Ack::get_payload_id) {
+            request.payload = PayloadData::fetch_from_redis(id, conn)?;
         }
+        Ok(request)
     }
 }
@@ -1,6 +1,7 @@
 use smallvec::SmallVec;
 
 pub mod coordinator;
+pub mod transport;
 pub mod worker;
 
 mod messages;
@@ -0,0 +1,316 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use redis::Commands;
+
+use crate::SUResult;
+
+/// Transport of the messages between the coordinator and the workers.
+///
+/// Messages are pushed to and fetched from named FIFO queues,
+/// and the payload of a message is stored aside by its id, and taken by the receiver.
+pub trait MessageTransport {
+    /// Push a message to the tail of the queue `key`.
+    fn push(&mut self, key: &str, msg: &[u8]) -> SUResult<()>;
+
+    /// Fetch a message from the head of the queue `key`, blocked until there is one.
+    fn fetch(&mut self, key: &str) -> SUResult<Vec<u8>>;
+
+    /// Fetch a message from the head of the queue `key` with timeout.
+    ///
+    /// If timeout is None, it will never be blocked and return `None` when there is no message.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] with the message fetched
+    /// - [`Ok(None)`] if timeout
+    fn fetch_timeout(&mut self, key: &str, timeout: Option<Duration>) -> SUResult<Option<Vec<u8>>>;
+
+    /// Store a payload by its id, an existing payload with the same id is never overwritten.
+    fn put_payload(&mut self, id: &[u8], data: &[u8]) -> SUResult<()>;
+
+    /// Take away the payload by its id.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] with the payload data
+    /// - [`Ok(None)`] if the payload does not exist
+    fn take_payload(&mut self, id: &[u8]) -> SUResult<Option<Bytes>>;
+
+    /// Size of the payload in bytes without fetching it, `0` if the payload does not exist.
+    fn payload_len(&mut self, id: &[u8]) -> SUResult<usize>;
+
+    /// Drop the payload by its id without fetching it.
+    fn drop_payload(&mut self, id: &[u8]) -> SUResult<()>;
+
+    /// Drop all the messages and payloads.
+    fn flush_all(&mut self) -> SUResult<()>;
+}
+
+/// Size of a payload chunk sent to redis in a single command.
+///
+/// redis-rs packs each command with a full copy of its arguments,
+/// so sending a large payload in one command doubles its memory footprint.
+/// Chunking caps the extra memory to the chunk size.
+pub const PAYLOAD_CHUNK_SIZE: usize = 1 << 20;
+
+impl MessageTransport for redis::Connection {
+    fn push(&mut self, key: &str, msg: &[u8]) -> SUResult<()> {
+        Ok(self.rpush(key, msg)?)
+    }
+
+    fn fetch(&mut self, key: &str) -> SUResult<Vec<u8>> {
+        let (_, msg): (String, Vec<u8>) = self.blpop(key, 0_f64)?;
+        Ok(msg)
+    }
+
+    fn fetch_timeout(&mut self, key: &str, timeout: Option<Duration>) -> SUResult<Option<Vec<u8>>> {
+        match timeout {
+            // a zero timeout makes `BLPOP` block forever
+            Some(timeout) if !timeout.is_zero() => {
+                let value: Option<(String, Vec<u8>)> = self.blpop(key, timeout.as_secs_f64())?;
+                Ok(value.map(|(_, msg)| msg))
+            }
+            _ => Ok(self.lpop(key, None)?),
+        }
+    }
+
+    /// The payload is sent in chunks of [`PAYLOAD_CHUNK_SIZE`] bytes.
+    fn put_payload(&mut self, id: &[u8], data: &[u8]) -> SUResult<()> {
+        let mut chunks = data.chunks(PAYLOAD_CHUNK_SIZE);
+        let first = chunks.next().unwrap_or_default();
+        let created: bool = redis::cmd("SET").arg(id).arg(first).arg("NX").query(self)?;
+        if !created {
+            // never overwrite an existing payload
+            return Ok(());
+        }
+        chunks.try_for_each(|chunk| self.append(id, chunk).map(|_: usize| ()))?;
+        Ok(())
+    }
+
+    fn take_payload(&mut self, id: &[u8]) -> SUResult<Option<Bytes>> {
+        let data: Option<Vec<u8>> = self.get_del(id)?;
+        Ok(data.map(Bytes::from))
+    }
+
+    fn payload_len(&mut self, id: &[u8]) -> SUResult<usize> {
+        Ok(self.strlen(id)?)
+    }
+
+    fn drop_payload(&mut self, id: &[u8]) -> SUResult<()> {
+        Ok(self.del(id)?)
+    }
+
+    fn flush_all(&mut self) -> SUResult<()> {
+        Ok(redis::cmd("FLUSHALL").query(self)?)
+    }
+}
+
+/// An in-process [`MessageTransport`], which runs the whole cluster without a redis server.
+///
+/// All the clones share the same queues and payloads,
+/// so each of the coordinator and the workers holds a clone as its connection.
+#[derive(Debug, Clone, Default)]
+pub struct InProcessTransport {
+    inner: Arc<(Mutex<InProcessState>, Condvar)>,
+}
+
+#[derive(Debug, Default)]
+struct InProcessState {
+    queues: HashMap<String, VecDeque<Vec<u8>>>,
+    payloads: HashMap<Vec<u8>, Bytes>,
+}
+
+impl InProcessTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn pop_front(state: &mut InProcessState, key: &str) -> Option<Vec<u8>> {
+        state.queues.get_mut(key).and_then(VecDeque::pop_front)
+    }
+}
+
+impl MessageTransport for InProcessTransport {
+    fn push(&mut self, key: &str, msg: &[u8]) -> SUResult<()> {
+        let (state, cond) = self.inner.as_ref();
+        state
+            .lock()
+            .unwrap()
+            .queues
+            .entry(key.to_owned())
+            .or_default()
+            .push_back(msg.to_vec());
+        cond.notify_all();
+        Ok(())
+    }
+
+    fn fetch(&mut self, key: &str) -> SUResult<Vec<u8>> {
+        let (state, cond) = self.inner.as_ref();
+        let mut state = state.lock().unwrap();
+        loop {
+            if let Some(msg) = Self::pop_front(&mut state, key) {
+                return Ok(msg);
+            }
+            state = cond.wait(state).unwrap();
+        }
+    }
+
+    fn fetch_timeout(&mut self, key: &str, timeout: Option<Duration>) -> SUResult<Option<Vec<u8>>> {
+        let (state, cond) = self.inner.as_ref();
+        let mut state = state.lock().unwrap();
+        if let Some(timeout) = timeout {
+            state = cond
+                .wait_timeout_while(state, timeout, |state| {
+                    state.queues.get(key).map_or(true, VecDeque::is_empty)
+                })
+                .unwrap()
+                .0;
+        }
+        Ok(Self::pop_front(&mut state, key))
+    }
+
+    fn put_payload(&mut self, id: &[u8], data: &[u8]) -> SUResult<()> {
+        let (state, _) = self.inner.as_ref();
+        state
+            .lock()
+            .unwrap()
+            .payloads
+            .entry(id.to_vec())
+            .or_insert_with(|| Bytes::copy_from_slice(data));
+        Ok(())
+    }
+
+    fn take_payload(&mut self, id: &[u8]) -> SUResult<Option<Bytes>> {
+        let (state, _) = self.inner.as_ref();
+        Ok(state.lock().unwrap().payloads.remove(id))
+    }
+
+    fn payload_len(&mut self, id: &[u8]) -> SUResult<usize> {
+        let (state, _) = self.inner.as_ref();
+        Ok(state
+            .lock()
+            .unwrap()
+            .payloads
+            .get(id)
+            .map_or(0, |payload| payload.len()))
+    }
+
+    fn drop_payload(&mut self, id: &[u8]) -> SUResult<()> {
+        self.take_payload(id).map(|_| ())
+    }
+
+    fn flush_all(&mut self) -> SUResult<()> {
+        let (state, _) = self.inner.as_ref();
+        let mut state = state.lock().unwrap();
+        state.queues.clear();
+        state.payloads.clear();
+        Ok(())
+    }
+}
+
+/// Parse the redis connection info from `url`.
+/// If `password` is [`Some`], it overrides the password in the url.
+///
+/// A `rediss://` url connects over TLS, which requires the `tls` feature.
+pub(super) fn redis_connection_info(
+    url: impl redis::IntoConnectionInfo,
+    password: Option<String>,
+) -> SUResult<redis::ConnectionInfo> {
+    let mut info = url.into_connection_info()?;
+    if password.is_some() {
+        info.redis.password = password;
+    }
+    Ok(info)
+}
+
+/// Connect to the in-process transport if set, otherwise to the redis server.
+pub(super) fn connect(
+    in_process: Option<&InProcessTransport>,
+    client: Option<&redis::Client>,
+) -> SUResult<Box<dyn MessageTransport + Send>> {
+    match (in_process, client) {
+        (Some(transport), _) => Ok(Box::new(transport.clone())),
+        (None, Some(client)) => Ok(Box::new(client.get_connection()?)),
+        (None, None) => Err(crate::SUError::Other(
+            "neither redis client nor in-process transport is set".into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{redis_connection_info, InProcessTransport, MessageTransport};
+
+    #[test]
+    fn in_process() {
+        let mut producer = InProcessTransport::new();
+        let mut consumer = producer.clone();
+        assert!(consumer.fetch_timeout("q", None).unwrap().is_none());
+        assert!(consumer
+            .fetch_timeout("q", Some(Duration::from_millis(10)))
+            .unwrap()
+            .is_none());
+        producer.push("q", b"1").unwrap();
+        producer.push("q", b"2").unwrap();
+        producer.push("r", b"3").unwrap();
+        assert_eq!(consumer.fetch("q").unwrap(), b"1");
+        assert_eq!(consumer.fetch_timeout("q", None).unwrap().unwrap(), b"2");
+        assert_eq!(consumer.fetch("r").unwrap(), b"3");
+
+        let handle = std::thread::spawn(move || consumer.fetch("q").unwrap());
+        std::thread::sleep(Duration::from_millis(10));
+        producer.push("q", b"4").unwrap();
+        assert_eq!(handle.join().unwrap(), b"4");
+
+        producer.put_payload(b"id", b"data").unwrap();
+        producer.put_payload(b"id", b"overwrite").unwrap();
+        assert_eq!(producer.take_payload(b"id").unwrap().unwrap(), &b"data"[..]);
+        assert!(producer.take_payload(b"id").unwrap().is_none());
+
+        producer.push("q", b"5").unwrap();
+        producer.put_payload(b"id", b"data").unwrap();
+        producer.flush_all().unwrap();
+        assert!(producer.fetch_timeout("q", None).unwrap().is_none());
+        assert!(producer.take_payload(b"id").unwrap().is_none());
+    }
+
+    #[test]
+    fn redis_password() {
+        let info = redis_connection_info("redis://:url-pw@127.0.0.1/2", None).unwrap();
+        assert_eq!(info.redis.password.as_deref(), Some("url-pw"));
+        assert_eq!(info.redis.db, 2);
+        let info = redis_connection_info("redis://:url-pw@127.0.0.1", Some("pw".into())).unwrap();
+        assert_eq!(info.redis.password.as_deref(), Some("pw"));
+        assert_eq!(
+            info.addr,
+            redis::ConnectionAddr::Tcp("127.0.0.1".into(), 6379)
+        );
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn rediss_with_auth() {
+        let info = redis_connection_info(
+            "rediss://user@redis.example.com:6380/1",
+            Some("secret".into()),
+        )
+        .unwrap();
+        assert_eq!(
+            info.addr,
+            redis::ConnectionAddr::TcpTls {
+                host: "redis.example.com".into(),
+                port: 6380,
+                insecure: false,
+                tls_params: None,
+            }
+        );
+        assert_eq!(info.redis.username.as_deref(), Some("user"));
+        assert_eq!(info.redis.password.as_deref(), Some("secret"));
+        assert_eq!(info.redis.db, 1);
+    }
+}
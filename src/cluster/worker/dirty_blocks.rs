@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+
+use crate::{storage::BlockId, SUResult};
+
+/// Directory under the hdd dev root marking the dirty blocks,
+/// which is skipped when iterating the blocks since its name is not a block id.
+const DIRTY_DIR: &str = "dirty-block";
+
+/// The blocks persisted to the hdd store by the worker on its own,
+/// e.g., evicted from the slice buffer, whose parity blocks are not updated yet.
+///
+/// Each dirty block is marked by an empty file named by the block id,
+/// so that the marks survive a restart of the worker.
+#[derive(Debug, Clone)]
+pub(super) struct DirtyBlocks {
+    dir: PathBuf,
+}
+
+impl DirtyBlocks {
+    /// Open the dirty marks stored in the hdd dev.
+    /// The directory is not created until a block is marked.
+    pub fn open(hdd_dev: impl AsRef<Path>) -> Self {
+        Self {
+            dir: hdd_dev.as_ref().join(DIRTY_DIR),
+        }
+    }
+
+    fn path(&self, block_id: BlockId) -> PathBuf {
+        self.dir.join(format!("{block_id:x}"))
+    }
+
+    /// Mark a block dirty.
+    pub fn mark(&self, block_id: BlockId) -> SUResult<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.path(block_id), [])?;
+        Ok(())
+    }
+
+    /// Clear the mark of a block.
+    ///
+    /// # Return
+    /// - [`Ok(true)`] if the block was marked dirty
+    /// - [`Ok(false)`] if the block was not marked
+    pub fn clear(&self, block_id: BlockId) -> SUResult<bool> {
+        match std::fs::remove_file(self.path(block_id)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use crate::storage::HDDStorage;
+
+    use super::DirtyBlocks;
+
+    #[test]
+    fn mark_clear() {
+        let hdd_dev = tempfile::tempdir().unwrap();
+        let dirty = DirtyBlocks::open(hdd_dev.path());
+        assert!(!dirty.clear(1).unwrap());
+        dirty.mark(1).unwrap();
+        dirty.mark(0x1f).unwrap();
+        dirty.mark(1).unwrap();
+        // the marks are kept by the dev
+        let dirty = DirtyBlocks::open(hdd_dev.path());
+        assert!(dirty.clear(1).unwrap());
+        assert!(!dirty.clear(1).unwrap());
+        assert!(dirty.clear(0x1f).unwrap());
+        // the marks are not taken as blocks
+        dirty.mark(2).unwrap();
+        let hdd_store =
+            HDDStorage::connect_to_dev(hdd_dev.path(), NonZeroUsize::new(4 << 10).unwrap())
+                .unwrap();
+        assert_eq!(hdd_store.iter_block_ids().unwrap().count(), 0);
+    }
+}
@@ -1,3 +1,5 @@
+mod dirty_blocks;
+
 use std::{
     num::NonZeroUsize,
     path::PathBuf,
@@ -9,8 +11,9 @@ use bytes::{Bytes, BytesMut};
 use crate::{
     cluster::dev_display,
     storage::{
-        BlockId, BlockStorage, EvictStrategySlice, FixedSizeSliceBuf, HDDStorage, NonEvict,
-        SliceBuffer, SliceStorage,
+        BlockId, BlockStorage, BufferEviction, EvictStrategySlice, FixedSizeSliceBuf, HDDStorage,
+        LruBlockEvict, MostModifiedBlockEvict, MostModifiedStripeEvict, NonEvict, SliceBuffer,
+        SliceOpt, SliceStorage,
     },
     SUError, SUResult,
 };
@@ -19,20 +22,52 @@ use super::{
     format_request_queue_key, format_response_queue_key,
     messages::{
         coordinator_request::{Head as RequestHead, Request},
-        worker_response::Response,
-        TaskID,
+        worker_response::{Ack, Nak, Response},
+        PayloadData, TaskID,
     },
+    transport::{InProcessTransport, MessageTransport},
     Ranges, WorkerID,
 };
 
+use dirty_blocks::DirtyBlocks;
+
+/// The eviction strategy of the worker's slice buffer.
+///
+/// The coordinator centralizes eviction decisions, so [`EvictKind::NonEvict`] is the default.
+/// Other strategies let the worker evict buffered slices on its own,
+/// and the evicted slices are persisted to the hdd store directly.
+/// Such a block is marked dirty until the coordinator persists it,
+/// which then re-encodes the parity of its stripe.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EvictKind {
+    /// Never evict, all the slices are persisted on request of the coordinator.
+    #[default]
+    NonEvict,
+    /// Evict the block with the most modified slices, see [`MostModifiedBlockEvict`].
+    MostModifiedBlock,
+    /// Evict the stripe with the most modified slices, see [`MostModifiedStripeEvict`].
+    MostModifiedStripe {
+        /// number of blocks in a stripe, i.e., `k + p`
+        stripe_m: NonZeroUsize,
+    },
+    /// Evict the least recently updated block, see [`LruBlockEvict`].
+    Lru,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct WorkerBuilder {
     id: Option<WorkerID>,
-    client: Option<redis::Client>,
+    redis_info: Option<redis::ConnectionInfo>,
+    redis_password: Option<String>,
+    in_process: Option<InProcessTransport>,
     queue_key: Option<(String, String)>,
     hdd_dev_path: Option<PathBuf>,
     ssd_dev_path: Option<PathBuf>,
     block_size: Option<NonZeroUsize>,
+    evict_kind: EvictKind,
+    buf_capacity: Option<NonZeroUsize>,
+    discard_on_shutdown: bool,
+    max_payload_bytes: Option<NonZeroUsize>,
 }
 
 impl WorkerBuilder {
@@ -45,8 +80,22 @@ impl WorkerBuilder {
         self
     }
 
+    /// Set the url of redis, a `rediss://` url connects over TLS.
     pub fn client(&mut self, url: impl redis::IntoConnectionInfo) -> &mut Self {
-        self.client = Some(redis::Client::open(url).expect("invalid redis url"));
+        self.redis_info = Some(url.into_connection_info().expect("invalid redis url"));
+        self
+    }
+
+    /// Set the password of redis, which overrides the password in the url if [`Some`].
+    pub fn redis_password(&mut self, password: Option<String>) -> &mut Self {
+        self.redis_password = password;
+        self
+    }
+
+    /// Talk to the coordinator over an in-process transport instead of redis.
+    /// The redis client is not required if this is set.
+    pub fn in_process_transport(&mut self, transport: InProcessTransport) -> &mut Self {
+        self.in_process = Some(transport);
         self
     }
 
@@ -65,6 +114,34 @@ impl WorkerBuilder {
         self
     }
 
+    /// Set the eviction strategy of the slice buffer, [`EvictKind::NonEvict`] by default.
+    /// A capacity must be set by [`WorkerBuilder::buf_capacity`] for any other strategy.
+    pub fn evict_strategy(&mut self, kind: EvictKind) -> &mut Self {
+        self.evict_kind = kind;
+        self
+    }
+
+    /// Set the maximum slice size the slice buffer can hold before eviction.
+    /// This is ignored if the eviction strategy is [`EvictKind::NonEvict`].
+    pub fn buf_capacity(&mut self, capacity: NonZeroUsize) -> &mut Self {
+        self.buf_capacity = Some(capacity);
+        self
+    }
+
+    /// Set whether the buffered updates are flushed to the hdd store on shutdown, `true` by default.
+    /// If `false`, the buffered updates are discarded on shutdown.
+    pub fn flush_on_shutdown(&mut self, flush: bool) -> &mut Self {
+        self.discard_on_shutdown = !flush;
+        self
+    }
+
+    /// Set the maximum size of a request payload, unlimited by default.
+    /// A request with a larger payload is rejected with a NAK, and its payload is never fetched.
+    pub fn max_payload_bytes(&mut self, size: NonZeroUsize) -> &mut Self {
+        self.max_payload_bytes = Some(size);
+        self
+    }
+
     pub fn work(&self) -> SUResult<()> {
         Worker::try_from(self.to_owned())?.work()
     }
@@ -72,45 +149,108 @@ impl WorkerBuilder {
 
 struct Worker {
     id: WorkerID,
-    client: redis::Client,
+    client: Option<redis::Client>,
+    in_process: Option<InProcessTransport>,
     request_queue_key: String,
     response_queue_key: String,
     ssd_dev_path: PathBuf,
     hdd_dev_path: PathBuf,
     block_size: usize,
+    evict_kind: EvictKind,
+    buf_capacity: Option<NonZeroUsize>,
+    flush_on_shutdown: bool,
+    max_payload_bytes: Option<usize>,
 }
 
 impl Worker {
     fn work(self) -> SUResult<()> {
+        let block_size = NonZeroUsize::new(self.block_size).unwrap();
+        let ssd_dev_path = self.ssd_dev_path.clone();
+        match (self.evict_kind, self.buf_capacity) {
+            (EvictKind::NonEvict, _) => {
+                self.work_with_buf(FixedSizeSliceBuf::connect_to_dev_with_evict(
+                    ssd_dev_path,
+                    block_size,
+                    NonEvict::default(),
+                )?)
+            }
+            (EvictKind::MostModifiedBlock, Some(capacity)) => {
+                self.work_with_buf(FixedSizeSliceBuf::connect_to_dev_with_evict(
+                    ssd_dev_path,
+                    block_size,
+                    MostModifiedBlockEvict::with_max_size(capacity),
+                )?)
+            }
+            (EvictKind::MostModifiedStripe { stripe_m }, Some(capacity)) => {
+                self.work_with_buf(FixedSizeSliceBuf::connect_to_dev_with_evict(
+                    ssd_dev_path,
+                    block_size,
+                    MostModifiedStripeEvict::new(stripe_m, capacity),
+                )?)
+            }
+            (EvictKind::Lru, Some(capacity)) => {
+                self.work_with_buf(FixedSizeSliceBuf::connect_to_dev_with_evict(
+                    ssd_dev_path,
+                    block_size,
+                    LruBlockEvict::with_max_size(capacity),
+                )?)
+            }
+            (kind, None) => Err(SUError::Other(format!(
+                "buffer capacity not set for evict strategy {kind:?}"
+            ))),
+        }
+    }
+
+    fn work_with_buf<E>(self, slice_buf: FixedSizeSliceBuf<E>) -> SUResult<()>
+    where
+        E: EvictStrategySlice + Send + 'static,
+    {
         const CH_SIZE: usize = 16;
         const GET_CONNECTION_ERR_STR: &str = "fail to get redis connection";
-        let recv_conn = self.client.get_connection().expect(GET_CONNECTION_ERR_STR);
-        let send_conn = self.client.get_connection().expect(GET_CONNECTION_ERR_STR);
+        let connect = || {
+            super::transport::connect(self.in_process.as_ref(), self.client.as_ref())
+                .expect(GET_CONNECTION_ERR_STR)
+        };
+        let recv_conn = connect();
+        let send_conn = connect();
         let hdd_dev = HDDStorage::connect_to_dev(
             &self.hdd_dev_path,
             NonZeroUsize::new(self.block_size).unwrap(),
         )?;
-        let slice_buf = FixedSizeSliceBuf::connect_to_dev_with_evict(
-            &self.ssd_dev_path,
-            NonZeroUsize::new(self.block_size).unwrap(),
-            NonEvict::default(),
-        )
-        .unwrap();
         let (request_send, request_recv) = std::sync::mpsc::sync_channel(CH_SIZE);
         let (response_send, response_recv) = std::sync::mpsc::sync_channel(CH_SIZE);
-        println!("worker id: {}", self.id.0);
-        println!("ssd device path: {}", dev_display(&self.ssd_dev_path));
-        println!("hdd device path: {}", dev_display(&self.hdd_dev_path));
-        println!("request queue key: {}", self.request_queue_key);
-        println!("response queue key: {}", self.response_queue_key);
-        println!("block size: {}", self.block_size);
-        println!("start working...");
+        log::info!("worker id: {}", self.id.0);
+        log::info!("ssd device path: {}", dev_display(&self.ssd_dev_path));
+        log::info!("hdd device path: {}", dev_display(&self.hdd_dev_path));
+        log::info!("request queue key: {}", self.request_queue_key);
+        log::info!("response queue key: {}", self.response_queue_key);
+        log::info!("block size: {}", self.block_size);
+        log::info!("evict strategy: {:?}", self.evict_kind);
+        log::info!("flush on shutdown: {}", self.flush_on_shutdown);
+        if let Some(max_payload_bytes) = self.max_payload_bytes {
+            log::info!("max payload size: {max_payload_bytes}");
+        }
+        log::info!("start working...");
 
+        let reject_send = response_send.clone();
         let recv_handle = std::thread::spawn(move || {
-            receiver_thread_handle(recv_conn, self.request_queue_key, request_send)
+            receiver_thread_handle(
+                recv_conn,
+                self.request_queue_key,
+                self.max_payload_bytes,
+                request_send,
+                reject_send,
+            )
         });
         let work_handle = std::thread::spawn(move || {
-            worker_thread_handle(self.id, request_recv, response_send, hdd_dev, slice_buf)
+            worker_thread_handle(
+                self.id,
+                request_recv,
+                response_send,
+                hdd_dev,
+                slice_buf,
+                self.flush_on_shutdown,
+            )
         });
         let send_handle = std::thread::spawn(move || {
             sender_thread_handle(send_conn, self.response_queue_key, response_recv)
@@ -134,9 +274,14 @@ impl TryFrom<WorkerBuilder> for Worker {
             id: value
                 .id
                 .ok_or_else(|| SUError::Other("worker id not set".into()))?,
-            client: value
-                .client
-                .ok_or_else(|| SUError::Other("redis client not set".into()))?,
+            client: match (&value.in_process, value.redis_info) {
+                (None, None) => return Err(SUError::Other("redis client not set".into())),
+                (_, Some(info)) => Some(redis::Client::open(
+                    super::transport::redis_connection_info(info, value.redis_password)?,
+                )?),
+                (Some(_), None) => None,
+            },
+            in_process: value.in_process,
             request_queue_key: request,
             response_queue_key: response,
             ssd_dev_path: value
@@ -149,18 +294,69 @@ impl TryFrom<WorkerBuilder> for Worker {
                 .block_size
                 .ok_or_else(|| SUError::Other("block size not set".into()))?
                 .get(),
+            evict_kind: value.evict_kind,
+            buf_capacity: match value.evict_kind {
+                EvictKind::NonEvict => value.buf_capacity,
+                _ => Some(value.buf_capacity.ok_or_else(|| {
+                    SUError::Other("buffer capacity not set for an evicting strategy".into())
+                })?),
+            },
+            flush_on_shutdown: !value.discard_on_shutdown,
+            max_payload_bytes: value.max_payload_bytes.map(NonZeroUsize::get),
         })
     }
 }
 
+/// Histogram of the sizes of the request payloads fetched by the workers.
+#[cfg(feature = "metrics")]
+const PAYLOAD_BYTES: &str = "su_worker_payload_bytes";
+
+/// Record the size of a request payload fetched,
+/// through the [`metrics`](::metrics) facade if the `metrics` feature is enabled.
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+#[inline]
+fn record_payload_bytes(bytes: usize) {
+    #[cfg(feature = "metrics")]
+    ::metrics::histogram!(PAYLOAD_BYTES).record(bytes as f64);
+}
+
+/// Fetch the requests and pass them to the worker thread.
+///
+/// A request whose payload is larger than `max_payload_bytes`, or fails to be fetched,
+/// is answered with a NAK through `reject_ch` right away, and never reaches the worker thread.
+/// A message that fails to be opened, e.g., corrupted or of another protocol version,
+/// is logged and dropped, as its task id is unknown to answer it.
 fn receiver_thread_handle(
-    mut conn: redis::Connection,
+    mut conn: Box<dyn MessageTransport + Send>,
     key: String,
+    max_payload_bytes: Option<usize>,
     ch: SyncSender<Request>,
+    reject_ch: SyncSender<Response>,
 ) -> SUResult<()> {
     let mut shutdown = false;
     while !shutdown {
-        let request = Request::fetch_from_redis(&mut conn, &key)?;
+        let envelope = conn.fetch(&key)?;
+        let request = match Request::open(&envelope) {
+            Ok(request) => request,
+            Err(e) => {
+                log::error!("message of {} bytes dropped: {e}", envelope.len());
+                continue;
+            }
+        };
+        let task_id = request.id;
+        let request = match request.fetch_payload(conn.as_mut(), max_payload_bytes) {
+            Ok(request) => request,
+            Err(e) => {
+                log::error!("task {task_id} rejected: {e}");
+                reject_ch
+                    .send(Response::nak(task_id, e))
+                    .expect("bad mpsc: all the consumers are disconnected");
+                continue;
+            }
+        };
+        if !request.payload.is_empty() {
+            record_payload_bytes(request.payload.len());
+        }
         shutdown = matches!(&request.head, RequestHead::Shutdown);
         ch.send(request)
             .expect("bad mpsc: all the consumers are disconnected");
@@ -169,29 +365,56 @@ fn receiver_thread_handle(
 }
 
 fn sender_thread_handle(
-    mut conn: redis::Connection,
+    mut conn: Box<dyn MessageTransport + Send>,
     key: String,
     ch: Receiver<Response>,
 ) -> SUResult<()> {
     while let Ok(response) = ch.recv() {
-        response.push_to_redis(&mut conn, &key)?;
+        response.push_to_redis(conn.as_mut(), &key)?;
     }
     Ok(())
 }
 
+/// Number of the recently acked mutations remembered by the worker to detect replays.
+const RECENT_TASK_NUM: usize = 1024;
+
+/// Apply the requests one by one.
+///
+/// The acks of the recent mutations are cached by their task ids.
+/// A replayed mutation, e.g., retried by the coordinator after its ack is lost,
+/// is answered with the cached ack instead of being applied again.
+/// A NAKed request is not cached, so that it is applied if retried.
+/// Neither is an ack carrying a payload, to keep the cache from holding blocks of data,
+/// so a replayed persist is applied again and NAKed as the block is no longer buffered.
 fn worker_thread_handle(
     worker_id: WorkerID,
     recv_ch: Receiver<Request>,
     send_ch: SyncSender<Response>,
     mut hdd_store: HDDStorage,
-    mut ssd_buf: FixedSizeSliceBuf<NonEvict>,
+    mut ssd_buf: FixedSizeSliceBuf<impl EvictStrategySlice>,
+    flush_on_shutdown: bool,
 ) -> SUResult<()> {
+    let dirty = DirtyBlocks::open(hdd_store.get_dev_root());
+    let mut recent_acks: lru::LruCache<TaskID, Ack> =
+        lru::LruCache::new(NonZeroUsize::new(RECENT_TASK_NUM).unwrap());
     while let Ok(Request {
         id: task_id,
         head,
         payload,
     }) = recv_ch.recv()
     {
+        if let Some(ack) = recent_acks.get(&task_id) {
+            log::debug!("task {task_id} replayed, answered with the cached ack");
+            let response = Response {
+                id: task_id,
+                head: Ok(ack.clone()),
+                payload: PayloadData::default(),
+            };
+            send_ch.send(response).unwrap();
+            continue;
+        }
+        log::debug!("task {task_id}: {head:?}");
+        let is_mutation = head.is_mutation();
         let response = match head {
             RequestHead::StoreBlock { id, .. } => {
                 do_store_block(task_id, &mut hdd_store, id, payload.unwrap())
@@ -200,19 +423,42 @@ fn worker_thread_handle(
                 do_retrieve_data(task_id, &mut hdd_store, id, ranges)
             }
             RequestHead::PersistUpdate { id } => {
-                do_persist_update(task_id, &mut hdd_store, &mut ssd_buf, id)
-            }
-            RequestHead::BufferUpdateData { id, ranges, .. } => {
-                do_buffer_update_data(task_id, &mut ssd_buf, id, ranges, payload.unwrap())
+                do_persist_update(task_id, &mut hdd_store, &mut ssd_buf, &dirty, id)
             }
+            RequestHead::BufferUpdateData { id, ranges, .. } => do_buffer_update_data(
+                task_id,
+                &mut hdd_store,
+                &mut ssd_buf,
+                &dirty,
+                id,
+                ranges,
+                payload.unwrap(),
+            ),
             RequestHead::UpdateParity { id, ranges, .. } => {
                 do_update_parity(task_id, &mut hdd_store, id, ranges, payload.unwrap())
             }
             RequestHead::FlushBuf => do_flush_buf(task_id, worker_id, &mut ssd_buf),
             RequestHead::DropStore => do_drop_store(task_id, worker_id, &mut hdd_store),
             RequestHead::HeartBeat => do_heartbeat(task_id, worker_id),
-            RequestHead::Shutdown => do_shutdown(task_id, worker_id),
+            RequestHead::Shutdown => do_shutdown(
+                task_id,
+                worker_id,
+                &mut hdd_store,
+                &mut ssd_buf,
+                &dirty,
+                flush_on_shutdown,
+            ),
+            RequestHead::Stat => do_stat(task_id, worker_id, &hdd_store, &ssd_buf),
         }?;
+        if let Err(Nak(e)) = &response.head {
+            log::error!("task {task_id} failed: {e}");
+        }
+        match &response.head {
+            Ok(ack) if is_mutation && !ack.has_payload() => {
+                recent_acks.put(task_id, ack.clone());
+            }
+            _ => (),
+        }
         send_ch.send(response).unwrap();
     }
     Ok(())
@@ -247,11 +493,11 @@ fn do_retrieve_data(
             Ok(None) => {
                 return Ok(Response::nak(
                     task_id,
-                    format!("block {block_id} not found"),
+                    SUError::not_found(format!("block {block_id} not found")),
                 ));
             }
-            Err(SUError::Range(range_err)) => {
-                return Ok(Response::nak(task_id, format!("range error: {range_err}")));
+            Err(e @ SUError::Range(_)) => {
+                return Ok(Response::nak(task_id, e));
             }
             Err(e) => {
                 return Err(e);
@@ -261,23 +507,32 @@ fn do_retrieve_data(
     Ok(Response::retrieve_slice(task_id, data.freeze()))
 }
 
+/// Persist the buffered slices of a block, and clear its dirty mark.
+///
+/// A dirty block with nothing buffered is acked with no slices,
+/// as it is persisted by the worker before, and only its parity is left to update.
 fn do_persist_update(
     task_id: TaskID,
     hdd_store: &mut HDDStorage,
     ssd_buf: &mut FixedSizeSliceBuf<impl EvictStrategySlice>,
+    dirty: &DirtyBlocks,
     block_id: BlockId,
 ) -> SUResult<Response> {
     let response = ssd_buf.pop_one(block_id);
     if response.is_none() {
-        return Ok(Response::nak(
-            task_id,
-            format!("block {block_id} not found"),
-        ));
+        return Ok(match dirty.clear(block_id) {
+            Ok(true) => Response::persist_update(task_id, Ranges::empty(), Bytes::new()),
+            Ok(false) => Response::nak(
+                task_id,
+                SUError::not_found(format!("block {block_id} not found")),
+            ),
+            Err(e) => Response::nak(task_id, e),
+        });
     }
     let eviction = response.unwrap();
     let mut ranges = Ranges::empty();
     let mut cursor = 0;
-    let result = eviction
+    let slices = eviction
         .data
         .slices
         .into_iter()
@@ -288,54 +543,118 @@ fn do_persist_update(
                     .0
                     .intersection_with(&range_collections::RangeSet2::from(range.clone()));
                 cursor += data.len();
-                Some((data, range))
+                Some((range.start, data))
             }
             crate::storage::SliceOpt::Absent(size) => {
                 cursor += size;
                 None
             }
         })
-        .map(|(data, range)| {
-            hdd_store
-                .put_slice(block_id, range.start, &data)
-                .map_err(|e| Response::nak(task_id, format!("fail to persist updates: {e}")))
-                .and_then(|opt| {
-                    opt.map(|_| data).ok_or_else(|| {
-                        Response::nak(task_id, format!("block {block_id} not found"))
-                    })
-                })
+        .collect::<Vec<_>>();
+    let result = hdd_store
+        .put_slices(
+            block_id,
+            &slices
+                .iter()
+                .map(|(offset, data)| (*offset, data.as_ref()))
+                .collect::<Vec<_>>(),
+        )
+        .map_err(|e| Response::nak(task_id, e))
+        .and_then(|opt| {
+            opt.ok_or_else(|| {
+                Response::nak(
+                    task_id,
+                    SUError::not_found(format!("block {block_id} not found")),
+                )
+            })
+        })
+        .and_then(|()| {
+            dirty
+                .clear(block_id)
+                .map_err(|e| Response::nak(task_id, e))
         })
-        .collect::<Result<Vec<_>, Response>>()
-        .map(|bytes| /* WARNING: flatten may cause vec memory reallocation */ bytes.into_iter().flatten().collect::<Bytes>())
+        .map(|_| /* WARNING: flatten may cause vec memory reallocation */ slices.into_iter().flat_map(|(_, data)| data).collect::<Bytes>())
         .map(|data| Response::persist_update(task_id, ranges, data))
         .unwrap_or_else(std::convert::identity);
     Ok(result)
 }
 
+/// Buffer the slices of an update request.
+///
+/// The ranges are checked against the block size up front,
+/// so that a request with any range out of the block is NAKed with nothing buffered.
+/// The blocks evicted are persisted and marked dirty, see [`persist_eviction`].
 fn do_buffer_update_data(
     task_id: TaskID,
+    hdd_store: &mut HDDStorage,
     ssd_buf: &mut FixedSizeSliceBuf<impl EvictStrategySlice>,
+    dirty: &DirtyBlocks,
     block_id: BlockId,
     ranges: Ranges,
     data: Bytes,
 ) -> SUResult<Response> {
+    let ranges = ranges.to_ranges();
+    let block_size = hdd_store.block_size();
+    if let Some(range) = ranges.iter().find(|range| range.end > block_size) {
+        return Ok(Response::nak(
+            task_id,
+            SUError::out_of_range(
+                (file!(), line!(), column!()),
+                Some(0..block_size),
+                range.clone(),
+            ),
+        ));
+    }
+    let ranges_len = ranges.iter().map(ExactSizeIterator::len).sum::<usize>();
+    if ranges_len != data.len() {
+        return Ok(Response::nak(
+            task_id,
+            SUError::invalid_arg(format!(
+                "payload of {} bytes does not match the ranges of {ranges_len} bytes",
+                data.len()
+            )),
+        ));
+    }
     let mut cursor = 0;
-    for range in ranges.to_ranges().iter() {
+    let mut pushed = Ok(());
+    for range in ranges.iter() {
         let update_slice = &data[cursor..cursor + range.len()];
-        let result = ssd_buf.push_slice(block_id, range.start, update_slice);
         cursor += range.len();
-        match result {
-            Ok(Some(_)) => unreachable!("unexpected eviction"),
-            Ok(None) => (),
-            Err(SUError::Range(e)) => {
-                return Ok(Response::nak(task_id, format!("range error: {e}")));
-            }
-            Err(e) => {
-                return Err(e);
-            }
+        pushed = ssd_buf
+            .push_slice(block_id, range.start, update_slice)
+            .and_then(|eviction| match eviction {
+                Some(eviction) => persist_eviction(&eviction, hdd_store, dirty),
+                None => Ok(()),
+            });
+        if pushed.is_err() {
+            break;
+        }
+    }
+    match pushed {
+        Ok(()) => Ok(Response::buffer_update_data(task_id)),
+        Err(SUError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            Ok(Response::nak(task_id, SUError::Io(e)))
         }
+        Err(e) => Err(e),
     }
-    Ok(Response::buffer_update_data(task_id))
+}
+
+/// Persist an evicted block to the hdd store,
+/// and mark it dirty beforehand, as the parity of its stripe is not updated.
+///
+/// # Error
+/// - [`SUError::Io`] if the evicted block is not found in the hdd store
+/// - any error occurring on marking or persisting
+fn persist_eviction(
+    eviction: &BufferEviction,
+    hdd_store: &HDDStorage,
+    dirty: &DirtyBlocks,
+) -> SUResult<()> {
+    let evicted_id = eviction.block_id;
+    dirty.mark(evicted_id)?;
+    eviction
+        .persist_to(hdd_store)?
+        .ok_or_else(|| SUError::not_found(format!("evicted block {evicted_id} not found")))
 }
 
 fn do_update_parity(
@@ -353,10 +672,13 @@ fn do_update_parity(
         match result {
             Ok(Some(_)) => (),
             Ok(None) => {
-                return Ok(Response::nak(task_id, format!("block id {id} not found")));
+                return Ok(Response::nak(
+                    task_id,
+                    SUError::not_found(format!("block id {id} not found")),
+                ));
             }
-            Err(SUError::Range(e)) => {
-                return Ok(Response::nak(task_id, format!("range error: {e}")));
+            Err(e @ SUError::Range(_)) => {
+                return Ok(Response::nak(task_id, e));
             }
             Err(e) => return Err(e),
         }
@@ -372,7 +694,7 @@ fn do_flush_buf(
     Ok(ssd_buf
         .cleanup_dev()
         .map(|_| Response::flush_buf(task_id, worker_id))
-        .unwrap_or_else(|e| Response::nak(task_id, format!("fail to flush buffer: {e}"))))
+        .unwrap_or_else(|e| Response::nak(task_id, e)))
 }
 
 fn do_drop_store(
@@ -391,7 +713,7 @@ fn do_drop_store(
     let response = purge_dir(dev_path)
         .and_then(|_| std::fs::create_dir_all(dev_path).map_err(SUError::Io))
         .map(|_| Response::drop_store(task_id, worker_id))
-        .unwrap_or_else(|e| Response::nak(task_id, format!("fail to drop store: {e}")));
+        .unwrap_or_else(|e| Response::nak(task_id, e));
     Ok(response)
 }
 
@@ -399,6 +721,545 @@ fn do_heartbeat(task_id: TaskID, worker_id: WorkerID) -> SUResult<Response> {
     Ok(Response::heartbeat(task_id, worker_id))
 }
 
-fn do_shutdown(task_id: TaskID, worker_id: WorkerID) -> SUResult<Response> {
+fn do_stat(
+    task_id: TaskID,
+    worker_id: WorkerID,
+    hdd_store: &HDDStorage,
+    ssd_buf: &FixedSizeSliceBuf<impl EvictStrategySlice>,
+) -> SUResult<Response> {
+    Ok(hdd_store
+        .iter_block_ids()
+        .map(|ids| Response::stat(task_id, worker_id, ssd_buf.len(), ids.count()))
+        .unwrap_or_else(|e| Response::nak(task_id, e)))
+}
+
+fn do_shutdown(
+    task_id: TaskID,
+    worker_id: WorkerID,
+    hdd_store: &mut HDDStorage,
+    ssd_buf: &mut FixedSizeSliceBuf<impl EvictStrategySlice>,
+    dirty: &DirtyBlocks,
+    flush: bool,
+) -> SUResult<Response> {
+    if flush {
+        if let Err(e) = drain_slice_buf(hdd_store, ssd_buf, dirty) {
+            return Ok(Response::nak(task_id, e));
+        }
+    }
     Ok(Response::shutdown(task_id, worker_id))
 }
+
+/// Pop all the buffered updates and persist them to the hdd store,
+/// marking each block dirty as the parity of its stripe is not updated.
+/// If a block fails to persist, its slices are pushed back to the buffer,
+/// and the blocks not popped yet remain buffered.
+///
+/// # Return
+/// - [`Ok`] with the number of blocks persisted
+/// - [`Err`] if any block is not found in the hdd store, or any io error occurs
+fn drain_slice_buf(
+    hdd_store: &mut HDDStorage,
+    ssd_buf: &mut FixedSizeSliceBuf<impl EvictStrategySlice>,
+    dirty: &DirtyBlocks,
+) -> SUResult<usize> {
+    let mut count = 0;
+    while let Some(eviction) = ssd_buf.pop() {
+        if let Err(e) = persist_eviction(&eviction, hdd_store, dirty) {
+            restore_eviction(ssd_buf, &eviction)?;
+            return Err(e);
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Push the present slices of a popped eviction back to the slice buffer.
+/// The buffer has room for them as they have just been popped,
+/// so an eviction caused is reported as an error rather than dropped.
+fn restore_eviction(ssd_buf: &impl SliceBuffer, eviction: &BufferEviction) -> SUResult<()> {
+    let mut cursor = 0;
+    for slice in eviction.data.slices.iter() {
+        match slice {
+            SliceOpt::Present(data) => {
+                if let Some(evicted) = ssd_buf.push_slice(eviction.block_id, cursor, data)? {
+                    return Err(SUError::Other(format!(
+                        "block {} evicted on restoring block {}",
+                        evicted.block_id, eviction.block_id
+                    )));
+                }
+                cursor += data.len();
+            }
+            SliceOpt::Absent(size) => cursor += size,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use bytes::Bytes;
+    use range_collections::RangeSet2;
+
+    use crate::{
+        cluster::{
+            messages::{
+                coordinator_request::{Head as RequestHead, Request},
+                worker_response::{Ack, Nak},
+                TaskID,
+            },
+            transport::{InProcessTransport, MessageTransport},
+            Ranges, WorkerID,
+        },
+        storage::{
+            BlockStorage, FixedSizeSliceBuf, HDDStorage, MostModifiedBlockEvict, NonEvict,
+            SliceBuffer,
+        },
+        SUError,
+    };
+
+    use super::{DirtyBlocks, EvictKind, Worker, WorkerBuilder};
+
+    const SEG_SIZE: usize = 4 << 10;
+    const BLOCK_SIZE: NonZeroUsize = unsafe { NonZeroUsize::new_unchecked(SEG_SIZE * 4) };
+
+    fn setup() -> (tempfile::TempDir, HDDStorage, FixedSizeSliceBuf<NonEvict>) {
+        let tempdir = tempfile::tempdir().unwrap();
+        let hdd_path = tempdir.path().join("hdd");
+        let ssd_path = tempdir.path().join("ssd");
+        std::fs::create_dir_all(&hdd_path).unwrap();
+        std::fs::create_dir_all(&ssd_path).unwrap();
+        let hdd_store = HDDStorage::connect_to_dev(hdd_path, BLOCK_SIZE).unwrap();
+        let ssd_buf =
+            FixedSizeSliceBuf::connect_to_dev_with_evict(ssd_path, BLOCK_SIZE, NonEvict::default())
+                .unwrap();
+        (tempdir, hdd_store, ssd_buf)
+    }
+
+    #[test]
+    fn evict_capacity_required() {
+        let mut builder = WorkerBuilder::default();
+        builder
+            .id(1)
+            .in_process_transport(InProcessTransport::new())
+            .hdd_dev_path("hdd")
+            .ssd_dev_path("ssd")
+            .block_size(BLOCK_SIZE);
+        assert!(Worker::try_from(builder.clone()).is_ok());
+        builder.evict_strategy(EvictKind::Lru);
+        assert!(matches!(
+            Worker::try_from(builder.clone()),
+            Err(SUError::Other(_))
+        ));
+        builder.buf_capacity(BLOCK_SIZE);
+        assert!(Worker::try_from(builder).is_ok());
+    }
+
+    #[test]
+    fn persist_evicted_block() {
+        let (tempdir, mut hdd_store, _) = setup();
+        let mut ssd_buf = FixedSizeSliceBuf::connect_to_dev_with_evict(
+            tempdir.path().join("ssd"),
+            BLOCK_SIZE,
+            MostModifiedBlockEvict::with_max_size(NonZeroUsize::new(SEG_SIZE).unwrap()),
+        )
+        .unwrap();
+        let dirty = DirtyBlocks::open(hdd_store.get_dev_root());
+        let zero = vec![0_u8; BLOCK_SIZE.get()];
+        (0..2).for_each(|id| hdd_store.put_block(id, &zero).unwrap());
+        let mut buffer_update = |block_id| {
+            super::do_buffer_update_data(
+                TaskID::assign(),
+                &mut hdd_store,
+                &mut ssd_buf,
+                &dirty,
+                block_id,
+                Ranges(RangeSet2::from(0..SEG_SIZE)),
+                Bytes::from(vec![1; SEG_SIZE]),
+            )
+            .unwrap()
+        };
+        // block 0 is evicted by the update of block 1
+        assert!(matches!(buffer_update(0).head, Ok(Ack::BufferUpdateData)));
+        assert!(matches!(buffer_update(1).head, Ok(Ack::BufferUpdateData)));
+        assert!(ssd_buf.modified_ranges(0).is_none());
+        let mut block = zero.clone();
+        hdd_store.get_block(0, &mut block).unwrap().unwrap();
+        assert!(block[..SEG_SIZE].iter().all(|b| *b == 1));
+
+        // the evicted block is acked with no slices, so that its parity gets updated
+        let mut persist_update = |block_id| {
+            super::do_persist_update(
+                TaskID::assign(),
+                &mut hdd_store,
+                &mut ssd_buf,
+                &dirty,
+                block_id,
+            )
+            .unwrap()
+        };
+        let response = persist_update(0);
+        assert!(matches!(response.head, Ok(Ack::PersistUpdate { .. })));
+        assert!(response.payload.is_empty());
+        let response = persist_update(1);
+        assert!(matches!(response.head, Ok(Ack::PersistUpdate { .. })));
+        assert_eq!(response.payload.len(), SEG_SIZE);
+        // both are persisted and clean
+        assert!(persist_update(0).head.is_err());
+        assert!(persist_update(1).head.is_err());
+    }
+
+    #[test]
+    fn shutdown_flush() {
+        let (tempdir, mut hdd_store, mut ssd_buf) = setup();
+        let dirty = DirtyBlocks::open(tempdir.path());
+        let zero = vec![0_u8; BLOCK_SIZE.get()];
+        hdd_store.put_block(0, &zero).unwrap();
+        hdd_store.put_block(1, &zero).unwrap();
+        ssd_buf.push_slice(0, SEG_SIZE, &[1; SEG_SIZE]).unwrap();
+        ssd_buf.push_slice(1, 0, &[2; SEG_SIZE * 2]).unwrap();
+        ssd_buf.push_slice(1, SEG_SIZE * 3, &[3; SEG_SIZE]).unwrap();
+
+        let response = super::do_shutdown(
+            TaskID::assign(),
+            WorkerID(1),
+            &mut hdd_store,
+            &mut ssd_buf,
+            &dirty,
+            true,
+        )
+        .unwrap();
+        assert!(matches!(response.head, Ok(Ack::Shutdown { .. })));
+        assert!(ssd_buf.is_empty());
+
+        let mut block = vec![0_u8; BLOCK_SIZE.get()];
+        hdd_store.get_block(0, &mut block).unwrap().unwrap();
+        assert!(block[..SEG_SIZE].iter().all(|b| *b == 0));
+        assert!(block[SEG_SIZE..SEG_SIZE * 2].iter().all(|b| *b == 1));
+        assert!(block[SEG_SIZE * 2..].iter().all(|b| *b == 0));
+        hdd_store.get_block(1, &mut block).unwrap().unwrap();
+        assert!(block[..SEG_SIZE * 2].iter().all(|b| *b == 2));
+        assert!(block[SEG_SIZE * 2..SEG_SIZE * 3].iter().all(|b| *b == 0));
+        assert!(block[SEG_SIZE * 3..].iter().all(|b| *b == 3));
+        // the flushed blocks are left for the coordinator to re-encode
+        assert!(dirty.clear(0).unwrap());
+        assert!(dirty.clear(1).unwrap());
+    }
+
+    #[test]
+    fn shutdown_discard() {
+        let (tempdir, mut hdd_store, mut ssd_buf) = setup();
+        let dirty = DirtyBlocks::open(tempdir.path());
+        let zero = vec![0_u8; BLOCK_SIZE.get()];
+        hdd_store.put_block(0, &zero).unwrap();
+        ssd_buf.push_slice(0, 0, &[1; SEG_SIZE]).unwrap();
+
+        let response = super::do_shutdown(
+            TaskID::assign(),
+            WorkerID(1),
+            &mut hdd_store,
+            &mut ssd_buf,
+            &dirty,
+            false,
+        )
+        .unwrap();
+        assert!(matches!(response.head, Ok(Ack::Shutdown { .. })));
+        let mut block = vec![0_u8; BLOCK_SIZE.get()];
+        hdd_store.get_block(0, &mut block).unwrap().unwrap();
+        assert_eq!(block, zero);
+        assert!(!dirty.clear(0).unwrap());
+
+        // flushing a block missing in the hdd store is reported, with its slices kept buffered
+        ssd_buf.push_slice(2, SEG_SIZE, &[1; SEG_SIZE]).unwrap();
+        let response = super::do_shutdown(
+            TaskID::assign(),
+            WorkerID(1),
+            &mut hdd_store,
+            &mut ssd_buf,
+            &dirty,
+            true,
+        )
+        .unwrap();
+        assert!(response.head.is_err());
+        let eviction = ssd_buf.pop_one(2).expect("failed block dropped");
+        hdd_store.put_block(2, &zero).unwrap();
+        eviction.persist_to(&hdd_store).unwrap().unwrap();
+        hdd_store.get_block(2, &mut block).unwrap().unwrap();
+        assert!(block[..SEG_SIZE].iter().all(|b| *b == 0));
+        assert!(block[SEG_SIZE..SEG_SIZE * 2].iter().all(|b| *b == 1));
+        assert!(block[SEG_SIZE * 2..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn stat() {
+        let (_tempdir, hdd_store, ssd_buf) = setup();
+        let zero = vec![0_u8; BLOCK_SIZE.get()];
+        (0..3).for_each(|id| hdd_store.put_block(id, &zero).unwrap());
+        ssd_buf.push_slice(0, 0, &[1; SEG_SIZE]).unwrap();
+        ssd_buf.push_slice(2, SEG_SIZE, &[1; SEG_SIZE]).unwrap();
+
+        let response = super::do_stat(TaskID::assign(), WorkerID(1), &hdd_store, &ssd_buf).unwrap();
+        assert_eq!(
+            response.head.unwrap(),
+            Ack::Stat {
+                worker_id: WorkerID(1),
+                buffer_len: SEG_SIZE * 2,
+                block_num: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn buffer_update_invalid_range() {
+        let (_tempdir, mut hdd_store, mut ssd_buf) = setup();
+        let zero = vec![0_u8; BLOCK_SIZE.get()];
+        (0..2).for_each(|id| hdd_store.put_block(id, &zero).unwrap());
+        // the second of the three ranges is out of the block
+        let mut ranges = RangeSet2::from(0..SEG_SIZE);
+        ranges.union_with(&RangeSet2::from(
+            BLOCK_SIZE.get()..BLOCK_SIZE.get() + SEG_SIZE,
+        ));
+        ranges.union_with(&RangeSet2::from(
+            BLOCK_SIZE.get() + SEG_SIZE * 2..BLOCK_SIZE.get() + SEG_SIZE * 3,
+        ));
+        let ranges = Ranges(ranges);
+        let data = Bytes::from(vec![1_u8; ranges.len()]);
+        let dirty = DirtyBlocks::open(hdd_store.get_dev_root());
+        let buffer_update = |hdd_store: &mut HDDStorage, ssd_buf: &mut _, block_id| {
+            super::do_buffer_update_data(
+                TaskID::assign(),
+                hdd_store,
+                ssd_buf,
+                &dirty,
+                block_id,
+                ranges.clone(),
+                data.clone(),
+            )
+            .unwrap()
+        };
+
+        // none of the ranges is buffered
+        let response = buffer_update(&mut hdd_store, &mut ssd_buf, 0);
+        assert!(matches!(response.head, Err(Nak(SUError::Range(_)))));
+        assert!(ssd_buf.is_empty());
+
+        // the slices buffered before are kept
+        ssd_buf.push_slice(1, SEG_SIZE, &[2; SEG_SIZE]).unwrap();
+        let response = buffer_update(&mut hdd_store, &mut ssd_buf, 1);
+        assert!(matches!(response.head, Err(Nak(SUError::Range(_)))));
+        assert_eq!(
+            ssd_buf.modified_ranges(1).unwrap().to_ranges(),
+            vec![SEG_SIZE..SEG_SIZE * 2]
+        );
+        let eviction = ssd_buf.pop_one(1).unwrap();
+        assert!(ssd_buf.is_empty());
+        let mut block = zero.clone();
+        eviction.persist_to(&hdd_store).unwrap().unwrap();
+        hdd_store.get_block(1, &mut block).unwrap().unwrap();
+        assert!(block[..SEG_SIZE].iter().all(|b| *b == 0));
+        assert!(block[SEG_SIZE..SEG_SIZE * 2].iter().all(|b| *b == 2));
+        assert!(block[SEG_SIZE * 2..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn reject_oversized_payload() {
+        const KEY: &str = "reject-oversized-payload";
+        let mut conn = InProcessTransport::new();
+        let oversized = Request::store_block(0, Bytes::from(vec![0_u8; SEG_SIZE + 1]));
+        let fit = Request::store_block(1, Bytes::from(vec![0_u8; SEG_SIZE]));
+        [&oversized, &fit, &Request::shutdown()]
+            .into_iter()
+            .for_each(|request| request.push_to_redis(&mut conn, KEY).unwrap());
+
+        let (request_send, request_recv) = std::sync::mpsc::sync_channel(4);
+        let (response_send, response_recv) = std::sync::mpsc::sync_channel(4);
+        super::receiver_thread_handle(
+            Box::new(conn.clone()),
+            KEY.to_string(),
+            Some(SEG_SIZE),
+            request_send,
+            response_send,
+        )
+        .unwrap();
+
+        // the oversized request is answered with a NAK, and its payload is dropped
+        let response = response_recv.try_recv().unwrap();
+        assert_eq!(response.id, oversized.id);
+        assert!(matches!(response.head, Err(Nak(SUError::InvalidArg(_)))));
+        assert!(response_recv.try_recv().is_err());
+        if let RequestHead::StoreBlock { payload, .. } = oversized.head {
+            assert_eq!(conn.payload_len(payload.as_bytes()).unwrap(), 0);
+        }
+
+        // the others reach the worker thread with their payloads
+        let request = request_recv.try_recv().unwrap();
+        assert_eq!(request, fit);
+        assert_eq!(request.payload.len(), SEG_SIZE);
+        assert_eq!(request_recv.try_recv().unwrap().head, RequestHead::Shutdown);
+        assert!(request_recv.try_recv().is_err());
+    }
+
+    #[test]
+    fn drop_undecodable_message() {
+        const KEY: &str = "drop-undecodable-message";
+        let mut conn = InProcessTransport::new();
+        let lost = Request::store_block(0, Bytes::from(vec![0_u8; SEG_SIZE]));
+        let heartbeat = Request::heartbeat();
+        conn.push(KEY, b"garbage").unwrap();
+        [&lost, &heartbeat, &Request::shutdown()]
+            .into_iter()
+            .for_each(|request| request.push_to_redis(&mut conn, KEY).unwrap());
+        if let RequestHead::StoreBlock { payload, .. } = &lost.head {
+            conn.drop_payload(payload.as_bytes()).unwrap();
+        }
+
+        let (request_send, request_recv) = std::sync::mpsc::sync_channel(4);
+        let (response_send, response_recv) = std::sync::mpsc::sync_channel(4);
+        super::receiver_thread_handle(
+            Box::new(conn),
+            KEY.to_string(),
+            None,
+            request_send,
+            response_send,
+        )
+        .unwrap();
+
+        // the garbage is dropped, and the request of a lost payload is answered with a NAK
+        let response = response_recv.try_recv().unwrap();
+        assert_eq!(response.id, lost.id);
+        assert!(matches!(response.head, Err(Nak(SUError::Other(_)))));
+        assert!(response_recv.try_recv().is_err());
+        // the receiver goes on with the requests after them
+        assert_eq!(request_recv.try_recv().unwrap(), heartbeat);
+        assert_eq!(request_recv.try_recv().unwrap().head, RequestHead::Shutdown);
+        assert!(request_recv.try_recv().is_err());
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn payload_bytes_metric() {
+        use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+        const KEY: &str = "payload-bytes-metric";
+        let mut conn = InProcessTransport::new();
+        [
+            Request::store_block(0, Bytes::from(vec![0_u8; SEG_SIZE])),
+            Request::heartbeat(),
+            Request::store_block(1, Bytes::from(vec![0_u8; 2 * SEG_SIZE])),
+            Request::shutdown(),
+        ]
+        .iter()
+        .for_each(|request| request.push_to_redis(&mut conn, KEY).unwrap());
+
+        let (request_send, _request_recv) = std::sync::mpsc::sync_channel(4);
+        let (response_send, _response_recv) = std::sync::mpsc::sync_channel(4);
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, || {
+            super::receiver_thread_handle(
+                Box::new(conn),
+                KEY.to_string(),
+                None,
+                request_send,
+                response_send,
+            )
+            .unwrap()
+        });
+        let samples = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .find(|(key, ..)| key.key().name() == super::PAYLOAD_BYTES)
+            .map(|(.., value)| match value {
+                DebugValue::Histogram(samples) => samples,
+                _ => unreachable!("payload bytes is a histogram"),
+            })
+            .unwrap();
+        // the requests without payloads are not recorded
+        assert_eq!(
+            samples.iter().map(|v| v.into_inner()).collect::<Vec<_>>(),
+            [SEG_SIZE as f64, (2 * SEG_SIZE) as f64]
+        );
+    }
+
+    #[test]
+    fn replayed_mutation() {
+        let (tempdir, hdd_store, ssd_buf) = setup();
+        let zero = vec![0_u8; BLOCK_SIZE.get()];
+        hdd_store.put_block(0, &zero).unwrap();
+        let ranges = Ranges(RangeSet2::from(0..SEG_SIZE));
+        let update = Request::buffer_update_data(0, ranges.clone(), Bytes::from(vec![1; SEG_SIZE]));
+        let overwrite = Request::buffer_update_data(0, ranges, Bytes::from(vec![2; SEG_SIZE]));
+
+        let (request_send, request_recv) = std::sync::mpsc::sync_channel(4);
+        let (response_send, response_recv) = std::sync::mpsc::sync_channel(4);
+        // the update is replayed after being overwritten
+        [
+            update.clone(),
+            overwrite,
+            update.clone(),
+            Request::shutdown(),
+        ]
+        .into_iter()
+        .for_each(|request| request_send.send(request).unwrap());
+        drop(request_send);
+        super::worker_thread_handle(
+            WorkerID(1),
+            request_recv,
+            response_send,
+            hdd_store,
+            ssd_buf,
+            true,
+        )
+        .unwrap();
+
+        let responses = response_recv.into_iter().collect::<Vec<_>>();
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses[0].id, update.id);
+        assert_eq!(responses[2].id, update.id);
+        assert!(responses
+            .iter()
+            .take(3)
+            .all(|response| matches!(response.head, Ok(Ack::BufferUpdateData))));
+
+        // the replayed update is not applied again
+        let hdd_store = HDDStorage::connect_to_dev(tempdir.path().join("hdd"), BLOCK_SIZE).unwrap();
+        let mut block = vec![0_u8; BLOCK_SIZE.get()];
+        hdd_store.get_block(0, &mut block).unwrap().unwrap();
+        assert!(block[..SEG_SIZE].iter().all(|b| *b == 2));
+        assert!(block[SEG_SIZE..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn replayed_persist() {
+        let (_tempdir, hdd_store, ssd_buf) = setup();
+        let zero = vec![0_u8; BLOCK_SIZE.get()];
+        hdd_store.put_block(0, &zero).unwrap();
+        let ranges = Ranges(RangeSet2::from(0..SEG_SIZE));
+        let update = Request::buffer_update_data(0, ranges, Bytes::from(vec![1; SEG_SIZE]));
+        let persist = Request::persist_update(0);
+
+        let (request_send, request_recv) = std::sync::mpsc::sync_channel(4);
+        let (response_send, response_recv) = std::sync::mpsc::sync_channel(4);
+        [update, persist.clone(), persist, Request::shutdown()]
+            .into_iter()
+            .for_each(|request| request_send.send(request).unwrap());
+        drop(request_send);
+        super::worker_thread_handle(
+            WorkerID(1),
+            request_recv,
+            response_send,
+            hdd_store,
+            ssd_buf,
+            true,
+        )
+        .unwrap();
+
+        let responses = response_recv.into_iter().collect::<Vec<_>>();
+        assert_eq!(responses.len(), 4);
+        assert!(matches!(responses[1].head, Ok(Ack::PersistUpdate { .. })));
+        assert_eq!(responses[1].payload.len(), SEG_SIZE);
+        // the ack with the payload is not cached, the replayed persist is applied again
+        assert!(responses[2].head.is_err());
+    }
+}
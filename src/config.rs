@@ -2,6 +2,8 @@ use std::{io::Read, num::NonZeroUsize, sync::OnceLock};
 
 use bytesize::ByteSize;
 
+use crate::{SUError, SUResult};
+
 #[derive(serde::Deserialize, Debug, PartialEq, Eq)]
 #[serde(rename_all = "PascalCase")]
 struct Config {
@@ -10,9 +12,14 @@ struct Config {
     block_size: ByteSize,
     block_num: usize,
     ssd_block_capacity: usize,
+    ssd_low_watermark: Option<usize>,
     out_dir_path: std::path::PathBuf,
     test_num: usize,
+    warmup_num: Option<usize>,
     slice_size: ByteSize,
+    threads: Option<NonZeroUsize>,
+    ssd_op_latency_us: Option<u64>,
+    hdd_op_latency_us: Option<u64>,
     standalone: Option<StandaloneConfig>,
     cluster: Option<ClusterConfig>,
 }
@@ -28,10 +35,22 @@ struct StandaloneConfig {
 #[serde(rename_all = "PascalCase")]
 struct ClusterConfig {
     redis_url: String,
+    redis_password: Option<String>,
     worker_num: NonZeroUsize,
     workers: Vec<WorkerConfig>,
+    heartbeat_interval_ms: Option<u64>,
+}
+
+impl ClusterConfig {
+    fn heartbeat_interval(&self) -> std::time::Duration {
+        self.heartbeat_interval_ms
+            .map_or(DEFAULT_HEARTBEAT_INTERVAL, std::time::Duration::from_millis)
+    }
 }
 
+/// Interval of heartbeat if not set in the config file.
+pub const DEFAULT_HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_millis(300);
+
 #[derive(serde::Deserialize, Debug, PartialEq, Eq)]
 #[serde(rename_all = "PascalCase")]
 struct WorkerConfig {
@@ -41,19 +60,98 @@ struct WorkerConfig {
 
 static CONFIG: OnceLock<Config> = OnceLock::new();
 
-/// Initialize configuration with toml file, and panic if any error occurs.
-pub fn init_config_toml(config_file: &std::path::Path) {
+/// Format of the config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Detect the format by the extension of `config_file`, `None` if not supported.
+    fn from_path(config_file: &std::path::Path) -> Option<Self> {
+        match config_file.extension()?.to_str()? {
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    fn parse(self, config_str: &str) -> Result<Config, String> {
+        match self {
+            Self::Toml => toml::from_str(config_str).map_err(|e| e.to_string()),
+            Self::Json => serde_json::from_str(config_str).map_err(|e| e.to_string()),
+            Self::Yaml => serde_yaml::from_str(config_str).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Initialize configuration with a config file in `format`.
+fn try_init_config_with_format(
+    config_file: &std::path::Path,
+    format: ConfigFormat,
+) -> SUResult<()> {
     let mut config_str = String::new();
-    std::fs::File::open(config_file)
-        .unwrap_or_else(|e| panic!("fail to open the config file: {e}"))
-        .read_to_string(&mut config_str)
-        .unwrap_or_else(|e| panic!("fail to read the config file: {e}"));
+    std::fs::File::open(config_file)?.read_to_string(&mut config_str)?;
+    let config = format
+        .parse(&config_str)
+        .map_err(|e| SUError::config(format!("fail to parse the config file: {e}")))?;
     CONFIG
-        .set(
-            toml::from_str(&config_str)
-                .unwrap_or_else(|e| panic!("fail to parse the config file: {e}")),
-        )
-        .expect("initialize config more than once");
+        .set(config)
+        .map_err(|_| SUError::config("initialize config more than once"))
+}
+
+/// Initialize configuration with a toml, json or yaml file.
+///
+/// The format is detected by the extension of `config_file`,
+/// which is one of `.toml`, `.json`, `.yaml` and `.yml`.
+///
+/// # Error
+/// - [`SUError::Io`] if the config file cannot be read
+/// - [`SUError::Config`] if the extension is not supported, the file is malformed,
+///   or the configuration is already initialized
+pub fn try_init_config(config_file: &std::path::Path) -> SUResult<()> {
+    let format = ConfigFormat::from_path(config_file).ok_or_else(|| {
+        SUError::config(format!(
+            "unsupported config file extension: {}, expect .toml, .json, .yaml or .yml",
+            config_file.display()
+        ))
+    })?;
+    try_init_config_with_format(config_file, format)
+}
+
+/// Initialize configuration with toml file, and panic if any error occurs.
+pub fn init_config_toml(config_file: &std::path::Path) {
+    try_init_config_with_format(config_file, ConfigFormat::Toml)
+        .unwrap_or_else(|e| panic!("fail to initialize config: {e}"));
+}
+
+/// Initialize configuration with a toml, json or yaml file, and panic if any error occurs.
+///
+/// See [`try_init_config`] for the formats supported.
+pub fn init_config_auto(config_file: &std::path::Path) {
+    try_init_config(config_file).unwrap_or_else(|e| panic!("fail to initialize config: {e}"));
+}
+
+/// Build the global thread pool, and panic if any error occurs.
+///
+/// The pool is sized with `threads` if given, otherwise with [`threads()`].
+/// Every subsystem running its parallel work on the global rayon pool honors this setting.
+/// This function must be called after `init_config_toml`, and before any parallel work is spawned.
+/// It is a no-op if the `rayon` feature is disabled.
+pub fn init_thread_pool(threads: Option<NonZeroUsize>) {
+    #[cfg(feature = "rayon")]
+    {
+        let threads = threads.map_or_else(self::threads, NonZeroUsize::get);
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .unwrap_or_else(|e| panic!("fail to build the global thread pool: {e}"));
+    }
+    #[cfg(not(feature = "rayon"))]
+    let _ = threads;
 }
 
 /// Validate the general configuration, and panic if any configuration is illegal.
@@ -61,42 +159,94 @@ pub fn init_config_toml(config_file: &std::path::Path) {
 /// To validate the standalone configuration, use `validate_standalone_config`.
 /// To validate the cluster configuration, use `validate_cluster_config`.
 pub fn validate_config() {
-    let config = CONFIG.get().expect("config not initialized");
+    try_validate_config().unwrap_or_else(|e| panic!("{e}"));
+}
+
+/// Validate the general configuration.
+///
+/// # Error
+/// - [`SUError::Config`] if the configuration is not initialized or any configuration is illegal
+pub fn try_validate_config() -> SUResult<()> {
+    validate(try_get_config()?)
+}
+
+/// Size of a segment, the slices are aligned with it in the slice buffer.
+const SEG_SIZE: u64 = 4 << 10;
+
+fn validate(config: &Config) -> SUResult<()> {
     if !config.out_dir_path.is_dir() {
-        panic!(
+        return Err(SUError::config(format!(
             "output path {} is not a directory",
             config.out_dir_path.display()
-        );
+        )));
+    }
+    if config.ec_k == 0 {
+        return Err(SUError::config("ec k must be greater than 0"));
+    }
+    if config.ec_p == 0 {
+        return Err(SUError::config("ec p must be greater than 0"));
+    }
+    if config
+        .ssd_low_watermark
+        .is_some_and(|low| low > config.ssd_block_capacity)
+    {
+        return Err(SUError::config(format!(
+            "ssd low watermark {} is greater than ssd block capacity {}",
+            config.ssd_low_watermark.unwrap(),
+            config.ssd_block_capacity
+        )));
     }
     if config.slice_size > config.block_size {
-        panic!(
+        return Err(SUError::config(format!(
             "slice size {} is greater than block size {}",
             config.slice_size, config.block_size
-        );
+        )));
     }
+    if config.slice_size.as_u64() == 0 || config.slice_size.as_u64() % SEG_SIZE != 0 {
+        return Err(SUError::config(format!(
+            "slice size {} is not a multiple of the segment size {}",
+            config.slice_size,
+            bytesize::ByteSize::b(SEG_SIZE)
+        )));
+    }
+    if config.block_size.as_u64() % config.slice_size.as_u64() != 0 {
+        return Err(SUError::config(format!(
+            "block size {} is not a multiple of slice size {}",
+            config.block_size, config.slice_size
+        )));
+    }
+    Ok(())
 }
 
 /// Validate the standalone configuration, and panic if any configuration is illegal.
 ///
 /// This function must be called after `validate_config`.
 pub fn validate_standalone_config() {
-    let config = CONFIG.get().expect("config not initialized");
-    let config = config
+    try_validate_standalone_config().unwrap_or_else(|e| panic!("{e}"));
+}
+
+/// Validate the standalone configuration.
+///
+/// # Error
+/// - [`SUError::Config`] if the standalone configuration is not set or illegal
+pub fn try_validate_standalone_config() -> SUResult<()> {
+    let config = try_get_config()?
         .standalone
         .as_ref()
-        .expect("standalone config not set");
+        .ok_or_else(|| SUError::config("standalone config not set"))?;
     if !config.hdd_dev_path.is_dir() {
-        panic!(
+        return Err(SUError::config(format!(
             "hdd dev path {} is not a directory",
             config.hdd_dev_path.display()
-        );
+        )));
     }
     if !config.ssd_dev_path.is_dir() {
-        panic!(
+        return Err(SUError::config(format!(
             "ssd dev path {} is not a directory",
             config.ssd_dev_path.display()
-        );
+        )));
     }
+    Ok(())
 }
 
 /// Validate the cluster configuration, and panic if any configuration is illegal
@@ -106,34 +256,60 @@ pub fn validate_standalone_config() {
 /// # Arguments
 /// - worker_id: the worker id to validate, and `None` stands for coordinator
 pub fn validate_cluster_config(worker_id: Option<usize>) {
-    let config = CONFIG.get().expect("config not initialized");
-    let cluster = config.cluster.as_ref().expect("cluster config not set");
-    if cluster.worker_num.get() < 1 {
-        panic!("worker num must be greater than 0");
-    }
+    try_validate_cluster_config(worker_id).unwrap_or_else(|e| panic!("{e}"));
+}
+
+/// Validate the cluster configuration.
+///
+/// # Arguments
+/// - worker_id: the worker id to validate, and `None` stands for coordinator
+///
+/// # Error
+/// - [`SUError::Config`] if the cluster configuration is not set or illegal
+pub fn try_validate_cluster_config(worker_id: Option<usize>) -> SUResult<()> {
+    let cluster = try_get_config()?
+        .cluster
+        .as_ref()
+        .ok_or_else(|| SUError::config("cluster config not set"))?;
     if cluster.worker_num.get() > cluster.workers.len() {
-        panic!("worker num must be equal to the number of worker dev path");
+        return Err(SUError::config(
+            "worker num must be equal to the number of worker dev path",
+        ));
     }
     if let Some(worker_id) = worker_id {
         if worker_id == 0 || worker_id > cluster.worker_num.get() {
-            panic!("worker id ranges from 0 to {}", cluster.worker_num.get());
+            return Err(SUError::config(format!(
+                "worker id ranges from 1 to {}",
+                cluster.worker_num.get()
+            )));
         }
         let worker = &cluster.workers[worker_id - 1];
         if !worker.ssd_dev_path.is_dir() {
-            panic!(
+            return Err(SUError::config(format!(
                 "worker {} ssd dev path {} is not a directory",
                 worker_id,
                 worker.ssd_dev_path.display()
-            );
+            )));
         }
         if !worker.hdd_dev_path.is_dir() {
-            panic!(
+            return Err(SUError::config(format!(
                 "worker {} hdd dev path {} is not a directory",
                 worker_id,
                 worker.hdd_dev_path.display()
-            );
+            )));
         }
     }
+    Ok(())
+}
+
+/// Get the configuration.
+///
+/// # Error
+/// - [`SUError::Config`] if not initialized
+fn try_get_config() -> SUResult<&'static Config> {
+    CONFIG
+        .get()
+        .ok_or_else(|| SUError::config("config not initialized"))
 }
 
 /// Get the configuration, panic if not initialized.
@@ -186,6 +362,11 @@ pub fn ssd_block_capacity() -> usize {
     get_config().ssd_block_capacity
 }
 
+/// Get the number of blocks the ssd buffer is drained down to once it is full, `None` if not set
+pub fn ssd_low_watermark() -> Option<usize> {
+    get_config().ssd_low_watermark
+}
+
 /// Get the size of a block
 pub fn block_size() -> usize {
     get_config().block_size.as_u64().try_into().unwrap()
@@ -201,16 +382,52 @@ pub fn test_load() -> usize {
     get_config().test_num
 }
 
+/// Get the number of the warmup updates issued before timing, `None` if not set
+pub fn warmup() -> Option<usize> {
+    get_config().warmup_num
+}
+
 /// Get the size of a update slice
 pub fn slice_size() -> usize {
     get_config().slice_size.as_u64().try_into().unwrap()
 }
 
+/// Get the number of threads for the global thread pool,
+/// default to the available parallelism of the machine if not set
+pub fn threads() -> usize {
+    get_config()
+        .threads
+        .or_else(|| std::thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get)
+}
+
+/// Get the latency injected to each operation on the ssd buffer, `None` if not set
+pub fn ssd_op_latency() -> Option<std::time::Duration> {
+    get_config()
+        .ssd_op_latency_us
+        .map(std::time::Duration::from_micros)
+}
+
+/// Get the latency injected to each operation on the hdd storage, `None` if not set
+pub fn hdd_op_latency() -> Option<std::time::Duration> {
+    get_config()
+        .hdd_op_latency_us
+        .map(std::time::Duration::from_micros)
+}
+
 /// Get the url to connect to redis
 pub fn redis_url() -> Option<String> {
     get_config().cluster.as_ref().map(|c| c.redis_url.clone())
 }
 
+/// Get the password to connect to redis, `None` if not set
+pub fn redis_password() -> Option<String> {
+    get_config()
+        .cluster
+        .as_ref()
+        .and_then(|c| c.redis_password.clone())
+}
+
 /// Get the number of workers
 pub fn worker_num() -> Option<usize> {
     get_config().cluster.as_ref().map(|c| c.worker_num.get())
@@ -232,7 +449,210 @@ pub fn worker_hdd_dev_path(worker_id: usize) -> Option<std::path::PathBuf> {
         .and_then(|c| c.workers.get(worker_id - 1).map(|w| w.hdd_dev_path.clone()))
 }
 
-/// Get the interval of heartbeat
+/// Get the interval of heartbeat, [`DEFAULT_HEARTBEAT_INTERVAL`] if not set
 pub fn heartbeat_interval() -> std::time::Duration {
-    std::time::Duration::from_millis(300)
+    get_config().cluster.as_ref().map_or(
+        DEFAULT_HEARTBEAT_INTERVAL,
+        ClusterConfig::heartbeat_interval,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::SUError;
+
+    use super::{try_init_config, validate, Config, ConfigFormat, DEFAULT_HEARTBEAT_INTERVAL};
+
+    fn config_toml(ec_k: usize, ec_p: usize, block_size: &str, slice_size: &str) -> String {
+        format!(
+            r#"
+EcK = {ec_k}
+EcP = {ec_p}
+BlockSize = "{block_size}"
+BlockNum = 12
+SsdBlockCapacity = 2
+TestNum = 8
+SliceSize = "{slice_size}"
+OutDirPath = "{}"
+"#,
+            std::env::temp_dir().display()
+        )
+    }
+
+    fn validate_err_msg(config: &str) -> Option<String> {
+        let config: Config = toml::from_str(config).unwrap();
+        validate(&config).err().map(|e| {
+            assert!(matches!(e, SUError::Config(_)));
+            e.to_string()
+        })
+    }
+
+    #[test]
+    fn validate_ec_and_size() {
+        assert_eq!(validate_err_msg(&config_toml(4, 2, "1MiB", "4KiB")), None);
+        assert_eq!(validate_err_msg(&config_toml(4, 2, "1MiB", "1MiB")), None);
+        [
+            (
+                config_toml(0, 2, "1MiB", "4KiB"),
+                "ec k must be greater than 0",
+            ),
+            (
+                config_toml(4, 0, "1MiB", "4KiB"),
+                "ec p must be greater than 0",
+            ),
+            (
+                config_toml(4, 2, "1MiB", "2KiB"),
+                "is not a multiple of the segment size",
+            ),
+            (
+                config_toml(4, 2, "1MiB", "0B"),
+                "is not a multiple of the segment size",
+            ),
+            (
+                config_toml(4, 2, "1MiB", "12KiB"),
+                "is not a multiple of slice size",
+            ),
+            (
+                config_toml(4, 2, "4KiB", "8KiB"),
+                "is greater than block size",
+            ),
+        ]
+        .into_iter()
+        .for_each(|(config, expect)| {
+            let msg = validate_err_msg(&config).expect("bad config passes validation");
+            assert!(msg.contains(expect), "unexpected error: {msg}");
+        });
+    }
+
+    #[test]
+    fn heartbeat_interval() {
+        let cluster_toml = |extra: &str| {
+            format!(
+                r#"{}
+[Cluster]
+RedisUrl = "redis://127.0.0.1"
+WorkerNum = 1
+Workers = [{{ SsdDevPath = "ssd", HddDevPath = "hdd" }}]
+{extra}
+"#,
+                config_toml(4, 2, "1MiB", "4KiB")
+            )
+        };
+        let config: Config = toml::from_str(&cluster_toml("HeartbeatIntervalMs = 1500")).unwrap();
+        assert_eq!(
+            config.cluster.unwrap().heartbeat_interval(),
+            Duration::from_millis(1500)
+        );
+        let config: Config = toml::from_str(&cluster_toml("")).unwrap();
+        assert_eq!(
+            config.cluster.unwrap().heartbeat_interval(),
+            DEFAULT_HEARTBEAT_INTERVAL
+        );
+    }
+
+    #[test]
+    fn config_formats() {
+        const TOML: &str = r#"
+EcK = 4
+EcP = 2
+BlockSize = "1MiB"
+BlockNum = 24
+SsdBlockCapacity = 8
+TestNum = 100
+SliceSize = "4KiB"
+OutDirPath = "./out/"
+
+[Standalone]
+SsdDevPath = "./ssd"
+HddDevPath = "./hdd"
+
+[Cluster]
+RedisUrl = "redis://127.0.0.1"
+WorkerNum = 2
+HeartbeatIntervalMs = 500
+Workers = [
+    { SsdDevPath = "./w1/ssd", HddDevPath = "./w1/hdd" },
+    { SsdDevPath = "./w2/ssd", HddDevPath = "./w2/hdd" },
+]
+"#;
+        const JSON: &str = r#"{
+    "EcK": 4,
+    "EcP": 2,
+    "BlockSize": "1MiB",
+    "BlockNum": 24,
+    "SsdBlockCapacity": 8,
+    "TestNum": 100,
+    "SliceSize": "4KiB",
+    "OutDirPath": "./out/",
+    "Standalone": { "SsdDevPath": "./ssd", "HddDevPath": "./hdd" },
+    "Cluster": {
+        "RedisUrl": "redis://127.0.0.1",
+        "WorkerNum": 2,
+        "HeartbeatIntervalMs": 500,
+        "Workers": [
+            { "SsdDevPath": "./w1/ssd", "HddDevPath": "./w1/hdd" },
+            { "SsdDevPath": "./w2/ssd", "HddDevPath": "./w2/hdd" }
+        ]
+    }
+}"#;
+        const YAML: &str = r#"
+EcK: 4
+EcP: 2
+BlockSize: 1MiB
+BlockNum: 24
+SsdBlockCapacity: 8
+TestNum: 100
+SliceSize: 4KiB
+OutDirPath: ./out/
+Standalone:
+  SsdDevPath: ./ssd
+  HddDevPath: ./hdd
+Cluster:
+  RedisUrl: redis://127.0.0.1
+  WorkerNum: 2
+  HeartbeatIntervalMs: 500
+  Workers:
+    - { SsdDevPath: ./w1/ssd, HddDevPath: ./w1/hdd }
+    - { SsdDevPath: ./w2/ssd, HddDevPath: ./w2/hdd }
+"#;
+        let from_toml = ConfigFormat::Toml.parse(TOML).unwrap();
+        assert_eq!(from_toml.block_size, bytesize::ByteSize::mib(1));
+        assert!(from_toml.standalone.is_some());
+        assert_eq!(ConfigFormat::Json.parse(JSON).unwrap(), from_toml);
+        assert_eq!(ConfigFormat::Yaml.parse(YAML).unwrap(), from_toml);
+
+        let format = |path: &str| ConfigFormat::from_path(std::path::Path::new(path));
+        assert_eq!(format("a/config.toml"), Some(ConfigFormat::Toml));
+        assert_eq!(format("config.json"), Some(ConfigFormat::Json));
+        assert_eq!(format("config.yaml"), Some(ConfigFormat::Yaml));
+        assert_eq!(format("config.yml"), Some(ConfigFormat::Yaml));
+        assert_eq!(format("config.ini"), None);
+        assert_eq!(format("config"), None);
+    }
+
+    #[test]
+    fn malformed_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, content: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+        // none of these initializes the global config
+        [
+            write("missing.toml", "EcK = 4\nEcP = 2\n"),
+            write("bad-type.json", r#"{ "EcK": "four" }"#),
+            write("bad-syntax.yaml", "EcK: [4\n"),
+            write("config.ini", "EcK = 4\n"),
+        ]
+        .iter()
+        .for_each(|path| {
+            let e = try_init_config(path).unwrap_err();
+            assert!(matches!(e, SUError::Config(_)), "unexpected error: {e}");
+        });
+        let e = try_init_config(&dir.path().join("absent.toml")).unwrap_err();
+        assert!(matches!(e, SUError::Io(_)), "unexpected error: {e}");
+    }
 }
@@ -1,5 +1,7 @@
 use bytes::BytesMut;
 
+use crate::{SUError, SUResult};
+
 /// A [`Block`] represents a unit in a [`Stripe`](crate::erasure_code::Stripe),
 /// which references to a continuous memory region.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -31,6 +33,90 @@ impl Block {
     pub fn block_size(&self) -> usize {
         self.0.len()
     }
+
+    /// Xor `other` into this block, i.e., `self ^= other`.
+    ///
+    /// # Error
+    /// - [`SUError::Range`] if the sizes of the blocks do not match
+    pub fn xor_assign(&mut self, other: &Block) -> SUResult<()> {
+        check_same_size(self, other, (file!(), line!(), column!()))?;
+        xor_slice(self, other);
+        Ok(())
+    }
+
+    /// Xor `a` and `b` into `out`, i.e., `out = a ^ b`.
+    ///
+    /// # Error
+    /// - [`SUError::Range`] if the sizes of the blocks do not match
+    pub fn xor_into(a: &Block, b: &Block, out: &mut Block) -> SUResult<()> {
+        check_same_size(out, a, (file!(), line!(), column!()))?;
+        check_same_size(out, b, (file!(), line!(), column!()))?;
+        xor_slice_into(a, b, out);
+        Ok(())
+    }
+
+    /// Multiply `src` by `coef` in GF(2^8), and accumulate the product into this block,
+    /// i.e., `self ^= coef * src`.
+    ///
+    /// # Error
+    /// - [`SUError::Range`] if the sizes of the blocks do not match
+    pub fn gf_mac(&mut self, coef: u8, src: &Block) -> SUResult<()> {
+        check_same_size(self, src, (file!(), line!(), column!()))?;
+        gf_mac_slice(self, coef, src);
+        Ok(())
+    }
+}
+
+/// Xor `other` into `target`, i.e., `target ^= other`.
+///
+/// # Panics
+/// - If the lengths of the slices do not match
+pub(crate) fn xor_slice(target: &mut [u8], other: &[u8]) {
+    assert_eq!(target.len(), other.len(), "slice length mismatch");
+    target.iter_mut().zip(other).for_each(|(t, o)| *t ^= *o);
+}
+
+/// Xor `a` and `b` into `out`, i.e., `out = a ^ b`.
+///
+/// # Panics
+/// - If the lengths of the slices do not match
+pub(crate) fn xor_slice_into(a: &[u8], b: &[u8], out: &mut [u8]) {
+    assert!(
+        out.len() == a.len() && out.len() == b.len(),
+        "slice length mismatch"
+    );
+    out.iter_mut()
+        .zip(a.iter().zip(b))
+        .for_each(|(o, (a, b))| *o = *a ^ *b);
+}
+
+/// Multiply `src` by `coef` in GF(2^8), and accumulate the product into `target`,
+/// i.e., `target ^= coef * src`.
+///
+/// # Panics
+/// - If the lengths of the slices do not match
+pub(crate) fn gf_mac_slice(target: &mut [u8], coef: u8, src: &[u8]) {
+    assert_eq!(target.len(), src.len(), "slice length mismatch");
+    target
+        .iter_mut()
+        .zip(src)
+        .for_each(|(t, s)| *t ^= isa_l::gf_mul(*s, coef));
+}
+
+/// Check if the size of `other` matches the size of `target`.
+fn check_same_size(
+    target: &Block,
+    other: &Block,
+    source_location: (&str, u32, u32),
+) -> SUResult<()> {
+    if target.block_size() != other.block_size() {
+        return Err(SUError::range_not_match(
+            source_location,
+            0..target.block_size(),
+            0..other.block_size(),
+        ));
+    }
+    Ok(())
 }
 
 impl From<Block> for Vec<u8> {
@@ -45,6 +131,12 @@ impl From<Block> for bytes::Bytes {
     }
 }
 
+impl From<Block> for BytesMut {
+    fn from(value: Block) -> Self {
+        value.0
+    }
+}
+
 impl From<BytesMut> for Block {
     fn from(value: BytesMut) -> Self {
         Self(value)
@@ -76,3 +168,76 @@ impl std::ops::DerefMut for Block {
         self.0.deref_mut()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+
+    use crate::SUError;
+
+    use super::Block;
+
+    const BLOCK_SIZE: usize = 4 << 10;
+
+    fn random_block() -> Block {
+        let mut block = Block::zero(BLOCK_SIZE);
+        rand::thread_rng().fill(block.as_mut());
+        block
+    }
+
+    #[test]
+    fn xor() {
+        let (a, b) = (random_block(), random_block());
+        let mut out = Block::zero(BLOCK_SIZE);
+        Block::xor_into(&a, &b, &mut out).unwrap();
+        (0..BLOCK_SIZE).for_each(|i| assert_eq!(out[i], a[i] ^ b[i]));
+        let mut c = a.clone();
+        c.xor_assign(&b).unwrap();
+        assert_eq!(c, out);
+        // xor is its own inverse
+        c.xor_assign(&b).unwrap();
+        assert_eq!(c, a);
+        c.xor_assign(&a).unwrap();
+        assert_eq!(c, Block::zero(BLOCK_SIZE));
+    }
+
+    #[test]
+    fn gf_mac() {
+        let src = random_block();
+        let mut acc = Block::zero(BLOCK_SIZE);
+        // multiplied by 1 is xor
+        acc.gf_mac(1, &src).unwrap();
+        assert_eq!(acc, src);
+        // multiplied by 0 is no-op
+        acc.gf_mac(0, &random_block()).unwrap();
+        assert_eq!(acc, src);
+        // a * x ^ b * x == (a ^ b) * x
+        let (a, b) = (0x1d, 0xa7);
+        let mut acc = Block::zero(BLOCK_SIZE);
+        acc.gf_mac(a, &src).unwrap();
+        acc.gf_mac(b, &src).unwrap();
+        let mut expect = Block::zero(BLOCK_SIZE);
+        expect.gf_mac(a ^ b, &src).unwrap();
+        assert_eq!(acc, expect);
+        (0..BLOCK_SIZE).for_each(|i| assert_eq!(expect[i], isa_l::gf_mul(src[i], a ^ b)));
+    }
+
+    #[test]
+    fn size_mismatch() {
+        let mut block = random_block();
+        let origin = block.clone();
+        let other = Block::zero(BLOCK_SIZE / 2);
+        assert!(matches!(block.xor_assign(&other), Err(SUError::Range(_))));
+        assert!(matches!(block.gf_mac(3, &other), Err(SUError::Range(_))));
+        assert_eq!(block, origin);
+        let mut out = Block::zero(BLOCK_SIZE);
+        assert!(matches!(
+            Block::xor_into(&origin, &other, &mut out),
+            Err(SUError::Range(_))
+        ));
+        assert!(matches!(
+            Block::xor_into(&origin, &origin, &mut Block::zero(BLOCK_SIZE + 1)),
+            Err(SUError::Range(_))
+        ));
+    }
+}
@@ -0,0 +1,581 @@
+use std::num::NonZeroUsize;
+
+use crate::{erasure_code::Block, SUError, SUResult};
+
+use super::{
+    block::{xor_slice, xor_slice_into},
+    check_partial_stripe_k_p, check_stripe_k_p, ErasureCode, PartialStripe, ReedSolomon, Stripe,
+};
+
+/// Make a local reconstruction code instance with `k` source blocks split into `l` local groups.
+///
+/// The parity blocks of a stripe are laid out as `l` local parity blocks followed by `g` global parity blocks.
+/// The local parity block of a group is the xor of the source blocks in the group,
+/// so that a single absent block of a group is repaired from the other blocks of the group only.
+/// The global parity blocks are encoded from all the source blocks by a cauchy [`ReedSolomon`] code.
+///
+/// Unlike [`ReedSolomon`], not every pattern of `l + g` absent blocks can be decoded,
+/// and which patterns can depends on `k`, `l` and `g`, so no number of absent blocks beyond one
+/// is guaranteed to be decodable in general.
+/// Decoding falls back to solving all the present blocks together
+/// if the local groups are not sufficient, and fails on a pattern that cannot be decoded.
+pub struct LocalReconstructionCode {
+    /// number of source data
+    k: usize,
+    /// number of local groups, each with a local parity
+    l: usize,
+    /// code of the global parity
+    global: ReedSolomon,
+}
+
+impl LocalReconstructionCode {
+    /// Make a [`LocalReconstructionCode`]`(k+l+g, k)` erasure code.
+    ///
+    /// # Parameters
+    /// - `k`: number of the source blocks
+    /// - `l`: number of the local groups, each of `k / l` source blocks and a local parity block
+    /// - `g`: number of the global parity blocks
+    ///
+    /// # Panics
+    /// - If `l` does not divide `k`
+    pub fn from_k_l_g(k: NonZeroUsize, l: NonZeroUsize, g: NonZeroUsize) -> Self {
+        assert!(
+            k.get() % l.get() == 0,
+            "{l} local groups cannot evenly split {k} source blocks"
+        );
+        Self {
+            k: k.get(),
+            l: l.get(),
+            global: ReedSolomon::from_k_p_cauchy(k, g),
+        }
+    }
+
+    /// number of the local groups, as well as the local parity blocks
+    #[inline]
+    pub fn l(&self) -> usize {
+        self.l
+    }
+
+    /// number of the global parity blocks
+    #[inline]
+    pub fn g(&self) -> usize {
+        self.global.p()
+    }
+
+    /// Get the local group of the block at `block_idx`.
+    ///
+    /// # Return
+    /// - [`Some`] with the index of the group if the block is a source block or a local parity block
+    /// - [`None`] if the block is a global parity block
+    ///
+    /// # Panics
+    /// - If `block_idx` is out of bounds
+    pub fn group_of(&self, block_idx: usize) -> Option<usize> {
+        assert!(
+            block_idx < self.m(),
+            "block index({block_idx}) is greater than m({})",
+            self.m()
+        );
+        if block_idx < self.k {
+            Some(block_idx / (self.k / self.l))
+        } else if block_idx < self.k + self.l {
+            Some(block_idx - self.k)
+        } else {
+            None
+        }
+    }
+
+    /// Get the index of the blocks in the local group `group`,
+    /// the source blocks in ascending order followed by the local parity block.
+    ///
+    /// # Panics
+    /// - If `group` is out of bounds
+    pub fn group_members(&self, group: usize) -> Vec<usize> {
+        assert!(
+            group < self.l,
+            "group({group}) is greater than l({})",
+            self.l
+        );
+        let group_size = self.k / self.l;
+        (group * group_size..(group + 1) * group_size)
+            .chain(std::iter::once(self.k + group))
+            .collect()
+    }
+
+    /// Get the blocks to read to repair the block at `block_idx` alone.
+    /// The other blocks of its local group are sufficient to repair a source or local parity block,
+    /// while all the source blocks are required to repair a global parity block.
+    ///
+    /// # Return
+    /// The indexes of the blocks in ascending order, excluding `block_idx`.
+    ///
+    /// # Panics
+    /// - If `block_idx` is out of bounds
+    pub fn repair_dependencies(&self, block_idx: usize) -> Vec<usize> {
+        match self.group_of(block_idx) {
+            Some(group) => self
+                .group_members(group)
+                .into_iter()
+                .filter(|idx| *idx != block_idx)
+                .collect(),
+            None => (0..self.k).collect(),
+        }
+    }
+
+    /// Repair the absent block at `block_idx` from the other blocks of its local group,
+    /// the blocks out of the group are neither read nor modified.
+    ///
+    /// # Error
+    /// - [`SUError::ErasureCode`] if the block is a global parity block
+    /// - [`SUError::ErasureCode`] if any other block of the local group is absent
+    /// - [`SUError::ErasureCode`] if `k` and `p` between this code and `partial_stripe` do not match
+    ///
+    /// # Panics
+    /// - If `block_idx` is out of bounds
+    pub fn repair_local(
+        &self,
+        partial_stripe: &mut PartialStripe,
+        block_idx: usize,
+    ) -> SUResult<()> {
+        check_partial_stripe_k_p(self, partial_stripe, file!(), line!(), column!())?;
+        let group = self.group_of(block_idx).ok_or_else(|| {
+            SUError::erasure_code(
+                (file!(), line!(), column!()),
+                format!("global parity block {block_idx} has no local group"),
+            )
+        })?;
+        let present = partial_stripe.present_block_index();
+        if !self
+            .repair_dependencies(block_idx)
+            .iter()
+            .all(|idx| present.contains(idx))
+        {
+            return Err(SUError::erasure_code(
+                (file!(), line!(), column!()),
+                format!("the local group {group} of block {block_idx} has other absent blocks"),
+            ));
+        }
+        self.repair_group(partial_stripe, group)
+    }
+
+    /// Recompute the only absent block of the local group `group` by xor-ing the others.
+    fn repair_group(&self, partial_stripe: &mut PartialStripe, group: usize) -> SUResult<()> {
+        let members = self.group_members(group);
+        let mut recover = Block::zero(partial_stripe.block_size());
+        let mut absent = None;
+        for (idx, block) in partial_stripe.iter() {
+            if !members.contains(&idx) {
+                continue;
+            }
+            match block {
+                Some(block) => recover.xor_assign(block)?,
+                None => {
+                    debug_assert!(absent.is_none(), "more than one block absent");
+                    absent = Some(idx);
+                }
+            }
+        }
+        if let Some(idx) = absent {
+            partial_stripe.replace_block(idx, Some(recover));
+        }
+        Ok(())
+    }
+
+    /// Get the coefficients of the block at `block_idx` on each source block,
+    /// which is a row of the generator matrix.
+    fn generator_row(&self, block_idx: usize) -> Vec<u8> {
+        let mut row = vec![0_u8; self.k];
+        if block_idx < self.k {
+            row[block_idx] = 1;
+        } else if block_idx < self.k + self.l {
+            let group_size = self.k / self.l;
+            let group = block_idx - self.k;
+            row[group * group_size..(group + 1) * group_size].fill(1);
+        } else {
+            row.copy_from_slice(self.global.parity_coefficients(block_idx - self.k - self.l));
+        }
+        row
+    }
+
+    /// Select `k` present blocks whose generator rows are linearly independent,
+    /// preferring the source blocks, then the local parity blocks and then the global ones.
+    ///
+    /// # Return
+    /// - [`Some`] with the index of the selected blocks
+    /// - [`None`] if the present blocks are not sufficient to decode the source blocks
+    fn select_survivors(&self, present: &[usize]) -> Option<Vec<usize>> {
+        // rows in echelon form, each normalized to 1 at its pivot
+        let mut basis: Vec<(usize, Vec<u8>)> = Vec::with_capacity(self.k);
+        let mut survivors = Vec::with_capacity(self.k);
+        for &idx in present {
+            let mut row = self.generator_row(idx);
+            basis.iter().for_each(|(pivot, base)| {
+                let coef = row[*pivot];
+                if coef != 0 {
+                    row.iter_mut()
+                        .zip(base)
+                        .for_each(|(r, b)| *r ^= isa_l::gf_mul(coef, *b));
+                }
+            });
+            if let Some(pivot) = row.iter().position(|r| *r != 0) {
+                let inv = isa_l::gf_inv(row[pivot]);
+                row.iter_mut().for_each(|r| *r = isa_l::gf_mul(*r, inv));
+                basis.push((pivot, row));
+                survivors.push(idx);
+                if survivors.len() == self.k {
+                    return Some(survivors);
+                }
+            }
+        }
+        None
+    }
+
+    /// Decode the absent source blocks from the `survivors` selected by [`Self::select_survivors()`].
+    fn decode_source(
+        &self,
+        partial_stripe: &mut PartialStripe,
+        survivors: &[usize],
+    ) -> SUResult<()> {
+        let (source, _) = partial_stripe.split_source_parity();
+        let absent = (0..self.k)
+            .filter(|idx| source[*idx].is_none())
+            .collect::<Vec<_>>();
+        if absent.is_empty() {
+            return Ok(());
+        }
+        let mat = survivors
+            .iter()
+            .flat_map(|idx| self.generator_row(*idx))
+            .collect::<Vec<_>>();
+        let inv_mat = isa_l::gf_invert_matrix(mat).ok_or_else(|| {
+            SUError::erasure_code(
+                (file!(), line!(), column!()),
+                "the generator rows of the survivors are not invertible",
+            )
+        })?;
+        let decode_mat = absent
+            .iter()
+            .flat_map(|idx| inv_mat[idx * self.k..(idx + 1) * self.k].iter().copied())
+            .collect::<Vec<_>>();
+        let block_size = partial_stripe.block_size();
+        let decode_table = isa_l::ec_init_tables_owned(self.k, absent.len(), decode_mat);
+        let mut recovered = Block::zero_n(absent.len(), block_size);
+        {
+            let blocks = partial_stripe
+                .iter()
+                .map(|(_, block)| block)
+                .collect::<Vec<_>>();
+            let survivor_block = survivors
+                .iter()
+                .map(|idx| blocks[*idx].unwrap())
+                .collect::<Vec<_>>();
+            isa_l::ec_encode_data(
+                block_size,
+                self.k,
+                absent.len(),
+                &decode_table,
+                survivor_block,
+                &mut recovered,
+            );
+        }
+        absent.into_iter().zip(recovered).for_each(|(idx, block)| {
+            partial_stripe.replace_block(idx, Some(block));
+        });
+        Ok(())
+    }
+
+    /// Encode the absent global parity blocks, with all the source blocks present.
+    fn encode_absent_global(&self, partial_stripe: &mut PartialStripe) {
+        let absent = partial_stripe
+            .absent_block_index()
+            .into_iter()
+            .filter(|idx| *idx >= self.k + self.l)
+            .collect::<Vec<_>>();
+        if absent.is_empty() {
+            return;
+        }
+        let mut global = Block::zero_n(self.g(), partial_stripe.block_size());
+        {
+            let (source, _) = partial_stripe.split_source_parity();
+            let source = source
+                .iter()
+                .map(|block| block.as_ref().unwrap())
+                .collect::<Vec<_>>();
+            self.global.encode_parities(&source, &mut global);
+        }
+        global
+            .into_iter()
+            .enumerate()
+            .map(|(i, block)| (self.k + self.l + i, block))
+            .filter(|(idx, _)| absent.contains(idx))
+            .for_each(|(idx, block)| {
+                partial_stripe.replace_block(idx, Some(block));
+            });
+    }
+}
+
+impl ErasureCode for LocalReconstructionCode {
+    /// number of the source block
+    #[inline]
+    fn k(&self) -> usize {
+        self.k
+    }
+    /// number of the local and global parity block
+    #[inline]
+    fn p(&self) -> usize {
+        self.l + self.g()
+    }
+    /// number of the source and parity block
+    #[inline]
+    fn m(&self) -> usize {
+        self.k() + self.p()
+    }
+    /// Encode the full stripe, the source blocks will remain unmodified,
+    /// and the local and global parity blocks will be encoded from the source blocks.
+    fn encode_stripe(&self, stripe: &mut Stripe) -> SUResult<()> {
+        check_stripe_k_p(self, stripe, file!(), line!(), column!())?;
+        let (source, parity) = stripe.split_mut_source_parity();
+        let (local, global) = parity.split_at_mut(self.l);
+        source
+            .chunks_exact(self.k / self.l)
+            .zip(local)
+            .try_for_each(|(group, local)| {
+                local.fill(0);
+                group.iter().try_for_each(|block| local.xor_assign(block))
+            })?;
+        self.global.encode_parities(source, global);
+        Ok(())
+    }
+    /// Decode the absent blocks from the present blocks in the `partial_stripe`.
+    /// A local group with a single absent block is repaired locally,
+    /// and the rest absent source blocks are solved from `k` independent present blocks.
+    /// If success, all the blocks in the `partial_stripe` will be present,
+    /// otherwise the `partial_stripe` will remain unmodified.
+    ///
+    /// # Return
+    /// - [`Ok`] if decode successfully, and all the blocks in the `partial_stripe` will be present.
+    /// - [`Err(SUError::ErasureCode)`] if any error occurs, and the `partial_stripe` will remain unmodified.
+    ///
+    /// # Error
+    /// - If the present blocks are not sufficient to decode the absent ones.
+    /// - If `k` and `p` between this erasure code and `partial_stripe` do not match
+    fn decode(&self, partial_stripe: &mut PartialStripe) -> SUResult<()> {
+        check_partial_stripe_k_p(self, partial_stripe, file!(), line!(), column!())?;
+        let absent = partial_stripe.absent_block_index();
+        if absent.is_empty() {
+            return Ok(());
+        }
+        let survivors = self
+            .select_survivors(&partial_stripe.present_block_index())
+            .ok_or_else(|| {
+                SUError::erasure_code(
+                    (file!(), line!(), column!()),
+                    format!(
+                        "cannot decode {} blocks {absent:?} by ({}, {}) lrc with {} local groups",
+                        absent.len(),
+                        self.m(),
+                        self.k(),
+                        self.l
+                    ),
+                )
+            })?;
+        // repair the local groups with a single absent block first
+        (0..self.l)
+            .filter(|group| {
+                let members = self.group_members(*group);
+                absent.iter().filter(|idx| members.contains(idx)).count() == 1
+            })
+            .try_for_each(|group| self.repair_group(partial_stripe, group))?;
+        self.decode_source(partial_stripe, &survivors)?;
+        // only the local parity blocks may be absent in the local groups now
+        (0..self.l).try_for_each(|group| self.repair_group(partial_stripe, group))?;
+        self.encode_absent_global(partial_stripe);
+        Ok(())
+    }
+    /// Update the stripe in delta manner.
+    /// That is, only the area `[offset, offset + update_slice.len())` of the source block
+    /// at `update_source_idx` are updated to the content of `update_slice`.
+    /// And then, the delta are xor-ed into the local parity block of its group,
+    /// and applied to the global parity blocks as [`ReedSolomon`] does.
+    ///
+    /// # Parameters
+    /// - `update_slice`: the content to copy to the target source block
+    /// - `update_source_idx`: the index of the source block to update in a stripe
+    /// - `offset`: the start of the region to update
+    /// - `partial_stripe`: partial stripe to update, all the parity blocks should be present,
+    ///   and will be updated source blocks.
+    ///
+    /// # Error
+    /// - [SUError::ErasureCode] if not all the parity blocks are present
+    /// - [SUError::ErasureCode] if the target source block to update is absent
+    /// - [SUError::Range] if the `update_source_idx` is out of source block bound
+    /// - [SUError::Range] if the updated area `[offset, offset + update_slice.len())` is out of block bound
+    fn delta_update(
+        &self,
+        update_slice: &[u8],
+        update_source_idx: usize,
+        offset: usize,
+        partial_stripe: &mut PartialStripe,
+    ) -> SUResult<()> {
+        check_partial_stripe_k_p(self, partial_stripe, file!(), line!(), column!())?;
+        let valid_range = 0..partial_stripe.block_size();
+        let range = offset..(offset + update_slice.len());
+        if range.end > valid_range.end {
+            return Err(SUError::out_of_range(
+                (file!(), line!(), column!()),
+                Some(valid_range),
+                range,
+            ));
+        }
+        let (source, parity) = partial_stripe.split_mut_source_parity();
+        if !parity.iter().all(Option::is_some) {
+            return Err(SUError::erasure_code(
+                (file!(), line!(), column!()),
+                "not all the parity blocks are present",
+            ));
+        }
+        let target_source = source
+            .get_mut(update_source_idx)
+            .ok_or_else(|| {
+                SUError::out_of_range(
+                    (file!(), line!(), column!()),
+                    Some(0..self.k),
+                    0..update_source_idx,
+                )
+            })?
+            .as_mut()
+            .ok_or_else(|| {
+                SUError::erasure_code(
+                    (file!(), line!(), column!()),
+                    format!("the target source block at {update_source_idx} is absent"),
+                )
+            })?;
+        let target_slice = &mut target_source[range.clone()];
+        let mut delta = vec![0_u8; range.len()];
+        xor_slice_into(target_slice, update_slice, &mut delta);
+        target_slice.copy_from_slice(update_slice);
+        let (local, global) = parity.split_at_mut(self.l);
+        let group = self.group_of(update_source_idx).unwrap();
+        xor_slice(&mut local[group].as_mut().unwrap()[range.clone()], &delta);
+        let mut global_slice = global
+            .iter_mut()
+            .map(|block| &mut block.as_mut().unwrap()[range.clone()])
+            .collect::<Vec<_>>();
+        self.global
+            .parity_delta_update(&delta, update_source_idx, &mut global_slice)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use super::super::test::*;
+    use super::LocalReconstructionCode;
+    use crate::erasure_code::{ErasureCode, PartialStripe, Stripe};
+
+    const L: usize = 2;
+    const G: usize = 2;
+
+    fn lrc() -> LocalReconstructionCode {
+        LocalReconstructionCode::from_k_l_g(
+            NonZeroUsize::new(K).unwrap(),
+            NonZeroUsize::new(L).unwrap(),
+            NonZeroUsize::new(G).unwrap(),
+        )
+    }
+
+    fn encoded_stripe(ec: &LocalReconstructionCode) -> Stripe {
+        let mut stripe = gen_stripes(ec.k(), ec.p()).swap_remove(0);
+        ec.encode_stripe(&mut stripe).unwrap();
+        stripe
+    }
+
+    fn blocks(stripe: &Stripe) -> Vec<&crate::erasure_code::Block> {
+        stripe
+            .as_source()
+            .iter()
+            .chain(stripe.as_parity())
+            .collect()
+    }
+
+    #[test]
+    fn delta_update() {
+        test_update(&lrc());
+        test_update_many(&lrc());
+    }
+
+    #[test]
+    fn verify() {
+        test_verify(&lrc());
+    }
+
+    #[test]
+    fn local_repair() {
+        let ec = lrc();
+        let stripe = encoded_stripe(&ec);
+        let blocks = blocks(&stripe);
+        (0..ec.k() + ec.l()).for_each(|idx| {
+            let dependencies = ec.repair_dependencies(idx);
+            assert_eq!(dependencies.len(), K / L);
+            // only the other blocks of the local group are present
+            let mut partial = PartialStripe::make_absent_from_k_p(
+                NonZeroUsize::new(ec.k()).unwrap(),
+                NonZeroUsize::new(ec.p()).unwrap(),
+                NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+            );
+            dependencies.iter().for_each(|&dep| {
+                partial.replace_block(dep, Some(blocks[dep].clone()));
+            });
+            ec.repair_local(&mut partial, idx).unwrap();
+            assert_eq!(partial.present_block_index().len(), K / L + 1);
+            assert_eq!(partial.replace_block(idx, None).as_ref(), Some(blocks[idx]));
+            // fails if any other block of the group is absent
+            partial.replace_block(dependencies[0], None);
+            assert!(ec.repair_local(&mut partial, idx).is_err());
+        });
+        // a global parity block has no local group
+        let global_idx = ec.k() + ec.l();
+        assert_eq!(
+            ec.repair_dependencies(global_idx),
+            (0..K).collect::<Vec<_>>()
+        );
+        let mut partial = PartialStripe::from(&stripe);
+        partial.replace_block(global_idx, None);
+        assert!(ec.repair_local(&mut partial, global_idx).is_err());
+    }
+
+    #[test]
+    fn decode() {
+        let ec = lrc();
+        let stripe = encoded_stripe(&ec);
+        let with_absent = |absent: &[usize]| {
+            let mut partial = PartialStripe::from(&stripe);
+            absent.iter().for_each(|idx| {
+                partial.replace_block(*idx, None);
+            });
+            partial
+        };
+        // any g + 1 absent blocks, which holds for these parameters but not in general
+        (1_u32..1 << ec.m())
+            .filter(|mask| mask.count_ones() as usize <= G + 1)
+            .for_each(|mask| {
+                let absent = (0..ec.m())
+                    .filter(|idx| mask & (1 << idx) != 0)
+                    .collect::<Vec<_>>();
+                let mut partial = with_absent(&absent);
+                ec.decode(&mut partial).unwrap();
+                assert_eq!(Stripe::try_from(partial).unwrap(), stripe, "{absent:?}");
+            });
+        // one absent block of each group and all the global parity blocks
+        let absent = [0, K / L, ec.k() + ec.l(), ec.k() + ec.l() + 1];
+        let mut partial = with_absent(&absent);
+        ec.decode(&mut partial).unwrap();
+        assert_eq!(Stripe::try_from(partial).unwrap(), stripe);
+        // the whole local group and a global parity block
+        let absent = [0, 1, ec.k(), ec.k() + ec.l()];
+        let mut partial = with_absent(&absent);
+        assert!(ec.decode(&mut partial).is_err());
+        assert_eq!(partial, with_absent(&absent));
+    }
+}
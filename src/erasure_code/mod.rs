@@ -1,11 +1,15 @@
 mod block;
+mod lrc;
 mod reed_solomon;
 mod stripe;
+mod xor_parity;
 
 pub use block::Block;
+pub use lrc::LocalReconstructionCode;
 pub use reed_solomon::ReedSolomon;
 pub use stripe::PartialStripe;
 pub use stripe::Stripe;
+pub use xor_parity::XorParity;
 
 use crate::{SUError, SUResult};
 
@@ -56,6 +60,43 @@ pub trait ErasureCode {
         offset: usize,
         partial_stripe: &mut PartialStripe,
     ) -> SUResult<()>;
+    /// Update the stripe in delta manner with a batch of updates,
+    /// which is equivalent to calling [`Self::delta_update()`] on each update in order.
+    /// The default implementation does exactly that.
+    ///
+    /// # Parameters
+    /// - `updates`: a batch of `(update_source_idx, offset, update_slice)`
+    /// - `partial_stripe`: partial stripe to update, all the parity blocks should be present,
+    ///   and will be updated source blocks.
+    ///
+    /// # Error
+    /// Same as [`Self::delta_update()`].
+    fn delta_update_many(
+        &self,
+        updates: &[(usize, usize, &[u8])],
+        partial_stripe: &mut PartialStripe,
+    ) -> SUResult<()> {
+        updates
+            .iter()
+            .try_for_each(|&(update_source_idx, offset, update_slice)| {
+                self.delta_update(update_slice, update_source_idx, offset, partial_stripe)
+            })
+    }
+    /// Verify whether the parity blocks of the `stripe` are consistent with its source blocks.
+    /// The default implementation re-encodes a clone of the `stripe` via [`Self::encode_stripe()`]
+    /// and compares the parity blocks.
+    ///
+    /// # Return
+    /// - [`Ok(true)`] if the parity blocks are consistent with the source blocks
+    /// - [`Ok(false)`] if not consistent
+    ///
+    /// # Error
+    /// - [`SUError::ErasureCode`] if `k` and `p` between the erasure code and `stripe` do not match
+    fn verify(&self, stripe: &Stripe) -> SUResult<bool> {
+        let mut expect = stripe.clone();
+        self.encode_stripe(&mut expect)?;
+        Ok(expect.as_parity() == stripe.as_parity())
+    }
 }
 
 /// check the k and p matches between erasure code interface and the `partial_stripe`
@@ -122,16 +163,15 @@ mod test {
 
     pub const K: usize = 4;
     pub const P: usize = 2;
-    pub const M: usize = K + P;
     pub const BLOCK_SIZE: usize = 4 << 10;
     pub const STRIPE_NUM: usize = 1 << 10;
 
-    pub fn gen_stripes() -> Vec<Stripe> {
+    pub fn gen_stripes(k: usize, p: usize) -> Vec<Stripe> {
         (0..STRIPE_NUM)
             .map(|_| {
                 Stripe::zero(
-                    NonZeroUsize::new(K).unwrap(),
-                    NonZeroUsize::new(P).unwrap(),
+                    NonZeroUsize::new(k).unwrap(),
+                    NonZeroUsize::new(p).unwrap(),
                     NonZeroUsize::new(BLOCK_SIZE).unwrap(),
                 )
             })
@@ -148,7 +188,7 @@ mod test {
 
     pub fn test_encode_decode(ec: &dyn ErasureCode) {
         let stripes = {
-            let mut s = gen_stripes();
+            let mut s = gen_stripes(ec.k(), ec.p());
             s.iter_mut()
                 .for_each(|stripe| ec.encode_stripe(stripe).unwrap());
             s
@@ -156,9 +196,9 @@ mod test {
         let corrupt_idx: Vec<Vec<_>> = (0..stripes.len())
             .map(|_| {
                 // randomly corrupt 1~p blocks
-                let corrupt_num = rand::thread_rng().gen_range(1..=P);
+                let corrupt_num = rand::thread_rng().gen_range(1..=ec.p());
                 let mut corrupt_idx = (0..corrupt_num)
-                    .map(|_| rand::thread_rng().gen_range(0..M))
+                    .map(|_| rand::thread_rng().gen_range(0..ec.m()))
                     .collect::<Vec<_>>();
                 corrupt_idx.sort();
                 corrupt_idx.dedup();
@@ -192,6 +232,121 @@ mod test {
             .for_each(|(a, b)| assert_stripe_eq(a, b));
     }
 
+    pub fn test_update_many(ec: &dyn ErasureCode) {
+        let mut stripes = gen_stripes(ec.k(), ec.p());
+        stripes
+            .iter_mut()
+            .for_each(|stripe| ec.encode_stripe(stripe).unwrap());
+        stripes.iter().for_each(|stripe| {
+            // random updates, which may overlap on the same source block
+            let update_num = rand::thread_rng().gen_range(0..=2 * ec.k());
+            let updates = (0..update_num)
+                .map(|_| {
+                    let source_idx = rand::thread_rng().gen_range(0..ec.k());
+                    let start = rand::thread_rng().gen_range(0..BLOCK_SIZE);
+                    let end = rand::thread_rng().gen_range(start + 1..=BLOCK_SIZE);
+                    let slice = rand::thread_rng()
+                        .sample_iter(rand::distributions::Standard)
+                        .take(end - start)
+                        .collect::<Vec<u8>>();
+                    (source_idx, start, slice)
+                })
+                .collect::<Vec<_>>();
+            let expect = {
+                let mut s = PartialStripe::from(stripe);
+                updates.iter().for_each(|(idx, offset, slice)| {
+                    ec.delta_update(slice, *idx, *offset, &mut s).unwrap()
+                });
+                Stripe::try_from(s).unwrap()
+            };
+            let result = {
+                let mut s = PartialStripe::from(stripe);
+                let updates = updates
+                    .iter()
+                    .map(|(idx, offset, slice)| (*idx, *offset, slice.as_slice()))
+                    .collect::<Vec<_>>();
+                ec.delta_update_many(&updates, &mut s).unwrap();
+                Stripe::try_from(s).unwrap()
+            };
+            assert_stripe_eq(&expect, &result);
+        });
+    }
+
+    pub fn test_verify(ec: &dyn ErasureCode) {
+        let mut stripes = gen_stripes(ec.k(), ec.p());
+        stripes
+            .iter_mut()
+            .for_each(|stripe| ec.encode_stripe(stripe).unwrap());
+        stripes
+            .iter()
+            .for_each(|stripe| assert!(ec.verify(stripe).unwrap()));
+        // flip a random byte of a random block
+        stripes.iter_mut().for_each(|stripe| {
+            let block_idx = rand::thread_rng().gen_range(0..ec.m());
+            let byte_idx = rand::thread_rng().gen_range(0..BLOCK_SIZE);
+            let block = if block_idx < ec.k() {
+                &mut stripe.as_mut_source()[block_idx]
+            } else {
+                &mut stripe.as_mut_parity()[block_idx - ec.k()]
+            };
+            block[byte_idx] ^= rand::thread_rng().gen_range(1..=u8::MAX);
+        });
+        stripes
+            .iter()
+            .for_each(|stripe| assert!(!ec.verify(stripe).unwrap()));
+        // k p mismatch
+        let stripe = Stripe::zero(
+            NonZeroUsize::new(ec.k() + 1).unwrap(),
+            NonZeroUsize::new(ec.p()).unwrap(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        );
+        assert!(matches!(
+            ec.verify(&stripe),
+            Err(crate::SUError::ErasureCode(_))
+        ));
+    }
+
+    /// Delegate to [`ReedSolomon`](super::ReedSolomon) except [`ErasureCode::verify()`],
+    /// to test the default implementation.
+    struct DefaultVerify(super::ReedSolomon);
+
+    impl ErasureCode for DefaultVerify {
+        fn k(&self) -> usize {
+            self.0.k()
+        }
+        fn p(&self) -> usize {
+            self.0.p()
+        }
+        fn m(&self) -> usize {
+            self.0.m()
+        }
+        fn encode_stripe(&self, stripe: &mut Stripe) -> crate::SUResult<()> {
+            self.0.encode_stripe(stripe)
+        }
+        fn decode(&self, partial_stripe: &mut PartialStripe) -> crate::SUResult<()> {
+            self.0.decode(partial_stripe)
+        }
+        fn delta_update(
+            &self,
+            update_slice: &[u8],
+            update_source_idx: usize,
+            offset: usize,
+            partial_stripe: &mut PartialStripe,
+        ) -> crate::SUResult<()> {
+            self.0
+                .delta_update(update_slice, update_source_idx, offset, partial_stripe)
+        }
+    }
+
+    #[test]
+    fn default_verify() {
+        let ec = DefaultVerify(super::ReedSolomon::from_k_p(
+            NonZeroUsize::new(K).unwrap(),
+            NonZeroUsize::new(P).unwrap(),
+        ));
+        test_verify(&ec);
+    }
+
     fn stripe_update(ec: &dyn ErasureCode, stripe: &Stripe) {
         let mut rng = rand::thread_rng();
         let range = {
@@ -199,7 +354,7 @@ mod test {
             let end = rand::thread_rng().gen_range(start + 1..BLOCK_SIZE);
             start..end
         };
-        let update_slice = (0..K)
+        let update_slice = (0..ec.k())
             .map(|_| {
                 rng.gen_bool(0.4).then(|| {
                     rand::thread_rng()
@@ -244,7 +399,7 @@ mod test {
     }
 
     pub fn test_update(ec: &dyn ErasureCode) {
-        let mut stripes = gen_stripes();
+        let mut stripes = gen_stripes(ec.k(), ec.p());
         stripes
             .iter_mut()
             .for_each(|stripe| ec.encode_stripe(stripe).unwrap());
@@ -254,23 +409,7 @@ mod test {
     fn assert_stripe_eq(a: &Stripe, b: &Stripe) {
         assert_eq!(a.k(), b.k());
         assert_eq!(a.p(), b.p());
-        for (i, (a, b)) in a
-            .as_source()
-            .iter()
-            .chain(a.as_parity())
-            .zip(b.as_source().iter().chain(b.as_parity()))
-            .enumerate()
-        {
-            if a != b {
-                let a_hex = hex::encode(a);
-                let b_hex = hex::encode(b);
-                panic!(
-                    "the {i}-th block not match:\n
-                    a:{}\n
-                    b:{}\n",
-                    a_hex, b_hex
-                );
-            }
-        }
+        let diff = a.diff(b);
+        assert!(diff.is_empty(), "blocks {diff:?} do not match");
     }
 }
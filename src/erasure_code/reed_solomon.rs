@@ -2,7 +2,10 @@ use std::num::NonZeroUsize;
 
 use crate::{erasure_code::Block, SUError, SUResult};
 
-use super::{check_partial_stripe_k_p, check_stripe_k_p, ErasureCode};
+use super::{
+    block::{gf_mac_slice, xor_slice},
+    check_partial_stripe_k_p, check_stripe_k_p, ErasureCode,
+};
 
 /// Make a reed-solomon erasure code instance.
 pub struct ReedSolomon {
@@ -19,10 +22,23 @@ pub struct ReedSolomon {
 impl ReedSolomon {
     /// Make a [`ReedSolomon`]`(k+p, k)` erasure code.
     pub fn from_k_p(k: NonZeroUsize, p: NonZeroUsize) -> Self {
-        let k = k.get();
-        let p = p.get();
-        let m = k + p;
-        let encode_mat = isa_l::gf_gen_rs_matrix(k, m);
+        let encode_mat = isa_l::gf_gen_rs_matrix(k.get(), k.get() + p.get());
+        Self::from_encode_mat(k.get(), p.get(), encode_mat)
+    }
+
+    /// Make a [`ReedSolomon`]`(k+p, k)` erasure code with a cauchy encode matrix.
+    ///
+    /// Unlike the vandermonde-based matrix used by [`Self::from_k_p`],
+    /// any `k` rows of a cauchy encode matrix are guaranteed to be invertible,
+    /// so that any `p` absent blocks can always be decoded.
+    pub fn from_k_p_cauchy(k: NonZeroUsize, p: NonZeroUsize) -> Self {
+        let encode_mat = isa_l::gf_gen_cauchy1_matrix(k.get(), k.get() + p.get());
+        Self::from_encode_mat(k.get(), p.get(), encode_mat)
+    }
+
+    /// Make a [`ReedSolomon`] from a `(k + p) * k` encode matrix
+    fn from_encode_mat(k: usize, p: usize, encode_mat: Vec<u8>) -> Self {
+        debug_assert_eq!(encode_mat.len(), (k + p) * k);
         let encode_parity_table = isa_l::ec_init_tables_owned(k, p, &encode_mat[(k * k)..]);
         Self {
             k,
@@ -32,78 +48,45 @@ impl ReedSolomon {
         }
     }
 
-    fn parity_delta_update(
-        &self,
-        source_slice: &[u8],
-        source_idx: usize,
-        parity_slice: &mut [&mut [u8]],
-    ) -> SUResult<()> {
-        parity_slice
-            .iter_mut()
-            .enumerate()
-            .for_each(|(parity_idx, parity_slice)| {
-                parity_slice
-                    .iter_mut()
-                    .zip(source_slice)
-                    .for_each(|(p, &d)| {
-                        let coef = self.encode_parity_table
-                            [source_idx * 32 + parity_idx * self.k * 32 + 1];
-                        *p ^= isa_l::gf_mul(d, coef);
-                    });
-            });
-        Ok(())
+    /// Encode the full stripes in parallel on the global thread pool,
+    /// each stripe is encoded by [`ErasureCode::encode_stripe()`].
+    ///
+    /// # Error
+    /// - If `k` and `p` between this [`ReedSolomon`] erasure code and any stripe do not match,
+    ///   and the other stripes may or may not be encoded.
+    #[cfg(feature = "rayon")]
+    pub fn encode_stripes(&self, stripes: &mut [super::Stripe]) -> SUResult<()> {
+        use rayon::prelude::*;
+        stripes
+            .par_iter_mut()
+            .try_for_each(|stripe| self.encode_stripe(stripe))
     }
-}
 
-impl ErasureCode for ReedSolomon {
-    /// number of the source block
-    #[inline]
-    fn k(&self) -> usize {
-        self.k
-    }
-    /// number of the parity block
-    #[inline]
-    fn p(&self) -> usize {
-        self.p
-    }
-    /// number of the source and parity block
-    #[inline]
-    fn m(&self) -> usize {
-        self.k() + self.p()
-    }
-    /// Encode the full stripe, the source blocks will remain unmodified,
-    /// and the parity blocks will be encoded from the source blocks.
-    fn encode_stripe(&self, stripe: &mut super::Stripe) -> crate::SUResult<()> {
-        check_stripe_k_p(self, stripe, file!(), line!(), column!())?;
-        let len = stripe.block_size();
-        let (source, parity) = stripe.split_mut_source_parity();
-        isa_l::ec_encode_data(
-            len,
-            self.k(),
-            self.p(),
-            &self.encode_parity_table,
-            source,
-            parity,
-        );
-        Ok(())
-    }
-    /// Decode the absent blocks from the present blocks in the `partial_stripe`.
-    /// If success, all the blocks in the `partial_stripe` will be present,
-    /// otherwise the `partial_stripe` will remain unmodified.
+    /// Decode the absent blocks from the present blocks in the `partial_stripe`,
+    /// the same as [`ErasureCode::decode()`], except that the blocks indexed by `prefer`
+    /// take priority when selecting the `k` survivors to decode from.
+    /// If the preferred present blocks are fewer than `k`,
+    /// the other present blocks are selected in index order.
     ///
-    /// # Return
-    /// - [`Ok`] if decode successfully, and all the blocks in the `partial_stripe` will be present.
-    /// - [`Err(SUError::ErasureCode)`] if any error occurs, and the `partial_stripe` will remain unmodified.
+    /// This is useful when fetching some blocks (e.g., the parity blocks) is more expensive than others.
+    ///
+    /// # Parameters
+    /// - `partial_stripe`: the partial stripe to decode
+    /// - `prefer`: index of the blocks preferred to decode from, in the order of priority
     ///
     /// # Error
     /// - If the number of absent blocks are greater than the number of parity blocks.
     /// - If `k` and `p` between this [`ReedSolomon`] erasure code and `partial_stripe` do not match
-    fn decode(&self, partial_stripe: &mut super::PartialStripe) -> crate::SUResult<()> {
+    pub fn decode_with_preference(
+        &self,
+        partial_stripe: &mut super::PartialStripe,
+        prefer: &[usize],
+    ) -> SUResult<()> {
         check_partial_stripe_k_p(self, partial_stripe, file!(), line!(), column!())?;
         let block_size = partial_stripe.block_size();
         let (present, absent) = partial_stripe.split_mut_present_absent();
         if absent.len() > self.p {
-            return Err(crate::SUError::erasure_code(
+            return Err(SUError::erasure_code(
                 (file!(), line!(), column!()),
                 format!(
                     "cannot decode {} blocks from {} blocks by ({}, {}) rs code",
@@ -114,12 +97,21 @@ impl ErasureCode for ReedSolomon {
                 ),
             ));
         }
-        // select the first k survivors
-        let (survivor_idx, survivor_block): (Vec<_>, Vec<_>) = present
+        // select k survivors, the preferred blocks first and then the others in index order
+        let mut survivor = present
             .iter()
-            .take(self.k)
             .map(|(idx, block_opt)| (*idx, block_opt.as_ref().unwrap()))
-            .unzip();
+            .collect::<Vec<_>>();
+        survivor.sort_by_key(|(idx, _)| {
+            prefer
+                .iter()
+                .position(|prefer_idx| prefer_idx == idx)
+                .unwrap_or(usize::MAX)
+        });
+        survivor.truncate(self.k);
+        // the rows of the decode matrix are in index order
+        survivor.sort_by_key(|(idx, _)| *idx);
+        let (survivor_idx, survivor_block): (Vec<_>, Vec<_>) = survivor.into_iter().unzip();
         let b = self
             .encode_mat
             .chunks_exact(self.k)
@@ -148,15 +140,10 @@ impl ErasureCode for ReedSolomon {
                     decode_vec.copy_from_slice(&inv_mat[k * corrupt_idx..k * corrupt_idx + k]);
                 } else {
                     // For non-src (parity) erasures need to multiply encode matrix * invert
-                    decode_vec.iter_mut().enumerate().for_each(|(i, b)| {
-                        *b = 0;
-                        for j in 0..k {
-                            *b ^= isa_l::gf_mul(
-                                inv_mat[j * k + i],
-                                self.encode_mat[k * corrupt_idx + j],
-                            );
-                        }
-                    })
+                    self.encode_mat[k * corrupt_idx..k * corrupt_idx + k]
+                        .iter()
+                        .zip(inv_mat.chunks_exact(k))
+                        .for_each(|(&coef, inv_row)| gf_mac_slice(decode_vec, coef, inv_row))
                 }
             },
         );
@@ -178,6 +165,144 @@ impl ErasureCode for ReedSolomon {
             });
         Ok(())
     }
+
+    /// Encode the `p` parity blocks from the `k` source blocks, all of the same size.
+    pub(super) fn encode_parities(
+        &self,
+        source: &[impl AsRef<[u8]>],
+        parity: &mut [impl AsMut<[u8]>],
+    ) {
+        debug_assert_eq!(source.len(), self.k);
+        debug_assert_eq!(parity.len(), self.p);
+        isa_l::ec_encode_data(
+            source[0].as_ref().len(),
+            self.k,
+            self.p,
+            &self.encode_parity_table,
+            source,
+            parity,
+        );
+    }
+
+    /// Encode only the parity block at `parity_idx` from the `k` source blocks into `out`,
+    /// with the row of the encode matrix of that parity.
+    ///
+    /// This is the minimal work to rebuild a single parity block,
+    /// compared with encoding all the `p` parity blocks by [`ErasureCode::encode_stripe()`].
+    ///
+    /// # Error
+    /// - [`SUError::ErasureCode`] if the number of the source blocks is not `k`
+    /// - [`SUError::Range`] if `parity_idx` is out of the parity bound
+    /// - [`SUError::Range`] if the size of any source block does not match the size of `out`
+    pub fn encode_parity(
+        &self,
+        source: &[Block],
+        parity_idx: usize,
+        out: &mut Block,
+    ) -> SUResult<()> {
+        if source.len() != self.k {
+            return Err(SUError::erasure_code(
+                (file!(), line!(), column!()),
+                format!(
+                    "{} source blocks are given, {} expected",
+                    source.len(),
+                    self.k
+                ),
+            ));
+        }
+        if parity_idx >= self.p {
+            return Err(SUError::out_of_range(
+                (file!(), line!(), column!()),
+                Some(0..self.p),
+                parity_idx..parity_idx + 1,
+            ));
+        }
+        let block_size = out.block_size();
+        if let Some(block) = source.iter().find(|block| block.block_size() != block_size) {
+            return Err(SUError::range_not_match(
+                (file!(), line!(), column!()),
+                0..block_size,
+                0..block.block_size(),
+            ));
+        }
+        let table = isa_l::ec_init_tables_owned(self.k, 1, self.parity_coefficients(parity_idx));
+        isa_l::ec_encode_data(
+            block_size,
+            self.k,
+            1,
+            &table,
+            source,
+            std::slice::from_mut(out),
+        );
+        Ok(())
+    }
+
+    /// Get the coefficients of the parity at `parity_idx` on each source block.
+    pub(super) fn parity_coefficients(&self, parity_idx: usize) -> &[u8] {
+        let row = self.k + parity_idx;
+        &self.encode_mat[row * self.k..(row + 1) * self.k]
+    }
+
+    /// Apply the delta of the source block at `source_idx` to the parity slices.
+    ///
+    /// The coefficient of the parity at `parity_idx` on the source at `source_idx`
+    /// is taken from the parity rows of the encode matrix,
+    /// which is the same coefficient expanded into `encode_parity_table` by `ec_init_tables`.
+    pub(super) fn parity_delta_update(
+        &self,
+        source_slice: &[u8],
+        source_idx: usize,
+        parity_slice: &mut [&mut [u8]],
+    ) -> SUResult<()> {
+        parity_slice
+            .iter_mut()
+            .enumerate()
+            .for_each(|(parity_idx, parity_slice)| {
+                let coef = self.encode_mat[(self.k + parity_idx) * self.k + source_idx];
+                gf_mac_slice(parity_slice, coef, source_slice);
+            });
+        Ok(())
+    }
+}
+
+impl ErasureCode for ReedSolomon {
+    /// number of the source block
+    #[inline]
+    fn k(&self) -> usize {
+        self.k
+    }
+    /// number of the parity block
+    #[inline]
+    fn p(&self) -> usize {
+        self.p
+    }
+    /// number of the source and parity block
+    #[inline]
+    fn m(&self) -> usize {
+        self.k() + self.p()
+    }
+    /// Encode the full stripe, the source blocks will remain unmodified,
+    /// and the parity blocks will be encoded from the source blocks.
+    fn encode_stripe(&self, stripe: &mut super::Stripe) -> crate::SUResult<()> {
+        check_stripe_k_p(self, stripe, file!(), line!(), column!())?;
+        let (source, parity) = stripe.split_mut_source_parity();
+        self.encode_parities(source, parity);
+        Ok(())
+    }
+    /// Decode the absent blocks from the present blocks in the `partial_stripe`.
+    /// If success, all the blocks in the `partial_stripe` will be present,
+    /// otherwise the `partial_stripe` will remain unmodified.
+    ///
+    /// # Return
+    /// - [`Ok`] if decode successfully, and all the blocks in the `partial_stripe` will be present.
+    /// - [`Err(SUError::ErasureCode)`] if any error occurs, and the `partial_stripe` will remain unmodified.
+    ///
+    /// # Error
+    /// - If the number of absent blocks are greater than the number of parity blocks.
+    /// - If `k` and `p` between this [`ReedSolomon`] erasure code and `partial_stripe` do not match
+    fn decode(&self, partial_stripe: &mut super::PartialStripe) -> crate::SUResult<()> {
+        self.decode_with_preference(partial_stripe, &[])
+    }
     /// Update the stripe in delta manner.
     /// That is, only the area `[offset, offset + update_slice.len())` of the source block
     /// at `update_source_idx` are updated to the content of `update_slice`.
@@ -255,6 +380,132 @@ impl ErasureCode for ReedSolomon {
         target_slice.copy_from_slice(update_slice);
         Ok(())
     }
+    /// Update the stripe in delta manner with a batch of updates,
+    /// which is equivalent to calling [`Self::delta_update()`] on each update in order.
+    ///
+    /// The deltas of all the updates are accumulated over the union range of the updates,
+    /// and the parity blocks are updated only once.
+    ///
+    /// # Parameters
+    /// - `updates`: a batch of `(update_source_idx, offset, update_slice)`
+    /// - `partial_stripe`: partial stripe to update, all the parity blocks should be present,
+    ///   and will be updated source blocks.
+    ///
+    /// # Error
+    /// All the updates are checked before any modification,
+    /// the `partial_stripe` will remain unmodified if any error occurs.
+    /// - [SUError::ErasureCode] if not all the parity blocks are present
+    /// - [SUError::ErasureCode] if any target source block to update is absent
+    /// - [SUError::Range] if any `update_source_idx` is out of source block bound
+    /// - [SUError::Range] if any updated area is out of block bound
+    fn delta_update_many(
+        &self,
+        updates: &[(usize, usize, &[u8])],
+        partial_stripe: &mut super::PartialStripe,
+    ) -> SUResult<()> {
+        // check k p
+        check_partial_stripe_k_p(self, partial_stripe, file!(), line!(), column!())?;
+        let valid_range = 0..partial_stripe.block_size();
+        let (source, parity) = partial_stripe.split_mut_source_parity();
+        if !parity.iter().all(Option::is_some) {
+            return Err(SUError::erasure_code(
+                (file!(), line!(), column!()),
+                "not all the parity blocks are present",
+            ));
+        }
+        // check updates
+        updates
+            .iter()
+            .try_for_each(|&(update_source_idx, offset, update_slice)| {
+                let range = offset..(offset + update_slice.len());
+                if range.end > valid_range.end {
+                    return Err(SUError::out_of_range(
+                        (file!(), line!(), column!()),
+                        Some(valid_range.clone()),
+                        range,
+                    ));
+                }
+                match source.get(update_source_idx) {
+                    None => Err(SUError::out_of_range(
+                        (file!(), line!(), column!()),
+                        Some(0..self.k),
+                        0..update_source_idx,
+                    )),
+                    Some(None) => Err(SUError::erasure_code(
+                        (file!(), line!(), column!()),
+                        format!("the target source block at {update_source_idx} is absent"),
+                    )),
+                    Some(Some(_)) => Ok(()),
+                }
+            })?;
+        let union_range = match (
+            updates.iter().map(|(_, offset, _)| *offset).min(),
+            updates
+                .iter()
+                .map(|(_, offset, slice)| offset + slice.len())
+                .max(),
+        ) {
+            (Some(start), Some(end)) if start < end => start..end,
+            _ => return Ok(()),
+        };
+        // accumulate deltas of each source block
+        let mut source_delta = vec![vec![0_u8; union_range.len()]; self.k];
+        updates
+            .iter()
+            .for_each(|&(update_source_idx, offset, update_slice)| {
+                let range = offset..(offset + update_slice.len());
+                let target_slice = &mut source[update_source_idx].as_mut().unwrap()[range.clone()];
+                let delta = &mut source_delta[update_source_idx]
+                    [range.start - union_range.start..range.end - union_range.start];
+                xor_slice(delta, target_slice);
+                xor_slice(delta, update_slice);
+                target_slice.copy_from_slice(update_slice);
+            });
+        // update parity once
+        let mut parity_delta = vec![vec![0_u8; union_range.len()]; self.p];
+        isa_l::ec_encode_data(
+            union_range.len(),
+            self.k,
+            self.p,
+            &self.encode_parity_table,
+            &source_delta,
+            &mut parity_delta,
+        );
+        parity
+            .iter_mut()
+            .zip(parity_delta)
+            .for_each(|(parity_block, delta)| {
+                xor_slice(
+                    &mut parity_block.as_mut().unwrap()[union_range.clone()],
+                    &delta,
+                );
+            });
+        Ok(())
+    }
+    /// Verify whether the parity blocks of the `stripe` are consistent with its source blocks.
+    /// The parity blocks are encoded into scratch buffers and compared with the stored ones.
+    ///
+    /// # Return
+    /// - [`Ok(true)`] if the parity blocks are consistent with the source blocks
+    /// - [`Ok(false)`] if not consistent
+    ///
+    /// # Error
+    /// - [`SUError::ErasureCode`] if `k` and `p` between this [`ReedSolomon`] erasure code and `stripe` do not match
+    fn verify(&self, stripe: &super::Stripe) -> SUResult<bool> {
+        check_stripe_k_p(self, stripe, file!(), line!(), column!())?;
+        let block_size = stripe.block_size();
+        let (source, parity) = stripe.split_source_parity();
+        let mut scratch = Block::zero_n(self.p(), block_size);
+        isa_l::ec_encode_data(
+            block_size,
+            self.k(),
+            self.p(),
+            &self.encode_parity_table,
+            source,
+            &mut scratch,
+        );
+        Ok(scratch.as_slice() == parity)
+    }
 }
 
 #[cfg(test)]
@@ -277,4 +528,253 @@ mod test {
             ReedSolomon::from_k_p(NonZeroUsize::new(K).unwrap(), NonZeroUsize::new(P).unwrap());
         test_update(&ec);
     }
+
+    #[test]
+    fn cauchy_encode_decode() {
+        let ec = ReedSolomon::from_k_p_cauchy(
+            NonZeroUsize::new(K).unwrap(),
+            NonZeroUsize::new(P).unwrap(),
+        );
+        test_encode_decode(&ec);
+    }
+
+    #[test]
+    fn cauchy_delta_update() {
+        let ec = ReedSolomon::from_k_p_cauchy(
+            NonZeroUsize::new(K).unwrap(),
+            NonZeroUsize::new(P).unwrap(),
+        );
+        test_update(&ec);
+    }
+
+    #[test]
+    fn cauchy_decode_all_erasure_patterns() {
+        use itertools::Itertools;
+        use rand::Rng;
+
+        use crate::erasure_code::{ErasureCode, PartialStripe, Stripe};
+
+        const K: usize = 10;
+        const P: usize = 4;
+        const BLOCK_SIZE: usize = 64;
+        let ec = ReedSolomon::from_k_p_cauchy(
+            NonZeroUsize::new(K).unwrap(),
+            NonZeroUsize::new(P).unwrap(),
+        );
+        let mut stripe = Stripe::zero(
+            NonZeroUsize::new(K).unwrap(),
+            NonZeroUsize::new(P).unwrap(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        );
+        stripe.iter_mut_source().for_each(|block| {
+            block
+                .iter_mut()
+                .for_each(|byte| *byte = rand::thread_rng().gen())
+        });
+        ec.encode_stripe(&mut stripe).unwrap();
+        (1..=P)
+            .flat_map(|absent_num| (0..K + P).combinations(absent_num))
+            .for_each(|absent_idx| {
+                let mut partial_stripe = PartialStripe::from(&stripe);
+                absent_idx.iter().for_each(|&idx| {
+                    partial_stripe.replace_block(idx, None);
+                });
+                ec.decode(&mut partial_stripe)
+                    .unwrap_or_else(|e| panic!("fail to decode with {absent_idx:?} absent, {e}"));
+                let decoded = Stripe::try_from(partial_stripe).unwrap();
+                assert_eq!(
+                    decoded.as_source(),
+                    stripe.as_source(),
+                    "source mismatch with {absent_idx:?} absent"
+                );
+                assert_eq!(
+                    decoded.as_parity(),
+                    stripe.as_parity(),
+                    "parity mismatch with {absent_idx:?} absent"
+                );
+            });
+    }
+
+    #[test]
+    fn verify() {
+        let ec =
+            ReedSolomon::from_k_p(NonZeroUsize::new(K).unwrap(), NonZeroUsize::new(P).unwrap());
+        test_verify(&ec);
+    }
+
+    #[test]
+    fn delta_update_many() {
+        let ec =
+            ReedSolomon::from_k_p(NonZeroUsize::new(K).unwrap(), NonZeroUsize::new(P).unwrap());
+        test_update_many(&ec);
+    }
+
+    #[test]
+    fn decode_with_preference() {
+        use rand::Rng;
+
+        use crate::erasure_code::{ErasureCode, PartialStripe, Stripe};
+
+        let ec =
+            ReedSolomon::from_k_p(NonZeroUsize::new(K).unwrap(), NonZeroUsize::new(P).unwrap());
+        let mut stripe = Stripe::zero(
+            NonZeroUsize::new(K).unwrap(),
+            NonZeroUsize::new(P).unwrap(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        );
+        stripe.iter_mut_source().for_each(|block| {
+            block
+                .iter_mut()
+                .for_each(|byte| *byte = rand::thread_rng().gen())
+        });
+        ec.encode_stripe(&mut stripe).unwrap();
+        // the first source block is absent, and the first parity block is corrupted
+        let decode_source = |prefer: &[usize]| {
+            let mut partial_stripe = PartialStripe::from(&stripe);
+            partial_stripe.replace_block(0, None);
+            let mut parity = stripe.as_parity()[0].clone();
+            parity.iter_mut().for_each(|byte| *byte = !*byte);
+            partial_stripe.replace_block(K, Some(parity));
+            ec.decode_with_preference(&mut partial_stripe, prefer)
+                .unwrap();
+            Stripe::try_from(partial_stripe).unwrap().as_source()[0].clone()
+        };
+        let expect = &stripe.as_source()[0];
+        // the corrupted parity is selected by default
+        assert_ne!(&decode_source(&[]), expect);
+        // the corrupted parity is avoided by preference
+        let prefer = (1..K).chain(K + 1..K + P).collect::<Vec<_>>();
+        assert_eq!(&decode_source(&prefer), expect);
+        // fall back to the other present blocks in index order
+        assert_eq!(&decode_source(&[K + 1]), expect);
+        // too many absent blocks
+        let mut partial_stripe = PartialStripe::from(&stripe);
+        (0..=P).for_each(|idx| {
+            partial_stripe.replace_block(idx, None);
+        });
+        assert!(matches!(
+            ec.decode_with_preference(&mut partial_stripe, &[0, 1]),
+            Err(crate::SUError::ErasureCode(_))
+        ));
+    }
+
+    #[test]
+    fn encode_parity() {
+        use rand::Rng;
+
+        use crate::erasure_code::{Block, ErasureCode, Stripe};
+
+        let ec =
+            ReedSolomon::from_k_p(NonZeroUsize::new(K).unwrap(), NonZeroUsize::new(P).unwrap());
+        let mut stripe = Stripe::zero(
+            NonZeroUsize::new(K).unwrap(),
+            NonZeroUsize::new(P).unwrap(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        );
+        stripe.iter_mut_source().for_each(|block| {
+            block
+                .iter_mut()
+                .for_each(|byte| *byte = rand::thread_rng().gen())
+        });
+        ec.encode_stripe(&mut stripe).unwrap();
+        (0..P).for_each(|parity_idx| {
+            let mut parity = Block::zero(BLOCK_SIZE);
+            ec.encode_parity(stripe.as_source(), parity_idx, &mut parity)
+                .unwrap();
+            assert_eq!(
+                parity,
+                stripe.as_parity()[parity_idx],
+                "parity {parity_idx}"
+            );
+        });
+
+        let mut parity = Block::zero(BLOCK_SIZE);
+        assert!(ec
+            .encode_parity(stripe.as_source(), P, &mut parity)
+            .is_err());
+        assert!(ec
+            .encode_parity(&stripe.as_source()[1..], 0, &mut parity)
+            .is_err());
+        let mut parity = Block::zero(BLOCK_SIZE + 1);
+        assert!(ec
+            .encode_parity(stripe.as_source(), 0, &mut parity)
+            .is_err());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn encode_stripes() {
+        use crate::erasure_code::ErasureCode;
+
+        let ec =
+            ReedSolomon::from_k_p(NonZeroUsize::new(K).unwrap(), NonZeroUsize::new(P).unwrap());
+        let mut expect = gen_stripes(K, P);
+        let mut result = expect.clone();
+        expect
+            .iter_mut()
+            .for_each(|stripe| ec.encode_stripe(stripe).unwrap());
+        ec.encode_stripes(&mut result).unwrap();
+        assert_eq!(expect, result);
+    }
+
+    #[test]
+    fn delta_update_all_coefficients() {
+        use rand::Rng;
+
+        use crate::erasure_code::{ErasureCode, PartialStripe, Stripe};
+
+        const BLOCK_SIZE: usize = 64;
+        [(1, 1), (2, 1), (3, 2), (4, 2), (6, 3), (10, 4), (12, 4)]
+            .into_iter()
+            .flat_map(|(k, p)| {
+                let k = NonZeroUsize::new(k).unwrap();
+                let p = NonZeroUsize::new(p).unwrap();
+                [
+                    ReedSolomon::from_k_p(k, p),
+                    ReedSolomon::from_k_p_cauchy(k, p),
+                ]
+            })
+            .for_each(|ec| {
+                let (k, p) = (ec.k(), ec.p());
+                let mut stripe = Stripe::zero(
+                    NonZeroUsize::new(k).unwrap(),
+                    NonZeroUsize::new(p).unwrap(),
+                    NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+                );
+                stripe.iter_mut_source().for_each(|block| {
+                    block
+                        .iter_mut()
+                        .for_each(|byte| *byte = rand::thread_rng().gen())
+                });
+                ec.encode_stripe(&mut stripe).unwrap();
+                // every parity block is compared against a full re-encode,
+                // so that all the (source_idx, parity_idx) pairs are covered
+                (0..k).for_each(|source_idx| {
+                    let start = rand::thread_rng().gen_range(0..BLOCK_SIZE);
+                    let end = rand::thread_rng().gen_range(start + 1..=BLOCK_SIZE);
+                    let update_slice = rand::thread_rng()
+                        .sample_iter(rand::distributions::Standard)
+                        .take(end - start)
+                        .collect::<Vec<u8>>();
+                    let expect = {
+                        let mut stripe = stripe.clone();
+                        stripe.as_mut_source()[source_idx][start..end]
+                            .copy_from_slice(&update_slice);
+                        ec.encode_stripe(&mut stripe).unwrap();
+                        stripe
+                    };
+                    let mut partial_stripe = PartialStripe::from(&stripe);
+                    ec.delta_update(&update_slice, source_idx, start, &mut partial_stripe)
+                        .unwrap();
+                    let result = Stripe::try_from(partial_stripe).unwrap();
+                    assert_eq!(
+                        expect,
+                        result,
+                        "delta update mismatch on source {source_idx} in RS({}, {k})",
+                        k + p
+                    );
+                    stripe = result;
+                });
+            });
+    }
 }
@@ -1,11 +1,29 @@
 use std::num::NonZeroUsize;
 
-use bytes::{BufMut, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 
-use crate::SUError;
+use crate::{SUError, SUResult};
 
 use super::Block;
 
+/// Check if `k` and `p` fit in the stripe.
+///
+/// # Error
+/// - [`SUError::ErasureCode`] if `k` or `p` exceeds [`u8::MAX`]
+fn check_k_p(
+    k: NonZeroUsize,
+    p: NonZeroUsize,
+    source_location: (&str, u32, u32),
+) -> SUResult<(u8, u8)> {
+    match (u8::try_from(k.get()), u8::try_from(p.get())) {
+        (Ok(k), Ok(p)) => Ok((k, p)),
+        _ => Err(SUError::erasure_code(
+            source_location,
+            format!("k({k}) or p({p}) exceeds {}", u8::MAX),
+        )),
+    }
+}
+
 /// A [`Stripe`] is composed of `k` source [`Block`]s and `p` parity [`Block`]s,
 /// and all the blocks in a stripe are guaranteed to be consistent.
 /// Typically a stripe can tolerant at most `p` block faults,
@@ -46,17 +64,43 @@ impl Stripe {
     ///
     /// # Panics
     /// - If `vec.len() != k + p`
+    /// - If the blocks are not of the same size
+    /// - If `k` or `p` exceeds [`u8::MAX`]
     pub fn from_vec(vec: Vec<Block>, k: NonZeroUsize, p: NonZeroUsize) -> Self {
-        let k = k.get();
-        let p = p.get();
-        assert_eq!(vec.len(), k + p);
+        Self::try_from_vec(vec, k, p).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Make a stripe from a vector of blocks like [`Self::from_vec()`],
+    /// but the malformed input is reported as an error instead of a panic.
+    ///
+    /// # Error
+    /// - [`SUError::ErasureCode`] if `vec.len() != k + p`,
+    ///   or the blocks are not of the same size,
+    ///   or `k` or `p` exceeds [`u8::MAX`]
+    pub fn try_from_vec(vec: Vec<Block>, k: NonZeroUsize, p: NonZeroUsize) -> SUResult<Self> {
+        let (k, p) = check_k_p(k, p, (file!(), line!(), column!()))?;
+        let m = usize::from(k) + usize::from(p);
+        if vec.len() != m {
+            return Err(SUError::erasure_code(
+                (file!(), line!(), column!()),
+                format!("{} blocks provided for a stripe of {m} blocks", vec.len()),
+            ));
+        }
         let block_size = vec.first().unwrap().block_size();
-        assert!(vec.iter().all(|block| block.block_size() == block_size));
-        Self {
-            k: k.try_into().unwrap(),
-            p: p.try_into().unwrap(),
-            stripe: vec,
+        if let Some((idx, block)) = vec
+            .iter()
+            .enumerate()
+            .find(|(_, block)| block.block_size() != block_size)
+        {
+            return Err(SUError::erasure_code(
+                (file!(), line!(), column!()),
+                format!(
+                    "size of block {idx} ({}) does not match block size {block_size}",
+                    block.block_size()
+                ),
+            ));
         }
+        Ok(Self { k, p, stripe: vec })
     }
 
     /// Convert this stripe to blocks
@@ -64,21 +108,112 @@ impl Stripe {
         self.stripe
     }
 
+    /// Make a stripe from a contiguous buffer holding `k` source blocks and `p` parity blocks back-to-back.
+    /// The buffer is split into blocks without copying.
+    ///
+    /// # Error
+    /// - [`SUError::Range`] if `data.len() != (k + p) * block_size`
+    pub fn from_contiguous_bytes(
+        mut data: BytesMut,
+        k: NonZeroUsize,
+        p: NonZeroUsize,
+        block_size: NonZeroUsize,
+    ) -> SUResult<Self> {
+        let m = k.get() + p.get();
+        let block_size = block_size.get();
+        if data.len() != m * block_size {
+            return Err(SUError::range_not_match(
+                (file!(), line!(), column!()),
+                0..m * block_size,
+                0..data.len(),
+            ));
+        }
+        let stripe = (0..m)
+            .map(|_| Block::split_from_buf(&mut data, block_size))
+            .collect();
+        Self::try_from_vec(stripe, k, p)
+    }
+
+    /// Get the content of all the blocks back-to-back in a contiguous buffer,
+    /// the source blocks first and then the parity blocks.
+    ///
+    /// # Note
+    /// This function implies data copy, see [`Self::into_contiguous_bytes()`] to avoid the copy.
+    pub fn to_contiguous_bytes(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(self.m() * self.block_size());
+        self.stripe.iter().for_each(|block| buf.put_slice(block));
+        buf.freeze()
+    }
+
+    /// Convert this stripe to a contiguous buffer with all the blocks back-to-back,
+    /// the source blocks first and then the parity blocks.
+    ///
+    /// No data is copied if the blocks are already contiguous in memory,
+    /// e.g., the stripe made by [`Self::zero()`] or [`Self::from_contiguous_bytes()`].
+    pub fn into_contiguous_bytes(self) -> Bytes {
+        let mut blocks = self.stripe.into_iter().map(BytesMut::from);
+        let mut buf = blocks.next().unwrap();
+        blocks.for_each(|block| buf.unsplit(block));
+        buf.freeze()
+    }
+
+    /// Get the CRC32 checksum of the source blocks in order.
+    /// The parity blocks are not covered, since they are determined by the source blocks.
+    ///
+    /// Comparing the checksum with the one taken before the blocks are lost
+    /// detects a stripe mis-decoded from silently corrupted blocks.
+    pub fn checksum(&self) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        self.iter_source().for_each(|block| hasher.update(block));
+        hasher.finalize()
+    }
+
+    /// Get the indices of the blocks differing from the ones of `other` at the same index,
+    /// in ascending order. Each pair of blocks is compared until the first differing byte.
+    ///
+    /// # Panics
+    /// - If `k` or `p` of the stripes do not match
+    pub fn diff(&self, other: &Stripe) -> Vec<usize> {
+        assert_eq!(
+            (self.k, self.p),
+            (other.k, other.p),
+            "k and p of the stripes do not match"
+        );
+        self.stripe
+            .iter()
+            .zip(other.stripe.iter())
+            .enumerate()
+            .filter_map(|(idx, (a, b))| (a != b).then_some(idx))
+            .collect()
+    }
+
     /// Make a stripe with `k` source blocks and `p` parity blocks,
     /// and the payload of all the blocks are filled with `0`.
+    ///
+    /// # Panics
+    /// - If `k` or `p` exceeds [`u8::MAX`]
     pub fn zero(k: NonZeroUsize, p: NonZeroUsize, block_size: NonZeroUsize) -> Self {
-        let k = k.get();
-        let p = p.get();
+        Self::try_zero(k, p, block_size).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Make a stripe filled with `0` like [`Self::zero()`],
+    /// but the malformed input is reported as an error instead of a panic.
+    ///
+    /// # Error
+    /// - [`SUError::ErasureCode`] if `k` or `p` exceeds [`u8::MAX`]
+    pub fn try_zero(k: NonZeroUsize, p: NonZeroUsize, block_size: NonZeroUsize) -> SUResult<Self> {
+        let (k, p) = check_k_p(k, p, (file!(), line!(), column!()))?;
+        let m = usize::from(k) + usize::from(p);
         let block_size = block_size.get();
-        let mut buf = BytesMut::zeroed(block_size * (k + p));
-        Self {
-            k: k.try_into().unwrap(),
-            p: p.try_into().unwrap(),
-            stripe: (0..k + p)
+        let mut buf = BytesMut::zeroed(block_size * m);
+        Ok(Self {
+            k,
+            p,
+            stripe: (0..m)
                 .map(|_| buf.split_to(block_size))
                 .map(Block::from)
                 .collect(),
-        }
+        })
     }
 
     /// Split a stripe to slices of source blocks and parity blocks
@@ -274,6 +409,42 @@ impl PartialStripe {
         )
     }
 
+    /// Set a block like [`Self::replace_block()`],
+    /// but the malformed input is reported as an error instead of a panic.
+    ///
+    /// # Return
+    /// - [`Ok`] with the old value of the block
+    /// - [`Err`] if any error occurs, and the partial stripe will remain unmodified
+    ///
+    /// # Error
+    /// - [`SUError::Range`] if `block_idx` is out of bounds
+    /// - [`SUError::ErasureCode`] if the size of `block` does not match the block size
+    pub fn try_replace_block(
+        &mut self,
+        block_idx: usize,
+        block: Option<Block>,
+    ) -> SUResult<Option<Block>> {
+        let m = self.m();
+        let block_size = self.block_size;
+        let target = self.stripe.get_mut(block_idx).ok_or_else(|| {
+            SUError::out_of_range(
+                (file!(), line!(), column!()),
+                Some(0..m),
+                block_idx..block_idx + 1,
+            )
+        })?;
+        if let Some(block) = block.as_ref().filter(|b| b.block_size() != block_size) {
+            return Err(SUError::erasure_code(
+                (file!(), line!(), column!()),
+                format!(
+                    "size of block {block_idx} ({}) does not match block size {block_size}",
+                    block.block_size()
+                ),
+            ));
+        }
+        Ok(std::mem::replace(target, block))
+    }
+
     /// Make a [`PartialStripe`] with `k` source blocks and `p` parity blocks.
     /// All the blocks are sized with `block_size` and absent.
     pub fn make_absent_from_k_p(
@@ -336,6 +507,17 @@ impl PartialStripe {
         (present, absent)
     }
 
+    /// Get an iterator over all the blocks in the order of the block index,
+    /// yielding the index with [`Some`] block if present, or [`None`] if absent.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (usize, Option<&Block>)> {
+        self.stripe.iter().map(Option::as_ref).enumerate()
+    }
+
+    /// Get an iterator over all the blocks like [`Self::iter()`], with the present blocks mutable.
+    pub fn iter_mut(&mut self) -> impl ExactSizeIterator<Item = (usize, Option<&mut Block>)> {
+        self.stripe.iter_mut().map(Option::as_mut).enumerate()
+    }
+
     /// Get an iterator over the present blocks with its index
     pub fn iter_present(&self) -> impl Iterator<Item = (usize, &Block)> {
         self.stripe
@@ -362,6 +544,38 @@ impl PartialStripe {
             .filter_map(|(idx, block_opt)| block_opt.is_none().then_some(idx))
             .collect()
     }
+
+    /// Get a minimal set of blocks sufficient to decode the block at `target_idx`.
+    ///
+    /// Any `k` blocks other than the target are sufficient to decode it,
+    /// the present blocks are preferred so that the absent blocks to fetch are minimized.
+    ///
+    /// # Return
+    /// - [`None`] if the target block is already present
+    /// - [`Some`] with the indexes of `k` blocks in ascending order, excluding `target_idx`
+    ///
+    /// # Panics
+    /// - if `target_idx` is out of bounds
+    pub fn decode_dependencies(&self, target_idx: usize) -> Option<Vec<usize>> {
+        let m = self.m();
+        let target = self
+            .stripe
+            .get(target_idx)
+            .unwrap_or_else(|| panic!("block index({target_idx}) is greater than m({m})"));
+        if target.is_some() {
+            return None;
+        }
+        let (present, absent): (Vec<_>, Vec<_>) = (0..m)
+            .filter(|idx| *idx != target_idx)
+            .partition(|idx| self.stripe[*idx].is_some());
+        let mut dependencies = present
+            .into_iter()
+            .chain(absent)
+            .take(self.k())
+            .collect::<Vec<_>>();
+        dependencies.sort_unstable();
+        Some(dependencies)
+    }
 }
 
 impl From<&Stripe> for PartialStripe {
@@ -398,3 +612,236 @@ impl From<Stripe> for PartialStripe {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use super::PartialStripe;
+    use crate::erasure_code::Block;
+
+    const K: usize = 4;
+    const P: usize = 2;
+    const BLOCK_SIZE: usize = 16;
+
+    fn make_partial_stripe(present: &[usize]) -> PartialStripe {
+        let mut partial_stripe = PartialStripe::make_absent_from_k_p(
+            NonZeroUsize::new(K).unwrap(),
+            NonZeroUsize::new(P).unwrap(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        );
+        present.iter().for_each(|idx| {
+            partial_stripe.replace_block(*idx, Some(Block::zero(BLOCK_SIZE)));
+        });
+        partial_stripe
+    }
+
+    #[test]
+    fn contiguous_bytes() {
+        use bytes::BytesMut;
+        use rand::Rng;
+
+        use super::Stripe;
+        use crate::SUError;
+
+        let k = NonZeroUsize::new(K).unwrap();
+        let p = NonZeroUsize::new(P).unwrap();
+        let block_size = NonZeroUsize::new(BLOCK_SIZE).unwrap();
+        let data = rand::thread_rng()
+            .sample_iter(rand::distributions::Standard)
+            .take((K + P) * BLOCK_SIZE)
+            .collect::<Vec<u8>>();
+        let stripe =
+            Stripe::from_contiguous_bytes(BytesMut::from(data.as_slice()), k, p, block_size)
+                .unwrap();
+        stripe
+            .iter_source()
+            .chain(stripe.iter_parity())
+            .zip(data.chunks_exact(BLOCK_SIZE))
+            .for_each(|(block, expect)| assert_eq!(block.as_ref(), expect));
+        assert_eq!(stripe.to_contiguous_bytes(), data);
+        assert_eq!(stripe.clone().into_contiguous_bytes(), data);
+        // not contiguous
+        let mut blocks = stripe.into_blocks();
+        blocks.reverse();
+        let reversed = Stripe::from_vec(blocks, k, p).into_contiguous_bytes();
+        assert!(reversed
+            .chunks_exact(BLOCK_SIZE)
+            .rev()
+            .eq(data.chunks_exact(BLOCK_SIZE)));
+        // length mismatch
+        let e = Stripe::from_contiguous_bytes(BytesMut::from(&data[1..]), k, p, block_size);
+        assert!(matches!(e, Err(SUError::Range(_))));
+    }
+
+    #[test]
+    fn checksum() {
+        use super::Stripe;
+
+        let mut stripe = Stripe::zero(
+            NonZeroUsize::new(K).unwrap(),
+            NonZeroUsize::new(P).unwrap(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        );
+        let checksum = stripe.checksum();
+        assert_eq!(checksum, crc32fast::hash(&[0; K * BLOCK_SIZE]));
+        // parity is not covered
+        stripe.iter_mut_parity().for_each(|parity| parity[0] = 1);
+        assert_eq!(stripe.checksum(), checksum);
+        stripe.iter_mut_source().last().unwrap()[BLOCK_SIZE - 1] ^= 0x80;
+        assert_ne!(stripe.checksum(), checksum);
+    }
+
+    #[test]
+    fn diff() {
+        use super::Stripe;
+
+        let stripe = Stripe::zero(
+            NonZeroUsize::new(K).unwrap(),
+            NonZeroUsize::new(P).unwrap(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        );
+        let mut other = stripe.clone();
+        assert!(stripe.diff(&other).is_empty());
+        // a source block and a parity block differ at the last byte
+        other.as_mut_source()[1][BLOCK_SIZE - 1] = 1;
+        other.as_mut_parity()[0][0] = 1;
+        assert_eq!(stripe.diff(&other), vec![1, K]);
+        assert_eq!(other.diff(&stripe), vec![1, K]);
+    }
+
+    #[test]
+    fn malformed_stripe() {
+        use super::Stripe;
+        use crate::SUError;
+
+        let k = NonZeroUsize::new(K).unwrap();
+        let p = NonZeroUsize::new(P).unwrap();
+        let block_size = NonZeroUsize::new(BLOCK_SIZE).unwrap();
+        assert!(Stripe::try_from_vec(Block::zero_n(K + P, BLOCK_SIZE), k, p).is_ok());
+        // block number mismatch
+        let e = Stripe::try_from_vec(Block::zero_n(K + P - 1, BLOCK_SIZE), k, p);
+        assert!(matches!(e, Err(SUError::ErasureCode(_))));
+        // block size mismatch
+        let mut blocks = Block::zero_n(K + P, BLOCK_SIZE);
+        blocks[K] = Block::zero(BLOCK_SIZE * 2);
+        let e = Stripe::try_from_vec(blocks, k, p);
+        assert!(matches!(e, Err(SUError::ErasureCode(_))));
+        // too many blocks
+        let many = NonZeroUsize::new(u8::MAX as usize + 1).unwrap();
+        let e = Stripe::try_from_vec(Block::zero_n(many.get() + P, BLOCK_SIZE), many, p);
+        assert!(matches!(e, Err(SUError::ErasureCode(_))));
+        assert!(matches!(
+            Stripe::try_zero(k, many, block_size),
+            Err(SUError::ErasureCode(_))
+        ));
+        assert_eq!(
+            Stripe::try_zero(k, p, block_size).unwrap(),
+            Stripe::zero(k, p, block_size)
+        );
+    }
+
+    #[test]
+    fn try_replace_block() {
+        use crate::SUError;
+
+        let mut partial_stripe = make_partial_stripe(&[0, 1]);
+        let block = Block::zero(BLOCK_SIZE);
+        assert!(partial_stripe
+            .try_replace_block(K, Some(block.clone()))
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            partial_stripe.try_replace_block(0, None).unwrap(),
+            Some(block.clone())
+        );
+        assert_eq!(partial_stripe, make_partial_stripe(&[1, K]));
+        // out of range index
+        let e = partial_stripe.try_replace_block(K + P, Some(block));
+        assert!(matches!(e, Err(SUError::Range(_))));
+        // block size mismatch
+        let e = partial_stripe.try_replace_block(0, Some(Block::zero(BLOCK_SIZE + 1)));
+        assert!(matches!(e, Err(SUError::ErasureCode(_))));
+        assert_eq!(partial_stripe, make_partial_stripe(&[1, K]));
+    }
+
+    #[test]
+    fn iter() {
+        let present = [0, 2, K + 1];
+        let mut partial_stripe = make_partial_stripe(&present);
+        assert_eq!(partial_stripe.iter().len(), K + P);
+        partial_stripe
+            .iter()
+            .zip(0..K + P)
+            .for_each(|((idx, block), expect_idx)| {
+                assert_eq!(idx, expect_idx);
+                assert_eq!(block.is_some(), present.contains(&idx));
+            });
+        partial_stripe
+            .iter_mut()
+            .filter_map(|(idx, block)| block.map(|block| (idx, block)))
+            .for_each(|(idx, block)| block.fill(idx as u8));
+        partial_stripe.replace_block(K, Some(Block::zero(BLOCK_SIZE)));
+        partial_stripe.replace_block(0, None);
+        let presence = partial_stripe
+            .iter()
+            .map(|(idx, block)| block.map(|block| (idx, block[0])))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            presence,
+            vec![
+                None,
+                None,
+                Some((2, 2)),
+                None,
+                Some((K, 0)),
+                Some((K + 1, K as u8 + 1))
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn from_vec_panics() {
+        super::Stripe::from_vec(
+            Block::zero_n(K, BLOCK_SIZE),
+            NonZeroUsize::new(K).unwrap(),
+            NonZeroUsize::new(P).unwrap(),
+        );
+    }
+
+    #[test]
+    fn decode_dependencies() {
+        // target present
+        let partial_stripe = make_partial_stripe(&[0, 1, 2, 3, 4, 5]);
+        assert_eq!(partial_stripe.decode_dependencies(2), None);
+        // enough present blocks, prefer the present ones
+        let partial_stripe = make_partial_stripe(&[1, 3, 4, 5]);
+        assert_eq!(
+            partial_stripe.decode_dependencies(0),
+            Some(vec![1, 3, 4, 5])
+        );
+        let partial_stripe = make_partial_stripe(&[0, 1, 2, 3, 4]);
+        assert_eq!(
+            partial_stripe.decode_dependencies(5),
+            Some(vec![0, 1, 2, 3])
+        );
+        // not enough present blocks, fetch the absent ones in index order
+        let partial_stripe = make_partial_stripe(&[4, 5]);
+        assert_eq!(
+            partial_stripe.decode_dependencies(1),
+            Some(vec![0, 2, 4, 5])
+        );
+        let partial_stripe = make_partial_stripe(&[]);
+        assert_eq!(
+            partial_stripe.decode_dependencies(0),
+            Some(vec![1, 2, 3, 4])
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn decode_dependencies_out_of_bounds() {
+        make_partial_stripe(&[]).decode_dependencies(K + P);
+    }
+}
@@ -0,0 +1,180 @@
+use std::num::NonZeroUsize;
+
+use crate::{erasure_code::Block, SUError, SUResult};
+
+use super::{block::xor_slice, check_partial_stripe_k_p, check_stripe_k_p, ErasureCode};
+
+/// Make a RAID-5 style erasure code instance with `k` source blocks and a single xor parity block.
+pub struct XorParity {
+    /// number of source data
+    k: usize,
+}
+
+impl XorParity {
+    /// Make a [`XorParity`]`(k+1, k)` erasure code.
+    pub fn from_k(k: NonZeroUsize) -> Self {
+        Self { k: k.get() }
+    }
+}
+
+/// Xor all the blocks in `blocks` into `target`
+///
+/// # Error
+/// - [`SUError::Range`] if the size of any block does not match `target`
+fn xor_all_into<'a>(
+    target: &mut Block,
+    mut blocks: impl Iterator<Item = &'a Block>,
+) -> SUResult<()> {
+    target.fill(0);
+    blocks.try_for_each(|block| target.xor_assign(block))
+}
+
+impl ErasureCode for XorParity {
+    /// number of the source block
+    #[inline]
+    fn k(&self) -> usize {
+        self.k
+    }
+    /// number of the parity block, which is always `1`
+    #[inline]
+    fn p(&self) -> usize {
+        1
+    }
+    /// number of the source and parity block
+    #[inline]
+    fn m(&self) -> usize {
+        self.k() + self.p()
+    }
+    /// Encode the full stripe, the source blocks will remain unmodified,
+    /// and the parity block will be the xor of all the source blocks.
+    fn encode_stripe(&self, stripe: &mut super::Stripe) -> SUResult<()> {
+        check_stripe_k_p(self, stripe, file!(), line!(), column!())?;
+        let (source, parity) = stripe.split_mut_source_parity();
+        xor_all_into(&mut parity[0], source.iter())
+    }
+    /// Decode the absent block from the present blocks in the `partial_stripe`.
+    /// If success, all the blocks in the `partial_stripe` will be present,
+    /// otherwise the `partial_stripe` will remain unmodified.
+    ///
+    /// # Return
+    /// - [`Ok`] if decode successfully, and all the blocks in the `partial_stripe` will be present.
+    /// - [`Err(SUError::ErasureCode)`] if any error occurs, and the `partial_stripe` will remain unmodified.
+    ///
+    /// # Error
+    /// - If more than one block is absent.
+    /// - If `k` and `p` between this [`XorParity`] erasure code and `partial_stripe` do not match
+    fn decode(&self, partial_stripe: &mut super::PartialStripe) -> SUResult<()> {
+        check_partial_stripe_k_p(self, partial_stripe, file!(), line!(), column!())?;
+        let block_size = partial_stripe.block_size();
+        let (present, mut absent) = partial_stripe.split_mut_present_absent();
+        if absent.len() > 1 {
+            return Err(SUError::erasure_code(
+                (file!(), line!(), column!()),
+                format!(
+                    "cannot decode {} blocks from {} blocks by ({}, {}) xor code",
+                    absent.len(),
+                    present.len(),
+                    self.m(),
+                    self.k()
+                ),
+            ));
+        }
+        if let Some((_, block)) = absent.pop() {
+            let mut recover = Block::zero(block_size);
+            xor_all_into(
+                &mut recover,
+                present
+                    .iter()
+                    .map(|(_, block_opt)| block_opt.as_ref().unwrap()),
+            )?;
+            block.replace(recover);
+        }
+        Ok(())
+    }
+    /// Update the stripe in delta manner.
+    /// That is, only the area `[offset, offset + update_slice.len())` of the source block
+    /// at `update_source_idx` are updated to the content of `update_slice`.
+    /// And then, the delta are xor-ed into the corresponding area of the parity block.
+    ///
+    /// # Parameters
+    /// - `update_slice`: the content to copy to the target source block
+    /// - `update_source_idx`: the index of the source block to update in a stripe
+    /// - `offset`: the start of the region to update
+    /// - `partial_stripe`: partial stripe to update, the parity block should be present,
+    ///   and will be updated source blocks.
+    ///
+    /// # Error
+    /// - [SUError::ErasureCode] if the parity block is absent
+    /// - [SUError::ErasureCode] if the target source block to update is absent
+    /// - [SUError::Range] if the `update_source_idx` is out of source block bound
+    /// - [SUError::Range] if the updated area `[offset, offset + update_slice.len())` is out of block bound
+    fn delta_update(
+        &self,
+        update_slice: &[u8],
+        update_source_idx: usize,
+        offset: usize,
+        partial_stripe: &mut super::PartialStripe,
+    ) -> SUResult<()> {
+        check_partial_stripe_k_p(self, partial_stripe, file!(), line!(), column!())?;
+        let valid_range = 0..partial_stripe.block_size();
+        let range = offset..(offset + update_slice.len());
+        if range.end > valid_range.end {
+            return Err(SUError::out_of_range(
+                (file!(), line!(), column!()),
+                Some(valid_range),
+                range,
+            ));
+        }
+        let (source, parity) = partial_stripe.split_mut_source_parity();
+        let parity = parity[0].as_mut().ok_or_else(|| {
+            SUError::erasure_code((file!(), line!(), column!()), "the parity block is absent")
+        })?;
+        let target_source = source
+            .get_mut(update_source_idx)
+            .ok_or_else(|| {
+                SUError::out_of_range(
+                    (file!(), line!(), column!()),
+                    Some(0..self.k),
+                    0..update_source_idx,
+                )
+            })?
+            .as_mut()
+            .ok_or_else(|| {
+                SUError::erasure_code(
+                    (file!(), line!(), column!()),
+                    format!("the target source block at {update_source_idx} is absent"),
+                )
+            })?;
+        let target_slice = &mut target_source[range.clone()];
+        xor_slice(&mut parity[range.clone()], target_slice);
+        xor_slice(&mut parity[range], update_slice);
+        target_slice.copy_from_slice(update_slice);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use super::super::test::*;
+    use super::XorParity;
+
+    #[test]
+    fn encode_decode() {
+        let ec = XorParity::from_k(NonZeroUsize::new(K).unwrap());
+        test_encode_decode(&ec);
+    }
+
+    #[test]
+    fn delta_update() {
+        let ec = XorParity::from_k(NonZeroUsize::new(K).unwrap());
+        test_update(&ec);
+    }
+
+    #[test]
+    fn verify() {
+        let ec = XorParity::from_k(NonZeroUsize::new(K).unwrap());
+        test_verify(&ec);
+    }
+}
@@ -1,3 +1,34 @@
+/// Stable discriminant of [`SUError`], to match on the kind of an error
+/// without depending on its message, e.g., of an error reported by a remote worker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum SUErrorKind {
+    Io,
+    InvalidArg,
+    Range,
+    ErasureCode,
+    Checksum,
+    Config,
+    Communication,
+    Other,
+}
+
+/// An error is serialized with its kind and message.
+///
+/// An io error keeps its raw os error code if any, and the code of its [`std::io::ErrorKind`] otherwise,
+/// while a redis error is deserialized as a client error with the original message,
+/// or as an io error of [`std::io::ErrorKind::TimedOut`] if it is retryable.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedError {
+    kind: SUErrorKind,
+    info: String,
+    /// [`SUError::is_retryable`] of the original error
+    retryable: bool,
+    /// raw os error code of an io error
+    os_code: Option<i32>,
+    /// position of the kind of an io error in [`IO_ERROR_KINDS`]
+    io_kind: Option<u8>,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum SUError {
     #[error("[kind: io, info:{0}]")]
@@ -8,6 +39,10 @@ pub enum SUError {
     Range(String),
     #[error("[kind: erasure code, info:{0}]")]
     ErasureCode(String),
+    #[error("[kind: checksum, info:{0}]")]
+    Checksum(String),
+    #[error("[kind: config, info:{0}]")]
+    Config(String),
     #[error("[kind: redis, info:{0}]")]
     Communication(#[from] redis::RedisError),
     #[error("[kind: other, info: {0}]")]
@@ -20,6 +55,17 @@ impl SUError {
         Self::InvalidArg(e.to_string())
     }
 
+    pub(crate) fn config(e: impl ToString) -> Self {
+        Self::Config(e.to_string())
+    }
+
+    pub(crate) fn not_found(e: impl ToString) -> Self {
+        Self::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            e.to_string(),
+        ))
+    }
+
     pub(crate) fn other(e: impl ToString) -> Self {
         Self::Other(e.to_string())
     }
@@ -84,6 +130,44 @@ impl SUError {
         ))
     }
 
+    /// Get the kind of the error.
+    pub fn kind(&self) -> SUErrorKind {
+        match self {
+            SUError::Io(_) => SUErrorKind::Io,
+            SUError::InvalidArg(_) => SUErrorKind::InvalidArg,
+            SUError::Range(_) => SUErrorKind::Range,
+            SUError::ErasureCode(_) => SUErrorKind::ErasureCode,
+            SUError::Checksum(_) => SUErrorKind::Checksum,
+            SUError::Config(_) => SUErrorKind::Config,
+            SUError::Communication(_) => SUErrorKind::Communication,
+            SUError::Other(_) => SUErrorKind::Other,
+        }
+    }
+
+    /// Whether the error is transient, so that the failed operation is worth retrying.
+    ///
+    /// # Return
+    /// - `true` for the interrupted, would-block or timed-out io errors,
+    ///   and the redis errors on timeout or dropped connection
+    /// - `false` for the others, which fail the same on retry
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            SUError::Io(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::WouldBlock
+                    | std::io::ErrorKind::TimedOut
+            ),
+            SUError::Communication(e) => e.is_timeout() || e.is_connection_dropped(),
+            SUError::InvalidArg(_)
+            | SUError::Range(_)
+            | SUError::ErasureCode(_)
+            | SUError::Checksum(_)
+            | SUError::Config(_)
+            | SUError::Other(_) => false,
+        }
+    }
+
     pub fn into_io_err(self) -> Option<std::io::Error> {
         if let SUError::Io(io_err) = self {
             Some(io_err)
@@ -93,4 +177,198 @@ impl SUError {
     }
 }
 
+/// Io error kinds kept across serialization, the others are deserialized as [`std::io::ErrorKind::Other`].
+///
+/// The position of a kind is its code in the serialized error,
+/// so a kind is only ever appended, or the protocol version is bumped.
+const IO_ERROR_KINDS: [std::io::ErrorKind; 17] = {
+    use std::io::ErrorKind::*;
+    [
+        NotFound,
+        PermissionDenied,
+        ConnectionRefused,
+        ConnectionReset,
+        ConnectionAborted,
+        NotConnected,
+        AddrInUse,
+        AddrNotAvailable,
+        BrokenPipe,
+        AlreadyExists,
+        WouldBlock,
+        InvalidInput,
+        InvalidData,
+        TimedOut,
+        WriteZero,
+        Interrupted,
+        UnexpectedEof,
+    ]
+};
+
+impl serde::Serialize for SUError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (info, os_code, io_kind) = match self {
+            SUError::Io(e) => (
+                e.to_string(),
+                e.raw_os_error(),
+                IO_ERROR_KINDS
+                    .iter()
+                    .position(|&k| k == e.kind())
+                    .map(|code| code as u8),
+            ),
+            SUError::Communication(e) => (e.to_string(), None, None),
+            SUError::InvalidArg(info)
+            | SUError::Range(info)
+            | SUError::ErasureCode(info)
+            | SUError::Checksum(info)
+            | SUError::Config(info)
+            | SUError::Other(info) => (info.clone(), None, None),
+        };
+        SerializedError {
+            kind: self.kind(),
+            info,
+            retryable: self.is_retryable(),
+            os_code,
+            io_kind,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for SUError {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let SerializedError {
+            kind,
+            info,
+            retryable,
+            os_code,
+            io_kind,
+        } = SerializedError::deserialize(deserializer)?;
+        Ok(match kind {
+            SUErrorKind::Io => SUError::Io(match os_code {
+                Some(code) => std::io::Error::from_raw_os_error(code),
+                None => std::io::Error::new(
+                    io_kind
+                        .and_then(|code| IO_ERROR_KINDS.get(usize::from(code)).copied())
+                        .unwrap_or(std::io::ErrorKind::Other),
+                    info,
+                ),
+            }),
+            SUErrorKind::InvalidArg => SUError::InvalidArg(info),
+            SUErrorKind::Range => SUError::Range(info),
+            SUErrorKind::ErasureCode => SUError::ErasureCode(info),
+            SUErrorKind::Checksum => SUError::Checksum(info),
+            SUErrorKind::Config => SUError::Config(info),
+            SUErrorKind::Communication if retryable => SUError::Communication(
+                std::io::Error::new(std::io::ErrorKind::TimedOut, info).into(),
+            ),
+            SUErrorKind::Communication => SUError::Communication(redis::RedisError::from((
+                redis::ErrorKind::ClientError,
+                "remote redis error",
+                info,
+            ))),
+            SUErrorKind::Other => SUError::Other(info),
+        })
+    }
+}
+
 pub type SUResult<T> = std::result::Result<T, SUError>;
+
+#[cfg(test)]
+mod test {
+    use super::{SUError, SUErrorKind};
+
+    fn round_trip(e: &SUError) -> SUError {
+        bincode::deserialize(&bincode::serialize(e).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let errors = [
+            SUError::not_found("block 1 not found"),
+            SUError::invalid_arg("bad argument"),
+            SUError::out_of_range((file!(), line!(), column!()), Some(0..4), 2..8),
+            SUError::erasure_code((file!(), line!(), column!()), "too many erasures"),
+            SUError::Checksum("block 2 corrupted".into()),
+            SUError::config("missing field"),
+            SUError::other("something wrong"),
+        ];
+        for e in errors.iter() {
+            let de = round_trip(e);
+            assert_eq!(de.kind(), e.kind());
+            assert_eq!(de.to_string(), e.to_string());
+        }
+        assert_eq!(
+            round_trip(&errors[0]).into_io_err().unwrap().kind(),
+            std::io::ErrorKind::NotFound
+        );
+        let e = SUError::Io(std::io::Error::from_raw_os_error(libc::ENOSPC));
+        let de = round_trip(&e);
+        assert_eq!(de.kind(), SUErrorKind::Io);
+        assert_eq!(de.to_string(), e.to_string());
+        assert_eq!(de.into_io_err().unwrap().raw_os_error(), Some(libc::ENOSPC));
+        let e = SUError::from(redis::RedisError::from((
+            redis::ErrorKind::IoError,
+            "connection lost",
+        )));
+        let de = round_trip(&e);
+        assert_eq!(de.kind(), SUErrorKind::Communication);
+        assert!(de.to_string().contains("connection lost"));
+    }
+
+    #[test]
+    fn is_retryable() {
+        use std::io::ErrorKind;
+        let io_err = |kind| SUError::Io(std::io::Error::new(kind, "io error"));
+        [
+            ErrorKind::Interrupted,
+            ErrorKind::WouldBlock,
+            ErrorKind::TimedOut,
+        ]
+        .into_iter()
+        .for_each(|kind| assert!(io_err(kind).is_retryable(), "{kind:?}"));
+        [
+            ErrorKind::NotFound,
+            ErrorKind::PermissionDenied,
+            ErrorKind::UnexpectedEof,
+        ]
+        .into_iter()
+        .for_each(|kind| assert!(!io_err(kind).is_retryable(), "{kind:?}"));
+        // the io error kind is kept across serialization
+        assert!(round_trip(&io_err(ErrorKind::TimedOut)).is_retryable());
+
+        let redis_io_err = |kind| {
+            SUError::from(redis::RedisError::from(std::io::Error::new(
+                kind,
+                "redis io error",
+            )))
+        };
+        assert!(redis_io_err(ErrorKind::TimedOut).is_retryable());
+        assert!(redis_io_err(ErrorKind::BrokenPipe).is_retryable());
+        let redis_type_err = SUError::from(redis::RedisError::from((
+            redis::ErrorKind::TypeError,
+            "type error",
+        )));
+        assert!(!redis_type_err.is_retryable());
+        // so is the retryability of a redis error, e.g., reported by a worker
+        [ErrorKind::TimedOut, ErrorKind::BrokenPipe]
+            .into_iter()
+            .for_each(|kind| {
+                let de = round_trip(&redis_io_err(kind));
+                assert_eq!(de.kind(), SUErrorKind::Communication);
+                assert!(de.is_retryable(), "{kind:?}");
+            });
+        assert!(!round_trip(&redis_type_err).is_retryable());
+
+        [
+            SUError::invalid_arg("bad argument"),
+            SUError::out_of_range((file!(), line!(), column!()), None, 2..8),
+            SUError::range_not_match((file!(), line!(), column!()), 0..4, 0..8),
+            SUError::erasure_code((file!(), line!(), column!()), "too many erasures"),
+            SUError::Checksum("block 2 corrupted".into()),
+            SUError::config("missing field"),
+            SUError::other("something wrong"),
+        ]
+        .iter()
+        .for_each(|e| assert!(!e.is_retryable(), "{e}"));
+    }
+}
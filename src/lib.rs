@@ -3,6 +3,11 @@ pub mod config;
 pub mod erasure_code;
 pub mod standalone;
 pub mod storage;
+pub mod trace_parser;
 
 mod error;
-pub use error::{SUError, SUResult};
+pub use error::{SUError, SUErrorKind, SUResult};
+#[cfg(feature = "logger")]
+mod logging;
+#[cfg(feature = "logger")]
+pub use logging::init_logger;
@@ -0,0 +1,49 @@
+/// Filter of the log records if `RUST_LOG` is not set,
+/// so that the debug records are suppressed by default.
+const DEFAULT_FILTER: &str = "info";
+
+/// Make a logger builder with the filter `rust_log`, in the syntax of `RUST_LOG`,
+/// or [`DEFAULT_FILTER`] if not set.
+fn builder(rust_log: Option<&str>) -> env_logger::Builder {
+    let mut builder = env_logger::Builder::new();
+    builder.parse_filters(rust_log.unwrap_or(DEFAULT_FILTER));
+    builder
+}
+
+/// Initialize the global logger, whose verbosity is controlled by `RUST_LOG`.
+///
+/// # Panics
+/// - If a global logger is already initialized
+pub fn init_logger() {
+    builder(std::env::var("RUST_LOG").ok().as_deref()).init();
+}
+
+#[cfg(test)]
+mod test {
+    use log::{Level, Log, Metadata};
+
+    fn enabled(logger: &env_logger::Logger, level: Level) -> bool {
+        logger.enabled(
+            &Metadata::builder()
+                .level(level)
+                .target("stripe_update::cluster::worker")
+                .build(),
+        )
+    }
+
+    #[test]
+    fn default_level() {
+        let logger = super::builder(None).build();
+        assert!(!enabled(&logger, Level::Debug));
+        assert!(!enabled(&logger, Level::Trace));
+        assert!(enabled(&logger, Level::Info));
+        assert!(enabled(&logger, Level::Error));
+
+        let logger = super::builder(Some("debug")).build();
+        assert!(enabled(&logger, Level::Debug));
+        assert!(!enabled(&logger, Level::Trace));
+
+        let logger = super::builder(Some("stripe_update::cluster::coordinator=debug")).build();
+        assert!(!enabled(&logger, Level::Debug));
+    }
+}
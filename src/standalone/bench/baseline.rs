@@ -10,7 +10,7 @@ use indicatif::ProgressIterator;
 use crate::{
     erasure_code::{Block, ErasureCode, PartialStripe, ReedSolomon},
     standalone::bench::UpdateRequest,
-    standalone::dev_display,
+    standalone::{dev_display, stripe_checksum::StripeChecksums},
     storage::{
         BlockId, BlockStorage, BufferEviction, FixedSizeSliceBuf, HDDStorage, PartialBlock,
         SSDStorage, SliceBuffer, SliceOpt, SliceStorage,
@@ -18,15 +18,24 @@ use crate::{
     SUResult,
 };
 
-use super::Bench;
+use super::{
+    latency::{self, LatencyRecorder},
+    throughput::{self, ThroughputSampler},
+    Bench, UpdateBenchResult,
+};
 
-struct UpdateCtx<E: ErasureCode> {
-    hdd_storage: HDDStorage,
-    block_size: usize,
-    ec: E,
+pub(super) struct UpdateCtx<E: ErasureCode> {
+    pub(super) hdd_storage: HDDStorage,
+    pub(super) block_size: usize,
+    pub(super) ec: E,
 }
 
-fn do_update<E: ErasureCode>(
+/// Apply the slices evicted of a block to the hdd storage,
+/// by writing back the whole source block and the whole parity blocks of its stripe.
+///
+/// # Return
+/// The number of bytes written to the hdd storage
+pub(super) fn do_update<E: ErasureCode>(
     UpdateCtx {
         hdd_storage,
         block_size,
@@ -34,7 +43,7 @@ fn do_update<E: ErasureCode>(
     }: &UpdateCtx<E>,
     block_id: BlockId,
     update_slices: Vec<SliceOpt>,
-) {
+) -> usize {
     let k = ec.k();
     let block_size = *block_size;
     let p = ec.p();
@@ -78,19 +87,28 @@ fn do_update<E: ErasureCode>(
     });
     ec.delta_update(&update_source, block_id % m, 0, &mut partial_stripe)
         .unwrap();
-    partial_stripe.iter_present().for_each(|(id, block)| {
-        let id = block_id - block_id % m + id;
-        hdd_storage.put_block(id, block).unwrap();
-    });
+    // the checksum of the stripe built no longer holds
+    StripeChecksums::open(hdd_storage.get_dev_root())
+        .remove(block_id / m)
+        .unwrap();
+    partial_stripe
+        .iter_present()
+        .map(|(id, block)| {
+            let id = block_id - block_id % m + id;
+            hdd_storage.put_block(id, block).unwrap();
+            block.len()
+        })
+        .sum()
 }
 
 impl Bench {
-    pub(super) fn baseline(&self) -> SUResult<()> {
+    pub(super) fn baseline(&self) -> SUResult<UpdateBenchResult> {
         const CHANNEL_SIZE: usize = 64;
         struct Ack();
-        let (update_producer, update_consumer) =
-            std::sync::mpsc::sync_channel::<UpdateRequest>(CHANNEL_SIZE);
-        let (ack_producer, ack_consumer) = std::sync::mpsc::sync_channel::<Ack>(CHANNEL_SIZE);
+        let (update_producer, update_consumer) = self.sync_channel::<UpdateRequest>(CHANNEL_SIZE);
+        let (ack_producer, ack_consumer) = self.sync_channel::<Ack>(CHANNEL_SIZE);
+        // signal the end of the warmup, when the throughput sampling starts
+        let (warmed_up_producer, warmed_up_consumer) = std::sync::mpsc::sync_channel::<()>(1);
         let (k, p) = self.k_p.expect("k or p not set");
         let m = k + p;
         let block_size = self.block_size.expect("block size not set");
@@ -101,11 +119,12 @@ impl Bench {
         let ssd_block_cap = self.ssd_block_cap.expect("ssd block capacity not set");
         let ssd_cap = ssd_block_cap * block_size;
         let test_load = self.test_num.expect("test num not set");
+        let warmup = self.warmup.unwrap_or(0);
+        let ssd_op_latency = self.ssd_op_latency;
+        let hdd_op_latency = self.hdd_op_latency;
         let ssd_dev_display = dev_display(&ssd_dev_path);
         let hdd_dev_display = dev_display(&hdd_dev_path);
-        if ssd_dev_path.read_dir().unwrap().next().is_some() {
-            panic!("ssd dev path: {ssd_dev_display} is not empty");
-        }
+        self.prepare_ssd_dev(&ssd_dev_path)?;
         println!("RS({m}, {k})");
         println!("block size: {block_size}");
         println!("block num: {block_num}");
@@ -114,12 +133,21 @@ impl Bench {
         println!("ssd block capacity: {ssd_block_cap}");
         println!("slice size: {slice_size}");
         println!("test num: {test_load}");
+        if warmup > 0 {
+            println!("warmup: {warmup}");
+        }
+        if let Some(latency) = ssd_op_latency {
+            println!("ssd op latency: {latency:?}");
+        }
+        if let Some(latency) = hdd_op_latency {
+            println!("hdd op latency: {latency:?}");
+        }
         // data generator
         let data_generator_handle = std::thread::spawn(move || {
             use rand::Rng;
             const SEG_SIZE: usize = 4 << 10;
             let seg_num = block_size / SEG_SIZE;
-            (0..test_load).for_each(|_| {
+            (0..warmup + test_load).for_each(|_| {
                 let offset = rand::thread_rng().gen_range(0..seg_num);
                 let offset = offset * SEG_SIZE;
                 let block_id = { (0..).map(|_| rand::thread_rng().gen_range(0..block_num)) }
@@ -146,20 +174,39 @@ impl Bench {
                 ReedSolomon::from_k_p(NonZeroUsize::new(k).unwrap(), NonZeroUsize::new(p).unwrap());
             let hdd_storage =
                 HDDStorage::connect_to_dev(hdd_dev_path, NonZeroUsize::new(block_size).unwrap())
-                    .unwrap();
+                    .unwrap()
+                    .with_op_latency(hdd_op_latency);
             let ssd_storage = FixedSizeSliceBuf::connect_to_dev(
                 ssd_dev_path,
                 NonZeroUsize::new(block_size).unwrap(),
                 NonZeroUsize::new(ssd_cap).unwrap(),
             )
-            .unwrap();
-            let mut duration = std::time::Duration::ZERO;
-            let mut cnt = 0_usize;
+            .unwrap()
+            .with_op_latency(ssd_op_latency);
+            let mut recorder = LatencyRecorder::new();
             let update_ctx = UpdateCtx::<ReedSolomon> {
                 hdd_storage,
                 block_size,
                 ec,
             };
+            // the warmup updates are neither timed nor acked
+            let warmed_up = super::warm_up(
+                &ssd_storage,
+                &update_consumer,
+                warmup,
+                |BufferEviction {
+                     block_id,
+                     data: PartialBlock { size, slices },
+                 }| {
+                    debug_assert_eq!(size, block_size);
+                    do_update(&update_ctx, block_id, slices);
+                },
+            )
+            .unwrap();
+            if warmup > 0 {
+                println!("warmed up the ssd buffer to {warmed_up}/{ssd_cap} bytes");
+            }
+            warmed_up_producer.send(()).unwrap();
             while let Ok(UpdateRequest {
                 slice_data,
                 block_id,
@@ -167,6 +214,7 @@ impl Bench {
             }) = update_consumer.recv()
             {
                 let epoch = std::time::Instant::now();
+                recorder.record_user_write(slice_data.len());
                 let evict = ssd_storage
                     .push_slice(block_id, offset, slice_data.as_slice())
                     .unwrap();
@@ -176,41 +224,46 @@ impl Bench {
                 }) = evict
                 {
                     debug_assert_eq!(size, block_size);
-                    do_update(&update_ctx, block_id, slices);
+                    recorder.record_hdd_write(do_update(&update_ctx, block_id, slices));
                 };
-                let elapsed = epoch.elapsed();
-                duration += elapsed;
-                cnt += 1;
+                recorder.record(epoch.elapsed());
                 ack_producer.send(Ack()).unwrap();
             }
             buffer_len_updater.store(0, std::sync::atomic::Ordering::SeqCst);
-            while let Some(BufferEviction {
-                block_id,
-                data: PartialBlock { size, slices },
-            }) = ssd_storage.pop()
-            {
-                let epoch = std::time::Instant::now();
-                debug_assert_eq!(size, block_size);
-                do_update(&update_ctx, block_id, slices);
-                duration += epoch.elapsed();
-                cnt += 1;
-                ack_producer.send(Ack()).unwrap();
-                buffer_len_updater.store(
-                    ssd_cap - ssd_storage.len(),
-                    std::sync::atomic::Ordering::SeqCst,
-                );
-            }
-            (duration, cnt)
+            ssd_storage.drain_into(
+                |BufferEviction {
+                     block_id,
+                     data: PartialBlock { size, slices },
+                 }| {
+                    let epoch = std::time::Instant::now();
+                    debug_assert_eq!(size, block_size);
+                    recorder.record_hdd_write(do_update(&update_ctx, block_id, slices));
+                    recorder.record_drain(epoch.elapsed());
+                    ack_producer.send(Ack()).unwrap();
+                    buffer_len_updater.store(
+                        ssd_cap - ssd_storage.len(),
+                        std::sync::atomic::Ordering::SeqCst,
+                    );
+                },
+            );
+            recorder
         });
 
-        std::thread::spawn(move || {
+        let sample_interval = self
+            .sample_interval
+            .unwrap_or(throughput::DEFAULT_SAMPLE_INTERVAL);
+        let samples = std::thread::spawn(move || {
+            warmed_up_consumer.recv().unwrap();
+            let sampler = ThroughputSampler::spawn(sample_interval);
             (0..test_load)
                 .progress_with_style(crate::standalone::progress_style_template(Some(
                     "benchmark baseline...",
                 )))
                 .for_each(|_| {
                     ack_consumer.recv().unwrap();
+                    sampler.complete_one();
                 });
+            let samples = sampler.finish();
             std::io::stdout().flush().unwrap();
             let bar = indicatif::ProgressBar::new(ssd_cap.try_into().unwrap());
             bar.set_style(crate::standalone::progress_style_template(Some(
@@ -225,28 +278,24 @@ impl Bench {
                 );
             }
             println!("clean up updates buffered in ssd...done");
+            samples
         })
         .join()
         .unwrap();
         data_generator_handle.join().unwrap();
-        let (duration, cnt) = encoder_handle.join().unwrap();
-        println!("benchmark baseline...done");
-        println!(
-            "benchmarked {test_load} updates request in {}s{}ms",
-            duration.as_secs(),
-            duration.as_millis()
-        );
-        println!(
-            "OPS: {}",
-            cnt * 1000 * 1000 / usize::try_from(duration.as_micros()).unwrap()
-        );
-        Ok(())
+        let recorder = encoder_handle.join().unwrap();
+        println!("benchmark {}...done", self.manner);
+        let result = recorder.into_result(&self.manner, test_load);
+        latency::report(&result, self.out_dir_path.as_deref())?;
+        if let Some(out_dir_path) = self.out_dir_path.as_deref() {
+            throughput::dump(&samples, &self.manner, out_dir_path)?;
+        }
+        Ok(result)
     }
 
     fn _legacy_baseline(&self) -> SUResult<()> {
         const CHANNEL_SIZE: usize = 1024;
-        let (update_producer, update_consumer) =
-            std::sync::mpsc::sync_channel::<UpdateRequest>(CHANNEL_SIZE);
+        let (update_producer, update_consumer) = self.sync_channel::<UpdateRequest>(CHANNEL_SIZE);
         let (k, p) = self.k_p.expect("k or p not set");
         let m = k + p;
         let block_size = self.block_size.expect("block size not set");
@@ -256,6 +305,8 @@ impl Bench {
         let block_num = self.block_num.expect("block num not set");
         let ssd_cap = self.ssd_block_cap.expect("ssd block capacity not set");
         let test_num = self.test_num.expect("test num not set");
+        let ssd_op_latency = self.ssd_op_latency;
+        let hdd_op_latency = self.hdd_op_latency;
         let ssd_dev_display = dev_display(&ssd_dev_path);
         let hdd_dev_display = dev_display(&hdd_dev_path);
         println!("RS({m}, {k})");
@@ -266,6 +317,12 @@ impl Bench {
         println!("ssd block capacity: {ssd_cap}");
         println!("slice size: {slice_size}");
         println!("test num: {test_num}");
+        if let Some(latency) = ssd_op_latency {
+            println!("ssd op latency: {latency:?}");
+        }
+        if let Some(latency) = hdd_op_latency {
+            println!("hdd op latency: {latency:?}");
+        }
         print!("benchmark start...");
         std::io::stdout().flush().unwrap();
         // data generator
@@ -295,14 +352,16 @@ impl Bench {
                 ReedSolomon::from_k_p(NonZeroUsize::new(k).unwrap(), NonZeroUsize::new(p).unwrap());
             let hdd_storage =
                 HDDStorage::connect_to_dev(hdd_dev_path, NonZeroUsize::new(block_size).unwrap())
-                    .unwrap();
+                    .unwrap()
+                    .with_op_latency(hdd_op_latency);
             let ssd_storage = SSDStorage::connect_to_dev(
                 ssd_dev_path,
                 NonZeroUsize::new(block_size).unwrap(),
                 NonZeroUsize::new(ssd_cap).unwrap(),
                 hdd_storage,
             )
-            .unwrap();
+            .unwrap()
+            .with_op_latency(ssd_op_latency);
             let mut duration = std::time::Duration::ZERO;
             let mut cnt = 0_usize;
             while let Ok(UpdateRequest {
@@ -0,0 +1,202 @@
+use std::{io::Write, num::NonZeroUsize, path::PathBuf, time::Duration};
+
+use indicatif::ProgressIterator;
+use rand::Rng;
+
+use crate::{
+    erasure_code::{ErasureCode, PartialStripe, ReedSolomon, Stripe},
+    SUError, SUResult,
+};
+
+use super::Bench;
+
+const RESULT_FILE_NAME: &str = "code-bench.toml";
+/// Number of in-memory stripes reused across the test load
+const STRIPE_POOL_SIZE: usize = 64;
+
+/// Throughput of the erasure code, with no disk io involved.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CodeBenchResult {
+    pub k: usize,
+    pub p: usize,
+    pub block_size: usize,
+    pub slice_size: usize,
+    pub test_load: usize,
+    /// throughput of full stripe encoding over the source data, in GiB/s
+    pub encode: f64,
+    /// throughput of decoding over the source data, in GiB/s,
+    /// the `i`-th element is measured with `i + 1` absent blocks
+    pub decode: Vec<f64>,
+    /// throughput of delta update over the update slices, in GiB/s
+    pub delta_update: f64,
+}
+
+/// Throughput in GiB/s
+pub(super) fn gib_per_sec(bytes: usize, duration: Duration) -> f64 {
+    bytes as f64 / (1_u64 << 30) as f64 / duration.as_secs_f64()
+}
+
+/// Write the `result` to `out_dir_path` in toml format
+///
+/// # Return
+/// Path to the result file
+fn dump_result(result: &CodeBenchResult, out_dir_path: &std::path::Path) -> SUResult<PathBuf> {
+    let path = out_dir_path.join(RESULT_FILE_NAME);
+    let content = toml::to_string_pretty(result).map_err(SUError::other)?;
+    std::fs::write(path.as_path(), content)?;
+    Ok(path)
+}
+
+impl Bench {
+    pub(super) fn code_only(&self) -> SUResult<()> {
+        let (k, p) = self.k_p.expect("k or p not set");
+        let m = k + p;
+        let block_size = self.block_size.expect("block size not set");
+        let slice_size = self.slice_size.expect("slice size not set");
+        let test_load = self.test_num.expect("test num not set");
+        let out_dir_path = self.out_dir_path.to_owned().expect("out dir path not set");
+        println!("RS({m}, {k})");
+        println!("block size: {block_size}");
+        println!("slice size: {slice_size}");
+        println!("test num: {test_load}");
+        println!("output directory path: {}", out_dir_path.display());
+        if slice_size > block_size {
+            return Err(SUError::out_of_range(
+                (file!(), line!(), column!()),
+                Some(0..block_size),
+                0..slice_size,
+            ));
+        }
+        let ec =
+            ReedSolomon::from_k_p(NonZeroUsize::new(k).unwrap(), NonZeroUsize::new(p).unwrap());
+        print!("generating stripes...");
+        std::io::stdout().flush().unwrap();
+        let mut stripes = (0..STRIPE_POOL_SIZE.min(test_load))
+            .map(|_| {
+                let mut stripe = Stripe::zero(
+                    NonZeroUsize::new(k).unwrap(),
+                    NonZeroUsize::new(p).unwrap(),
+                    NonZeroUsize::new(block_size).unwrap(),
+                );
+                stripe.iter_mut_source().for_each(|block| {
+                    block
+                        .iter_mut()
+                        .for_each(|byte| *byte = rand::thread_rng().gen())
+                });
+                stripe
+            })
+            .collect::<Vec<_>>();
+        println!("done");
+        // encode
+        let mut encode_duration = Duration::ZERO;
+        (0..test_load)
+            .progress_with_style(crate::standalone::progress_style_template(Some(
+                "encode...",
+            )))
+            .try_for_each(|i| {
+                let stripe = &mut stripes[i % STRIPE_POOL_SIZE];
+                let now = std::time::Instant::now();
+                ec.encode_stripe(stripe)?;
+                encode_duration += now.elapsed();
+                SUResult::Ok(())
+            })?;
+        // decode
+        let decode = (1..=p)
+            .map(|absent_num| {
+                let mut decode_duration = Duration::ZERO;
+                (0..test_load)
+                    .progress_with_style(crate::standalone::progress_style_template(Some(
+                        format!("decode with {absent_num} absent...").as_str(),
+                    )))
+                    .try_for_each(|i| {
+                        let stripe = &stripes[i % STRIPE_POOL_SIZE];
+                        let mut partial_stripe = PartialStripe::from(stripe);
+                        rand::seq::index::sample(&mut rand::thread_rng(), m, absent_num)
+                            .into_iter()
+                            .for_each(|idx| {
+                                partial_stripe.replace_block(idx, None);
+                            });
+                        let now = std::time::Instant::now();
+                        ec.decode(&mut partial_stripe)?;
+                        decode_duration += now.elapsed();
+                        SUResult::Ok(())
+                    })?;
+                Ok(gib_per_sec(test_load * k * block_size, decode_duration))
+            })
+            .collect::<SUResult<Vec<_>>>()?;
+        // delta update
+        let mut partial_stripes = stripes.iter().map(PartialStripe::from).collect::<Vec<_>>();
+        drop(stripes);
+        let mut update_duration = Duration::ZERO;
+        (0..test_load)
+            .progress_with_style(crate::standalone::progress_style_template(Some(
+                "delta update...",
+            )))
+            .try_for_each(|i| {
+                let partial_stripe = &mut partial_stripes[i % STRIPE_POOL_SIZE];
+                let source_idx = rand::thread_rng().gen_range(0..k);
+                let offset = rand::thread_rng().gen_range(0..=block_size - slice_size);
+                let slice_data = rand::thread_rng()
+                    .sample_iter(rand::distributions::Standard)
+                    .take(slice_size)
+                    .collect::<Vec<u8>>();
+                let now = std::time::Instant::now();
+                ec.delta_update(&slice_data, source_idx, offset, partial_stripe)?;
+                update_duration += now.elapsed();
+                SUResult::Ok(())
+            })?;
+        let result = CodeBenchResult {
+            k,
+            p,
+            block_size,
+            slice_size,
+            test_load,
+            encode: gib_per_sec(test_load * k * block_size, encode_duration),
+            decode,
+            delta_update: gib_per_sec(test_load * slice_size, update_duration),
+        };
+        println!("encode: {:.3} GiB/s", result.encode);
+        result
+            .decode
+            .iter()
+            .zip(1..)
+            .for_each(|(throughput, absent_num)| {
+                println!("decode with {absent_num} absent: {throughput:.3} GiB/s")
+            });
+        println!("delta update: {:.3} GiB/s", result.delta_update);
+        let path = dump_result(&result, &out_dir_path)?;
+        println!("result dumped to {}", path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::standalone::bench::{Bench, Manner};
+
+    use super::{CodeBenchResult, RESULT_FILE_NAME};
+
+    #[test]
+    fn code_only_result() {
+        const K: usize = 4;
+        const P: usize = 2;
+        let out_dir = tempfile::tempdir().unwrap();
+        Bench::new()
+            .k_p(K, P)
+            .block_size(4 << 10)
+            .slice_size(4 << 10)
+            .test_load(256)
+            .out_dir_path(out_dir.path())
+            .manner(Manner::CodeOnly)
+            .run()
+            .unwrap();
+        let content = std::fs::read_to_string(out_dir.path().join(RESULT_FILE_NAME)).unwrap();
+        let result: CodeBenchResult = toml::from_str(&content).unwrap();
+        assert_eq!((result.k, result.p), (K, P));
+        assert_eq!(result.decode.len(), P);
+        assert!(result.encode > 0.0);
+        assert!(result.decode.iter().all(|throughput| *throughput > 0.0));
+        assert!(result.delta_update > 0.0);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{SUError, SUResult};
+
+use super::{Bench, Manner, UpdateBenchResult};
+
+/// A row of the table comparing the manners, see [`Bench::compare`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ComparisonRow {
+    pub manner: String,
+    /// updates per second, or blocks reconstructed per second for [`Manner::DegradedRead`]
+    pub ops: f64,
+    /// not available for the manners without writes
+    pub write_amplification: Option<f64>,
+    /// p99 latency in microseconds,
+    /// only available for the update manners with the `latency-histogram` feature
+    pub p99_us: Option<f64>,
+}
+
+impl From<UpdateBenchResult> for ComparisonRow {
+    fn from(result: UpdateBenchResult) -> Self {
+        Self {
+            manner: result.manner,
+            ops: result.ops,
+            write_amplification: result.write_amplification,
+            p99_us: result.latency.map(|latency| latency.p99_us),
+        }
+    }
+}
+
+impl Bench {
+    /// Run each of `manners` on the same dataset in order, and make a row of the comparison for each.
+    ///
+    /// The ssd device is purged before each update manner,
+    /// so that a manner never starts with the slices buffered by the previous one.
+    ///
+    /// # Error
+    /// - [`SUError::InvalidArg`] if a manner is neither [`Manner::Baseline`],
+    ///   [`Manner::MergeStripe`] nor [`Manner::DegradedRead`]
+    /// - any error of the purge or the benchmark of a manner
+    pub fn compare(&self, manners: &[Manner]) -> SUResult<Vec<ComparisonRow>> {
+        manners
+            .iter()
+            .map(|manner| {
+                let mut bench = self.clone();
+                bench.manner(manner.clone()).auto_clean(true);
+                match manner {
+                    Manner::Baseline | Manner::MergeStripe => {
+                        let result = if matches!(manner, Manner::Baseline) {
+                            bench.baseline()?
+                        } else {
+                            bench.merge_stripe()?
+                        };
+                        Ok(result.into())
+                    }
+                    Manner::DegradedRead => Ok(ComparisonRow {
+                        manner: manner.to_string(),
+                        ops: bench.degraded_read()?,
+                        write_amplification: None,
+                        p99_us: None,
+                    }),
+                    _ => Err(SUError::invalid_arg(format!(
+                        "manner {manner} is not comparable"
+                    ))),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Print the `rows` as a table, an unavailable metric is shown as `-`.
+pub fn print_comparison(rows: &[ComparisonRow]) {
+    let or_dash = |value: Option<f64>, precision: usize| {
+        value.map_or_else(|| "-".to_string(), |value| format!("{value:.precision$}"))
+    };
+    println!(
+        "{:<16}{:>12}{:>12}{:>12}",
+        "manner", "OPS", "write amp", "p99 (us)"
+    );
+    rows.iter().for_each(|row| {
+        println!(
+            "{:<16}{:>12.0}{:>12}{:>12}",
+            row.manner,
+            row.ops,
+            or_dash(row.write_amplification, 2),
+            or_dash(row.p99_us, 1)
+        );
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::standalone::{
+        bench::{Bench, Manner},
+        data_builder::build_test_dev,
+    };
+
+    #[test]
+    fn compare() {
+        const BLOCK_SIZE: usize = 64 << 10;
+        const BLOCK_NUM: usize = 12;
+        const K: usize = 4;
+        const P: usize = 2;
+        let ssd_dev = tempfile::tempdir().unwrap();
+        let hdd_dev = build_test_dev(K, P, BLOCK_NUM, BLOCK_SIZE);
+        let rows = Bench::new()
+            .k_p(K, P)
+            .block_size(BLOCK_SIZE)
+            .block_num(BLOCK_NUM)
+            .ssd_block_capacity(2)
+            .slice_size(4 << 10)
+            .test_load(64)
+            .ssd_dev_path(ssd_dev.path())
+            .hdd_dev_path(hdd_dev.path())
+            .compare(&[Manner::Baseline, Manner::MergeStripe, Manner::DegradedRead])
+            .unwrap();
+        super::print_comparison(&rows);
+        assert_eq!(
+            rows.iter()
+                .map(|row| row.manner.as_str())
+                .collect::<Vec<_>>(),
+            ["baseline", "merge_stripe", "degraded_read"]
+        );
+        assert!(rows.iter().all(|row| row.ops > 0.0));
+        assert!(rows[..2]
+            .iter()
+            .all(|row| row.write_amplification.unwrap() > 0.0));
+        assert!(rows[2].write_amplification.is_none());
+
+        assert!(Bench::new().compare(&[Manner::CodeOnly]).is_err());
+    }
+}
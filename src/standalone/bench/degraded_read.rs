@@ -0,0 +1,121 @@
+use std::{num::NonZeroUsize, time::Duration};
+
+use bytes::BytesMut;
+use indicatif::ProgressIterator;
+use rand::Rng;
+
+use crate::{
+    erasure_code::{ErasureCode, PartialStripe, ReedSolomon},
+    standalone::dev_display,
+    storage::{BlockStorage, HDDStorage},
+    SUError, SUResult,
+};
+
+use super::{code_only::gib_per_sec, Bench};
+
+impl Bench {
+    /// Reconstruct one absent block of each stripe.
+    ///
+    /// # Return
+    /// - [`Ok`] with the number of the blocks reconstructed per second
+    pub(super) fn degraded_read(&self) -> SUResult<f64> {
+        let (k, p) = self.k_p.expect("k or p not set");
+        let m = k + p;
+        let block_size = self.block_size.expect("block size not set");
+        let block_num = self.block_num.expect("block num not set");
+        let hdd_dev_path = self.hdd_dev_path.clone().expect("hdd dev path not set");
+        let hdd_op_latency = self.hdd_op_latency;
+        let stripe_num = block_num / m;
+        println!("RS({m}, {k})");
+        println!("block size: {block_size}");
+        println!("block num: {block_num}");
+        println!("stripe num: {stripe_num}");
+        println!("hdd dev path: {}", dev_display(&hdd_dev_path));
+        if let Some(latency) = hdd_op_latency {
+            println!("hdd op latency: {latency:?}");
+        }
+        let ec =
+            ReedSolomon::from_k_p(NonZeroUsize::new(k).unwrap(), NonZeroUsize::new(p).unwrap());
+        let hdd_storage =
+            HDDStorage::connect_to_dev(hdd_dev_path, NonZeroUsize::new(block_size).unwrap())?
+                .with_op_latency(hdd_op_latency);
+        let mut read_duration = Duration::ZERO;
+        let mut decode_duration = Duration::ZERO;
+        (0..stripe_num)
+            .progress_with_style(crate::standalone::progress_style_template(Some(
+                "benchmark degraded read...",
+            )))
+            .try_for_each(|stripe_id| {
+                // one block of each stripe is absent
+                let absent_idx = rand::thread_rng().gen_range(0..m);
+                let mut partial_stripe = PartialStripe::make_absent_from_k_p(
+                    NonZeroUsize::new(k).unwrap(),
+                    NonZeroUsize::new(p).unwrap(),
+                    NonZeroUsize::new(block_size).unwrap(),
+                );
+                let epoch = std::time::Instant::now();
+                for idx in (0..m).filter(|idx| *idx != absent_idx) {
+                    let block_id = stripe_id * m + idx;
+                    let mut block = BytesMut::zeroed(block_size);
+                    hdd_storage
+                        .get_block(block_id, &mut block)?
+                        .ok_or_else(|| SUError::other(format!("block {block_id} not found")))?;
+                    partial_stripe.replace_block(idx, Some(block.into()));
+                }
+                read_duration += epoch.elapsed();
+                let epoch = std::time::Instant::now();
+                ec.decode(&mut partial_stripe)?;
+                decode_duration += epoch.elapsed();
+                let reconstructed = partial_stripe.replace_block(absent_idx, None).unwrap();
+                debug_assert_eq!(
+                    hdd_storage
+                        .get_block_owned(stripe_id * m + absent_idx)?
+                        .unwrap()
+                        .as_slice(),
+                    reconstructed.as_ref()
+                );
+                SUResult::Ok(())
+            })?;
+        println!("benchmark degraded read...done");
+        let reconstructed_bytes = stripe_num * block_size;
+        println!(
+            "reconstructed {stripe_num} blocks in {}ms, {}ms spent on reading",
+            (read_duration + decode_duration).as_millis(),
+            read_duration.as_millis()
+        );
+        println!(
+            "decode: {:.3} GiB/s",
+            gib_per_sec(reconstructed_bytes, decode_duration)
+        );
+        println!(
+            "degraded read: {:.3} GiB/s",
+            gib_per_sec(reconstructed_bytes, read_duration + decode_duration)
+        );
+        Ok(stripe_num as f64 / (read_duration + decode_duration).as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::standalone::{
+        bench::{Bench, Manner},
+        data_builder::build_test_dev,
+    };
+
+    #[test]
+    fn degraded_read() {
+        const BLOCK_SIZE: usize = 64 << 10;
+        const BLOCK_NUM: usize = 24;
+        const K: usize = 4;
+        const P: usize = 2;
+        let hdd_dev = build_test_dev(K, P, BLOCK_NUM, BLOCK_SIZE);
+        Bench::new()
+            .k_p(K, P)
+            .block_size(BLOCK_SIZE)
+            .block_num(BLOCK_NUM)
+            .hdd_dev_path(hdd_dev.path())
+            .manner(Manner::DegradedRead)
+            .run()
+            .unwrap();
+    }
+}
@@ -1,17 +1,179 @@
-use std::num::NonZeroUsize;
+use std::{
+    io::Write,
+    num::NonZeroUsize,
+    sync::{atomic::AtomicUsize, Arc},
+};
+
+use bytes::BytesMut;
+use indicatif::ProgressIterator;
+use range_collections::RangeSet2;
+use rayon::prelude::*;
 
 use crate::{
-    bench::UpdateRequest, erasure_code::ReedSolomon, standalone_cmds::dev_display, SUResult,
+    erasure_code::{Block, ErasureCode, PartialStripe, ReedSolomon},
+    standalone::bench::UpdateRequest,
+    standalone::{dev_display, stripe_checksum::StripeChecksums},
+    storage::{
+        BufferEviction, EvictStrategySlice, FixedSizeSliceBuf, HDDStorage, MostModifiedStripeEvict,
+        PartialBlock, SliceBuffer, SliceOpt, SliceStorage, StripeId,
+    },
+    SUResult,
 };
 
-use super::Bench;
+use super::{
+    latency::{self, LatencyRecorder},
+    merge_stripe::{fetch_stripe, present_ranges, rangeset_to_ranges, UpdateCtx},
+    Bench,
+};
+
+/// Apply the updates of a stripe in delta manner, with the per-block work distributed over `pool`.
+///
+/// Each updated source block is read and turned into its parity delta by a task of its own,
+/// while the parity blocks are read by the other tasks, so that the parity encode overlaps the disk reads.
+/// The parity deltas are accumulated into the parity blocks at last,
+/// and the updated ranges of the source and parity blocks are written back.
+///
+/// # Return
+/// The number of bytes written to the hdd storage
+///
+/// # Panics
+/// - If any block of the stripe is absent from the hdd storage
+/// - If any slice is not aligned with the segment size, or the slices do not tile the block
+fn do_dist_update<EC: ErasureCode + Sync, EV: EvictStrategySlice>(
+    UpdateCtx {
+        hdd_storage,
+        block_size,
+        ec,
+        slice_buf: _,
+    }: &UpdateCtx<EC, EV>,
+    pool: &rayon::ThreadPool,
+    stripe_id: StripeId,
+    stripe_update_slices: Vec<Option<Vec<SliceOpt>>>,
+) -> usize {
+    let k = ec.k();
+    let p = ec.p();
+    let m = ec.m();
+    let block_size = *block_size;
+    let first_block_id = stripe_id.into_inner() * m;
+    debug_assert_eq!(stripe_update_slices.len(), k);
+    let union_range = stripe_update_slices
+        .iter()
+        .flatten()
+        .map(|update_slice| present_ranges(update_slice, block_size))
+        .fold(RangeSet2::<usize>::empty(), |acc, this| acc.union(&this));
+    let union_range = rangeset_to_ranges(union_range);
+    let read_union_range = |block_id| {
+        let mut data = BytesMut::zeroed(block_size);
+        union_range.iter().for_each(|range| {
+            hdd_storage
+                .get_slice(block_id, range.start, &mut data[range.to_owned()])
+                .unwrap()
+                .unwrap();
+        });
+        Block::from(data)
+    };
+    let (mut parity, deltas) = pool.install(|| {
+        rayon::join(
+            || {
+                (k..m)
+                    .into_par_iter()
+                    .map(|idx| read_union_range(first_block_id + idx))
+                    .collect::<Vec<_>>()
+            },
+            || {
+                stripe_update_slices
+                    .into_par_iter()
+                    .enumerate()
+                    .filter_map(|(idx, update_slices)| update_slices.map(|slices| (idx, slices)))
+                    .map(|(idx, update_slices)| {
+                        // a partial stripe with the source block and zeroed parity blocks,
+                        // the parity blocks hold the delta of this source block after the update
+                        let mut partial_stripe = PartialStripe::make_absent_from_k_p(
+                            NonZeroUsize::new(k).unwrap(),
+                            NonZeroUsize::new(p).unwrap(),
+                            NonZeroUsize::new(block_size).unwrap(),
+                        );
+                        partial_stripe
+                            .replace_block(idx, Some(read_union_range(first_block_id + idx)));
+                        (k..m).for_each(|parity_idx| {
+                            partial_stripe.replace_block(parity_idx, Some(Block::zero(block_size)));
+                        });
+                        let mut offset = 0;
+                        update_slices.iter().for_each(|update| match update {
+                            SliceOpt::Present(data) => {
+                                ec.delta_update(data, idx, offset, &mut partial_stripe)
+                                    .unwrap();
+                                offset += data.len();
+                            }
+                            SliceOpt::Absent(size) => offset += size,
+                        });
+                        (idx, partial_stripe)
+                    })
+                    .collect::<Vec<_>>()
+            },
+        )
+    });
+
+    // the checksum of the stripe built no longer holds
+    StripeChecksums::open(hdd_storage.get_dev_root())
+        .remove(stripe_id.into_inner())
+        .unwrap();
+    pool.install(|| {
+        parity
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(parity_idx, parity_block)| {
+                deltas.iter().for_each(|(_, partial_stripe)| {
+                    let delta = partial_stripe.split_source_parity().1[parity_idx]
+                        .as_ref()
+                        .unwrap();
+                    union_range.iter().for_each(|range| {
+                        parity_block[range.to_owned()]
+                            .iter_mut()
+                            .zip(delta[range.to_owned()].iter())
+                            .for_each(|(a, b)| *a ^= *b);
+                    });
+                });
+            });
+        deltas
+            .par_iter()
+            .map(|(idx, partial_stripe)| {
+                (
+                    *idx,
+                    partial_stripe.split_source_parity().0[*idx]
+                        .as_ref()
+                        .unwrap(),
+                )
+            })
+            .chain(
+                parity
+                    .par_iter()
+                    .enumerate()
+                    .map(|(idx, block)| (k + idx, block)),
+            )
+            .map(|(idx, block_data)| {
+                let slices = union_range
+                    .iter()
+                    .map(|range| (range.start, &block_data[range.to_owned()]))
+                    .collect::<Vec<_>>();
+                hdd_storage
+                    .put_slices(first_block_id + idx, &slices)
+                    .unwrap()
+                    .unwrap();
+                slices.iter().map(|(_, data)| data.len()).sum::<usize>()
+            })
+            .sum()
+    })
+}
 
 impl Bench {
     pub(super) fn dist_merge(&self) -> SUResult<()> {
         const CHANNEL_SIZE: usize = 64;
+        const SEG_SIZE: usize = 4 << 10;
         struct Ack();
-        let sync_channel = std::sync::mpsc::sync_channel::<UpdateRequest>(CHANNEL_SIZE);
+        let sync_channel = self.sync_channel::<UpdateRequest>(CHANNEL_SIZE);
         let (update_producer, update_consumer) = sync_channel;
+        let (ack_producer, ack_consumer) = self.sync_channel::<Ack>(CHANNEL_SIZE);
         let (k, p) = self.k_p.expect("k or p not set");
         let m = k + p;
         let block_size = self.block_size.expect("block size not set");
@@ -22,11 +184,12 @@ impl Bench {
         let ssd_block_cap = self.ssd_block_cap.expect("ssd block capacity not set");
         let ssd_cap = ssd_block_cap * block_size;
         let test_load = self.test_num.expect("test num not set");
+        let warmup = self.warmup.unwrap_or(0);
+        let ssd_op_latency = self.ssd_op_latency;
+        let hdd_op_latency = self.hdd_op_latency;
         let ssd_dev_display = dev_display(&ssd_dev_path);
         let hdd_dev_display = dev_display(&hdd_dev_path);
-        if ssd_dev_path.read_dir().unwrap().next().is_some() {
-            panic!("ssd dev path: {ssd_dev_display} is not empty");
-        }
+        self.prepare_ssd_dev(&ssd_dev_path)?;
         println!("RS({m}, {k})");
         println!("block size: {block_size}");
         println!("block num: {block_num}");
@@ -35,13 +198,21 @@ impl Bench {
         println!("ssd block capacity: {ssd_cap}");
         println!("slice size: {slice_size}");
         println!("test num: {test_load}");
-
+        if warmup > 0 {
+            println!("warmup: {warmup}");
+        }
+        println!("dist threads: {m}");
+        if let Some(latency) = ssd_op_latency {
+            println!("ssd op latency: {latency:?}");
+        }
+        if let Some(latency) = hdd_op_latency {
+            println!("hdd op latency: {latency:?}");
+        }
         // data generator
         let data_generator_handle = std::thread::spawn(move || {
             use rand::Rng;
-            const SEG_SIZE: usize = 4 << 10;
             let seg_num = block_size / SEG_SIZE;
-            (0..test_load).for_each(|_| {
+            (0..warmup + test_load).for_each(|_| {
                 let offset = rand::thread_rng().gen_range(0..seg_num);
                 let offset = offset * SEG_SIZE;
                 let block_id = { (0..).map(|_| rand::thread_rng().gen_range(0..block_num)) }
@@ -61,14 +232,56 @@ impl Bench {
                     .unwrap();
             });
         });
-
+        let buffer_len_monitor = Arc::new(AtomicUsize::new(0));
+        let buffer_len_updater = Arc::clone(&buffer_len_monitor);
         let encoder_handle = std::thread::spawn(move || {
+            // one thread for each block of a stripe
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(m)
+                .thread_name(|i| format!("dist-merge-{i}"))
+                .build()
+                .unwrap_or_else(|e| panic!("fail to build the dist merge thread pool: {e}"));
             let ec =
                 ReedSolomon::from_k_p(NonZeroUsize::new(k).unwrap(), NonZeroUsize::new(p).unwrap());
-
-            let mut duration = std::time::Duration::ZERO;
-            let mut cnt = 0_usize;
-
+            let hdd_storage =
+                HDDStorage::connect_to_dev(hdd_dev_path, NonZeroUsize::new(block_size).unwrap())
+                    .unwrap()
+                    .with_op_latency(hdd_op_latency);
+            let ssd_storage = FixedSizeSliceBuf::connect_to_dev_with_evict(
+                ssd_dev_path,
+                NonZeroUsize::new(block_size).unwrap(),
+                MostModifiedStripeEvict::new(
+                    NonZeroUsize::new(m).unwrap(),
+                    NonZeroUsize::new(ssd_cap).unwrap(),
+                ),
+            )
+            .unwrap()
+            .with_op_latency(ssd_op_latency);
+            let mut recorder = LatencyRecorder::new();
+            let update_ctx = UpdateCtx::<ReedSolomon, MostModifiedStripeEvict> {
+                hdd_storage,
+                block_size,
+                slice_buf: ssd_storage,
+                ec,
+            };
+            // the warmup updates are neither timed nor acked
+            let warmed_up = super::warm_up(
+                &update_ctx.slice_buf,
+                &update_consumer,
+                warmup,
+                |BufferEviction {
+                     block_id,
+                     data: PartialBlock { size, slices },
+                 }| {
+                    debug_assert_eq!(size, block_size);
+                    let (stripe_id, updates) = fetch_stripe(&update_ctx, block_id, slices);
+                    do_dist_update(&update_ctx, &pool, stripe_id, updates);
+                },
+            )
+            .unwrap();
+            if warmup > 0 {
+                println!("warmed up the ssd buffer to {warmed_up}/{ssd_cap} bytes");
+            }
             while let Ok(UpdateRequest {
                 slice_data,
                 block_id,
@@ -76,16 +289,241 @@ impl Bench {
             }) = update_consumer.recv()
             {
                 let epoch = std::time::Instant::now();
-
-                let elapsed = epoch.elapsed();
-                duration += elapsed;
-                cnt += 1;
+                recorder.record_user_write(slice_data.len());
+                let evict = update_ctx
+                    .slice_buf
+                    .push_slice(block_id, offset, slice_data.as_slice())
+                    .unwrap();
+                if let Some(BufferEviction {
+                    block_id,
+                    data: PartialBlock { size, slices },
+                }) = evict
+                {
+                    debug_assert_eq!(size, block_size);
+                    let (stripe_id, updates) = fetch_stripe(&update_ctx, block_id, slices);
+                    recorder.record_hdd_write(do_dist_update(
+                        &update_ctx,
+                        &pool,
+                        stripe_id,
+                        updates,
+                    ));
+                };
+                recorder.record(epoch.elapsed());
+                ack_producer.send(Ack()).unwrap();
             }
-            (duration, cnt)
+            buffer_len_updater.store(0, std::sync::atomic::Ordering::SeqCst);
+            update_ctx.slice_buf.drain_into(
+                |BufferEviction {
+                     block_id,
+                     data: PartialBlock { size, slices },
+                 }| {
+                    let epoch = std::time::Instant::now();
+                    debug_assert_eq!(size, block_size);
+                    let (stripe_id, updates) = fetch_stripe(&update_ctx, block_id, slices);
+                    recorder.record_hdd_write(do_dist_update(
+                        &update_ctx,
+                        &pool,
+                        stripe_id,
+                        updates,
+                    ));
+                    recorder.record_drain(epoch.elapsed());
+                    ack_producer.send(Ack()).unwrap();
+                    buffer_len_updater.store(
+                        ssd_cap - update_ctx.slice_buf.len(),
+                        std::sync::atomic::Ordering::SeqCst,
+                    );
+                },
+            );
+            recorder
         });
 
+        // ack: show progress
+        std::thread::spawn(move || {
+            (0..test_load)
+                .progress_with_style(crate::standalone::progress_style_template(Some(
+                    "benchmark dist merge...",
+                )))
+                .for_each(|_| {
+                    ack_consumer.recv().unwrap();
+                });
+            std::io::stdout().flush().unwrap();
+            let bar = indicatif::ProgressBar::new(ssd_cap.try_into().unwrap());
+            bar.set_style(crate::standalone::progress_style_template(Some(
+                "clean up updates buffered in ssd...",
+            )));
+            while let Ok(_ack) = ack_consumer.recv() {
+                bar.set_position(
+                    buffer_len_monitor
+                        .load(std::sync::atomic::Ordering::SeqCst)
+                        .try_into()
+                        .unwrap(),
+                );
+            }
+            println!("clean up updates buffered in ssd...done");
+        })
+        .join()
+        .unwrap();
         data_generator_handle.join().unwrap();
-        println!("benchmark baseline...done");
-        todo!()
+        let recorder = encoder_handle.join().unwrap();
+        println!("benchmark {}...done", self.manner);
+        let result = recorder.into_result(&self.manner, test_load);
+        latency::report(&result, self.out_dir_path.as_deref())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use bytes::BytesMut;
+
+    use crate::{
+        erasure_code::{Block, ErasureCode, ReedSolomon, Stripe},
+        standalone::{
+            bench::{
+                merge_stripe::{fetch_stripe, UpdateCtx},
+                UpdateRequest,
+            },
+            data_builder::build_test_dev,
+        },
+        storage::{
+            BlockStorage, BufferEviction, FixedSizeSliceBuf, HDDStorage, MostModifiedStripeEvict,
+            PartialBlock, SliceBuffer,
+        },
+    };
+
+    use super::do_dist_update;
+
+    const BLOCK_NUM: usize = 12;
+    const BLOCK_SIZE: usize = 64 << 10;
+    const SLICE_SIZE: usize = 4 << 10;
+    const SSD_BLOCK_CAP: usize = 2;
+    const TEST_LOAD: usize = BLOCK_NUM * BLOCK_SIZE / SLICE_SIZE * 2;
+    const EC_K: usize = 4;
+    const EC_P: usize = 2;
+    const EC_M: usize = EC_K + EC_P;
+
+    #[test]
+    fn test_do_dist_update() {
+        let ssd_dev = tempfile::tempdir().unwrap();
+        let hdd_dev = build_test_dev(EC_K, EC_P, BLOCK_NUM, BLOCK_SIZE);
+        let update_ctx = UpdateCtx {
+            hdd_storage: HDDStorage::connect_to_dev(
+                hdd_dev.path().to_path_buf(),
+                NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+            )
+            .unwrap(),
+            block_size: BLOCK_SIZE,
+            ec: ReedSolomon::from_k_p(
+                NonZeroUsize::new(EC_K).unwrap(),
+                NonZeroUsize::new(EC_P).unwrap(),
+            ),
+            slice_buf: FixedSizeSliceBuf::connect_to_dev_with_evict(
+                ssd_dev.path().to_path_buf(),
+                NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+                MostModifiedStripeEvict::new(
+                    NonZeroUsize::new(EC_M).unwrap(),
+                    NonZeroUsize::new(SSD_BLOCK_CAP * BLOCK_SIZE).unwrap(),
+                ),
+            )
+            .unwrap(),
+        };
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(EC_M)
+            .build()
+            .unwrap();
+        let mut block_ref = (0..BLOCK_NUM)
+            .map(|block_id| {
+                update_ctx
+                    .hdd_storage
+                    .get_block_owned(block_id)
+                    .unwrap()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        use rand::Rng;
+        (0..TEST_LOAD)
+            .map(|_| {
+                let offset = rand::thread_rng().gen_range(0..BLOCK_SIZE / SLICE_SIZE);
+                let offset = offset * SLICE_SIZE;
+                let block_id = { (0..).map(|_| rand::thread_rng().gen_range(0..BLOCK_NUM)) }
+                    .find(|id| (0..EC_K).contains(&(*id % EC_M)))
+                    .unwrap();
+                let slice_data = rand::thread_rng()
+                    .sample_iter(rand::distributions::Standard)
+                    .take(SLICE_SIZE)
+                    .collect::<Vec<_>>();
+                UpdateRequest {
+                    slice_data,
+                    block_id,
+                    offset,
+                }
+            })
+            .for_each(
+                |UpdateRequest {
+                     slice_data,
+                     block_id,
+                     offset,
+                 }| {
+                    block_ref[block_id][offset..offset + slice_data.len()]
+                        .copy_from_slice(&slice_data);
+                    let evict = update_ctx
+                        .slice_buf
+                        .push_slice(block_id, offset, slice_data.as_slice())
+                        .unwrap();
+                    if let Some(BufferEviction {
+                        block_id,
+                        data: PartialBlock { size, slices },
+                    }) = evict
+                    {
+                        assert_eq!(size, BLOCK_SIZE);
+                        let (stripe_id, updates) = fetch_stripe(&update_ctx, block_id, slices);
+                        do_dist_update(&update_ctx, &pool, stripe_id, updates);
+                    }
+                },
+            );
+        while let Some(BufferEviction {
+            block_id,
+            data: PartialBlock { size, slices },
+        }) = update_ctx.slice_buf.pop()
+        {
+            assert_eq!(size, BLOCK_SIZE);
+            let (stripe_id, updates) = fetch_stripe(&update_ctx, block_id, slices);
+            do_dist_update(&update_ctx, &pool, stripe_id, updates);
+        }
+        // check content against a full re-encode
+        let to_stripe = |blocks: Vec<Block>| {
+            Stripe::from_vec(
+                blocks,
+                NonZeroUsize::new(EC_K).unwrap(),
+                NonZeroUsize::new(EC_P).unwrap(),
+            )
+        };
+        block_ref
+            .chunks_exact(EC_M)
+            .enumerate()
+            .for_each(|(stripe_id, stripe)| {
+                let mut expect = to_stripe(
+                    stripe
+                        .iter()
+                        .map(|block| Block::from(BytesMut::from(block.as_slice())))
+                        .collect(),
+                );
+                update_ctx.ec.encode_stripe(&mut expect).unwrap();
+                let hdd_stripe = to_stripe(
+                    (stripe_id * EC_M..(stripe_id + 1) * EC_M)
+                        .map(|block_id| {
+                            let block = update_ctx
+                                .hdd_storage
+                                .get_block_owned(block_id)
+                                .unwrap()
+                                .unwrap();
+                            Block::from(BytesMut::from(block.as_slice()))
+                        })
+                        .collect(),
+                );
+                assert_eq!(expect, hdd_stripe, "stripe {stripe_id} mismatches");
+            });
     }
 }
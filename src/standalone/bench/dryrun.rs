@@ -1,16 +1,118 @@
-use std::{io::Write, num::NonZeroUsize, path::PathBuf};
+use std::{collections::HashMap, io::Write, num::NonZeroUsize, ops::Range, path::PathBuf};
 
 use indicatif::ProgressIterator;
 use rand::Rng;
+use range_collections::{RangeSet, RangeSet2};
 
 use crate::{
-    storage::{EvictStrategySlice, MostModifiedStripeEvict},
-    SUResult,
+    storage::{BlockId, EvictStrategySlice, MostModifiedStripeEvict, StripeId},
+    SUError, SUResult,
 };
 
 use super::Bench;
 
 const PLOT_FILE_NAME: &str = "dryrun-trace.svg";
+const SUMMARY_FILE_NAME: &str = "dryrun-summary.toml";
+
+/// Summary of the disk access trace generated by a dry run.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DryRunSummary {
+    /// bytes read from the ssd, i.e., the buffered slices of the evicted blocks
+    pub ssd_read_bytes: usize,
+    /// bytes written to the ssd, i.e., the update slices
+    pub ssd_write_bytes: usize,
+    /// bytes read from the hdd, i.e., the updated ranges of the evicted source blocks and their parity blocks
+    pub hdd_read_bytes: usize,
+    /// bytes written to the hdd, the same ranges as read
+    pub hdd_write_bytes: usize,
+    /// number of the distinct source blocks touched by the updates
+    pub touched_blocks: usize,
+    /// number of the distinct stripes touched by the updates
+    pub touched_stripes: usize,
+    /// average proportion of the source data modified in a touched stripe, in `[0, 1]`
+    pub avg_stripe_modified_fraction: f64,
+}
+
+/// Accumulate the disk accesses of a dry run.
+///
+/// An update writes its slice to the ssd, and an evicted block is flushed in delta manner,
+/// which reads the evicted slices from the ssd, and reads and writes the same ranges of
+/// the source block and each parity block on the hdd.
+struct TraceStats {
+    k: usize,
+    p: usize,
+    block_size: usize,
+    ssd_read_bytes: usize,
+    ssd_write_bytes: usize,
+    hdd_read_bytes: usize,
+    hdd_write_bytes: usize,
+    modified: HashMap<BlockId, RangeSet2<usize>>,
+}
+
+impl TraceStats {
+    fn new(k: usize, p: usize, block_size: usize) -> Self {
+        Self {
+            k,
+            p,
+            block_size,
+            ssd_read_bytes: 0,
+            ssd_write_bytes: 0,
+            hdd_read_bytes: 0,
+            hdd_write_bytes: 0,
+            modified: HashMap::new(),
+        }
+    }
+
+    /// Record an update of `range` to the source block `block_id`.
+    fn update(&mut self, block_id: BlockId, range: Range<usize>) {
+        self.ssd_write_bytes += range.len();
+        self.modified
+            .entry(block_id)
+            .or_insert_with(RangeSet::empty)
+            .union_with(&RangeSet2::from(range));
+    }
+
+    /// Record an eviction of `size` bytes from a source block.
+    fn evict(&mut self, size: usize) {
+        self.ssd_read_bytes += size;
+        self.hdd_read_bytes += size * (1 + self.p);
+        self.hdd_write_bytes += size * (1 + self.p);
+    }
+
+    fn summary(&self) -> DryRunSummary {
+        let m = self.k + self.p;
+        let mut stripe_modified: HashMap<StripeId, usize> = HashMap::new();
+        self.modified.iter().for_each(|(block_id, ranges)| {
+            let size = super::merge_stripe::rangeset_to_ranges(ranges.clone())
+                .iter()
+                .map(Range::len)
+                .sum::<usize>();
+            *stripe_modified
+                .entry(StripeId::from(block_id / m))
+                .or_default() += size;
+        });
+        let stripe_size = self.k * self.block_size;
+        let avg_stripe_modified_fraction = if stripe_modified.is_empty() {
+            0.0
+        } else {
+            stripe_modified
+                .values()
+                .map(|&size| size as f64 / stripe_size as f64)
+                .sum::<f64>()
+                / stripe_modified.len() as f64
+        };
+        DryRunSummary {
+            ssd_read_bytes: self.ssd_read_bytes,
+            ssd_write_bytes: self.ssd_write_bytes,
+            hdd_read_bytes: self.hdd_read_bytes,
+            hdd_write_bytes: self.hdd_write_bytes,
+            touched_blocks: self.modified.len(),
+            touched_stripes: stripe_modified.len(),
+            avg_stripe_modified_fraction,
+        }
+    }
+}
 
 /// Draw a plot named with `dryrun-trace` in `out_path`
 ///
@@ -104,6 +206,7 @@ impl Bench {
             NonZeroUsize::new(ssd_cap * block_size).expect("capacity is set to zero"),
         );
         let mut ssd_hit_cnt: usize = 0;
+        let mut trace_stats = TraceStats::new(k, p, block_size);
         let mut evictions = (0..test_num)
             .progress()
             .with_style(crate::standalone::progress_style_template(Some(
@@ -117,6 +220,7 @@ impl Bench {
                 if mm_evict.contains(block_id) {
                     ssd_hit_cnt += 1;
                 }
+                trace_stats.update(block_id, offset..(offset + slice_size));
                 mm_evict.push(block_id, offset..(offset + slice_size))
             })
             .map(|(_, ranges)| ranges.len())
@@ -124,6 +228,7 @@ impl Bench {
         while let Some((_block_id, range)) = mm_evict.pop_first() {
             evictions.push(range.len());
         }
+        evictions.iter().for_each(|&size| trace_stats.evict(size));
         let evicted_num = evictions.len();
         evictions.sort();
         assert!(evictions.iter().all(|&size| size <= block_size));
@@ -160,6 +265,25 @@ impl Bench {
                 acc
             })
             .collect::<Vec<_>>();
+        let summary = trace_stats.summary();
+        println!(
+            "ssd read: {} bytes, ssd write: {} bytes",
+            summary.ssd_read_bytes, summary.ssd_write_bytes
+        );
+        println!(
+            "hdd read: {} bytes, hdd write: {} bytes",
+            summary.hdd_read_bytes, summary.hdd_write_bytes
+        );
+        println!(
+            "touched {} blocks of {} stripes, average stripe modified: {:.2}%",
+            summary.touched_blocks,
+            summary.touched_stripes,
+            summary.avg_stripe_modified_fraction * 100.0
+        );
+        let summary_path = out_dir_path.join(SUMMARY_FILE_NAME);
+        let content = toml::to_string_pretty(&summary).map_err(SUError::other)?;
+        std::fs::write(summary_path.as_path(), content)?;
+        println!("summary dumped to {}", summary_path.display());
         print!("drawing plot...");
         std::io::stdout().flush().unwrap();
 
@@ -168,9 +292,37 @@ impl Bench {
                 println!("done, plot path: {}", path.display());
             }
             Err(e) => {
-                eprintln!("fail to draw the plot: {e}")
+                println!("failed");
+                log::error!("fail to draw the plot: {e}")
             }
         };
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::TraceStats;
+
+    #[test]
+    fn summary() {
+        const BLOCK_SIZE: usize = 16 << 10;
+        // RS(3, 2): block 0, 1 in stripe 0, and block 3, 4 in stripe 1
+        let mut stats = TraceStats::new(2, 1, BLOCK_SIZE);
+        stats.update(0, 0..4096);
+        stats.update(0, 2048..6144);
+        stats.update(1, 0..4096);
+        stats.update(3, 0..BLOCK_SIZE);
+        stats.evict(6144);
+        stats.evict(BLOCK_SIZE);
+        let summary = stats.summary();
+        assert_eq!(summary.ssd_write_bytes, 3 * 4096 + BLOCK_SIZE);
+        assert_eq!(summary.ssd_read_bytes, 6144 + BLOCK_SIZE);
+        assert_eq!(summary.hdd_read_bytes, 2 * (6144 + BLOCK_SIZE));
+        assert_eq!(summary.hdd_write_bytes, 2 * (6144 + BLOCK_SIZE));
+        assert_eq!(summary.touched_blocks, 3);
+        assert_eq!(summary.touched_stripes, 2);
+        // stripe 0: (6144 + 4096) / 32768, stripe 1: 16384 / 32768
+        assert_eq!(summary.avg_stripe_modified_fraction, (0.3125 + 0.5) / 2.0);
+    }
+}
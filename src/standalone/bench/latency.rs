@@ -0,0 +1,332 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::{SUError, SUResult};
+
+/// Result of an update benchmark.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct UpdateBenchResult {
+    pub manner: String,
+    pub test_load: usize,
+    /// number of the evictions drained from the buffer after the test load
+    pub drained: usize,
+    /// number of the measured operations, which is `test_load + drained`
+    pub count: usize,
+    /// total time spent in the measured operations, in microseconds
+    pub duration_us: u64,
+    /// measured operations per second
+    pub ops: f64,
+    /// latency distribution of the measured operations,
+    /// only available with the `latency-histogram` feature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<LatencyPercentiles>,
+    /// bytes of the measured update requests
+    #[serde(default)]
+    pub user_bytes: u64,
+    /// bytes written to the hdd by the measured operations, parity included
+    #[serde(default)]
+    pub hdd_bytes: u64,
+    /// `hdd_bytes / user_bytes`, the key metric telling the update manners apart,
+    /// not available if nothing is updated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub write_amplification: Option<f64>,
+}
+
+/// Latency percentiles of the measured operations, in microseconds.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LatencyPercentiles {
+    /// number of the latencies recorded
+    pub count: u64,
+    pub p50_us: f64,
+    pub p99_us: f64,
+    pub p999_us: f64,
+    pub max_us: f64,
+}
+
+/// Record the latency of each update, and the evictions drained at the end.
+/// The bytes of the updates and the bytes they cost the hdd are counted as well,
+/// to report the write amplification.
+pub(super) struct LatencyRecorder {
+    duration: Duration,
+    count: usize,
+    drained: usize,
+    user_bytes: usize,
+    hdd_bytes: usize,
+    #[cfg(feature = "latency-histogram")]
+    histogram: hdrhistogram::Histogram<u64>,
+}
+
+impl LatencyRecorder {
+    pub fn new() -> Self {
+        Self {
+            duration: Duration::ZERO,
+            count: 0,
+            drained: 0,
+            user_bytes: 0,
+            hdd_bytes: 0,
+            #[cfg(feature = "latency-histogram")]
+            histogram: hdrhistogram::Histogram::new(3).expect("bad histogram sigfig"),
+        }
+    }
+
+    /// Record the latency of an update request.
+    pub fn record(&mut self, latency: Duration) {
+        self.duration += latency;
+        self.count += 1;
+        #[cfg(feature = "latency-histogram")]
+        self.histogram
+            .record(latency.as_nanos().try_into().unwrap_or(u64::MAX))
+            .expect("auto-resized histogram never fails");
+    }
+
+    /// Record the latency of an eviction drained from the buffer.
+    pub fn record_drain(&mut self, latency: Duration) {
+        self.record(latency);
+        self.drained += 1;
+    }
+
+    /// Count the bytes of an update request.
+    pub fn record_user_write(&mut self, bytes: usize) {
+        self.user_bytes += bytes;
+    }
+
+    /// Count the bytes written to the hdd by an update or an eviction drained.
+    pub fn record_hdd_write(&mut self, bytes: usize) {
+        self.hdd_bytes += bytes;
+    }
+
+    #[cfg(feature = "latency-histogram")]
+    fn percentiles(&self) -> Option<LatencyPercentiles> {
+        let us = |nanos: u64| nanos as f64 / 1000.0;
+        Some(LatencyPercentiles {
+            count: self.histogram.len(),
+            p50_us: us(self.histogram.value_at_quantile(0.5)),
+            p99_us: us(self.histogram.value_at_quantile(0.99)),
+            p999_us: us(self.histogram.value_at_quantile(0.999)),
+            max_us: us(self.histogram.max()),
+        })
+    }
+
+    #[cfg(not(feature = "latency-histogram"))]
+    fn percentiles(&self) -> Option<LatencyPercentiles> {
+        None
+    }
+
+    pub fn into_result(self, manner: impl ToString, test_load: usize) -> UpdateBenchResult {
+        UpdateBenchResult {
+            manner: manner.to_string(),
+            test_load,
+            drained: self.drained,
+            count: self.count,
+            duration_us: self.duration.as_micros().try_into().unwrap(),
+            ops: self.count as f64 / self.duration.as_secs_f64(),
+            latency: self.percentiles(),
+            user_bytes: self.user_bytes.try_into().unwrap(),
+            hdd_bytes: self.hdd_bytes.try_into().unwrap(),
+            write_amplification: (self.user_bytes > 0)
+                .then(|| self.hdd_bytes as f64 / self.user_bytes as f64),
+        }
+    }
+}
+
+/// Print the `result`, and write it to `out_dir_path` in toml format if set.
+///
+/// # Return
+/// Path to the result file if dumped
+pub(super) fn report(
+    result: &UpdateBenchResult,
+    out_dir_path: Option<&std::path::Path>,
+) -> SUResult<Option<PathBuf>> {
+    let duration = Duration::from_micros(result.duration_us);
+    println!(
+        "benchmarked {} updates request in {}s{}ms",
+        result.test_load,
+        duration.as_secs(),
+        duration.subsec_millis()
+    );
+    println!("OPS: {:.0}", result.ops);
+    println!(
+        "write amplification: {} ({} written to hdd for {} updated)",
+        result
+            .write_amplification
+            .map_or_else(|| "n/a".to_string(), |wa| format!("{wa:.2}")),
+        bytesize::ByteSize::b(result.hdd_bytes),
+        bytesize::ByteSize::b(result.user_bytes)
+    );
+    if let Some(latency) = &result.latency {
+        println!(
+            "latency: p50 {:.1}us, p99 {:.1}us, p999 {:.1}us, max {:.1}us",
+            latency.p50_us, latency.p99_us, latency.p999_us, latency.max_us
+        );
+    }
+    out_dir_path
+        .map(|out_dir_path| {
+            let path = out_dir_path.join(format!("{}-bench.toml", result.manner));
+            let content = toml::to_string_pretty(result).map_err(SUError::other)?;
+            std::fs::write(path.as_path(), content)?;
+            println!("result dumped to {}", path.display());
+            Ok(path)
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use crate::{
+        erasure_code::ReedSolomon,
+        standalone::{
+            bench::{baseline, merge_stripe, Bench, Manner},
+            data_builder::build_test_dev,
+        },
+        storage::{
+            BufferEviction, FixedSizeSliceBuf, HDDStorage, MostModifiedStripeEvict, SliceBuffer,
+        },
+    };
+
+    use super::{LatencyRecorder, UpdateBenchResult};
+
+    const BLOCK_SIZE: usize = 64 << 10;
+    const BLOCK_NUM: usize = 12;
+    const TEST_LOAD: usize = 128;
+    const K: usize = 4;
+    const P: usize = 2;
+
+    fn run_update_bench(manner: Manner) -> UpdateBenchResult {
+        let ssd_dev = tempfile::tempdir().unwrap();
+        let out_dir = tempfile::tempdir().unwrap();
+        let hdd_dev = build_test_dev(K, P, BLOCK_NUM, BLOCK_SIZE);
+        Bench::new()
+            .k_p(K, P)
+            .block_size(BLOCK_SIZE)
+            .block_num(BLOCK_NUM)
+            .ssd_block_capacity(2)
+            .slice_size(4 << 10)
+            .test_load(TEST_LOAD)
+            .ssd_dev_path(ssd_dev.path())
+            .hdd_dev_path(hdd_dev.path())
+            .out_dir_path(out_dir.path())
+            .manner(manner.clone())
+            .run()
+            .unwrap();
+        let path = out_dir.path().join(format!("{manner}-bench.toml"));
+        toml::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn update_bench_result() {
+        for manner in [Manner::Baseline, Manner::MergeStripe] {
+            let result = run_update_bench(manner);
+            assert_eq!(result.test_load, TEST_LOAD);
+            assert!(result.drained > 0);
+            assert_eq!(result.count, TEST_LOAD + result.drained);
+            assert!(result.ops > 0.0);
+            #[cfg(feature = "latency-histogram")]
+            {
+                let latency = result.latency.unwrap();
+                assert_eq!(latency.count, u64::try_from(result.count).unwrap());
+                assert!(latency.p50_us <= latency.p99_us);
+                assert!(latency.p99_us <= latency.p999_us);
+                assert!(latency.p999_us <= latency.max_us);
+            }
+            #[cfg(not(feature = "latency-histogram"))]
+            assert!(result.latency.is_none());
+        }
+    }
+
+    #[test]
+    fn write_amplification() {
+        const SEG_SIZE: usize = 4 << 10;
+        const M: usize = K + P;
+        let hdd_dev = build_test_dev(K, P, M, BLOCK_SIZE);
+        let hdd_storage = || {
+            HDDStorage::connect_to_dev(
+                hdd_dev.path().to_path_buf(),
+                NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+            )
+            .unwrap()
+        };
+        let ec =
+            || ReedSolomon::from_k_p(NonZeroUsize::new(K).unwrap(), NonZeroUsize::new(P).unwrap());
+        // 4K to block 0, 8K to block 1, and 4K overwriting block 0, nothing is evicted until drained
+        let updates = [(0, SEG_SIZE), (1, 2 * SEG_SIZE), (0, SEG_SIZE)];
+
+        // each block evicted writes back the whole block and the parity blocks:
+        // 2 blocks * (1 + P) * BLOCK_SIZE for 16K updated
+        let ssd_dev = tempfile::tempdir().unwrap();
+        let slice_buf = FixedSizeSliceBuf::connect_to_dev(
+            ssd_dev.path(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+            NonZeroUsize::new(M * BLOCK_SIZE).unwrap(),
+        )
+        .unwrap();
+        let update_ctx = baseline::UpdateCtx {
+            hdd_storage: hdd_storage(),
+            block_size: BLOCK_SIZE,
+            ec: ec(),
+        };
+        let mut recorder = LatencyRecorder::new();
+        updates.iter().for_each(|&(block_id, len)| {
+            recorder.record_user_write(len);
+            let evict = slice_buf.push_slice(block_id, 0, &vec![0xff; len]).unwrap();
+            assert!(evict.is_none());
+        });
+        slice_buf.drain_into(|BufferEviction { block_id, data }| {
+            recorder.record_hdd_write(baseline::do_update(&update_ctx, block_id, data.slices))
+        });
+        let result = recorder.into_result(Manner::Baseline, updates.len());
+        assert_eq!(result.user_bytes, 16 << 10);
+        assert_eq!(
+            result.hdd_bytes,
+            u64::try_from(2 * (1 + P) * BLOCK_SIZE).unwrap()
+        );
+        assert_eq!(result.write_amplification, Some(24.0));
+
+        // the stripe is written back once, only the union range 0..8K
+        // of the 2 source blocks updated and the parity blocks: (2 + P) * 8K for 16K updated
+        let ssd_dev = tempfile::tempdir().unwrap();
+        let update_ctx = merge_stripe::UpdateCtx {
+            hdd_storage: hdd_storage(),
+            block_size: BLOCK_SIZE,
+            slice_buf: FixedSizeSliceBuf::connect_to_dev_with_evict(
+                ssd_dev.path(),
+                NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+                MostModifiedStripeEvict::new(
+                    NonZeroUsize::new(M).unwrap(),
+                    NonZeroUsize::new(M * BLOCK_SIZE).unwrap(),
+                ),
+            )
+            .unwrap(),
+            ec: ec(),
+        };
+        let mut recorder = LatencyRecorder::new();
+        updates.iter().for_each(|&(block_id, len)| {
+            recorder.record_user_write(len);
+            let evict = update_ctx
+                .slice_buf
+                .push_slice(block_id, 0, &vec![0xff; len])
+                .unwrap();
+            assert!(evict.is_none());
+        });
+        update_ctx
+            .slice_buf
+            .drain_into(|BufferEviction { block_id, data }| {
+                let (stripe_id, updates) =
+                    merge_stripe::fetch_stripe(&update_ctx, block_id, data.slices);
+                recorder.record_hdd_write(merge_stripe::do_update(&update_ctx, stripe_id, updates))
+            });
+        let result = recorder.into_result(Manner::MergeStripe, updates.len());
+        assert_eq!(result.user_bytes, 16 << 10);
+        assert_eq!(
+            result.hdd_bytes,
+            u64::try_from((2 + P) * (8 << 10)).unwrap()
+        );
+        assert_eq!(result.write_amplification, Some(2.0));
+
+        // nothing updated
+        let result = LatencyRecorder::new().into_result(Manner::Baseline, 0);
+        assert!(result.write_amplification.is_none());
+    }
+}
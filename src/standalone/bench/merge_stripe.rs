@@ -12,7 +12,7 @@ use range_collections::{RangeSet, RangeSet2};
 use crate::{
     erasure_code::{Block, ErasureCode, PartialStripe, ReedSolomon, Stripe},
     standalone::bench::UpdateRequest,
-    standalone::dev_display,
+    standalone::{dev_display, stripe_checksum::StripeChecksums},
     storage::{
         BlockId, BufferEviction, EvictStrategySlice, FixedSizeSliceBuf, HDDStorage,
         MostModifiedStripeEvict, PartialBlock, SliceBuffer, SliceOpt, SliceStorage, StripeId,
@@ -20,6 +20,8 @@ use crate::{
     SUResult,
 };
 
+const SEG_SIZE: usize = 4 << 10;
+
 pub fn rangeset_to_ranges(range_set: RangeSet2<usize>) -> Vec<Range<usize>> {
     range_set
         .boundaries()
@@ -28,16 +30,48 @@ pub fn rangeset_to_ranges(range_set: RangeSet2<usize>) -> Vec<Range<usize>> {
         .collect()
 }
 
-use super::Bench;
+/// Convert the slices of a block to the ranges of its present slices.
+///
+/// # Panics
+/// - If any present slice is not aligned with the segment size
+/// - If the slices do not tile the block, that is, their accumulated size does not equal to `block_size`
+pub(super) fn present_ranges(update_slice: &[SliceOpt], block_size: usize) -> RangeSet2<usize> {
+    let mut range_set: RangeSet2<usize> = RangeSet::empty();
+    let mut offset = 0;
+    update_slice.iter().for_each(|update| match update {
+        SliceOpt::Present(slice) => {
+            let range = offset..offset + slice.len();
+            if range.start % SEG_SIZE != 0 || range.end % SEG_SIZE != 0 {
+                panic!(
+                    "present slice [{}..{}) is not aligned with segment size {SEG_SIZE}",
+                    range.start, range.end
+                );
+            }
+            range_set.union_with(&RangeSet2::from(range));
+            offset += slice.len();
+        }
+        SliceOpt::Absent(size) => offset += size,
+    });
+    if offset != block_size {
+        panic!("slices with accumulated size {offset} do not tile the block of size {block_size}");
+    }
+    range_set
+}
+
+use super::{
+    latency::{self, LatencyRecorder},
+    throughput::{self, ThroughputSampler},
+    Bench, UpdateBenchResult,
+};
 #[derive(Debug)]
-struct UpdateCtx<EC: ErasureCode, EV: EvictStrategySlice> {
-    hdd_storage: HDDStorage,
-    block_size: usize,
-    slice_buf: FixedSizeSliceBuf<EV>,
-    ec: EC,
+pub(super) struct UpdateCtx<EC: ErasureCode, EV: EvictStrategySlice> {
+    pub(super) hdd_storage: HDDStorage,
+    pub(super) block_size: usize,
+    pub(super) slice_buf: FixedSizeSliceBuf<EV>,
+    pub(super) ec: EC,
 }
 
-fn fetch_stripe<EC: ErasureCode, EV: EvictStrategySlice>(
+pub(super) fn fetch_stripe<EC: ErasureCode, EV: EvictStrategySlice>(
     UpdateCtx {
         hdd_storage: _,
         block_size: _,
@@ -58,7 +92,12 @@ fn fetch_stripe<EC: ErasureCode, EV: EvictStrategySlice>(
     (stripe_id, updates)
 }
 
-fn do_update<EC: ErasureCode, EV: EvictStrategySlice>(
+/// Apply the slices evicted of the source blocks of a stripe to the hdd storage,
+/// by writing back the union of the updated ranges of the updated source blocks and the parity blocks.
+///
+/// # Return
+/// The number of bytes written to the hdd storage
+pub(super) fn do_update<EC: ErasureCode, EV: EvictStrategySlice>(
     UpdateCtx {
         hdd_storage,
         block_size,
@@ -67,7 +106,7 @@ fn do_update<EC: ErasureCode, EV: EvictStrategySlice>(
     }: &UpdateCtx<EC, EV>,
     stripe_id: StripeId,
     stripe_update_slices: Vec<Option<Vec<SliceOpt>>>,
-) {
+) -> usize {
     let k = ec.k();
     let block_size = *block_size;
     let p = ec.p();
@@ -81,24 +120,10 @@ fn do_update<EC: ErasureCode, EV: EvictStrategySlice>(
     let union_range = stripe_update_slices
         .iter()
         .filter(|update_slice| update_slice.is_some())
-        .map(|update_slice| {
-            let mut range_set: RangeSet2<usize> = RangeSet::empty();
-            let mut offset = 0;
-            update_slice
-                .as_ref()
-                .unwrap()
-                .iter()
-                .for_each(|update| match update {
-                    SliceOpt::Present(slice) => {
-                        range_set.union_with(&RangeSet2::from(offset..offset + slice.len()));
-                        offset += slice.len();
-                    }
-                    SliceOpt::Absent(size) => offset += size,
-                });
-            range_set
-        })
+        .map(|update_slice| present_ranges(update_slice.as_ref().unwrap(), block_size))
         .fold(RangeSet2::<usize>::empty(), |acc, this| acc.union(&this));
     let union_range = rangeset_to_ranges(union_range);
+    let union_len = union_range.iter().map(Range::len).sum::<usize>();
     let is_full_update = update_src_block_num == k;
     let mut buf = BytesMut::zeroed(block_size * (update_src_block_num + p));
     let mut partial_stripe = PartialStripe::make_absent_from_k_p(
@@ -133,6 +158,10 @@ fn do_update<EC: ErasureCode, EV: EvictStrategySlice>(
         debug_assert!(ret.is_none());
     });
 
+    // the checksum of the stripe built no longer holds
+    StripeChecksums::open(hdd_storage.get_dev_root())
+        .remove(stripe_id.into_inner())
+        .unwrap();
     if is_full_update {
         let mut stripe = Stripe::try_from(partial_stripe).unwrap();
         ec.encode_stripe(&mut stripe).unwrap();
@@ -159,15 +188,24 @@ fn do_update<EC: ErasureCode, EV: EvictStrategySlice>(
             })
         });
     }
+    // the whole stripe is written back on a full update
+    union_len
+        * if is_full_update {
+            m
+        } else {
+            update_src_block_num + p
+        }
 }
 
 impl Bench {
-    pub(super) fn merge_stripe(&self) -> SUResult<()> {
+    pub(super) fn merge_stripe(&self) -> SUResult<UpdateBenchResult> {
         const CHANNEL_SIZE: usize = 64;
         struct Ack();
-        let sync_channel = std::sync::mpsc::sync_channel::<UpdateRequest>(CHANNEL_SIZE);
+        let sync_channel = self.sync_channel::<UpdateRequest>(CHANNEL_SIZE);
         let (update_producer, update_consumer) = sync_channel;
-        let (ack_producer, ack_consumer) = std::sync::mpsc::sync_channel::<Ack>(CHANNEL_SIZE);
+        let (ack_producer, ack_consumer) = self.sync_channel::<Ack>(CHANNEL_SIZE);
+        // signal the end of the warmup, when the throughput sampling starts
+        let (warmed_up_producer, warmed_up_consumer) = std::sync::mpsc::sync_channel::<()>(1);
         let (k, p) = self.k_p.expect("k or p not set");
         let m = k + p;
         let block_size = self.block_size.expect("block size not set");
@@ -177,26 +215,43 @@ impl Bench {
         let block_num = self.block_num.expect("block num not set");
         let ssd_block_cap = self.ssd_block_cap.expect("ssd block capacity not set");
         let ssd_cap = ssd_block_cap * block_size;
+        let ssd_low_watermark = self.ssd_low_watermark.unwrap_or(ssd_block_cap);
+        assert!(
+            ssd_low_watermark <= ssd_block_cap,
+            "ssd low watermark {ssd_low_watermark} is greater than ssd block capacity {ssd_block_cap}"
+        );
         let test_load = self.test_num.expect("test num not set");
+        let warmup = self.warmup.unwrap_or(0);
+        let ssd_op_latency = self.ssd_op_latency;
+        let hdd_op_latency = self.hdd_op_latency;
         let ssd_dev_display = dev_display(&ssd_dev_path);
         let hdd_dev_display = dev_display(&hdd_dev_path);
-        if ssd_dev_path.read_dir().unwrap().next().is_some() {
-            panic!("ssd dev path: {ssd_dev_display} is not empty");
-        }
+        self.prepare_ssd_dev(&ssd_dev_path)?;
         println!("RS({m}, {k})");
         println!("block size: {block_size}");
         println!("block num: {block_num}");
         println!("hdd dev path: {hdd_dev_display}");
         println!("ssd dev path: {ssd_dev_display}");
         println!("ssd block capacity: {ssd_cap}");
+        if ssd_low_watermark < ssd_block_cap {
+            println!("ssd low watermark: {}", ssd_low_watermark * block_size);
+        }
         println!("slice size: {slice_size}");
         println!("test num: {test_load}");
+        if warmup > 0 {
+            println!("warmup: {warmup}");
+        }
+        if let Some(latency) = ssd_op_latency {
+            println!("ssd op latency: {latency:?}");
+        }
+        if let Some(latency) = hdd_op_latency {
+            println!("hdd op latency: {latency:?}");
+        }
         // data generator
         let data_generator_handle = std::thread::spawn(move || {
             use rand::Rng;
-            const SEG_SIZE: usize = 4 << 10;
             let seg_num = block_size / SEG_SIZE;
-            (0..test_load).for_each(|_| {
+            (0..warmup + test_load).for_each(|_| {
                 let offset = rand::thread_rng().gen_range(0..seg_num);
                 let offset = offset * SEG_SIZE;
                 let block_id = { (0..).map(|_| rand::thread_rng().gen_range(0..block_num)) }
@@ -223,24 +278,45 @@ impl Bench {
                 ReedSolomon::from_k_p(NonZeroUsize::new(k).unwrap(), NonZeroUsize::new(p).unwrap());
             let hdd_storage =
                 HDDStorage::connect_to_dev(hdd_dev_path, NonZeroUsize::new(block_size).unwrap())
-                    .unwrap();
+                    .unwrap()
+                    .with_op_latency(hdd_op_latency);
             let ssd_storage = FixedSizeSliceBuf::connect_to_dev_with_evict(
                 ssd_dev_path,
                 NonZeroUsize::new(block_size).unwrap(),
-                MostModifiedStripeEvict::new(
+                MostModifiedStripeEvict::with_watermarks(
                     NonZeroUsize::new(m).unwrap(),
+                    ssd_low_watermark * block_size,
                     NonZeroUsize::new(ssd_cap).unwrap(),
                 ),
             )
-            .unwrap();
-            let mut duration = std::time::Duration::ZERO;
-            let mut cnt = 0_usize;
+            .unwrap()
+            .with_op_latency(ssd_op_latency);
+            let mut recorder = LatencyRecorder::new();
             let update_ctx = UpdateCtx::<ReedSolomon, MostModifiedStripeEvict> {
                 hdd_storage,
                 block_size,
                 slice_buf: ssd_storage,
                 ec,
             };
+            // the warmup updates are neither timed nor acked
+            let warmed_up = super::warm_up(
+                &update_ctx.slice_buf,
+                &update_consumer,
+                warmup,
+                |BufferEviction {
+                     block_id,
+                     data: PartialBlock { size, slices },
+                 }| {
+                    debug_assert_eq!(size, block_size);
+                    let (stripe_id, updates) = fetch_stripe(&update_ctx, block_id, slices);
+                    do_update(&update_ctx, stripe_id, updates);
+                },
+            )
+            .unwrap();
+            if warmup > 0 {
+                println!("warmed up the ssd buffer to {warmed_up}/{ssd_cap} bytes");
+            }
+            warmed_up_producer.send(()).unwrap();
             while let Ok(UpdateRequest {
                 slice_data,
                 block_id,
@@ -248,54 +324,61 @@ impl Bench {
             }) = update_consumer.recv()
             {
                 let epoch = std::time::Instant::now();
-                let evict = update_ctx
+                recorder.record_user_write(slice_data.len());
+                let evictions = update_ctx
                     .slice_buf
-                    .push_slice(block_id, offset, slice_data.as_slice())
+                    .push_slice_batch(block_id, offset, slice_data.as_slice())
                     .unwrap();
-                if let Some(BufferEviction {
-                    block_id,
-                    data: PartialBlock { size, slices },
-                }) = evict
-                {
-                    debug_assert_eq!(size, block_size);
-                    let (stripe_id, updates) = fetch_stripe(&update_ctx, block_id, slices);
-                    do_update(&update_ctx, stripe_id, updates);
-                };
-                let elapsed = epoch.elapsed();
-                duration += elapsed;
-                cnt += 1;
+                evictions.into_iter().for_each(
+                    |BufferEviction {
+                         block_id,
+                         data: PartialBlock { size, slices },
+                     }| {
+                        debug_assert_eq!(size, block_size);
+                        let (stripe_id, updates) = fetch_stripe(&update_ctx, block_id, slices);
+                        recorder.record_hdd_write(do_update(&update_ctx, stripe_id, updates));
+                    },
+                );
+                recorder.record(epoch.elapsed());
                 ack_producer.send(Ack()).unwrap();
             }
             buffer_len_updater.store(0, std::sync::atomic::Ordering::SeqCst);
-            while let Some(BufferEviction {
-                block_id,
-                data: PartialBlock { size, slices },
-            }) = update_ctx.slice_buf.pop()
-            {
-                let epoch = std::time::Instant::now();
-                debug_assert_eq!(size, block_size);
-                let (stripe_id, updates) = fetch_stripe(&update_ctx, block_id, slices);
-                do_update(&update_ctx, stripe_id, updates);
-                duration += epoch.elapsed();
-                cnt += 1;
-                ack_producer.send(Ack()).unwrap();
-                buffer_len_updater.store(
-                    ssd_cap - update_ctx.slice_buf.len(),
-                    std::sync::atomic::Ordering::SeqCst,
-                );
-            }
-            (duration, cnt)
+            update_ctx.slice_buf.drain_into(
+                |BufferEviction {
+                     block_id,
+                     data: PartialBlock { size, slices },
+                 }| {
+                    let epoch = std::time::Instant::now();
+                    debug_assert_eq!(size, block_size);
+                    let (stripe_id, updates) = fetch_stripe(&update_ctx, block_id, slices);
+                    recorder.record_hdd_write(do_update(&update_ctx, stripe_id, updates));
+                    recorder.record_drain(epoch.elapsed());
+                    ack_producer.send(Ack()).unwrap();
+                    buffer_len_updater.store(
+                        ssd_cap - update_ctx.slice_buf.len(),
+                        std::sync::atomic::Ordering::SeqCst,
+                    );
+                },
+            );
+            recorder
         });
 
         // ack: show progress
-        std::thread::spawn(move || {
+        let sample_interval = self
+            .sample_interval
+            .unwrap_or(throughput::DEFAULT_SAMPLE_INTERVAL);
+        let samples = std::thread::spawn(move || {
+            warmed_up_consumer.recv().unwrap();
+            let sampler = ThroughputSampler::spawn(sample_interval);
             (0..test_load)
                 .progress_with_style(crate::standalone::progress_style_template(Some(
                     "benchmark baseline...",
                 )))
                 .for_each(|_| {
                     ack_consumer.recv().unwrap();
+                    sampler.complete_one();
                 });
+            let samples = sampler.finish();
             std::io::stdout().flush().unwrap();
             let bar = indicatif::ProgressBar::new(ssd_cap.try_into().unwrap());
             bar.set_style(crate::standalone::progress_style_template(Some(
@@ -310,22 +393,19 @@ impl Bench {
                 );
             }
             println!("clean up updates buffered in ssd...done");
+            samples
         })
         .join()
         .unwrap();
         data_generator_handle.join().unwrap();
-        let (duration, cnt) = encoder_handle.join().unwrap();
-        println!("benchmark baseline...done");
-        println!(
-            "benchmarked {test_load} updates request in {}s{}ms",
-            duration.as_secs(),
-            duration.as_millis()
-        );
-        println!(
-            "OPS: {}",
-            cnt * 1000 * 1000 / usize::try_from(duration.as_micros()).unwrap()
-        );
-        Ok(())
+        let recorder = encoder_handle.join().unwrap();
+        println!("benchmark {}...done", self.manner);
+        let result = recorder.into_result(&self.manner, test_load);
+        latency::report(&result, self.out_dir_path.as_deref())?;
+        if let Some(out_dir_path) = self.out_dir_path.as_deref() {
+            throughput::dump(&samples, &self.manner, out_dir_path)?;
+        }
+        Ok(result)
     }
 }
 
@@ -357,22 +437,14 @@ mod test {
     const EC_K: usize = 4;
     const EC_P: usize = 2;
     const EC_M: usize = EC_K + EC_P;
-    #[ignore]
-    #[test]
-    fn test_do_update() {
-        let ssd_dev = tempfile::tempdir().unwrap();
-        let hdd_dev = tempfile::tempdir().unwrap();
-        crate::standalone::data_builder::DataBuilder::new()
-            .block_num(BLOCK_NUM)
-            .block_size(BLOCK_SIZE)
-            .hdd_dev_path(hdd_dev.path())
-            .purge(true)
-            .k_p(EC_K, EC_P)
-            .build()
-            .unwrap();
-        let update_ctx = UpdateCtx {
+
+    fn make_update_ctx(
+        ssd_dev: &std::path::Path,
+        hdd_dev: &std::path::Path,
+    ) -> UpdateCtx<ReedSolomon, MostModifiedStripeEvict> {
+        UpdateCtx {
             hdd_storage: HDDStorage::connect_to_dev(
-                hdd_dev.path().to_path_buf(),
+                hdd_dev.to_path_buf(),
                 NonZeroUsize::new(BLOCK_SIZE).unwrap(),
             )
             .unwrap(),
@@ -382,7 +454,7 @@ mod test {
                 NonZeroUsize::new(EC_P).unwrap(),
             ),
             slice_buf: FixedSizeSliceBuf::connect_to_dev_with_evict(
-                ssd_dev.path().to_path_buf(),
+                ssd_dev.to_path_buf(),
                 NonZeroUsize::new(BLOCK_SIZE).unwrap(),
                 MostModifiedStripeEvict::new(
                     NonZeroUsize::new(EC_M).unwrap(),
@@ -390,7 +462,54 @@ mod test {
                 ),
             )
             .unwrap(),
-        };
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "is not aligned with segment size")]
+    fn do_update_misaligned_slice() {
+        let ssd_dev = tempfile::tempdir().unwrap();
+        let hdd_dev = tempfile::tempdir().unwrap();
+        let update_ctx = make_update_ctx(ssd_dev.path(), hdd_dev.path());
+        let slices = vec![
+            SliceOpt::Absent(SLICE_SIZE / 2),
+            SliceOpt::Present(vec![0_u8; SLICE_SIZE].into()),
+            SliceOpt::Absent(BLOCK_SIZE - SLICE_SIZE - SLICE_SIZE / 2),
+        ];
+        let mut updates = vec![None; EC_K];
+        updates[0] = Some(slices);
+        do_update(&update_ctx, 0.into(), updates);
+    }
+
+    #[test]
+    #[should_panic(expected = "do not tile the block")]
+    fn do_update_non_tiling_slices() {
+        let ssd_dev = tempfile::tempdir().unwrap();
+        let hdd_dev = tempfile::tempdir().unwrap();
+        let update_ctx = make_update_ctx(ssd_dev.path(), hdd_dev.path());
+        let slices = vec![
+            SliceOpt::Present(vec![0_u8; SLICE_SIZE].into()),
+            SliceOpt::Absent(BLOCK_SIZE),
+        ];
+        let mut updates = vec![None; EC_K];
+        updates[0] = Some(slices);
+        do_update(&update_ctx, 0.into(), updates);
+    }
+
+    #[ignore]
+    #[test]
+    fn test_do_update() {
+        let ssd_dev = tempfile::tempdir().unwrap();
+        let hdd_dev = tempfile::tempdir().unwrap();
+        crate::standalone::data_builder::DataBuilder::new()
+            .block_num(BLOCK_NUM)
+            .block_size(BLOCK_SIZE)
+            .hdd_dev_path(hdd_dev.path())
+            .purge(true)
+            .k_p(EC_K, EC_P)
+            .build()
+            .unwrap();
+        let update_ctx = make_update_ctx(ssd_dev.path(), hdd_dev.path());
         let mut block_ref = (0..BLOCK_NUM)
             .map(|block_id| {
                 let block = update_ctx
@@ -1,11 +1,31 @@
-use std::path::PathBuf;
+use std::{
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, SyncSender},
+};
 
-use crate::{storage::BlockId, SUResult};
+use crate::{
+    standalone::{clean::Cleaner, dev_display},
+    storage::{BlockId, BufferEviction, SliceBuffer},
+    SUError, SUResult,
+};
+
+pub use code_only::CodeBenchResult;
+pub use compare::{print_comparison, ComparisonRow};
+pub use dryrun::DryRunSummary;
+pub use latency::{LatencyPercentiles, UpdateBenchResult};
 
 mod baseline;
-// mod dist_merge;
+mod code_only;
+mod compare;
+mod degraded_read;
+#[cfg(feature = "rayon")]
+mod dist_merge;
 mod dryrun;
+mod latency;
 mod merge_stripe;
+mod throughput;
+mod trace_replay;
 
 #[derive(Debug, Default, serde::Deserialize, Clone, clap::ValueEnum)]
 pub enum Manner {
@@ -14,8 +34,17 @@ pub enum Manner {
     Baseline,
     /// Merge the updates of a stripe
     MergeStripe,
+    /// Merge the updates of a stripe, and distribute the per-block delta computation
+    /// over a thread pool. Requires the `rayon` feature.
+    DistMerge,
     /// No disk write/read is performed, only generate and report disk access trace.
     TraceDryRun,
+    /// No disk write/read is performed, only measure the throughput of the erasure code.
+    CodeOnly,
+    /// No update is performed, reconstruct one absent block of each stripe from the others.
+    DegradedRead,
+    /// Replay the writes of a trace file in order, the reads are skipped.
+    TraceReplay,
 }
 
 impl std::fmt::Display for Manner {
@@ -23,22 +52,34 @@ impl std::fmt::Display for Manner {
         match self {
             Manner::Baseline => f.write_str("baseline"),
             Manner::MergeStripe => f.write_str("merge_stripe"),
+            Manner::DistMerge => f.write_str("dist_merge"),
             Manner::TraceDryRun => f.write_str("trace_dryrun"),
+            Manner::CodeOnly => f.write_str("code_only"),
+            Manner::DegradedRead => f.write_str("degraded_read"),
+            Manner::TraceReplay => f.write_str("trace_replay"),
         }
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Bench {
     block_size: Option<usize>,
     block_num: Option<usize>,
     ssd_block_cap: Option<usize>,
+    ssd_low_watermark: Option<usize>,
     ssd_dev_path: Option<PathBuf>,
     hdd_dev_path: Option<PathBuf>,
     k_p: Option<(usize, usize)>,
     test_num: Option<usize>,
+    warmup: Option<usize>,
     slice_size: Option<usize>,
     out_dir_path: Option<PathBuf>,
+    ssd_op_latency: Option<std::time::Duration>,
+    hdd_op_latency: Option<std::time::Duration>,
+    trace_path: Option<PathBuf>,
+    channel_capacity: Option<usize>,
+    sample_interval: Option<std::time::Duration>,
+    auto_clean: bool,
     manner: Manner,
 }
 
@@ -62,6 +103,13 @@ impl Bench {
         self
     }
 
+    /// Set the number of blocks the ssd buffer is drained down to in a batch once it is full,
+    /// the ssd block capacity if not set. Only the merge-stripe benchmark evicts in batches.
+    pub fn ssd_low_watermark(&mut self, ssd_low_watermark: usize) -> &mut Self {
+        self.ssd_low_watermark = Some(ssd_low_watermark);
+        self
+    }
+
     pub fn ssd_dev_path(&mut self, ssd_dev_path: impl Into<PathBuf>) -> &mut Self {
         self.ssd_dev_path = Some(ssd_dev_path.into());
         self
@@ -82,6 +130,14 @@ impl Bench {
         self
     }
 
+    /// Issue `warmup` updates to fill the ssd buffer before timing,
+    /// so that the evictions happen from the start of the timed region.
+    /// The warmup updates are not counted in the test load nor the duration reported.
+    pub fn warmup(&mut self, warmup: usize) -> &mut Self {
+        self.warmup = Some(warmup);
+        self
+    }
+
     pub fn slice_size(&mut self, slice_size: usize) -> &mut Self {
         self.slice_size = Some(slice_size);
         self
@@ -97,11 +153,85 @@ impl Bench {
         self
     }
 
+    pub fn ssd_op_latency(&mut self, latency: Option<std::time::Duration>) -> &mut Self {
+        self.ssd_op_latency = latency;
+        self
+    }
+
+    pub fn hdd_op_latency(&mut self, latency: Option<std::time::Duration>) -> &mut Self {
+        self.hdd_op_latency = latency;
+        self
+    }
+
+    /// Set the trace file to replay, in the format of [`crate::trace_parser::AliTraceParser`].
+    /// Required by [`Manner::TraceReplay`].
+    pub fn trace_path(&mut self, trace_path: impl Into<PathBuf>) -> &mut Self {
+        self.trace_path = Some(trace_path.into());
+        self
+    }
+
+    /// Set the capacity of the channels between the pipeline stages of the benchmark,
+    /// a default capacity of each benchmark is used if not set.
+    ///
+    /// A small capacity serializes the pipeline, while a large one hides the memory growth.
+    pub fn channel_capacity(&mut self, capacity: NonZeroUsize) -> &mut Self {
+        self.channel_capacity = Some(capacity.get());
+        self
+    }
+
+    /// Set the interval to sample the number of the completed updates,
+    /// which is 1s by default. The throughput over time is dumped to `out_dir_path` in csv format
+    /// by the baseline and merge-stripe benchmarks.
+    pub fn sample_interval(&mut self, interval: std::time::Duration) -> &mut Self {
+        self.sample_interval = Some(interval);
+        self
+    }
+
+    /// Set whether to purge the ssd device before a benchmark buffering slices in it,
+    /// instead of failing if it is not empty, `false` by default.
+    pub fn auto_clean(&mut self, auto_clean: bool) -> &mut Self {
+        self.auto_clean = auto_clean;
+        self
+    }
+
+    /// Make sure the ssd device is empty before buffering slices in it,
+    /// purging it first if [`Bench::auto_clean`] is set.
+    ///
+    /// # Error
+    /// - [`SUError::Other`] if the ssd device is not empty and [`Bench::auto_clean`] is not set
+    /// - any error of the purge, see [`Cleaner::run`]
+    fn prepare_ssd_dev(&self, ssd_dev_path: &Path) -> SUResult<()> {
+        if ssd_dev_path.read_dir()?.next().is_none() {
+            return Ok(());
+        }
+        if !self.auto_clean {
+            return Err(SUError::other(format!(
+                "ssd dev path: {} is not empty",
+                dev_display(ssd_dev_path)
+            )));
+        }
+        Cleaner::new().ssd_dev_path(ssd_dev_path).run().map(|_| ())
+    }
+
+    /// Make a bounded channel of the configured capacity, or `default_capacity` if not set.
+    fn sync_channel<T>(&self, default_capacity: usize) -> (SyncSender<T>, Receiver<T>) {
+        std::sync::mpsc::sync_channel(self.channel_capacity.unwrap_or(default_capacity))
+    }
+
     pub fn run(&self) -> SUResult<()> {
         match self.manner {
-            Manner::Baseline => self.baseline(),
-            Manner::MergeStripe => self.merge_stripe(),
+            Manner::Baseline => self.baseline().map(|_| ()),
+            Manner::MergeStripe => self.merge_stripe().map(|_| ()),
+            #[cfg(feature = "rayon")]
+            Manner::DistMerge => self.dist_merge(),
+            #[cfg(not(feature = "rayon"))]
+            Manner::DistMerge => Err(crate::SUError::invalid_arg(
+                "dist merge requires the `rayon` feature",
+            )),
             Manner::TraceDryRun => self.dryrun(),
+            Manner::CodeOnly => self.code_only(),
+            Manner::DegradedRead => self.degraded_read().map(|_| ()),
+            Manner::TraceReplay => self.trace_replay(),
         }
     }
 }
@@ -111,3 +241,137 @@ struct UpdateRequest {
     block_id: BlockId,
     offset: usize,
 }
+
+/// Push the first `warmup` update requests received into `slice_buf` without timing,
+/// and handle the evictions caused by `on_evict` as the timed ones.
+///
+/// # Return
+/// - [`Ok`] with the length of the buffer at the start of the timed region
+/// - [`Err`] on any error occurring when pushing a slice
+fn warm_up(
+    slice_buf: &impl SliceBuffer,
+    update_consumer: &Receiver<UpdateRequest>,
+    warmup: usize,
+    mut on_evict: impl FnMut(BufferEviction),
+) -> SUResult<usize> {
+    for UpdateRequest {
+        slice_data,
+        block_id,
+        offset,
+    } in update_consumer.iter().take(warmup)
+    {
+        slice_buf
+            .push_slice_batch(block_id, offset, &slice_data)?
+            .into_iter()
+            .for_each(&mut on_evict);
+    }
+    Ok(slice_buf.len())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{num::NonZeroUsize, sync::mpsc::TrySendError};
+
+    use crate::{
+        standalone::data_builder::build_test_dev,
+        storage::{FixedSizeSliceBuf, SliceBuffer},
+        SUError,
+    };
+
+    use super::{Bench, UpdateRequest};
+
+    #[test]
+    fn channel_capacity() {
+        const DEFAULT_CAPACITY: usize = 4;
+        let fill = |bench: &Bench| {
+            let (producer, _consumer) = bench.sync_channel::<usize>(DEFAULT_CAPACITY);
+            (0..)
+                .find(|&i| matches!(producer.try_send(i), Err(TrySendError::Full(_))))
+                .unwrap()
+        };
+        let mut bench = Bench::new();
+        assert_eq!(fill(&bench), DEFAULT_CAPACITY);
+        bench.channel_capacity(NonZeroUsize::new(1).unwrap());
+        assert_eq!(fill(&bench), 1);
+    }
+
+    #[test]
+    fn warm_up() {
+        const BLOCK_SIZE: usize = 64 << 10;
+        const SLICE_SIZE: usize = 4 << 10;
+        const SEG_NUM: usize = BLOCK_SIZE / SLICE_SIZE;
+        const BUF_CAP: usize = 4 * BLOCK_SIZE;
+        const WARMUP: usize = BUF_CAP / SLICE_SIZE;
+        let ssd_dev = tempfile::tempdir().unwrap();
+        let slice_buf = FixedSizeSliceBuf::connect_to_dev(
+            ssd_dev.path(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+            NonZeroUsize::new(BUF_CAP).unwrap(),
+        )
+        .unwrap();
+        let (producer, consumer) = std::sync::mpsc::channel();
+        // the warmup updates and the timed ones, all disjoint
+        (0..WARMUP * 2).for_each(|i| {
+            producer
+                .send(UpdateRequest {
+                    slice_data: vec![1; SLICE_SIZE],
+                    block_id: i / SEG_NUM,
+                    offset: i % SEG_NUM * SLICE_SIZE,
+                })
+                .unwrap();
+        });
+        let mut evicted = 0;
+        let len = super::warm_up(&slice_buf, &consumer, WARMUP, |_| evicted += 1).unwrap();
+        // the buffer is full at the start of the timed region, and the next update evicts
+        assert_eq!(len, BUF_CAP);
+        assert_eq!(evicted, 0);
+        let UpdateRequest {
+            slice_data,
+            block_id,
+            offset,
+        } = consumer.recv().unwrap();
+        assert!(slice_buf
+            .push_slice(block_id, offset, &slice_data)
+            .unwrap()
+            .is_some());
+        // the timed updates are left in the channel
+        assert_eq!(consumer.try_iter().count(), WARMUP - 1);
+    }
+
+    #[test]
+    fn dirty_ssd_dev() {
+        const BLOCK_SIZE: usize = 64 << 10;
+        const BLOCK_NUM: usize = 12;
+        const K: usize = 4;
+        const P: usize = 2;
+        let ssd_dev = tempfile::tempdir().unwrap();
+        let hdd_dev = build_test_dev(K, P, BLOCK_NUM, BLOCK_SIZE);
+        let mut bench = Bench::new();
+        bench
+            .k_p(K, P)
+            .block_size(BLOCK_SIZE)
+            .block_num(BLOCK_NUM)
+            .ssd_block_capacity(2)
+            .slice_size(4 << 10)
+            .test_load(16)
+            .ssd_dev_path(ssd_dev.path())
+            .hdd_dev_path(hdd_dev.path());
+        // left by a previous run
+        let stale_block = crate::storage::Sharding::default().block_path(ssd_dev.path(), 0xAB);
+        std::fs::create_dir_all(stale_block.parent().unwrap()).unwrap();
+        std::fs::write(&stale_block, [0; 16]).unwrap();
+
+        assert!(matches!(bench.baseline(), Err(SUError::Other(_))));
+        assert!(matches!(bench.merge_stripe(), Err(SUError::Other(_))));
+        assert!(stale_block.is_file());
+
+        bench.auto_clean(true).baseline().unwrap();
+        assert!(!stale_block.exists());
+        bench.merge_stripe().unwrap();
+
+        // never purge a directory which is not a device
+        std::fs::write(ssd_dev.path().join("notes.txt"), b"").unwrap();
+        assert!(matches!(bench.baseline(), Err(SUError::InvalidArg(_))));
+        assert!(ssd_dev.path().join("notes.txt").is_file());
+    }
+}
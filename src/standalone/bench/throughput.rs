@@ -0,0 +1,165 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{RecvTimeoutError, SyncSender},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use crate::SUResult;
+
+/// Interval of the throughput sampling if not set.
+pub(super) const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Number of the operations completed at a point of the benchmark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Sample {
+    pub elapsed: Duration,
+    pub completed: usize,
+}
+
+/// Sample the number of the completed operations at a fixed interval on a background thread,
+/// which shows how the throughput evolves, e.g., when the evictions kick in.
+pub(super) struct ThroughputSampler {
+    completed: Arc<AtomicUsize>,
+    stop: SyncSender<()>,
+    handle: JoinHandle<Vec<Sample>>,
+}
+
+impl ThroughputSampler {
+    /// Start sampling every `interval`, from zero operations completed.
+    pub fn spawn(interval: Duration) -> Self {
+        let completed = Arc::new(AtomicUsize::new(0));
+        let (stop, stopped) = std::sync::mpsc::sync_channel(1);
+        let monitor = Arc::clone(&completed);
+        let handle = std::thread::spawn(move || {
+            let epoch = Instant::now();
+            let sample = || Sample {
+                elapsed: epoch.elapsed(),
+                completed: monitor.load(Ordering::SeqCst),
+            };
+            let mut samples = vec![sample()];
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                samples.push(sample());
+            }
+            samples.push(sample());
+            samples
+        });
+        Self {
+            completed,
+            stop,
+            handle,
+        }
+    }
+
+    /// Count an operation completed.
+    pub fn complete_one(&self) {
+        self.completed.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Stop sampling, and return the samples in time order,
+    /// the last of which is taken on stop.
+    pub fn finish(self) -> Vec<Sample> {
+        let _ = self.stop.send(());
+        self.handle.join().unwrap()
+    }
+}
+
+/// Write the `samples` to `out_dir_path` as `<manner>-throughput.csv`,
+/// with the elapsed time, the completed operations and the throughput since the previous sample.
+///
+/// # Return
+/// Path to the csv file
+pub(super) fn dump(
+    samples: &[Sample],
+    manner: impl std::fmt::Display,
+    out_dir_path: &Path,
+) -> SUResult<PathBuf> {
+    let path = out_dir_path.join(format!("{manner}-throughput.csv"));
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(&path)?);
+    writeln!(writer, "ElapsedMs,Completed,Ops")?;
+    let mut prev: Option<&Sample> = None;
+    for sample in samples {
+        let ops = prev.map_or(0.0, |prev| {
+            let secs = (sample.elapsed - prev.elapsed).as_secs_f64();
+            if secs > 0.0 {
+                (sample.completed - prev.completed) as f64 / secs
+            } else {
+                0.0
+            }
+        });
+        writeln!(
+            writer,
+            "{},{},{ops:.0}",
+            sample.elapsed.as_millis(),
+            sample.completed
+        )?;
+        prev = Some(sample);
+    }
+    writer.flush()?;
+    println!("throughput samples dumped to {}", path.display());
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::standalone::{
+        bench::{Bench, Manner},
+        data_builder::build_test_dev,
+    };
+
+    const BLOCK_SIZE: usize = 64 << 10;
+    const BLOCK_NUM: usize = 12;
+    const TEST_LOAD: usize = 128;
+    const K: usize = 4;
+    const P: usize = 2;
+
+    #[test]
+    fn throughput_series() {
+        for manner in [Manner::Baseline, Manner::MergeStripe] {
+            let ssd_dev = tempfile::tempdir().unwrap();
+            let out_dir = tempfile::tempdir().unwrap();
+            let hdd_dev = build_test_dev(K, P, BLOCK_NUM, BLOCK_SIZE);
+            Bench::new()
+                .k_p(K, P)
+                .block_size(BLOCK_SIZE)
+                .block_num(BLOCK_NUM)
+                .ssd_block_capacity(2)
+                .slice_size(4 << 10)
+                .test_load(TEST_LOAD)
+                .sample_interval(Duration::from_millis(1))
+                .ssd_dev_path(ssd_dev.path())
+                .hdd_dev_path(hdd_dev.path())
+                .out_dir_path(out_dir.path())
+                .manner(manner.clone())
+                .run()
+                .unwrap();
+            let csv =
+                std::fs::read_to_string(out_dir.path().join(format!("{manner}-throughput.csv")))
+                    .unwrap();
+            let mut lines = csv.lines();
+            assert_eq!(lines.next(), Some("ElapsedMs,Completed,Ops"));
+            let series = lines
+                .map(|line| {
+                    let columns = line.split(',').collect::<Vec<_>>();
+                    assert_eq!(columns.len(), 3);
+                    (
+                        columns[0].parse::<u128>().unwrap(),
+                        columns[1].parse::<usize>().unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            assert!(series.len() >= 2);
+            assert_eq!(series.last().unwrap().1, TEST_LOAD);
+            assert!(series
+                .windows(2)
+                .all(|w| w[0].0 <= w[1].0 && w[0].1 <= w[1].1));
+        }
+    }
+}
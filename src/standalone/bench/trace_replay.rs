@@ -0,0 +1,284 @@
+use std::{num::NonZeroUsize, ops::Range};
+
+use indicatif::ProgressIterator;
+
+use crate::{
+    erasure_code::ReedSolomon,
+    standalone::dev_display,
+    storage::{BlockId, BufferEviction, FixedSizeSliceBuf, HDDStorage, PartialBlock, SliceBuffer},
+    trace_parser::{AliTraceParser, Operation, Trace},
+    SUError, SUResult,
+};
+
+use super::{
+    baseline::{do_update, UpdateCtx},
+    latency::{self, LatencyRecorder},
+    Bench,
+};
+
+const SEG_SIZE: usize = 4 << 10;
+
+/// Map a trace onto the source blocks, the parity blocks are never mapped.
+///
+/// The device of the trace is regarded as the concatenation of all the source blocks,
+/// and it wraps around if the trace goes beyond `source_block_num` source blocks.
+/// The accessed area is extended to align with the segment size.
+///
+/// # Return
+/// The block ids and the inner-block ranges accessed by the trace, in the order of the device offset.
+fn map_trace(
+    &Trace { offset, size, .. }: &Trace,
+    block_size: usize,
+    k: usize,
+    m: usize,
+    source_block_num: usize,
+) -> Vec<(BlockId, Range<usize>)> {
+    let start = offset / SEG_SIZE * SEG_SIZE;
+    let end = (offset + size).div_ceil(SEG_SIZE) * SEG_SIZE;
+    let mut mapped = Vec::new();
+    let mut pos = start;
+    while pos < end {
+        let inner_offset = pos % block_size;
+        let len = (end - pos).min(block_size - inner_offset);
+        let source_idx = pos / block_size % source_block_num;
+        let block_id = source_idx / k * m + source_idx % k;
+        mapped.push((block_id, inner_offset..inner_offset + len));
+        pos += len;
+    }
+    mapped
+}
+
+/// Content of the `seq`-th write replayed, each byte of which is `seq` truncated to `u8`.
+fn replay_data(seq: usize, len: usize) -> Vec<u8> {
+    vec![seq as u8; len]
+}
+
+impl Bench {
+    pub(super) fn trace_replay(&self) -> SUResult<()> {
+        let (k, p) = self.k_p.expect("k or p not set");
+        let m = k + p;
+        let block_size = self.block_size.expect("block size not set");
+        let hdd_dev_path = self.hdd_dev_path.clone().expect("hdd dev path not set");
+        let ssd_dev_path = self.ssd_dev_path.clone().expect("ssd dev path not set");
+        let block_num = self.block_num.expect("block num not set");
+        let ssd_block_cap = self.ssd_block_cap.expect("ssd block capacity not set");
+        let ssd_cap = ssd_block_cap * block_size;
+        let trace_path = self.trace_path.clone().expect("trace path not set");
+        let ssd_op_latency = self.ssd_op_latency;
+        let hdd_op_latency = self.hdd_op_latency;
+        let ssd_dev_display = dev_display(&ssd_dev_path);
+        let hdd_dev_display = dev_display(&hdd_dev_path);
+        if block_num / m == 0 {
+            return Err(SUError::invalid_arg(format!(
+                "block num {block_num} is less than a stripe of {m} blocks, no source block to replay on"
+            )));
+        }
+        self.prepare_ssd_dev(&ssd_dev_path)?;
+        let source_block_num = block_num / m * k;
+        println!("RS({m}, {k})");
+        println!("block size: {block_size}");
+        println!("block num: {block_num}");
+        println!("hdd dev path: {hdd_dev_display}");
+        println!("ssd dev path: {ssd_dev_display}");
+        println!("ssd block capacity: {ssd_block_cap}");
+        println!("trace path: {}", trace_path.display());
+        if let Some(test_load) = self.test_num {
+            println!("test num: {test_load}");
+        }
+        if let Some(latency) = ssd_op_latency {
+            println!("ssd op latency: {latency:?}");
+        }
+        if let Some(latency) = hdd_op_latency {
+            println!("hdd op latency: {latency:?}");
+        }
+        let mut parser = AliTraceParser::open(&trace_path)?;
+        let ec =
+            ReedSolomon::from_k_p(NonZeroUsize::new(k).unwrap(), NonZeroUsize::new(p).unwrap());
+        let hdd_storage =
+            HDDStorage::connect_to_dev(hdd_dev_path, NonZeroUsize::new(block_size).unwrap())?
+                .with_op_latency(hdd_op_latency);
+        let ssd_storage = FixedSizeSliceBuf::connect_to_dev(
+            ssd_dev_path,
+            NonZeroUsize::new(block_size).unwrap(),
+            NonZeroUsize::new(ssd_cap).unwrap(),
+        )?
+        .with_op_latency(ssd_op_latency);
+        let update_ctx = UpdateCtx::<ReedSolomon> {
+            hdd_storage,
+            block_size,
+            ec,
+        };
+        let mut recorder = LatencyRecorder::new();
+        let mut skipped_reads = 0_usize;
+        let mut writes = 0_usize;
+        // replay the writes in order, at most `test_load` writes if set
+        parser
+            .by_ref()
+            .filter(|trace| match trace.op {
+                Operation::Read => {
+                    skipped_reads += 1;
+                    false
+                }
+                Operation::Write => true,
+            })
+            .take(self.test_num.unwrap_or(usize::MAX))
+            .progress_with(
+                indicatif::ProgressBar::new_spinner().with_message("benchmark trace replay..."),
+            )
+            .try_for_each(|trace| {
+                let epoch = std::time::Instant::now();
+                for (block_id, range) in map_trace(&trace, block_size, k, m, source_block_num) {
+                    let slice_data = replay_data(writes, range.len());
+                    recorder.record_user_write(slice_data.len());
+                    if let Some(BufferEviction {
+                        block_id,
+                        data: PartialBlock { size, slices },
+                    }) = ssd_storage.push_slice(block_id, range.start, &slice_data)?
+                    {
+                        debug_assert_eq!(size, block_size);
+                        recorder.record_hdd_write(do_update(&update_ctx, block_id, slices));
+                    }
+                }
+                recorder.record(epoch.elapsed());
+                writes += 1;
+                SUResult::Ok(())
+            })?;
+        println!("clean up updates buffered in ssd...");
+        ssd_storage.drain_into(
+            |BufferEviction {
+                 block_id,
+                 data: PartialBlock { size, slices },
+             }| {
+                let epoch = std::time::Instant::now();
+                debug_assert_eq!(size, block_size);
+                recorder.record_hdd_write(do_update(&update_ctx, block_id, slices));
+                recorder.record_drain(epoch.elapsed());
+            },
+        );
+        println!("clean up updates buffered in ssd...done");
+        println!("benchmark {}...done", self.manner);
+        println!(
+            "replayed {writes} writes, skipped {skipped_reads} reads and {} malformed lines",
+            parser.malformed()
+        );
+        let result = recorder.into_result(&self.manner, writes);
+        latency::report(&result, self.out_dir_path.as_deref())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use bytes::BytesMut;
+
+    use crate::{
+        erasure_code::{Block, ErasureCode, ReedSolomon, Stripe},
+        standalone::{
+            bench::{Bench, Manner},
+            data_builder::build_test_dev,
+        },
+        storage::{BlockStorage, HDDStorage},
+        SUError,
+    };
+
+    const BLOCK_SIZE: usize = 64 << 10;
+    const BLOCK_NUM: usize = 12;
+    const K: usize = 4;
+    const P: usize = 2;
+    const M: usize = K + P;
+
+    #[test]
+    fn trace_replay() {
+        // 8 source blocks: block 0, 1, 2, 3 in stripe 0, and block 6, 7, 8, 9 in stripe 1
+        const TRACE: &str = "\
+0,W,0,4096,0
+0,R,8192,4096,1
+0,W,69632,4096,2
+0,W,61440,8192,3
+0,W,300000,100,4
+malformed
+0,W,524288,4096,5
+";
+        let ssd_dev = tempfile::tempdir().unwrap();
+        let trace_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(trace_file.path(), TRACE).unwrap();
+        let hdd_dev = build_test_dev(K, P, BLOCK_NUM, BLOCK_SIZE);
+        let hdd_storage =
+            HDDStorage::connect_to_dev(hdd_dev.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap())
+                .unwrap();
+        let mut expect = (0..BLOCK_NUM)
+            .map(|block_id| hdd_storage.get_block_owned(block_id).unwrap().unwrap())
+            .collect::<Vec<_>>();
+        // (block id, inner-block range, content) of each write
+        [
+            (0, 0..4096, 0),
+            (1, 4096..8192, 1),
+            (0, 61440..65536, 2),
+            (1, 0..4096, 2),
+            // [299008..303104) of the device, extended to the segment size
+            (6, 36864..40960, 3),
+            // wraps around to the first source block
+            (0, 0..4096, 4),
+        ]
+        .into_iter()
+        .for_each(|(block_id, range, content)| expect[block_id][range].fill(content));
+
+        Bench::new()
+            .k_p(K, P)
+            .block_size(BLOCK_SIZE)
+            .block_num(BLOCK_NUM)
+            .ssd_block_capacity(1)
+            .ssd_dev_path(ssd_dev.path())
+            .hdd_dev_path(hdd_dev.path())
+            .trace_path(trace_file.path())
+            .manner(Manner::TraceReplay)
+            .run()
+            .unwrap();
+
+        let ec =
+            ReedSolomon::from_k_p(NonZeroUsize::new(K).unwrap(), NonZeroUsize::new(P).unwrap());
+        expect
+            .chunks_exact(M)
+            .enumerate()
+            .for_each(|(stripe_id, expect)| {
+                let blocks = (stripe_id * M..(stripe_id + 1) * M)
+                    .map(|block_id| hdd_storage.get_block_owned(block_id).unwrap().unwrap())
+                    .collect::<Vec<_>>();
+                assert_eq!(&blocks[..K], &expect[..K], "stripe {stripe_id} mismatches");
+                let stripe = Stripe::from_vec(
+                    blocks
+                        .iter()
+                        .map(|block| Block::from(BytesMut::from(block.as_slice())))
+                        .collect(),
+                    NonZeroUsize::new(K).unwrap(),
+                    NonZeroUsize::new(P).unwrap(),
+                );
+                assert!(
+                    ec.verify(&stripe).unwrap(),
+                    "stripe {stripe_id} inconsistent"
+                );
+            });
+    }
+
+    #[test]
+    fn too_few_blocks() {
+        let ssd_dev = tempfile::tempdir().unwrap();
+        let hdd_dev = tempfile::tempdir().unwrap();
+        let trace_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(trace_file.path(), "0,W,0,4096,0\n").unwrap();
+        let e = Bench::new()
+            .k_p(K, P)
+            .block_size(BLOCK_SIZE)
+            .block_num(M - 1)
+            .ssd_block_capacity(1)
+            .ssd_dev_path(ssd_dev.path())
+            .hdd_dev_path(hdd_dev.path())
+            .trace_path(trace_file.path())
+            .manner(Manner::TraceReplay)
+            .run()
+            .unwrap_err();
+        assert!(matches!(e, SUError::InvalidArg(_)), "{e}");
+    }
+}
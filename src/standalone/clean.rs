@@ -3,14 +3,32 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::{standalone::dev_display, SUResult};
+use crate::{standalone::dev_display, storage::META_EXTENSION, SUError, SUResult};
 
 #[derive(Debug, Default)]
 pub struct Cleaner {
     ssd_dev_path: Option<PathBuf>,
     hdd_dev_path: Option<PathBuf>,
+    out_dir_path: Option<PathBuf>,
+    force: bool,
 }
 
+/// Summary of the files removed by [`Cleaner::run`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CleanReport {
+    /// number of the files removed from the ssd device
+    pub ssd_files: usize,
+    /// number of the files removed from the hdd device
+    pub hdd_files: usize,
+    /// number of the result files removed from the output directory
+    pub out_files: usize,
+    /// total size of the removed files in bytes
+    pub bytes_freed: u64,
+}
+
+/// Extensions of the result files generated by the benchmarks.
+const RESULT_FILE_EXTENSIONS: [&str; 3] = ["toml", "csv", "json"];
+
 impl Cleaner {
     pub fn new() -> Self {
         Self::default()
@@ -26,27 +44,227 @@ impl Cleaner {
         self
     }
 
-    pub fn run(&self) -> SUResult<()> {
-        fn purge_dir(path: &Path) -> SUResult<()> {
+    /// Set the output directory, whose result files are removed.
+    /// The directory itself and any other entries in it are preserved.
+    pub fn out_dir_path(&mut self, out_dir_path: impl AsRef<std::path::Path>) -> &mut Self {
+        self.out_dir_path = Some(out_dir_path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set whether to purge a device directory even if it contains files other than the blocks,
+    /// `false` by default.
+    pub fn force(&mut self, force: bool) -> &mut Self {
+        self.force = force;
+        self
+    }
+
+    /// Purge the device directories and remove the results in the output directory.
+    ///
+    /// # Error
+    /// - [`SUError::InvalidArg`] if a device directory contains a file which is not a block file,
+    ///   and [`Cleaner::force`] is not set. Nothing is removed in this case.
+    /// - [`SUError::Io`] if any io error occurs
+    pub fn run(&self) -> SUResult<CleanReport> {
+        fn purge_dir(path: &Path) -> SUResult<(usize, u64)> {
             use std::fs;
+            let (mut files, mut bytes) = (0, 0);
+            for entry in walkdir::WalkDir::new(path).min_depth(1) {
+                let entry = entry.map_err(std::io::Error::from)?;
+                if entry.file_type().is_file() {
+                    files += 1;
+                    bytes += entry.metadata().map_err(std::io::Error::from)?.len();
+                }
+            }
             for entry in fs::read_dir(path)? {
-                fs::remove_dir_all(entry?.path())?;
+                let path = entry?.path();
+                if path.is_dir() {
+                    fs::remove_dir_all(path)?;
+                } else {
+                    fs::remove_file(path)?;
+                }
             }
-            Ok(())
+            Ok((files, bytes))
         }
+        fn remove_results(path: &Path) -> SUResult<(usize, u64)> {
+            use std::fs;
+            let (mut files, mut bytes) = (0, 0);
+            for entry in fs::read_dir(path)? {
+                let path = entry?.path();
+                let is_result = path.is_file()
+                    && path
+                        .extension()
+                        .and_then(|ext| ext.to_str())
+                        .is_some_and(|ext| RESULT_FILE_EXTENSIONS.contains(&ext));
+                if is_result {
+                    bytes += fs::metadata(&path)?.len();
+                    fs::remove_file(path)?;
+                    files += 1;
+                }
+            }
+            Ok((files, bytes))
+        }
+        // check all the devices before purging any of them
+        if !self.force {
+            for dev in [&self.ssd_dev_path, &self.hdd_dev_path]
+                .into_iter()
+                .flatten()
+            {
+                if let Some(file) = find_non_block_file(dev)? {
+                    return Err(SUError::invalid_arg(format!(
+                        "{} does not look like a device directory, {} is not a block file, use --force to purge it anyway",
+                        dev_display(dev),
+                        file.display()
+                    )));
+                }
+            }
+        }
+        let mut report = CleanReport::default();
         if self.ssd_dev_path.is_some() {
             let dev = self.ssd_dev_path.as_ref().unwrap();
             print!("purging ssd dev ({})...", dev_display(dev));
             std::io::stdout().flush().unwrap();
-            purge_dir(dev)?;
+            let (files, bytes) = purge_dir(dev)?;
+            report.ssd_files = files;
+            report.bytes_freed += bytes;
             println!("done");
         }
         if self.hdd_dev_path.is_some() {
             let dev = self.hdd_dev_path.as_ref().unwrap();
             print!("purging hdd dev ({})...", dev_display(dev));
-            purge_dir(dev)?;
+            let (files, bytes) = purge_dir(dev)?;
+            report.hdd_files = files;
+            report.bytes_freed += bytes;
             println!("done")
         }
-        Ok(())
+        if let Some(out_dir) = self.out_dir_path.as_ref() {
+            print!("removing results in ({})...", out_dir.display());
+            std::io::stdout().flush().unwrap();
+            let (files, bytes) = remove_results(out_dir)?;
+            report.out_files = files;
+            report.bytes_freed += bytes;
+            println!("done");
+        }
+        Ok(report)
+    }
+}
+
+/// Whether a file looks like one stored in a device directory,
+/// i.e., its name is a block id in hex, optionally with the extension of a slice buffer metadata file.
+fn is_block_file(path: &Path) -> bool {
+    let hex_stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .is_some_and(|stem| !stem.is_empty() && stem.chars().all(|c| c.is_ascii_hexdigit()));
+    hex_stem && path.extension().map_or(true, |ext| ext == META_EXTENSION)
+}
+
+/// Find a file under the device directory `dev` which is not a block file.
+///
+/// # Return
+/// - [`Ok(Some)`] with the path of the first such file found
+/// - [`Ok(None)`] if all the files are block files
+/// - [`Err`] if the directory cannot be read
+fn find_non_block_file(dev: &Path) -> SUResult<Option<PathBuf>> {
+    for entry in walkdir::WalkDir::new(dev).min_depth(1) {
+        let entry = entry.map_err(std::io::Error::from)?;
+        if entry.file_type().is_file() && !is_block_file(entry.path()) {
+            return Ok(Some(entry.into_path()));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{storage::Sharding, SUError};
+
+    #[test]
+    fn clean_out_dir() {
+        let out_dir = tempfile::tempdir().unwrap();
+        let sub_dir = out_dir.path().join("traces");
+        std::fs::create_dir(&sub_dir).unwrap();
+        let results = [
+            "baseline-bench.toml",
+            "baseline-throughput.csv",
+            "dryrun.json",
+        ];
+        results
+            .iter()
+            .chain(["notes.txt"].iter())
+            .for_each(|name| std::fs::write(out_dir.path().join(name), b"result").unwrap());
+
+        let report = super::Cleaner::new()
+            .out_dir_path(out_dir.path())
+            .run()
+            .unwrap();
+        assert_eq!(report.out_files, results.len());
+        assert!(out_dir.path().is_dir());
+        assert!(results
+            .iter()
+            .all(|name| !out_dir.path().join(name).exists()));
+        assert!(out_dir.path().join("notes.txt").exists());
+        assert!(sub_dir.is_dir());
+    }
+
+    #[test]
+    fn report() {
+        let ssd_dev = tempfile::tempdir().unwrap();
+        let hdd_dev = tempfile::tempdir().unwrap();
+        let sharding = Sharding::default();
+        (0..3).for_each(|block_id| {
+            let path = sharding.block_path(hdd_dev.path(), block_id);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, [0; 100]).unwrap();
+        });
+        let path = sharding.block_path(ssd_dev.path(), 0x1AB);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, [0; 10]).unwrap();
+        std::fs::write(path.with_extension("meta"), [0; 8]).unwrap();
+
+        let report = super::Cleaner::new()
+            .ssd_dev_path(ssd_dev.path())
+            .hdd_dev_path(hdd_dev.path())
+            .run()
+            .unwrap();
+        assert_eq!(
+            report,
+            super::CleanReport {
+                ssd_files: 2,
+                hdd_files: 3,
+                out_files: 0,
+                bytes_freed: 318,
+            }
+        );
+        assert!(ssd_dev.path().is_dir());
+        assert_eq!(ssd_dev.path().read_dir().unwrap().count(), 0);
+        assert_eq!(hdd_dev.path().read_dir().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn refuse_non_device_dir() {
+        let ssd_dev = tempfile::tempdir().unwrap();
+        let other_dir = tempfile::tempdir().unwrap();
+        let block_path = Sharding::default().block_path(ssd_dev.path(), 1);
+        std::fs::create_dir_all(block_path.parent().unwrap()).unwrap();
+        std::fs::write(&block_path, [0; 10]).unwrap();
+        std::fs::write(other_dir.path().join("Cargo.toml"), b"").unwrap();
+
+        // nothing is removed, even from the valid device directory
+        let result = super::Cleaner::new()
+            .ssd_dev_path(ssd_dev.path())
+            .hdd_dev_path(other_dir.path())
+            .run();
+        assert!(matches!(result, Err(SUError::InvalidArg(_))));
+        assert!(block_path.is_file());
+        assert!(other_dir.path().join("Cargo.toml").is_file());
+
+        let report = super::Cleaner::new()
+            .ssd_dev_path(ssd_dev.path())
+            .hdd_dev_path(other_dir.path())
+            .force(true)
+            .run()
+            .unwrap();
+        assert_eq!((report.ssd_files, report.hdd_files), (1, 1));
+        assert!(!other_dir.path().join("Cargo.toml").exists());
     }
 }
@@ -7,6 +7,7 @@ use indicatif::ProgressIterator;
 
 use crate::{
     erasure_code::{ErasureCode, ReedSolomon, Stripe},
+    standalone::stripe_checksum::StripeChecksums,
     storage::{BlockStorage, HDDStorage},
     SUResult,
 };
@@ -19,7 +20,10 @@ pub struct DataBuilder {
     ssd_dev_path: Option<PathBuf>,
     hdd_dev_path: Option<PathBuf>,
     purge: bool,
+    resume: bool,
     k_p: Option<(usize, usize)>,
+    threads: Option<usize>,
+    stripe_checksum: bool,
 }
 
 impl DataBuilder {
@@ -57,11 +61,36 @@ impl DataBuilder {
         self
     }
 
+    /// Skip the stripes already built, i.e., all of whose block files are complete,
+    /// so that an interrupted build can be resumed. Disabled by default.
+    pub fn resume(&mut self, resume: bool) -> &mut Self {
+        self.resume = resume;
+        self
+    }
+
     pub fn k_p(&mut self, k: usize, p: usize) -> &mut Self {
         self.k_p = Some((k, p));
         self
     }
 
+    /// Set the number of the encoder threads, which is 1 by default.
+    ///
+    /// # Panics
+    /// - If `threads` is 0
+    pub fn threads(&mut self, threads: usize) -> &mut Self {
+        assert_ne!(threads, 0, "encoder threads should be non-zero");
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Store the checksum of each stripe built, see [`StripeChecksums`],
+    /// so that a mis-decoded stripe can be detected on verifying and rebuilding.
+    /// Disabled by default, in which case the stale checksums of the stripes built are removed.
+    pub fn stripe_checksum(&mut self, stripe_checksum: bool) -> &mut Self {
+        self.stripe_checksum = stripe_checksum;
+        self
+    }
+
     pub fn build(&self) -> SUResult<()> {
         const CHANNEL_SIZE: usize = 1024;
         let (source_stripe_producer, source_stripe_consumer) =
@@ -70,13 +99,16 @@ impl DataBuilder {
             std::sync::mpsc::sync_channel::<StripeItem>(CHANNEL_SIZE);
         let (k, p) = self.k_p.expect("k or p not set");
         let m = k + p;
-        let block_num = self.block_num.expect("block num not set");
+        let mut block_num = self.block_num.expect("block num not set");
+        let stripe_num = block_num.div_ceil(m);
         if block_num % m != 0 {
-            panic!("block number: {block_num} is not multiple of ec m: {m}");
+            println!("ec-m [{m}] cannot divide block num [{block_num}], round up stripe number to {stripe_num}");
+            block_num = stripe_num * m;
         }
-        let stripe_num = block_num / m;
         let block_size = self.block_size.expect("block size not set");
         let hdd_dev_path = self.hdd_dev_path.clone().expect("hdd dev path not set");
+        let threads = self.threads.unwrap_or(1);
+        let stripe_checksum = self.stripe_checksum;
         fn dev_display(dev: &Path) -> String {
             let mut display = dev.display().to_string();
             if dev.is_symlink() {
@@ -90,6 +122,8 @@ impl DataBuilder {
         println!("block num: {block_num}");
         println!("stripe num: {stripe_num}");
         println!("hdd dev path: {hdd_dev_display}");
+        println!("encoder threads: {threads}");
+        println!("stripe checksum: {stripe_checksum}");
         if self.purge {
             print!("purging dir...");
             fn purge_dir(path: &Path) -> SUResult<()> {
@@ -102,11 +136,36 @@ impl DataBuilder {
             purge_dir(hdd_dev_path.as_path())?;
             println!("done")
         }
+        let stripes_to_build = if self.resume {
+            let hdd_storage = HDDStorage::connect_to_dev(
+                hdd_dev_path.as_path(),
+                NonZeroUsize::new(block_size).unwrap(),
+            )?;
+            let mut stripes_to_build = Vec::new();
+            for stripe_id in 0..stripe_num {
+                for block_id in stripe_id * m..(stripe_id + 1) * m {
+                    if !hdd_storage.is_block_complete(block_id)? {
+                        stripes_to_build.push(stripe_id);
+                        break;
+                    }
+                }
+            }
+            println!(
+                "resume: {} stripes built, {} stripes to build",
+                stripe_num - stripes_to_build.len(),
+                stripes_to_build.len()
+            );
+            stripes_to_build
+        } else {
+            (0..stripe_num).collect::<Vec<_>>()
+        };
+        let build_stripe_num = stripes_to_build.len();
+        let built_block_num = build_stripe_num * m;
         let epoch = std::time::Instant::now();
         // data generator
         let generator_handle = std::thread::spawn(move || {
             use rand::Rng;
-            (0..stripe_num).for_each(|stripe_id| {
+            stripes_to_build.into_iter().for_each(|stripe_id| {
                 let mut stripe = Stripe::zero(
                     NonZeroUsize::new(k).unwrap(),
                     NonZeroUsize::new(p).unwrap(),
@@ -126,30 +185,44 @@ impl DataBuilder {
                     .unwrap();
             });
         });
-        // data encoder
-        let encoder_handle = std::thread::spawn(move || {
-            let ec =
-                ReedSolomon::from_k_p(NonZeroUsize::new(k).unwrap(), NonZeroUsize::new(p).unwrap());
-            while let Ok(StripeItem {
-                mut stripe,
-                block_id_range,
-            }) = source_stripe_consumer.recv()
-            {
-                ec.encode_stripe(&mut stripe).unwrap();
-                encoded_stripe_producer
-                    .send(StripeItem {
-                        stripe,
+        // data encoders, the stripes are independent and the store tolerates any order
+        let source_stripe_consumer =
+            std::sync::Arc::new(std::sync::Mutex::new(source_stripe_consumer));
+        let encoder_handles = (0..threads)
+            .map(|_| {
+                let source_stripe_consumer = source_stripe_consumer.clone();
+                let encoded_stripe_producer = encoded_stripe_producer.clone();
+                std::thread::spawn(move || {
+                    let ec = ReedSolomon::from_k_p(
+                        NonZeroUsize::new(k).unwrap(),
+                        NonZeroUsize::new(p).unwrap(),
+                    );
+                    // the lock guard is dropped once a stripe is received
+                    let recv = || source_stripe_consumer.lock().unwrap().recv();
+                    while let Ok(StripeItem {
+                        mut stripe,
                         block_id_range,
-                    })
-                    .unwrap();
-            }
-        });
+                    }) = recv()
+                    {
+                        ec.encode_stripe(&mut stripe).unwrap();
+                        encoded_stripe_producer
+                            .send(StripeItem {
+                                stripe,
+                                block_id_range,
+                            })
+                            .unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(encoded_stripe_producer);
         // data store
         let store_handle = std::thread::spawn(move || {
+            let checksums = StripeChecksums::open(&hdd_dev_path);
             let hdd_storage =
                 HDDStorage::connect_to_dev(hdd_dev_path, NonZeroUsize::new(block_size).unwrap())
                     .unwrap();
-            (0..stripe_num)
+            (0..build_stripe_num)
                 .map(|_| {
                     encoded_stripe_consumer
                         .recv()
@@ -162,6 +235,12 @@ impl DataBuilder {
                          block_id_range,
                      }| {
                         assert_eq!(block_id_range.len(), stripe.m());
+                        let stripe_id = block_id_range.start / stripe.m();
+                        if stripe_checksum {
+                            checksums.put(stripe_id, stripe.checksum()).unwrap();
+                        } else {
+                            checksums.remove(stripe_id).unwrap();
+                        }
                         stripe
                             .iter_source()
                             .chain(stripe.iter_parity())
@@ -173,17 +252,19 @@ impl DataBuilder {
             println!("building data...done");
         });
         generator_handle.join().unwrap();
-        encoder_handle.join().unwrap();
+        encoder_handles
+            .into_iter()
+            .for_each(|handle| handle.join().unwrap());
         store_handle.join().unwrap();
         let elapsed = epoch.elapsed();
         println!(
-            "built {block_num} blocks in {}s{}ms",
+            "built {built_block_num} blocks in {}s{}ms",
             elapsed.as_secs(),
             elapsed.as_millis()
         );
         println!(
             "throughput: {} blocks/s",
-            block_num * 1000 * 1000 / usize::try_from(elapsed.as_micros()).unwrap()
+            built_block_num * 1000 * 1000 / usize::try_from(elapsed.as_micros()).unwrap()
         );
         Ok(())
     }
@@ -193,3 +274,163 @@ struct StripeItem {
     stripe: Stripe,
     block_id_range: std::ops::Range<usize>,
 }
+
+/// Build `block_num` blocks of `block_size` encoded in stripes of `k + p` blocks
+/// on a new temporary hdd device for the tests, which is removed on drop.
+#[cfg(test)]
+pub(crate) fn build_test_dev(
+    k: usize,
+    p: usize,
+    block_num: usize,
+    block_size: usize,
+) -> tempfile::TempDir {
+    build_test_dev_with(k, p, block_num, block_size, |_| ())
+}
+
+/// Same as [`build_test_dev`], with the builder further set by `configure` before building.
+#[cfg(test)]
+pub(crate) fn build_test_dev_with(
+    k: usize,
+    p: usize,
+    block_num: usize,
+    block_size: usize,
+    configure: impl FnOnce(&mut DataBuilder),
+) -> tempfile::TempDir {
+    let hdd_dev = tempfile::tempdir().unwrap();
+    let mut builder = DataBuilder::new();
+    builder
+        .block_num(block_num)
+        .block_size(block_size)
+        .hdd_dev_path(hdd_dev.path())
+        .k_p(k, p);
+    configure(&mut builder);
+    builder.build().unwrap();
+    hdd_dev
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use crate::storage::{BlockStorage, HDDStorage};
+
+    use super::DataBuilder;
+
+    const BLOCK_SIZE: usize = 4 << 10;
+    const EC_K: usize = 4;
+    const EC_P: usize = 2;
+    const EC_M: usize = EC_K + EC_P;
+
+    #[test]
+    fn build_non_divisible_block_num() {
+        const BLOCK_NUM: usize = EC_M * 3 + 1;
+        const EXPECT_BLOCK_NUM: usize = EC_M * 4;
+        let hdd_dev = tempfile::tempdir().unwrap();
+        DataBuilder::new()
+            .block_num(BLOCK_NUM)
+            .block_size(BLOCK_SIZE)
+            .hdd_dev_path(hdd_dev.path())
+            .k_p(EC_K, EC_P)
+            .build()
+            .unwrap();
+        let block_cnt = walkdir::WalkDir::new(hdd_dev.path())
+            .into_iter()
+            .map(|entry| entry.unwrap())
+            .filter(|entry| entry.file_type().is_file())
+            .count();
+        assert_eq!(block_cnt, EXPECT_BLOCK_NUM);
+        let hdd_store =
+            HDDStorage::connect_to_dev(hdd_dev.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap())
+                .unwrap();
+        (0..EXPECT_BLOCK_NUM)
+            .for_each(|id| assert!(hdd_store.get_block_owned(id).unwrap().is_some()));
+        assert!(hdd_store
+            .get_block_owned(EXPECT_BLOCK_NUM)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn resume() {
+        const STRIPE_NUM: usize = 8;
+        let hdd_dev = tempfile::tempdir().unwrap();
+        let mut builder = DataBuilder::new();
+        builder
+            .block_num(STRIPE_NUM * EC_M)
+            .block_size(BLOCK_SIZE)
+            .hdd_dev_path(hdd_dev.path())
+            .k_p(EC_K, EC_P);
+        builder.build().unwrap();
+        let hdd_store =
+            HDDStorage::connect_to_dev(hdd_dev.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap())
+                .unwrap();
+        let built = (0..STRIPE_NUM * EC_M)
+            .map(|block_id| hdd_store.get_block_owned(block_id).unwrap().unwrap())
+            .collect::<Vec<_>>();
+        // interrupted build: stripe 6 and 7 not built, and stripe 5 partially built
+        (5 * EC_M + 3..STRIPE_NUM * EC_M).for_each(|block_id| {
+            hdd_store.remove_block(block_id).unwrap().unwrap();
+        });
+        // a block of stripe 1 lost
+        hdd_store.remove_block(EC_M + 1).unwrap().unwrap();
+        builder.resume(true).build().unwrap();
+        let incomplete = [1, 5, 6, 7];
+        (0..STRIPE_NUM).for_each(|stripe_id| {
+            let block_ids = stripe_id * EC_M..(stripe_id + 1) * EC_M;
+            let blocks = block_ids
+                .clone()
+                .map(|block_id| hdd_store.get_block_owned(block_id).unwrap().unwrap())
+                .collect::<Vec<_>>();
+            if incomplete.contains(&stripe_id) {
+                // rebuilt with new random data
+                assert_ne!(blocks[0], built[block_ids.start], "stripe {stripe_id}");
+            } else {
+                assert_eq!(blocks, built[block_ids], "stripe {stripe_id}");
+            }
+        });
+    }
+
+    #[test]
+    fn build_multi_threaded() {
+        use crate::erasure_code::{ErasureCode, ReedSolomon, Stripe};
+        const STRIPE_NUM: usize = 64;
+        let hdd_dev = tempfile::tempdir().unwrap();
+        DataBuilder::new()
+            .block_num(STRIPE_NUM * EC_M)
+            .block_size(BLOCK_SIZE)
+            .hdd_dev_path(hdd_dev.path())
+            .k_p(EC_K, EC_P)
+            .threads(4)
+            .build()
+            .unwrap();
+        let hdd_store =
+            HDDStorage::connect_to_dev(hdd_dev.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap())
+                .unwrap();
+        // the parity blocks stored should be the same as the ones encoded in a single thread
+        let ec = ReedSolomon::from_k_p(
+            NonZeroUsize::new(EC_K).unwrap(),
+            NonZeroUsize::new(EC_P).unwrap(),
+        );
+        (0..STRIPE_NUM).for_each(|stripe_id| {
+            let blocks = (stripe_id * EC_M..(stripe_id + 1) * EC_M)
+                .map(|block_id| hdd_store.get_block_owned(block_id).unwrap().unwrap())
+                .collect::<Vec<_>>();
+            let mut stripe = Stripe::zero(
+                NonZeroUsize::new(EC_K).unwrap(),
+                NonZeroUsize::new(EC_P).unwrap(),
+                NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+            );
+            stripe
+                .iter_mut_source()
+                .zip(&blocks)
+                .for_each(|(source, stored)| source.copy_from_slice(stored));
+            ec.encode_stripe(&mut stripe).unwrap();
+            stripe
+                .iter_parity()
+                .zip(&blocks[EC_K..])
+                .for_each(|(parity, stored)| {
+                    assert_eq!(parity.as_ref(), stored.as_slice(), "stripe {stripe_id}")
+                });
+        });
+    }
+}
@@ -1,6 +1,10 @@
 pub mod bench;
 pub mod clean;
 pub mod data_builder;
+pub mod rebuild;
+pub mod reencode;
+pub mod stripe_checksum;
+pub mod verify;
 
 fn progress_style_template(msg: Option<&str>) -> indicatif::ProgressStyle {
     match msg {
@@ -0,0 +1,221 @@
+use std::{num::NonZeroUsize, path::PathBuf};
+
+use bytes::BytesMut;
+
+use crate::{
+    erasure_code::{Block, ErasureCode, PartialStripe, ReedSolomon, Stripe},
+    standalone::{dev_display, stripe_checksum::StripeChecksums},
+    storage::{BlockId, BlockStorage, HDDStorage},
+    SUError, SUResult,
+};
+
+/// Rebuild a block stored in the hdd dev from the surviving blocks of its stripe,
+/// and write the reconstructed block back.
+///
+/// The target block is regarded as absent no matter whether it exists,
+/// and so are the other blocks missing in the stripe.
+/// If the checksum of the stripe is stored, see [`StripeChecksums`],
+/// the decoded stripe is checked against it before the block is written back.
+/// Otherwise, a parity block is re-encoded from the source blocks only,
+/// if none of them is missing.
+#[derive(Debug, Default)]
+pub struct Rebuilder {
+    block_size: Option<usize>,
+    hdd_dev_path: Option<PathBuf>,
+    k_p: Option<(usize, usize)>,
+    block_id: Option<BlockId>,
+}
+
+impl Rebuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn block_size(&mut self, block_size: usize) -> &mut Self {
+        self.block_size = Some(block_size);
+        self
+    }
+
+    pub fn hdd_dev_path(&mut self, hdd_dev_path: impl AsRef<std::path::Path>) -> &mut Self {
+        self.hdd_dev_path = Some(hdd_dev_path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn k_p(&mut self, k: usize, p: usize) -> &mut Self {
+        self.k_p = Some((k, p));
+        self
+    }
+
+    pub fn block_id(&mut self, block_id: BlockId) -> &mut Self {
+        self.block_id = Some(block_id);
+        self
+    }
+
+    /// Rebuild the block.
+    ///
+    /// # Error
+    /// - [`crate::SUError::Io`] if the hdd dev cannot be read or written
+    /// - [`crate::SUError::ErasureCode`] if more than `p` blocks of the stripe are absent
+    /// - [`crate::SUError::Checksum`] if the decoded stripe mismatches its checksum,
+    ///   i.e., some surviving blocks are corrupted, and nothing is written back
+    pub fn run(&self) -> SUResult<()> {
+        let (k, p) = self.k_p.expect("k or p not set");
+        let m = k + p;
+        let block_size = self.block_size.expect("block size not set");
+        let hdd_dev_path = self.hdd_dev_path.clone().expect("hdd dev path not set");
+        let block_id = self.block_id.expect("block id not set");
+        let stripe_id = block_id / m;
+        let target_idx = block_id % m;
+        println!("RS({m}, {k})");
+        println!("block size: {block_size}");
+        println!("hdd dev path: {}", dev_display(&hdd_dev_path));
+        println!("rebuild block {block_id}, index {target_idx} of stripe {stripe_id}");
+        let ec =
+            ReedSolomon::from_k_p(NonZeroUsize::new(k).unwrap(), NonZeroUsize::new(p).unwrap());
+        let checksums = StripeChecksums::open(&hdd_dev_path);
+        let hdd_storage =
+            HDDStorage::connect_to_dev(hdd_dev_path, NonZeroUsize::new(block_size).unwrap())?;
+        let checksum = checksums.get(stripe_id)?;
+        let epoch = std::time::Instant::now();
+        let mut read_bytes = 0;
+        let mut read_duration = std::time::Duration::ZERO;
+        // the target block is regarded as absent without reading
+        let mut read_survivor = |idx: usize| -> SUResult<Option<Block>> {
+            if idx == target_idx {
+                return Ok(None);
+            }
+            let read_epoch = std::time::Instant::now();
+            let survivor_id = stripe_id * m + idx;
+            let mut block = BytesMut::zeroed(block_size);
+            let found = hdd_storage.get_block(survivor_id, &mut block)?.is_some();
+            read_duration += read_epoch.elapsed();
+            if !found {
+                println!("block {survivor_id} of stripe {stripe_id} is missing");
+                return Ok(None);
+            }
+            read_bytes += block_size;
+            Ok(Some(block.into()))
+        };
+        let source = (0..k)
+            .map(&mut read_survivor)
+            .collect::<SUResult<Vec<_>>>()?;
+        // a parity block is re-encoded from the source blocks only,
+        // unless the whole stripe is required to check its checksum
+        let reconstructed =
+            if target_idx >= k && checksum.is_none() && source.iter().all(Option::is_some) {
+                let source = source.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+                let mut parity = Block::zero(block_size);
+                ec.encode_parity(&source, target_idx - k, &mut parity)?;
+                parity
+            } else {
+                let mut partial_stripe = PartialStripe::make_absent_from_k_p(
+                    NonZeroUsize::new(k).unwrap(),
+                    NonZeroUsize::new(p).unwrap(),
+                    NonZeroUsize::new(block_size).unwrap(),
+                );
+                source.into_iter().enumerate().for_each(|(idx, block)| {
+                    partial_stripe.replace_block(idx, block);
+                });
+                for idx in k..m {
+                    partial_stripe.replace_block(idx, read_survivor(idx)?);
+                }
+                ec.decode(&mut partial_stripe)?;
+                let stripe = Stripe::try_from(partial_stripe)?;
+                if checksum.is_some_and(|checksum| checksum != stripe.checksum()) {
+                    return Err(SUError::Checksum(format!(
+                        "decoded stripe {stripe_id} mismatches its checksum"
+                    )));
+                }
+                stripe.into_blocks().swap_remove(target_idx)
+            };
+        hdd_storage.put_block(block_id, reconstructed.as_ref())?;
+        let duration = epoch.elapsed();
+        println!(
+            "rebuilt block {block_id} in {}ms, {}ms spent on reading {read_bytes} bytes",
+            duration.as_millis(),
+            read_duration.as_millis()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use crate::{
+        standalone::data_builder::{build_test_dev, build_test_dev_with},
+        storage::{BlockStorage, HDDStorage},
+        SUError,
+    };
+
+    use super::Rebuilder;
+
+    const BLOCK_SIZE: usize = 4 << 10;
+    const EC_K: usize = 4;
+    const EC_P: usize = 2;
+    const EC_M: usize = EC_K + EC_P;
+    const STRIPE_NUM: usize = 4;
+
+    #[test]
+    fn rebuild_removed_block() {
+        let hdd_dev = build_test_dev(EC_K, EC_P, STRIPE_NUM * EC_M, BLOCK_SIZE);
+        let hdd_store =
+            HDDStorage::connect_to_dev(hdd_dev.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap())
+                .unwrap();
+        let mut rebuilder = Rebuilder::new();
+        rebuilder
+            .block_size(BLOCK_SIZE)
+            .hdd_dev_path(hdd_dev.path())
+            .k_p(EC_K, EC_P);
+        // a source block and a parity block of different stripes
+        for block_id in [EC_M + 2, 3 * EC_M + EC_K + 1] {
+            let expect = hdd_store.get_block_owned(block_id).unwrap().unwrap();
+            hdd_store.remove_block(block_id).unwrap().unwrap();
+            assert!(hdd_store.get_block_owned(block_id).unwrap().is_none());
+            rebuilder.block_id(block_id).run().unwrap();
+            assert_eq!(
+                hdd_store.get_block_owned(block_id).unwrap().unwrap(),
+                expect,
+                "block {block_id} mismatches"
+            );
+        }
+        // too many blocks of a stripe are absent
+        (0..=EC_P).for_each(|idx| {
+            hdd_store.remove_block(2 * EC_M + idx).unwrap().unwrap();
+        });
+        assert!(rebuilder.block_id(2 * EC_M).run().is_err());
+    }
+
+    #[test]
+    fn corrupted_survivor_detected() {
+        let hdd_dev = build_test_dev_with(EC_K, EC_P, STRIPE_NUM * EC_M, BLOCK_SIZE, |builder| {
+            builder.stripe_checksum(true);
+        });
+        let hdd_store =
+            HDDStorage::connect_to_dev(hdd_dev.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap())
+                .unwrap();
+        let mut rebuilder = Rebuilder::new();
+        rebuilder
+            .block_size(BLOCK_SIZE)
+            .hdd_dev_path(hdd_dev.path())
+            .k_p(EC_K, EC_P);
+        // a bit flip in a present block of stripe 1 is decoded into the target silently
+        let target = EC_M;
+        let mut survivor = hdd_store.get_block_owned(EC_M + 1).unwrap().unwrap();
+        survivor[0] ^= 0x01;
+        hdd_store.put_block(EC_M + 1, &survivor).unwrap();
+        hdd_store.remove_block(target).unwrap().unwrap();
+        let e = rebuilder.block_id(target).run().unwrap_err();
+        assert!(matches!(e, SUError::Checksum(_)));
+        assert!(hdd_store.get_block_owned(target).unwrap().is_none());
+        // the intact stripes are rebuilt as usual
+        let expect = hdd_store.get_block_owned(2 * EC_M).unwrap().unwrap();
+        hdd_store.remove_block(2 * EC_M).unwrap().unwrap();
+        rebuilder.block_id(2 * EC_M).run().unwrap();
+        assert_eq!(
+            hdd_store.get_block_owned(2 * EC_M).unwrap().unwrap(),
+            expect
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use std::{num::NonZeroUsize, path::PathBuf};
+
+use indicatif::ProgressIterator;
+
+use crate::{
+    erasure_code::{ErasureCode, ReedSolomon, Stripe},
+    standalone::dev_display,
+    storage::{BlockStorage, HDDStorage},
+    SUError, SUResult,
+};
+
+/// Regenerate the parity blocks of every stripe from the source blocks stored in the hdd dev.
+///
+/// Unlike [`DataBuilder`](super::data_builder::DataBuilder),
+/// the source blocks are left untouched, only the parity blocks are overwritten.
+#[derive(Debug, Default)]
+pub struct Reencoder {
+    block_size: Option<usize>,
+    block_num: Option<usize>,
+    hdd_dev_path: Option<PathBuf>,
+    k_p: Option<(usize, usize)>,
+}
+
+impl Reencoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn block_size(&mut self, block_size: usize) -> &mut Self {
+        self.block_size = Some(block_size);
+        self
+    }
+
+    pub fn block_num(&mut self, block_num: usize) -> &mut Self {
+        self.block_num = Some(block_num);
+        self
+    }
+
+    pub fn hdd_dev_path(&mut self, hdd_dev_path: impl AsRef<std::path::Path>) -> &mut Self {
+        self.hdd_dev_path = Some(hdd_dev_path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn k_p(&mut self, k: usize, p: usize) -> &mut Self {
+        self.k_p = Some((k, p));
+        self
+    }
+
+    /// Re-encode all the stripes.
+    ///
+    /// # Return
+    /// - [`Ok`] with the number of stripes re-encoded
+    /// - [`Err`] on any error occurring
+    ///
+    /// # Error
+    /// - [`SUError::Other`] if any source block is missing in the hdd dev
+    pub fn run(&self) -> SUResult<usize> {
+        let (k, p) = self.k_p.expect("k or p not set");
+        let m = k + p;
+        let block_num = self.block_num.expect("block num not set");
+        let stripe_num = block_num.div_ceil(m);
+        let block_size = self.block_size.expect("block size not set");
+        let hdd_dev_path = self.hdd_dev_path.clone().expect("hdd dev path not set");
+        println!("RS({m}, {k})");
+        println!("block size: {block_size}");
+        println!("stripe num: {stripe_num}");
+        println!("hdd dev path: {}", dev_display(&hdd_dev_path));
+        let ec =
+            ReedSolomon::from_k_p(NonZeroUsize::new(k).unwrap(), NonZeroUsize::new(p).unwrap());
+        let hdd_storage =
+            HDDStorage::connect_to_dev(hdd_dev_path, NonZeroUsize::new(block_size).unwrap())?;
+        let mut stripe = Stripe::zero(
+            NonZeroUsize::new(k).unwrap(),
+            NonZeroUsize::new(p).unwrap(),
+            NonZeroUsize::new(block_size).unwrap(),
+        );
+        let epoch = std::time::Instant::now();
+        (0..stripe_num)
+            .progress_with_style(super::progress_style_template(Some(
+                "re-encoding parity...",
+            )))
+            .try_for_each(|stripe_id| {
+                let block_id_start = stripe_id * m;
+                stripe
+                    .iter_mut_source()
+                    .zip(block_id_start..)
+                    .try_for_each(|(block, block_id)| {
+                        hdd_storage
+                            .get_block(block_id, block.as_mut())?
+                            .ok_or_else(|| {
+                                SUError::other(format!("source block {block_id} not found"))
+                            })
+                    })?;
+                ec.encode_stripe(&mut stripe)?;
+                stripe
+                    .iter_parity()
+                    .zip(block_id_start + k..)
+                    .try_for_each(|(block, block_id)| hdd_storage.put_block(block_id, block))
+            })?;
+        let elapsed = epoch.elapsed();
+        println!("re-encoding parity...done");
+        println!(
+            "re-encoded {stripe_num} stripes in {}s{}ms",
+            elapsed.as_secs(),
+            elapsed.subsec_millis()
+        );
+        Ok(stripe_num)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use crate::{
+        standalone::data_builder::build_test_dev,
+        storage::{BlockStorage, HDDStorage},
+    };
+
+    use super::Reencoder;
+
+    const BLOCK_SIZE: usize = 4 << 10;
+    const EC_K: usize = 4;
+    const EC_P: usize = 2;
+    const EC_M: usize = EC_K + EC_P;
+    const STRIPE_NUM: usize = 16;
+
+    #[test]
+    fn reencode_corrupted_parity() {
+        let hdd_dev = build_test_dev(EC_K, EC_P, STRIPE_NUM * EC_M, BLOCK_SIZE);
+        let hdd_store =
+            HDDStorage::connect_to_dev(hdd_dev.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap())
+                .unwrap();
+        let blocks = (0..STRIPE_NUM * EC_M)
+            .map(|id| hdd_store.get_block_owned(id).unwrap().unwrap())
+            .collect::<Vec<_>>();
+        // corrupt parity
+        (0..STRIPE_NUM * EC_M)
+            .filter(|id| id % EC_M >= EC_K)
+            .for_each(|id| hdd_store.put_block(id, &[0_u8; BLOCK_SIZE]).unwrap());
+        let stripe_num = Reencoder::new()
+            .block_num(STRIPE_NUM * EC_M)
+            .block_size(BLOCK_SIZE)
+            .hdd_dev_path(hdd_dev.path())
+            .k_p(EC_K, EC_P)
+            .run()
+            .unwrap();
+        assert_eq!(stripe_num, STRIPE_NUM);
+        blocks.iter().enumerate().for_each(|(id, block)| {
+            assert_eq!(&hdd_store.get_block_owned(id).unwrap().unwrap(), block);
+        });
+    }
+
+    #[test]
+    fn reencode_missing_source() {
+        let hdd_dev = tempfile::tempdir().unwrap();
+        let ret = Reencoder::new()
+            .block_num(EC_M)
+            .block_size(BLOCK_SIZE)
+            .hdd_dev_path(hdd_dev.path())
+            .k_p(EC_K, EC_P)
+            .run();
+        assert!(matches!(ret, Err(crate::SUError::Other(_))));
+    }
+}
@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+
+use crate::SUResult;
+
+/// Directory under the hdd dev root holding the stripe checksums,
+/// which is skipped when iterating the blocks since its name is not a block id.
+const CHECKSUM_DIR: &str = "stripe-checksum";
+
+/// The checksums of the stripes built, see [`crate::erasure_code::Stripe::checksum`].
+///
+/// Each checksum is stored as a little-endian `u32` in a metadata file named by the stripe id,
+/// apart from the blocks, so that it survives the loss of any block of the stripe.
+/// A stripe updated by the benchmarks has its checksum removed before being written back,
+/// as the checksum no longer holds, and such a stripe is then verified by its parity only.
+#[derive(Debug, Clone)]
+pub struct StripeChecksums {
+    dir: PathBuf,
+}
+
+impl StripeChecksums {
+    /// Open the stripe checksums stored in the hdd dev.
+    /// The metadata directory is not created until a checksum is put.
+    pub fn open(hdd_dev: impl AsRef<Path>) -> Self {
+        Self {
+            dir: hdd_dev.as_ref().join(CHECKSUM_DIR),
+        }
+    }
+
+    fn path(&self, stripe_id: usize) -> PathBuf {
+        self.dir.join(format!("{stripe_id:x}"))
+    }
+
+    /// Store the checksum of a stripe, overwriting the previous one.
+    pub fn put(&self, stripe_id: usize, checksum: u32) -> SUResult<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.path(stripe_id), checksum.to_le_bytes())?;
+        Ok(())
+    }
+
+    /// Get the checksum of a stripe.
+    ///
+    /// # Return
+    /// - [`Some`] with the checksum stored
+    /// - [`None`] if no checksum of the stripe is stored
+    ///
+    /// # Error
+    /// - [`crate::SUError::Checksum`] if the stored checksum is malformed
+    pub fn get(&self, stripe_id: usize) -> SUResult<Option<u32>> {
+        let bytes = match std::fs::read(self.path(stripe_id)) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let bytes: [u8; 4] = bytes.try_into().map_err(|bytes: Vec<u8>| {
+            crate::SUError::Checksum(format!(
+                "checksum of stripe {stripe_id} is malformed with {} bytes",
+                bytes.len()
+            ))
+        })?;
+        Ok(Some(u32::from_le_bytes(bytes)))
+    }
+
+    /// Remove the checksum of a stripe if it exists.
+    pub fn remove(&self, stripe_id: usize) -> SUResult<()> {
+        match std::fs::remove_file(self.path(stripe_id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::StripeChecksums;
+
+    #[test]
+    fn put_get_remove() {
+        let hdd_dev = tempfile::tempdir().unwrap();
+        let checksums = StripeChecksums::open(hdd_dev.path());
+        assert!(checksums.get(1).unwrap().is_none());
+        checksums.remove(1).unwrap();
+        checksums.put(1, 0xdead_beef).unwrap();
+        checksums.put(17, 1).unwrap();
+        assert_eq!(checksums.get(1).unwrap(), Some(0xdead_beef));
+        checksums.put(1, 2).unwrap();
+        assert_eq!(checksums.get(1).unwrap(), Some(2));
+        checksums.remove(1).unwrap();
+        assert!(checksums.get(1).unwrap().is_none());
+        assert_eq!(checksums.get(17).unwrap(), Some(1));
+        // malformed
+        std::fs::write(hdd_dev.path().join(super::CHECKSUM_DIR).join("11"), [0; 3]).unwrap();
+        assert!(matches!(
+            checksums.get(17),
+            Err(crate::SUError::Checksum(_))
+        ));
+    }
+}
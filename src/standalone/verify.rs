@@ -0,0 +1,231 @@
+use std::{collections::BTreeSet, num::NonZeroUsize, path::PathBuf};
+
+use indicatif::ProgressIterator;
+
+use crate::{
+    erasure_code::{ErasureCode, ReedSolomon, Stripe},
+    standalone::dev_display,
+    standalone::stripe_checksum::StripeChecksums,
+    storage::{BlockStorage, HDDStorage},
+    SUResult,
+};
+
+/// Verify the consistency of every stripe stored in the hdd dev,
+/// which detects the silent corruption of the blocks.
+///
+/// The stripes are enumerated from the blocks stored,
+/// and a stripe with any block missing is regarded as inconsistent,
+/// as well as a stripe mismatching its checksum if stored, see [`StripeChecksums`].
+#[derive(Debug, Default)]
+pub struct Verifier {
+    block_size: Option<usize>,
+    hdd_dev_path: Option<PathBuf>,
+    k_p: Option<(usize, usize)>,
+}
+
+impl Verifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn block_size(&mut self, block_size: usize) -> &mut Self {
+        self.block_size = Some(block_size);
+        self
+    }
+
+    pub fn hdd_dev_path(&mut self, hdd_dev_path: impl AsRef<std::path::Path>) -> &mut Self {
+        self.hdd_dev_path = Some(hdd_dev_path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn k_p(&mut self, k: usize, p: usize) -> &mut Self {
+        self.k_p = Some((k, p));
+        self
+    }
+
+    /// Verify all the stripes.
+    ///
+    /// # Return
+    /// - [`Ok`] with the ids of the inconsistent stripes, in ascending order
+    /// - [`Err`] on any error occurring
+    ///
+    /// # Error
+    /// - [`crate::SUError::Io`] if the hdd dev cannot be read
+    pub fn run(&self) -> SUResult<Vec<usize>> {
+        let (k, p) = self.k_p.expect("k or p not set");
+        let m = k + p;
+        let block_size = self.block_size.expect("block size not set");
+        let hdd_dev_path = self.hdd_dev_path.clone().expect("hdd dev path not set");
+        println!("RS({m}, {k})");
+        println!("block size: {block_size}");
+        println!("hdd dev path: {}", dev_display(&hdd_dev_path));
+        let ec =
+            ReedSolomon::from_k_p(NonZeroUsize::new(k).unwrap(), NonZeroUsize::new(p).unwrap());
+        let checksums = StripeChecksums::open(&hdd_dev_path);
+        let hdd_storage =
+            HDDStorage::connect_to_dev(hdd_dev_path, NonZeroUsize::new(block_size).unwrap())?;
+        let stripe_ids = hdd_storage
+            .iter_block_ids()?
+            .map(|block_id| block_id / m)
+            .collect::<BTreeSet<_>>();
+        println!("stripe num: {}", stripe_ids.len());
+        let mut stripe = Stripe::zero(
+            NonZeroUsize::new(k).unwrap(),
+            NonZeroUsize::new(p).unwrap(),
+            NonZeroUsize::new(block_size).unwrap(),
+        );
+        let mut inconsistent = Vec::new();
+        stripe_ids
+            .iter()
+            .progress_with_style(super::progress_style_template(Some("verifying stripes...")))
+            .try_for_each(|&stripe_id| {
+                let (source, parity) = stripe.split_mut_source_parity();
+                let mut all_present = true;
+                for (block, block_id) in source.iter_mut().chain(parity).zip(stripe_id * m..) {
+                    if hdd_storage.get_block(block_id, block.as_mut())?.is_none() {
+                        println!("block {block_id} of stripe {stripe_id} is missing");
+                        all_present = false;
+                        break;
+                    }
+                }
+                if !all_present {
+                    inconsistent.push(stripe_id);
+                } else if !ec.verify(&stripe)? {
+                    let mut expect = stripe.clone();
+                    ec.encode_stripe(&mut expect)?;
+                    let mismatched = stripe
+                        .diff(&expect)
+                        .into_iter()
+                        .map(|idx| stripe_id * m + idx)
+                        .collect::<Vec<_>>();
+                    println!(
+                        "parity blocks {mismatched:?} of stripe {stripe_id} mismatch the source blocks"
+                    );
+                    inconsistent.push(stripe_id);
+                } else if checksums
+                    .get(stripe_id)?
+                    .is_some_and(|checksum| checksum != stripe.checksum())
+                {
+                    println!("stripe {stripe_id} mismatches its checksum");
+                    inconsistent.push(stripe_id);
+                }
+                SUResult::Ok(())
+            })?;
+        println!("verifying stripes...done");
+        Ok(inconsistent)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use crate::{
+        standalone::data_builder::{build_test_dev, build_test_dev_with},
+        storage::{BlockStorage, HDDStorage},
+    };
+
+    use super::Verifier;
+
+    const BLOCK_SIZE: usize = 4 << 10;
+    const EC_K: usize = 4;
+    const EC_P: usize = 2;
+    const EC_M: usize = EC_K + EC_P;
+    const STRIPE_NUM: usize = 8;
+
+    #[test]
+    fn verify_corrupted_stripes() {
+        let hdd_dev = build_test_dev(EC_K, EC_P, STRIPE_NUM * EC_M, BLOCK_SIZE);
+        let mut verifier = Verifier::new();
+        verifier
+            .block_size(BLOCK_SIZE)
+            .hdd_dev_path(hdd_dev.path())
+            .k_p(EC_K, EC_P);
+        assert!(verifier.run().unwrap().is_empty());
+
+        let hdd_store =
+            HDDStorage::connect_to_dev(hdd_dev.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap())
+                .unwrap();
+        // corrupt a parity block of stripe 2
+        let mut parity = hdd_store.get_block_owned(2 * EC_M + EC_K).unwrap().unwrap();
+        parity[0] ^= 0xff;
+        hdd_store.put_block(2 * EC_M + EC_K, &parity).unwrap();
+        assert_eq!(verifier.run().unwrap(), vec![2]);
+        // remove a source block of stripe 5
+        hdd_store.remove_block(5 * EC_M + 1).unwrap().unwrap();
+        assert_eq!(verifier.run().unwrap(), vec![2, 5]);
+    }
+
+    #[test]
+    fn verify_stripe_checksum() {
+        use crate::erasure_code::{ErasureCode, ReedSolomon, Stripe};
+
+        let hdd_dev = build_test_dev_with(EC_K, EC_P, STRIPE_NUM * EC_M, BLOCK_SIZE, |builder| {
+            builder.stripe_checksum(true);
+        });
+        let mut verifier = Verifier::new();
+        verifier
+            .block_size(BLOCK_SIZE)
+            .hdd_dev_path(hdd_dev.path())
+            .k_p(EC_K, EC_P);
+        assert!(verifier.run().unwrap().is_empty());
+
+        let hdd_store =
+            HDDStorage::connect_to_dev(hdd_dev.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap())
+                .unwrap();
+        // stripe 3 is overwritten with consistent but different blocks
+        let mut stripe = Stripe::zero(
+            NonZeroUsize::new(EC_K).unwrap(),
+            NonZeroUsize::new(EC_P).unwrap(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        );
+        ReedSolomon::from_k_p(
+            NonZeroUsize::new(EC_K).unwrap(),
+            NonZeroUsize::new(EC_P).unwrap(),
+        )
+        .encode_stripe(&mut stripe)
+        .unwrap();
+        stripe
+            .iter_source()
+            .chain(stripe.iter_parity())
+            .zip(3 * EC_M..)
+            .for_each(|(block, block_id)| hdd_store.put_block(block_id, block).unwrap());
+        assert_eq!(verifier.run().unwrap(), vec![3]);
+    }
+
+    #[test]
+    fn verify_updated_stripes() {
+        use crate::standalone::bench::{Bench, Manner};
+
+        let mut manners = vec![Manner::Baseline, Manner::MergeStripe];
+        if cfg!(feature = "rayon") {
+            manners.push(Manner::DistMerge);
+        }
+        for manner in manners {
+            let hdd_dev =
+                build_test_dev_with(EC_K, EC_P, STRIPE_NUM * EC_M, BLOCK_SIZE, |builder| {
+                    builder.stripe_checksum(true);
+                });
+            let ssd_dev = tempfile::tempdir().unwrap();
+            Bench::new()
+                .k_p(EC_K, EC_P)
+                .block_size(BLOCK_SIZE)
+                .block_num(STRIPE_NUM * EC_M)
+                .ssd_block_capacity(2)
+                .slice_size(BLOCK_SIZE)
+                .test_load(64)
+                .ssd_dev_path(ssd_dev.path())
+                .hdd_dev_path(hdd_dev.path())
+                .manner(manner.clone())
+                .run()
+                .unwrap();
+            // the checksums of the updated stripes do not report them as corrupted
+            let mut verifier = Verifier::new();
+            verifier
+                .block_size(BLOCK_SIZE)
+                .hdd_dev_path(hdd_dev.path())
+                .k_p(EC_K, EC_P);
+            assert!(verifier.run().unwrap().is_empty(), "{manner}");
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use std::{future::Future, num::NonZeroUsize, os::unix::fs::FileExt, path::PathBuf, sync::Arc};
+
+use bytes::Bytes;
+
+use crate::{SUError, SUResult};
+
+use super::utility::{block_id_to_path, check_block_range};
+use super::{BlockId, BlockStorage};
+
+/// The asynchronous counterpart of [`BlockStorage`].
+///
+/// The buffers are owned, since they may be moved to a blocking thread.
+pub trait AsyncBlockStorage {
+    /// Storing data to a block.
+    /// A new block will be created if the block does not exist.
+    ///
+    /// # Error
+    /// - [`SUError::Range`] if `block_data.len()` does not match block size
+    fn put_block(
+        &self,
+        block_id: BlockId,
+        block_data: Bytes,
+    ) -> impl Future<Output = SUResult<()>> + Send;
+    /// Retrieving data from a full block.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success with the block data returned
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    fn get_block(&self, block_id: BlockId) -> impl Future<Output = SUResult<Option<Bytes>>> + Send;
+    /// Remove a block.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    fn remove_block(&self, block_id: BlockId) -> impl Future<Output = SUResult<Option<()>>> + Send;
+    /// Get size of a block
+    fn block_size(&self) -> usize;
+}
+
+/// Join a blocking task, propagating its panic.
+async fn join_blocking<T, F>(f: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
+/// An adapter running the operations of a synchronous [`BlockStorage`] on the blocking thread pool.
+#[derive(Debug)]
+pub struct BlockingAdapter<S>(Arc<S>);
+
+impl<S> BlockingAdapter<S>
+where
+    S: BlockStorage + Send + Sync + 'static,
+{
+    pub fn new(inner: S) -> Self {
+        Self(Arc::new(inner))
+    }
+
+    /// Get the wrapped storage.
+    pub fn inner(&self) -> &S {
+        &self.0
+    }
+}
+
+impl<S> AsyncBlockStorage for BlockingAdapter<S>
+where
+    S: BlockStorage + Send + Sync + 'static,
+{
+    async fn put_block(&self, block_id: BlockId, block_data: Bytes) -> SUResult<()> {
+        let inner = self.0.clone();
+        join_blocking(move || inner.put_block(block_id, &block_data)).await
+    }
+
+    async fn get_block(&self, block_id: BlockId) -> SUResult<Option<Bytes>> {
+        let inner = self.0.clone();
+        join_blocking(move || inner.get_block_owned(block_id))
+            .await
+            .map(|data| data.map(Bytes::from))
+    }
+
+    async fn remove_block(&self, block_id: BlockId) -> SUResult<Option<()>> {
+        let inner = self.0.clone();
+        join_blocking(move || inner.remove_block(block_id)).await
+    }
+
+    fn block_size(&self) -> usize {
+        self.0.block_size()
+    }
+}
+
+/// An asynchronous HDD storage, whose block files have the same layout as [`super::HDDStorage`]
+/// without the checksum, so that either of them can read the blocks stored by the other.
+#[derive(Debug, Clone)]
+pub struct AsyncHDDStorage {
+    dev: PathBuf,
+    block_size: usize,
+}
+
+impl AsyncHDDStorage {
+    /// Connect the [`AsyncHDDStorage`] to a device to store the blocks.
+    ///
+    /// # Error
+    /// [`SUError::Io(std::io::ErrorKind::NotFound)`] if `dev_path` not existing
+    pub async fn connect_to_dev(
+        dev_path: impl Into<PathBuf>,
+        block_size: NonZeroUsize,
+    ) -> SUResult<Self> {
+        let dev_path: PathBuf = dev_path.into();
+        if !tokio::fs::try_exists(dev_path.as_path()).await? {
+            return Err(SUError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "dev path not found",
+            )));
+        }
+        Ok(Self {
+            dev: dev_path,
+            block_size: block_size.get(),
+        })
+    }
+}
+
+impl AsyncBlockStorage for AsyncHDDStorage {
+    async fn put_block(&self, block_id: BlockId, block_data: Bytes) -> SUResult<()> {
+        check_block_range(
+            file!(),
+            line!(),
+            column!(),
+            block_data.len(),
+            self.block_size,
+        )?;
+        let path = block_id_to_path(self.dev.as_path(), block_id);
+        tokio::fs::create_dir_all(path.parent().unwrap()).await?;
+        tokio::fs::write(path, block_data).await?;
+        Ok(())
+    }
+
+    async fn get_block(&self, block_id: BlockId) -> SUResult<Option<Bytes>> {
+        let path = block_id_to_path(self.dev.as_path(), block_id);
+        let f = match tokio::fs::File::open(path).await {
+            Ok(f) => f.into_std().await,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(SUError::Io(e)),
+        };
+        let block_size = self.block_size;
+        // tokio has no positioned read, which is done on a blocking thread
+        join_blocking(move || {
+            let mut block_data = vec![0_u8; block_size];
+            f.read_exact_at(&mut block_data, 0)?;
+            Ok(Some(Bytes::from(block_data)))
+        })
+        .await
+    }
+
+    async fn remove_block(&self, block_id: BlockId) -> SUResult<Option<()>> {
+        match tokio::fs::remove_file(block_id_to_path(self.dev.as_path(), block_id)).await {
+            Ok(()) => Ok(Some(())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(SUError::Io(e)),
+        }
+    }
+
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use bytes::Bytes;
+    use rand::Rng;
+
+    use crate::{
+        storage::{BlockStorage, HDDStorage},
+        SUError,
+    };
+
+    use super::{AsyncBlockStorage, AsyncHDDStorage, BlockingAdapter};
+
+    const BLOCK_SIZE: usize = 4 << 10;
+    const BLOCK_NUM: usize = 256;
+
+    fn random_block_data() -> Bytes {
+        rand::thread_rng()
+            .sample_iter(rand::distributions::Standard)
+            .take(BLOCK_SIZE)
+            .collect::<Vec<u8>>()
+            .into()
+    }
+
+    async fn put_get_block(store: &impl AsyncBlockStorage) {
+        let blocks = (0..BLOCK_NUM)
+            .map(|_| random_block_data())
+            .collect::<Vec<_>>();
+        for (block_id, block) in blocks.iter().enumerate() {
+            store.put_block(block_id, block.clone()).await.unwrap();
+        }
+        for (block_id, block) in blocks.iter().enumerate() {
+            assert_eq!(&store.get_block(block_id).await.unwrap().unwrap(), block);
+        }
+        // overwrite
+        let block = random_block_data();
+        store.put_block(0, block.clone()).await.unwrap();
+        assert_eq!(store.get_block(0).await.unwrap().unwrap(), block);
+        // not existing
+        assert!(store.get_block(BLOCK_NUM).await.unwrap().is_none());
+        // size not matching
+        let e = store
+            .put_block(BLOCK_NUM, Bytes::from(vec![0_u8; BLOCK_SIZE + 1]))
+            .await;
+        assert!(matches!(e, Err(SUError::Range(_))));
+        // remove
+        assert!(store.remove_block(1).await.unwrap().is_some());
+        assert!(store.get_block(1).await.unwrap().is_none());
+        assert!(store.remove_block(1).await.unwrap().is_none());
+        assert_eq!(store.block_size(), BLOCK_SIZE);
+    }
+
+    #[tokio::test]
+    async fn async_hdd_storage() {
+        let tempdir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            AsyncHDDStorage::connect_to_dev(
+                tempdir.path().join("not-exist"),
+                NonZeroUsize::new(BLOCK_SIZE).unwrap()
+            )
+            .await,
+            Err(SUError::Io(_))
+        ));
+        let store =
+            AsyncHDDStorage::connect_to_dev(tempdir.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap())
+                .await
+                .unwrap();
+        put_get_block(&store).await;
+        // interoperable with the synchronous storage
+        let sync_store =
+            HDDStorage::connect_to_dev(tempdir.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap())
+                .unwrap();
+        let block = random_block_data();
+        sync_store.put_block(BLOCK_NUM, &block).unwrap();
+        assert_eq!(store.get_block(BLOCK_NUM).await.unwrap().unwrap(), block);
+        let block = random_block_data();
+        store.put_block(BLOCK_NUM + 1, block.clone()).await.unwrap();
+        assert_eq!(
+            sync_store.get_block_owned(BLOCK_NUM + 1).unwrap().unwrap(),
+            block
+        );
+    }
+
+    #[tokio::test]
+    async fn blocking_adapter() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let store = BlockingAdapter::new(
+            HDDStorage::connect_to_dev(tempdir.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap())
+                .unwrap(),
+        );
+        put_get_block(&store).await;
+        assert!(store.inner().get_block_owned(0).unwrap().is_some());
+    }
+}
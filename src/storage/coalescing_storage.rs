@@ -0,0 +1,443 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use crate::{SUError, SUResult};
+
+use super::utility::{check_block_range, check_slice_range};
+use super::{BlockId, BlockStorage, SliceStorage};
+
+/// Slices written to a block but not yet flushed to the inner storage.
+#[derive(Debug)]
+struct Pending {
+    block_id: BlockId,
+    /// non-overlapping and non-adjacent slices, indexed by the start offset
+    slices: BTreeMap<usize, Vec<u8>>,
+}
+
+impl Pending {
+    fn new(block_id: BlockId) -> Self {
+        Self {
+            block_id,
+            slices: BTreeMap::new(),
+        }
+    }
+
+    /// Buffer a slice, merged with the buffered slices overlapping or adjacent to it.
+    /// The newer data wins on the overlapped area.
+    fn insert(&mut self, inner_block_offset: usize, slice_data: &[u8]) {
+        let mut start = inner_block_offset;
+        let mut end = inner_block_offset + slice_data.len();
+        let merged = self
+            .slices
+            .range(..=end)
+            .rev()
+            .take_while(|(offset, data)| *offset + data.len() >= inner_block_offset)
+            .map(|(offset, _)| *offset)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|offset| {
+                let data = self.slices.remove(&offset).unwrap();
+                start = start.min(offset);
+                end = end.max(offset + data.len());
+                (offset, data)
+            })
+            .collect::<Vec<_>>();
+        let mut buf = vec![0_u8; end - start];
+        merged.iter().for_each(|(offset, data)| {
+            buf[offset - start..offset - start + data.len()].copy_from_slice(data)
+        });
+        buf[inner_block_offset - start..inner_block_offset - start + slice_data.len()]
+            .copy_from_slice(slice_data);
+        self.slices.insert(start, buf);
+    }
+
+    /// Overwrite `slice_data`, read from `inner_block_offset`, with the buffered slices.
+    fn overlay(&self, inner_block_offset: usize, slice_data: &mut [u8]) {
+        let end = inner_block_offset + slice_data.len();
+        self.slices
+            .range(..end)
+            .filter(|(offset, data)| *offset + data.len() > inner_block_offset)
+            .for_each(|(offset, data)| {
+                let lo = inner_block_offset.max(*offset);
+                let hi = end.min(offset + data.len());
+                slice_data[lo - inner_block_offset..hi - inner_block_offset]
+                    .copy_from_slice(&data[lo - offset..hi - offset]);
+            });
+    }
+}
+
+/// A wrapper buffering the slice writes to a block, which are flushed to the inner storage
+/// as merged contiguous writes on [`CoalescingStorage::flush`], or once a different block is accessed.
+///
+/// Only the slices of a single block are buffered at a time.
+/// The first slice written to a block goes through to the inner storage,
+/// which tells whether the block exists, and the following ones are buffered.
+/// The reads through the wrapper observe the buffered slices,
+/// while the reads on the inner storage directly do not until they are flushed.
+///
+/// The buffered slices are flushed on drop as the last resort, where an error is only logged,
+/// so call [`CoalescingStorage::flush`] before dropping to handle it.
+#[derive(Debug)]
+pub struct CoalescingStorage<S>
+where
+    S: BlockStorage + SliceStorage,
+{
+    inner: S,
+    block_size: usize,
+    pending: Mutex<Option<Pending>>,
+}
+
+impl<S> CoalescingStorage<S>
+where
+    S: BlockStorage + SliceStorage,
+{
+    /// Make a [`CoalescingStorage`] on top of `inner`.
+    pub fn new(inner: S) -> Self {
+        Self {
+            block_size: inner.block_size(),
+            inner,
+            pending: Mutex::new(None),
+        }
+    }
+
+    /// Get the inner storage.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Flush the buffered slices to the inner storage.
+    ///
+    /// The slices are kept buffered on an error of the inner storage, so that the flush can be retried.
+    ///
+    /// # Error
+    /// - [`SUError::Other`] if the block is removed from the inner storage before flushing,
+    ///   and the slices are discarded
+    /// - any error of the inner storage
+    pub fn flush(&self) -> SUResult<()> {
+        self.flush_pending(&mut self.pending.lock().unwrap())
+    }
+
+    fn flush_pending(&self, pending: &mut Option<Pending>) -> SUResult<()> {
+        let Some(flushing) = pending.take() else {
+            return Ok(());
+        };
+        if flushing.slices.is_empty() {
+            return Ok(());
+        }
+        let block_id = flushing.block_id;
+        let slices = flushing
+            .slices
+            .iter()
+            .map(|(offset, data)| (*offset, data.as_slice()))
+            .collect::<Vec<_>>();
+        match self.inner.put_slices(block_id, &slices) {
+            Ok(Some(())) => Ok(()),
+            Ok(None) => Err(SUError::other(format!(
+                "block {block_id} removed before flushing"
+            ))),
+            Err(e) => {
+                *pending = Some(flushing);
+                Err(e)
+            }
+        }
+    }
+
+    /// Take the pending slices of `block_id` if any, or flush the ones of the other block.
+    fn take_or_flush(&self, pending: &mut Option<Pending>, block_id: BlockId) -> SUResult<()> {
+        if pending.as_ref().is_some_and(|p| p.block_id == block_id) {
+            pending.take();
+            return Ok(());
+        }
+        self.flush_pending(pending)
+    }
+}
+
+impl<S> Drop for CoalescingStorage<S>
+where
+    S: BlockStorage + SliceStorage,
+{
+    fn drop(&mut self) {
+        self.flush()
+            .unwrap_or_else(|e| log::error!("fail to flush the buffered slices, error: {e}"));
+    }
+}
+
+impl<S> BlockStorage for CoalescingStorage<S>
+where
+    S: BlockStorage + SliceStorage,
+{
+    /// Storing data to a block, which supersedes the buffered slices of the block.
+    ///
+    /// # Error
+    /// - [SUError::Range] if `block_data.len()` does not match block size
+    fn put_block(&self, block_id: BlockId, block_data: &[u8]) -> SUResult<()> {
+        check_block_range(
+            file!(),
+            line!(),
+            column!(),
+            block_data.len(),
+            self.block_size,
+        )?;
+        let mut pending = self.pending.lock().unwrap();
+        self.take_or_flush(&mut pending, block_id)?;
+        self.inner.put_block(block_id, block_data)?;
+        // the block is known to exist
+        *pending = Some(Pending::new(block_id));
+        Ok(())
+    }
+
+    /// Retrieving data from a full block, with the buffered slices applied.
+    ///
+    /// # Error
+    /// - [`SUError::Range`] if `block_data.len()` does not match the block length
+    fn get_block(&self, block_id: BlockId, block_data: &mut [u8]) -> SUResult<Option<()>> {
+        check_block_range(
+            file!(),
+            line!(),
+            column!(),
+            block_data.len(),
+            self.block_size,
+        )?;
+        self.get_slice(block_id, 0, block_data)
+    }
+
+    /// Remove a block, discarding its buffered slices.
+    fn remove_block(&self, block_id: BlockId) -> SUResult<Option<()>> {
+        let mut pending = self.pending.lock().unwrap();
+        self.take_or_flush(&mut pending, block_id)?;
+        self.inner.remove_block(block_id)
+    }
+
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+}
+
+impl<S> SliceStorage for CoalescingStorage<S>
+where
+    S: BlockStorage + SliceStorage,
+{
+    /// Buffering a slice to a specific area of a block.
+    /// The slices of the other block buffered are flushed first.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    ///
+    /// # Error
+    /// - [SUError::Range] if the area specified is out of the block range
+    fn put_slice(
+        &self,
+        block_id: BlockId,
+        inner_block_offset: usize,
+        slice_data: &[u8],
+    ) -> SUResult<Option<()>> {
+        check_slice_range(
+            file!(),
+            line!(),
+            column!(),
+            inner_block_offset..inner_block_offset + slice_data.len(),
+            self.block_size,
+        )?;
+        let mut pending = self.pending.lock().unwrap();
+        match pending.as_mut() {
+            Some(p) if p.block_id == block_id => {
+                p.insert(inner_block_offset, slice_data);
+                Ok(Some(()))
+            }
+            _ => {
+                self.flush_pending(&mut pending)?;
+                let res = self
+                    .inner
+                    .put_slice(block_id, inner_block_offset, slice_data)?;
+                if res.is_some() {
+                    *pending = Some(Pending::new(block_id));
+                }
+                Ok(res)
+            }
+        }
+    }
+
+    /// Retrieving slice data from a specific area of a block, with the buffered slices applied.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success, and the buffer `slice_data` with be filled with the corresponding data.
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    ///
+    /// # Error
+    /// - [SUError::Range] if the area specified is out of the block range
+    fn get_slice(
+        &self,
+        block_id: BlockId,
+        inner_block_offset: usize,
+        slice_data: &mut [u8],
+    ) -> SUResult<Option<()>> {
+        check_slice_range(
+            file!(),
+            line!(),
+            column!(),
+            inner_block_offset..inner_block_offset + slice_data.len(),
+            self.block_size,
+        )?;
+        let mut pending = self.pending.lock().unwrap();
+        if pending.as_ref().map_or(true, |p| p.block_id != block_id) {
+            self.flush_pending(&mut pending)?;
+        }
+        let res = self
+            .inner
+            .get_slice(block_id, inner_block_offset, slice_data)?;
+        if let (Some(()), Some(p)) = (res, pending.as_ref()) {
+            p.overlay(inner_block_offset, slice_data);
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+    use std::num::NonZeroUsize;
+
+    use crate::{
+        storage::{BlockStorage, HDDStorage, SliceStorage},
+        SUError,
+    };
+
+    use super::CoalescingStorage;
+    const BLOCK_SIZE: usize = 4 << 10;
+    const BLOCK_NUM: usize = 4;
+
+    fn hdd_storage(dev: &std::path::Path) -> HDDStorage {
+        let store =
+            HDDStorage::connect_to_dev(dev, NonZeroUsize::new(BLOCK_SIZE).unwrap()).unwrap();
+        (0..BLOCK_NUM).for_each(|block_id| store.put_block(block_id, &[0; BLOCK_SIZE]).unwrap());
+        store
+    }
+
+    fn random_slice(len: usize) -> Vec<u8> {
+        rand::thread_rng()
+            .sample_iter(rand::distributions::Standard)
+            .take(len)
+            .collect()
+    }
+
+    #[test]
+    fn same_as_unbuffered() {
+        let (plain_dev, coalescing_dev) =
+            (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let plain = hdd_storage(plain_dev.path());
+        let coalescing = CoalescingStorage::new(hdd_storage(coalescing_dev.path()));
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            // stay on a block for a run of writes
+            let block_id = rng.gen_range(0..BLOCK_NUM);
+            let mut offset = rng.gen_range(0..BLOCK_SIZE);
+            for _ in 0..rng.gen_range(1..16) {
+                let len = rng.gen_range(1..=(BLOCK_SIZE - offset).min(256));
+                let slice = random_slice(len);
+                plain.put_slice(block_id, offset, &slice).unwrap().unwrap();
+                coalescing
+                    .put_slice(block_id, offset, &slice)
+                    .unwrap()
+                    .unwrap();
+                // adjacent, overlapping or apart
+                offset =
+                    (offset + len).saturating_sub(rng.gen_range(0..64)) + rng.gen_range(0..2) * 32;
+                if offset >= BLOCK_SIZE {
+                    break;
+                }
+            }
+            let len = rng.gen_range(1..=BLOCK_SIZE - offset.min(BLOCK_SIZE - 1));
+            let start = rng.gen_range(0..=BLOCK_SIZE - len);
+            assert_eq!(
+                coalescing
+                    .get_slice_owned(block_id, start..start + len)
+                    .unwrap(),
+                plain.get_slice_owned(block_id, start..start + len).unwrap()
+            );
+        }
+        coalescing.flush().unwrap();
+        for block_id in 0..BLOCK_NUM {
+            assert_eq!(
+                coalescing
+                    .inner()
+                    .get_block_owned(block_id)
+                    .unwrap()
+                    .unwrap(),
+                plain.get_block_owned(block_id).unwrap().unwrap(),
+                "block {block_id} mismatches"
+            );
+        }
+    }
+
+    #[test]
+    fn coalesce_slices() {
+        let dev = tempfile::tempdir().unwrap();
+        let store = CoalescingStorage::new(hdd_storage(dev.path()));
+        let mut expect = vec![0_u8; BLOCK_SIZE];
+        // adjacent, overlapping and apart
+        for (offset, len) in [(0, 16), (16, 16), (64, 32), (8, 64), (200, 8), (100, 4)] {
+            let slice = random_slice(len);
+            store.put_slice(1, offset, &slice).unwrap().unwrap();
+            expect[offset..offset + len].copy_from_slice(&slice);
+        }
+        let buffered = store.pending.lock().unwrap().as_ref().map(|p| {
+            p.slices
+                .iter()
+                .map(|(offset, data)| (*offset, data.len()))
+                .collect::<Vec<_>>()
+        });
+        // the first slice is written through
+        assert_eq!(buffered, Some(vec![(8, 88), (100, 4), (200, 8)]));
+        // read your writes before flushing
+        assert_eq!(store.get_block_owned(1).unwrap().unwrap(), expect);
+        assert_ne!(store.inner().get_block_owned(1).unwrap().unwrap(), expect);
+        // touching another block flushes
+        assert!(store.get_slice_owned(2, 0..8).unwrap().is_some());
+        assert_eq!(store.inner().get_block_owned(1).unwrap().unwrap(), expect);
+        assert!(store.pending.lock().unwrap().is_none());
+        // put block supersedes the buffered slices
+        store.put_slice(3, 0, &[1; 8]).unwrap().unwrap();
+        store.put_slice(3, 8, &[1; 8]).unwrap().unwrap();
+        store.put_block(3, &[2; BLOCK_SIZE]).unwrap();
+        store.flush().unwrap();
+        assert_eq!(
+            store.inner().get_block_owned(3).unwrap().unwrap(),
+            vec![2; BLOCK_SIZE]
+        );
+        // block not existing
+        assert!(store.put_slice(BLOCK_NUM, 0, &[1; 8]).unwrap().is_none());
+        assert!(store.get_slice_owned(BLOCK_NUM, 0..8).unwrap().is_none());
+        let e = store.put_slice(0, BLOCK_SIZE - 4, &[1; 8]).unwrap_err();
+        assert!(matches!(e, SUError::Range(_)));
+        // flushed on drop
+        store.put_slice(0, 0, &[1; 8]).unwrap().unwrap();
+        store.put_slice(0, 8, &[1; 8]).unwrap().unwrap();
+        drop(store);
+        let hdd_store =
+            HDDStorage::connect_to_dev(dev.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap()).unwrap();
+        assert_eq!(
+            hdd_store.get_slice_owned(0, 0..16).unwrap().unwrap(),
+            vec![1; 16]
+        );
+    }
+
+    #[test]
+    fn flush_removed_block() {
+        let dev = tempfile::tempdir().unwrap();
+        let store = CoalescingStorage::new(hdd_storage(dev.path()));
+        store.put_slice(0, 0, &[1; 8]).unwrap().unwrap();
+        store.put_slice(0, 8, &[1; 8]).unwrap().unwrap();
+        store.inner().remove_block(0).unwrap().unwrap();
+        assert!(matches!(store.flush(), Err(SUError::Other(_))));
+        // the slices are discarded, so nothing is left to flush
+        store.flush().unwrap();
+
+        // the error of flushing on drop is logged instead of panicking
+        store.put_slice(1, 0, &[1; 8]).unwrap().unwrap();
+        store.put_slice(1, 8, &[1; 8]).unwrap().unwrap();
+        store.inner().remove_block(1).unwrap().unwrap();
+        drop(store);
+    }
+}
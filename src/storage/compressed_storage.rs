@@ -0,0 +1,308 @@
+use std::num::NonZeroUsize;
+
+use crate::{SUError, SUResult};
+
+use super::utility::{check_block_range, check_slice_range};
+use super::{BlockId, BlockStorage, SliceStorage};
+
+/// Header value marking a block stored without compression
+const RAW_BLOCK: u32 = u32::MAX;
+
+/// A wrapper compressing the full blocks with zstd before storing them into the inner storage.
+///
+/// Each inner block consists of a header with the compressed length, followed by the compressed data.
+/// The block is stored as it is if it does not shrink after compression.
+/// Slice operations are done by read-modify-write of the full block.
+#[derive(Debug)]
+pub struct CompressedStorage<S> {
+    inner: S,
+    block_size: usize,
+    level: i32,
+}
+
+impl<S> CompressedStorage<S>
+where
+    S: BlockStorage,
+{
+    /// Length of the header prepended to each inner block
+    pub const HEADER_LEN: usize = std::mem::size_of::<u32>();
+
+    /// Make a [`CompressedStorage`] on top of `inner`, with the default compression level.
+    ///
+    /// # Parameter
+    /// - `inner`: the storage to store the compressed blocks,
+    ///   whose block size should be `block_size + HEADER_LEN`
+    /// - `block_size`: size of each uncompressed block
+    ///
+    /// # Error
+    /// - [`SUError::InvalidArg`] if the block size of `inner` does not match
+    pub fn new(inner: S, block_size: NonZeroUsize) -> SUResult<Self> {
+        let block_size = block_size.get();
+        if inner.block_size() != block_size + Self::HEADER_LEN {
+            return Err(SUError::invalid_arg(format!(
+                "inner block size {} does not match {block_size} + header {}",
+                inner.block_size(),
+                Self::HEADER_LEN
+            )));
+        }
+        Ok(Self {
+            inner,
+            block_size,
+            level: zstd::DEFAULT_COMPRESSION_LEVEL,
+        })
+    }
+
+    /// Set the zstd compression level.
+    pub fn with_level(mut self, level: i32) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Get the inner storage.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Consume the [`CompressedStorage`], returning the inner storage.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Encode a block into the layout of the inner block.
+    fn encode(&self, block_data: &[u8]) -> SUResult<Vec<u8>> {
+        let mut inner_block = vec![0_u8; self.inner.block_size()];
+        let (header, payload) = inner_block.split_at_mut(Self::HEADER_LEN);
+        let compressed = zstd::bulk::compress(block_data, self.level)?;
+        if compressed.len() < self.block_size {
+            header.copy_from_slice(&u32::try_from(compressed.len()).unwrap().to_le_bytes());
+            payload[..compressed.len()].copy_from_slice(&compressed);
+        } else {
+            header.copy_from_slice(&RAW_BLOCK.to_le_bytes());
+            payload.copy_from_slice(block_data);
+        }
+        Ok(inner_block)
+    }
+
+    /// Decode a block from the layout of the inner block.
+    ///
+    /// # Error
+    /// - [`SUError::Other`] if the inner block is malformed
+    fn decode(&self, inner_block: &[u8], block_data: &mut [u8]) -> SUResult<()> {
+        let (header, payload) = inner_block.split_at(Self::HEADER_LEN);
+        let header = u32::from_le_bytes(header.try_into().unwrap());
+        if header == RAW_BLOCK {
+            block_data.copy_from_slice(payload);
+            return Ok(());
+        }
+        let compressed = payload
+            .get(..usize::try_from(header).unwrap())
+            .ok_or_else(|| SUError::other(format!("invalid compressed length {header}")))?;
+        let len = zstd::bulk::decompress_to_buffer(compressed, block_data)?;
+        if len != self.block_size {
+            return Err(SUError::other(format!(
+                "decompressed length {len} does not match block size {}",
+                self.block_size
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl<S> BlockStorage for CompressedStorage<S>
+where
+    S: BlockStorage,
+{
+    /// Compress and store data to a block.
+    /// A new block will be created if the block does not exist.
+    ///
+    /// # Error
+    /// - [SUError::Range] if `block_data.len()` does not match block size
+    fn put_block(&self, block_id: BlockId, block_data: &[u8]) -> SUResult<()> {
+        check_block_range(
+            file!(),
+            line!(),
+            column!(),
+            block_data.len(),
+            self.block_size,
+        )?;
+        self.inner.put_block(block_id, &self.encode(block_data)?)
+    }
+
+    /// Retrieving and decompressing data from a full block.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success, and the buffer `block_data` filled with the corresponding data
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    ///
+    /// # Error
+    /// - [`SUError::Range`] if `block_data.len()` does not match the block length
+    fn get_block(&self, block_id: BlockId, block_data: &mut [u8]) -> SUResult<Option<()>> {
+        check_block_range(
+            file!(),
+            line!(),
+            column!(),
+            block_data.len(),
+            self.block_size,
+        )?;
+        self.inner
+            .get_block_owned(block_id)?
+            .map(|inner_block| self.decode(&inner_block, block_data))
+            .transpose()
+    }
+
+    fn remove_block(&self, block_id: BlockId) -> SUResult<Option<()>> {
+        self.inner.remove_block(block_id)
+    }
+
+    /// Get size of an uncompressed block
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+}
+
+impl<S> SliceStorage for CompressedStorage<S>
+where
+    S: BlockStorage,
+{
+    /// Storing data from a slice to a specific area of a block,
+    /// by read-modify-write of the full block.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    ///
+    /// # Error
+    /// - [SUError::Range] if the area specified is out of the block range
+    fn put_slice(
+        &self,
+        block_id: BlockId,
+        inner_block_offset: usize,
+        slice_data: &[u8],
+    ) -> SUResult<Option<()>> {
+        let slice_range = inner_block_offset..inner_block_offset + slice_data.len();
+        check_slice_range(
+            file!(),
+            line!(),
+            column!(),
+            slice_range.clone(),
+            self.block_size,
+        )?;
+        self.get_block_owned(block_id)?
+            .map(|mut block| {
+                block[slice_range].copy_from_slice(slice_data);
+                self.put_block(block_id, &block)
+            })
+            .transpose()
+    }
+
+    /// Retrieving slice data from a specific area of a block to a slice buffer,
+    /// by decompressing the full block.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success, and the buffer `slice_data` with be filled with the corresponding data.
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    ///
+    /// # Error
+    /// - [SUError::Range] if the area specified is out of the block range
+    fn get_slice(
+        &self,
+        block_id: BlockId,
+        inner_block_offset: usize,
+        slice_data: &mut [u8],
+    ) -> SUResult<Option<()>> {
+        let slice_range = inner_block_offset..inner_block_offset + slice_data.len();
+        check_slice_range(
+            file!(),
+            line!(),
+            column!(),
+            slice_range.clone(),
+            self.block_size,
+        )?;
+        Ok(self
+            .get_block_owned(block_id)?
+            .map(|block| slice_data.copy_from_slice(&block[slice_range])))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+    use std::num::NonZeroUsize;
+
+    use crate::{
+        storage::{BlockStorage, MemStorage, SliceStorage},
+        SUError,
+    };
+
+    use super::CompressedStorage;
+    const BLOCK_SIZE: usize = 4 << 10;
+    type Storage = CompressedStorage<MemStorage>;
+
+    fn compressed_mem_storage() -> Storage {
+        let mem_store =
+            MemStorage::new(NonZeroUsize::new(BLOCK_SIZE + Storage::HEADER_LEN).unwrap());
+        CompressedStorage::new(mem_store, NonZeroUsize::new(BLOCK_SIZE).unwrap()).unwrap()
+    }
+
+    /// Get the compressed length recorded in the header of the inner block
+    fn stored_header(store: &Storage, block_id: usize) -> u32 {
+        let inner_block = store.inner().get_block_owned(block_id).unwrap().unwrap();
+        u32::from_le_bytes(inner_block[..Storage::HEADER_LEN].try_into().unwrap())
+    }
+
+    #[test]
+    fn put_get_block() {
+        let store = compressed_mem_storage();
+        // compressible
+        let compressible = (0..BLOCK_SIZE).map(|i| (i / 64) as u8).collect::<Vec<_>>();
+        store.put_block(0, &compressible).unwrap();
+        assert!(usize::try_from(stored_header(&store, 0)).unwrap() < BLOCK_SIZE / 4);
+        assert_eq!(store.get_block_owned(0).unwrap().unwrap(), compressible);
+        // incompressible
+        let incompressible = rand::thread_rng()
+            .sample_iter(rand::distributions::Standard)
+            .take(BLOCK_SIZE)
+            .collect::<Vec<u8>>();
+        store.put_block(1, &incompressible).unwrap();
+        assert_eq!(stored_header(&store, 1), super::RAW_BLOCK);
+        assert_eq!(store.get_block_owned(1).unwrap().unwrap(), incompressible);
+        // overwrite
+        store.put_block(0, &incompressible).unwrap();
+        assert_eq!(store.get_block_owned(0).unwrap().unwrap(), incompressible);
+        assert!(store.get_block_owned(2).unwrap().is_none());
+        assert!(store.remove_block(0).unwrap().is_some());
+        assert!(store.get_block_owned(0).unwrap().is_none());
+    }
+
+    #[test]
+    fn put_get_slice() {
+        let store = compressed_mem_storage();
+        let mut block = vec![0_u8; BLOCK_SIZE];
+        store.put_block(0, &block).unwrap();
+        let slice = rand::thread_rng()
+            .sample_iter(rand::distributions::Standard)
+            .take(BLOCK_SIZE / 8)
+            .collect::<Vec<u8>>();
+        store.put_slice(0, 100, &slice).unwrap().unwrap();
+        block[100..100 + slice.len()].copy_from_slice(&slice);
+        assert_eq!(
+            store.get_slice_owned(0, 50..200).unwrap().unwrap(),
+            block[50..200]
+        );
+        assert_eq!(store.get_block_owned(0).unwrap().unwrap(), block);
+        assert!(store.put_slice(1, 0, &slice).unwrap().is_none());
+        let e = store.get_slice_owned(0, 0..BLOCK_SIZE + 1).unwrap_err();
+        assert!(matches!(e, SUError::Range(_)));
+    }
+
+    #[test]
+    fn inner_block_size_mismatch() {
+        let mem_store = MemStorage::new(NonZeroUsize::new(BLOCK_SIZE).unwrap());
+        let e =
+            CompressedStorage::new(mem_store, NonZeroUsize::new(BLOCK_SIZE).unwrap()).unwrap_err();
+        assert!(matches!(e, SUError::InvalidArg(_)));
+    }
+}
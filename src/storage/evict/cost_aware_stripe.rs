@@ -0,0 +1,265 @@
+use std::{
+    cell::{Cell, RefCell},
+    num::NonZeroUsize,
+    ops::Range,
+};
+
+use crate::storage::{BlockId, StripeId};
+
+use super::{
+    most_modified_stripe::{InnerStripeIdx, StripeRangeSet},
+    EvictStrategySlice, RangeSet,
+};
+
+/// Modified ranges of a stripe, ordered by the flush efficiency,
+/// i.e. `modified_bytes / (modified_source_count + p)`,
+/// which is the modified bytes merged per block read on flushing the stripe.
+#[derive(Debug)]
+struct CostAwareRangeSet {
+    ranges: StripeRangeSet,
+    k: usize,
+    p: usize,
+}
+
+impl CostAwareRangeSet {
+    /// Return the modified bytes and the number of blocks to read on flushing the stripe
+    fn cost(&self) -> (usize, usize) {
+        let modified_source_count = self.ranges.range_vec[..self.k]
+            .iter()
+            .filter(|ranges| !ranges.is_empty())
+            .count();
+        (self.ranges.len, modified_source_count + self.p)
+    }
+}
+
+impl PartialEq for CostAwareRangeSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for CostAwareRangeSet {}
+
+impl Ord for CostAwareRangeSet {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // compare the ratios without division
+        let (self_bytes, self_reads) = self.cost();
+        let (other_bytes, other_reads) = other.cost();
+        (self_bytes * other_reads).cmp(&(other_bytes * self_reads))
+    }
+}
+
+impl PartialOrd for CostAwareRangeSet {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+type InnerQueue = RefCell<priority_queue::PriorityQueue<StripeId, CostAwareRangeSet>>;
+
+/// A container with stripe and the ranges of its blocks as entries.
+/// If current size exceeds the maximum size, the most modified block in the stripe
+/// with the largest `modified_bytes / (modified_source_count + p)` will be evicted.
+/// That is, the stripes whose flush merges the most modified bytes per block read,
+/// counting the `p` parity blocks to fetch, are preferred.
+#[derive(Debug)]
+pub struct CostAwareStripeEvict {
+    k: usize,
+    p: usize,
+    max_size: usize,
+    queue: InnerQueue,
+    cur_size: Cell<usize>,
+}
+
+impl CostAwareStripeEvict {
+    /// Make a [`CostAwareStripeEvict`] instance.
+    ///
+    /// # Parameter
+    /// - `k`: number of the source blocks in a stripe
+    /// - `p`: number of the parity blocks in a stripe
+    /// - `max_size`: max slice size this instance can maintain.
+    pub fn new(k: NonZeroUsize, p: NonZeroUsize, max_size: NonZeroUsize) -> Self {
+        CostAwareStripeEvict {
+            k: k.get(),
+            p: p.get(),
+            max_size: max_size.get(),
+            queue: RefCell::new(priority_queue::PriorityQueue::with_capacity(64)),
+            cur_size: Cell::new(0),
+        }
+    }
+
+    fn stripe_m(&self) -> usize {
+        self.k + self.p
+    }
+
+    fn block_id_to_stripe_idx(&self, block_id: BlockId) -> (StripeId, InnerStripeIdx) {
+        (
+            (block_id / self.stripe_m()).into(),
+            block_id % self.stripe_m(),
+        )
+    }
+
+    fn stripe_idx_to_block_to_id(&self, stripe_id: StripeId, idx: InnerStripeIdx) -> BlockId {
+        stripe_id.into_inner() * self.stripe_m() + idx
+    }
+}
+
+impl EvictStrategySlice for CostAwareStripeEvict {
+    fn contains(&self, block_id: BlockId) -> bool {
+        let (stripe_id, idx) = self.block_id_to_stripe_idx(block_id);
+        self.queue
+            .borrow()
+            .get(&stripe_id)
+            .map(|(_, ranges)| !ranges.ranges.get_at(idx).is_empty())
+            .unwrap_or(false)
+    }
+
+    fn len(&self) -> usize {
+        self.cur_size.get()
+    }
+
+    fn capacity(&self) -> usize {
+        self.max_size
+    }
+
+    fn get(&self, block_id: BlockId) -> Option<RangeSet> {
+        let (stripe_id, idx) = self.block_id_to_stripe_idx(block_id);
+        self.queue
+            .borrow()
+            .get(&stripe_id)
+            .map(|(_, ranges)| ranges.ranges.get_at(idx).clone())
+    }
+
+    fn push(&self, block_id: BlockId, range: Range<usize>) -> Option<(BlockId, RangeSet)> {
+        let (stripe_id, idx) = self.block_id_to_stripe_idx(block_id);
+        let mut queue = self.queue.borrow_mut();
+        if queue.get_priority(&stripe_id).is_none() {
+            let ret = queue.push(
+                stripe_id,
+                CostAwareRangeSet {
+                    ranges: StripeRangeSet::with_m(self.stripe_m()),
+                    k: self.k,
+                    p: self.p,
+                },
+            );
+            debug_assert!(ret.is_none());
+        }
+        let mut inc_range_opt = None::<smallvec::SmallVec<[Range<usize>; 1]>>;
+        let ret = queue.change_priority_by(&stripe_id, |stripe_ranges| {
+            let inc_range = stripe_ranges.ranges.insert_at(idx, range);
+            inc_range_opt = Some(inc_range);
+        });
+        assert!(ret);
+        let inc_range = inc_range_opt.unwrap();
+        (!inc_range.is_empty())
+            .then(|| {
+                let inc_size: usize = inc_range.iter().map(std::ops::Range::len).sum();
+                self.cur_size.set(self.cur_size.get() + inc_size);
+                drop(queue);
+                (self.cur_size.get() > self.max_size).then(|| self.pop_first().unwrap())
+            })
+            .flatten()
+    }
+
+    fn pop_first(&self) -> Option<(BlockId, RangeSet)> {
+        let block_id = self.peek_first()?;
+        let range_set = self.pop_with_id(block_id).unwrap();
+        Some((block_id, range_set))
+    }
+
+    fn peek_first(&self) -> Option<BlockId> {
+        self.queue
+            .borrow()
+            .peek()
+            .map(|(&evict_stripe_id, stripe_ranges)| {
+                let max_len_block_idx = stripe_ranges
+                    .ranges
+                    .range_vec
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(_, item)| item.len())
+                    .map(|(idx, _)| idx)
+                    .unwrap();
+                self.stripe_idx_to_block_to_id(evict_stripe_id, max_len_block_idx)
+            })
+    }
+
+    fn pop_with_id(&self, block_id: BlockId) -> Option<RangeSet> {
+        let mut queue = self.queue.borrow_mut();
+        let (stripe_id, block_idx) = self.block_id_to_stripe_idx(block_id);
+        let mut range_opt = None::<RangeSet>;
+        let mut empty_stripe = false;
+        let _ret = queue.change_priority_by(&stripe_id, |stripe_ranges| {
+            let range = stripe_ranges.ranges.take_at(block_idx);
+            self.cur_size.set(self.cur_size.get() - range.len());
+            empty_stripe = stripe_ranges.ranges.len == 0;
+            if !range.is_empty() {
+                range_opt = Some(range);
+            }
+        });
+        if empty_stripe {
+            let _ = queue.remove(&stripe_id).unwrap();
+        }
+        range_opt
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use crate::storage::{
+        evict::{cost_aware_stripe::CostAwareStripeEvict, MostModifiedStripeEvict},
+        EvictStrategySlice,
+    };
+
+    #[test]
+    fn test_cost_aware_stripe() {
+        const MAX_SIZE: usize = 120;
+        const EC_K: usize = 4;
+        const EC_P: usize = 2;
+        const EC_M: usize = EC_K + EC_P;
+        let ca = CostAwareStripeEvict::new(
+            NonZeroUsize::new(EC_K).unwrap(),
+            NonZeroUsize::new(EC_P).unwrap(),
+            NonZeroUsize::new(MAX_SIZE).unwrap(),
+        );
+        let mms = MostModifiedStripeEvict::new(
+            NonZeroUsize::new(EC_M).unwrap(),
+            NonZeroUsize::new(MAX_SIZE).unwrap(),
+        );
+        // stripe 0: 1 source with 50 bytes, 50 / (1 + 2) = 16.7
+        // stripe 1: 4 sources with 60 bytes, 60 / (4 + 2) = 10
+        let pushes = [(0, 0..50), (6, 0..15), (7, 0..15), (8, 0..15), (9, 0..15)];
+        pushes.iter().for_each(|(block_id, range)| {
+            assert!(ca.push(*block_id, range.clone()).is_none());
+            assert!(mms.push(*block_id, range.clone()).is_none());
+        });
+        assert_eq!(ca.len(), 110);
+        assert_eq!(mms.len(), 110);
+        // stripe 2: 1 source with 20 bytes, 20 / (1 + 2) = 6.7, exceeding the max size
+        let evict = ca.push(12, 0..20).unwrap(); // 80: (6..=9: 15 each), (12: [0..20])
+        assert_eq!(evict.0, 0);
+        assert_eq!(evict.1.to_ranges(), vec![0..50]);
+        assert_eq!(ca.len(), 80);
+        // the most modified stripe evicts the stripe 1 instead
+        let evict = mms.push(12, 0..20).unwrap();
+        assert_eq!(evict.0 / EC_M, 1);
+        // stripe 1: 60 / 6 = 10, 45 / 5 = 9, 30 / 4 = 7.5 before stripe 2: 20 / 3 = 6.7
+        (0..3).for_each(|_| {
+            let evict = ca.pop_first().unwrap();
+            assert_eq!(evict.0 / EC_M, 1);
+            assert_eq!(evict.1.to_ranges(), vec![0..15]);
+        });
+        // stripe 2: 20 / 3 = 6.7 before stripe 1: 15 / 3 = 5
+        assert_eq!(ca.get(12).unwrap().to_ranges(), vec![0..20]);
+        let evict = ca.pop_first().unwrap();
+        assert_eq!(evict.0, 12);
+        assert_eq!(ca.len(), 15);
+        let evict = ca.pop_first().unwrap();
+        assert_eq!(evict.0 / EC_M, 1);
+        assert!(!ca.contains(evict.0));
+        assert!(ca.pop_first().is_none());
+        assert!(ca.is_empty());
+    }
+}
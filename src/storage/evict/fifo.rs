@@ -0,0 +1,171 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, VecDeque},
+    num::NonZeroUsize,
+    ops::Range,
+};
+
+use crate::storage::BlockId;
+
+use super::{EvictStrategySlice, RangeSet};
+
+/// A container with block and its ranges as entries.
+/// This eviction strategy record the slice range size of a block, and maintain a maximum size.
+/// If current size exceeds the maximum size, the earliest inserted block will be evicted,
+/// regardless of how much of it was modified.
+///
+/// This can be used as the baseline of the most modified eviction strategies.
+#[derive(Debug)]
+pub struct FifoEvict {
+    /// block ids in insertion order
+    queue: RefCell<VecDeque<BlockId>>,
+    map: RefCell<HashMap<BlockId, RangeSet>>,
+    max_size: usize,
+    cur_size: Cell<usize>,
+}
+
+impl FifoEvict {
+    /// Make a [`FifoEvict`] instance.
+    ///
+    /// # Parameter
+    /// - `max_size`: max slice size this instance can maintain.
+    pub fn with_max_size(max_size: NonZeroUsize) -> Self {
+        Self {
+            queue: Default::default(),
+            map: Default::default(),
+            max_size: max_size.get(),
+            cur_size: Cell::new(0),
+        }
+    }
+}
+
+impl EvictStrategySlice for FifoEvict {
+    /// Return `true` if the evict contains a block, otherwise `false`.
+    fn contains(&self, block_id: BlockId) -> bool {
+        self.map.borrow().contains_key(&block_id)
+    }
+
+    /// Return the current size of the slices stored.
+    fn len(&self) -> usize {
+        self.cur_size.get()
+    }
+
+    /// Return the maximum slice size can store before eviction.
+    fn capacity(&self) -> usize {
+        self.max_size
+    }
+
+    /// Get the slice ranges corresponding to the block.
+    ///
+    /// # Returns
+    /// - [`Some`] with the modified ranges if the block exists
+    /// - [`None`] if the block does not exist
+    fn get(&self, block_id: BlockId) -> Option<RangeSet> {
+        self.map.borrow().get(&block_id).cloned()
+    }
+
+    /// Push a slice range to a block.
+    /// If the block already exists, the corresponding slice range will be merged and updated,
+    /// and its insertion order remains unchanged.
+    /// If the block does not exist, a new entry will be inserted at the back.
+    /// This may cause a eviction, and the evicted entry with [`BlockId`] and corresponding ranges will be returned.
+    ///
+    /// # Parameters
+    /// - `block_id`: the id of the block to update
+    /// - `range`: a new range to push
+    ///
+    /// # Return
+    /// - [`Some`] if a block with its range was evicted.
+    /// - [`None`] if no eviction happens
+    fn push(&self, block_id: BlockId, range: Range<usize>) -> Option<(BlockId, RangeSet)> {
+        let inc_ranges = {
+            let mut map = self.map.borrow_mut();
+            match map.get_mut(&block_id) {
+                Some(range_set) => range_set.insert(range),
+                None => {
+                    let mut range_set = RangeSet::default();
+                    let inc_range = range_set.insert(range);
+                    map.insert(block_id, range_set);
+                    self.queue.borrow_mut().push_back(block_id);
+                    inc_range
+                }
+            }
+        };
+        let inc_size: usize = inc_ranges.iter().map(Range::len).sum();
+        self.cur_size.set(self.cur_size.get() + inc_size);
+        (self.cur_size.get() > self.max_size)
+            .then(|| self.pop_first())
+            .flatten()
+    }
+
+    /// Pop the earliest inserted block with its corresponding ranges.
+    ///
+    /// # Return
+    /// - [`Some`] the earliest inserted block with its corresponding ranges
+    /// - [`None`] if empty
+    fn pop_first(&self) -> Option<(BlockId, RangeSet)> {
+        let block_id = self.queue.borrow_mut().pop_front()?;
+        let ranges = self.map.borrow_mut().remove(&block_id).unwrap();
+        self.cur_size.set(self.cur_size.get() - ranges.len());
+        Some((block_id, ranges))
+    }
+
+    fn peek_first(&self) -> Option<BlockId> {
+        self.queue.borrow().front().copied()
+    }
+
+    /// Pop the block with its corresponding ranges by `block_id`
+    ///
+    /// # Return
+    /// -[`Some`] ranges previously pushed if the block exits
+    /// -[`None`] if the block does not exit
+    fn pop_with_id(&self, block_id: BlockId) -> Option<RangeSet> {
+        let ranges = self.map.borrow_mut().remove(&block_id)?;
+        self.queue.borrow_mut().retain(|id| *id != block_id);
+        self.cur_size.set(self.cur_size.get() - ranges.len());
+        Some(ranges)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use crate::storage::evict::{fifo::FifoEvict, EvictStrategySlice};
+
+    #[test]
+    fn test_evict() {
+        const MAX_SIZE: usize = 40;
+        let fifo = FifoEvict::with_max_size(NonZeroUsize::new(MAX_SIZE).unwrap());
+        assert!(fifo.push(1, 5..20).is_none()); // [1: 5..20]
+        assert!(fifo.push(1, 0..10).is_none()); // [1: 0..20]
+        assert_eq!(fifo.len(), 20);
+        assert!(fifo.push(2, 20..30).is_none()); // [1: 0..20], [2: 20..30]
+        assert!(fifo.push(3, 30..40).is_none()); // [1: 0..20], [2: 20..30], [3: 30..40]
+        let evict = fifo.pop_with_id(3).unwrap(); // [1: 0..20], [2: 20..30]
+        assert_eq!(evict.to_ranges(), vec![30..40]);
+        assert_eq!(fifo.len(), 30);
+        // the earliest block is evicted, though block 2 is the most modified
+        let evict = fifo.push(2, 50..70).unwrap(); // [2: 20..30, 50..70]
+        assert_eq!(evict.0, 1);
+        assert_eq!(evict.1.to_ranges(), vec![0..20]);
+        assert_eq!(fifo.len(), 30);
+        assert!(fifo.push(3, 0..10).is_none()); // [2: 20..30, 50..70], [3: 0..10]
+                                                // updating an existing block does not change its order
+        let evict = fifo.push(3, 10..20).unwrap(); // [3: 0..20]
+        assert_eq!(evict.0, 2);
+        assert_eq!(evict.1.to_ranges(), vec![20..30, 50..70]);
+        assert!(fifo.push(4, 0..10).is_none()); // [3: 0..20], [4: 0..10]
+        assert!(fifo.push(3, 30..40).is_none()); // [3: 0..20, 30..40], [4: 0..10]
+        assert_eq!(fifo.len(), 40);
+        assert!(fifo.contains(3));
+        assert_eq!(fifo.get(4).unwrap().to_ranges(), vec![0..10]);
+        let evict = fifo.pop_first().unwrap(); // [4: 0..10]
+        assert_eq!(evict.0, 3);
+        assert_eq!(evict.1.to_ranges(), vec![0..20, 30..40]);
+        let evict = fifo.pop_first().unwrap(); // empty
+        assert_eq!(evict.0, 4);
+        assert!(fifo.pop_first().is_none());
+        assert!(fifo.is_empty());
+    }
+}
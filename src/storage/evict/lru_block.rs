@@ -0,0 +1,157 @@
+use std::{
+    cell::{Cell, RefCell},
+    num::NonZeroUsize,
+    ops::Range,
+};
+
+use crate::storage::BlockId;
+
+use super::{EvictStrategySlice, RangeSet};
+
+/// A container with block and its ranges as entries.
+/// This eviction strategy record the slice range size of a block, and maintain a maximum size.
+/// If current size exceeds the maximum size, the least recently updated block will be evicted.
+#[derive(Debug)]
+pub struct LruBlockEvict {
+    lru: RefCell<lru::LruCache<BlockId, RangeSet>>,
+    max_size: usize,
+    cur_size: Cell<usize>,
+}
+
+impl LruBlockEvict {
+    /// Make a [`LruBlockEvict`] instance.
+    ///
+    /// # Parameter
+    /// - `max_size`: max slice size this instance can maintain.
+    pub fn with_max_size(max_size: NonZeroUsize) -> Self {
+        Self {
+            lru: RefCell::new(lru::LruCache::unbounded()),
+            max_size: max_size.get(),
+            cur_size: Cell::new(0),
+        }
+    }
+}
+
+impl EvictStrategySlice for LruBlockEvict {
+    /// Return `true` if the evict contains a block, otherwise `false`.
+    fn contains(&self, block_id: BlockId) -> bool {
+        self.lru.borrow().contains(&block_id)
+    }
+
+    /// Return the current size of the slices stored.
+    fn len(&self) -> usize {
+        self.cur_size.get()
+    }
+
+    /// Return the maximum slice size can store before eviction.
+    fn capacity(&self) -> usize {
+        self.max_size
+    }
+
+    /// Get the slice ranges corresponding to the block.
+    /// This does not update the lru list.
+    ///
+    /// # Returns
+    /// - [`Some`] with the modified ranges if the block exists
+    /// - [`None`] if the block does not exist
+    fn get(&self, block_id: BlockId) -> Option<RangeSet> {
+        self.lru.borrow().peek(&block_id).cloned()
+    }
+
+    /// Push a slice range to a block, and mark the block as the most recently used.
+    /// If the block already exists, the corresponding slice range will be merged and updated.
+    /// If the block does not exist, a new entry will be inserted.
+    /// This may cause a eviction, and the evicted entry with [`BlockId`] and corresponding ranges will be returned.
+    ///
+    /// # Parameters
+    /// - `block_id`: the id of the block to update
+    /// - `range`: a new range to push
+    ///
+    /// # Return
+    /// - [`Some`] if a block with its range was evicted.
+    /// - [`None`] if no eviction happens
+    fn push(&self, block_id: BlockId, range: Range<usize>) -> Option<(BlockId, RangeSet)> {
+        let inc_ranges = {
+            let mut lru = self.lru.borrow_mut();
+            match lru.get_mut(&block_id) {
+                Some(range_set) => range_set.insert(range),
+                None => {
+                    let mut range_set = RangeSet::default();
+                    let inc_range = range_set.insert(range);
+                    lru.push(block_id, range_set);
+                    inc_range
+                }
+            }
+        };
+        let inc_size: usize = inc_ranges.iter().map(Range::len).sum();
+        self.cur_size.set(self.cur_size.get() + inc_size);
+        (self.cur_size.get() > self.max_size)
+            .then(|| self.pop_first())
+            .flatten()
+    }
+
+    /// Pop the least recently updated block with its corresponding ranges.
+    ///
+    /// # Return
+    /// - [`Some`] the least recently updated block with its corresponding ranges
+    /// - [`None`] if empty
+    fn pop_first(&self) -> Option<(BlockId, RangeSet)> {
+        let (block_id, ranges) = self.lru.borrow_mut().pop_lru()?;
+        self.cur_size.set(self.cur_size.get() - ranges.len());
+        Some((block_id, ranges))
+    }
+
+    fn peek_first(&self) -> Option<BlockId> {
+        self.lru.borrow().peek_lru().map(|(&block_id, _)| block_id)
+    }
+
+    /// Pop the block with its corresponding ranges by `block_id`
+    ///
+    /// # Return
+    /// -[`Some`] ranges previously pushed if the block exits
+    /// -[`None`] if the block does not exit
+    fn pop_with_id(&self, block_id: BlockId) -> Option<RangeSet> {
+        let ranges = self.lru.borrow_mut().pop(&block_id)?;
+        self.cur_size.set(self.cur_size.get() - ranges.len());
+        Some(ranges)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use crate::storage::evict::{lru_block::LruBlockEvict, EvictStrategySlice};
+
+    #[test]
+    fn test_evict() {
+        const MAX_SIZE: usize = 40;
+        let lru = LruBlockEvict::with_max_size(NonZeroUsize::new(MAX_SIZE).unwrap());
+        assert!(lru.push(1, 5..20).is_none()); // [1: 5..20]
+        assert!(lru.push(1, 0..10).is_none()); // [1: 0..20]
+        assert_eq!(lru.len(), 20);
+        assert!(lru.push(2, 20..30).is_none()); // [1: 0..20], [2: 20..30]
+        assert!(lru.push(3, 30..40).is_none()); // [1: 0..20], [2: 20..30], [3: 30..40]
+        let evict = lru.pop_with_id(3).unwrap(); // [1: 0..20], [2: 20..30]
+        assert_eq!(evict.to_ranges(), vec![30..40]);
+        assert_eq!(lru.len(), 30);
+        // updating block 1 makes block 2 the least recently used
+        assert!(lru.push(1, 20..25).is_none()); // [2: 20..30], [1: 0..25]
+        let evict = lru.push(3, 0..10).unwrap(); // [1: 0..25], [3: 0..10]
+        assert_eq!(evict.0, 2);
+        assert_eq!(evict.1.to_ranges(), vec![20..30]);
+        assert_eq!(lru.len(), 35);
+        // get does not update the lru list
+        assert_eq!(lru.get(1).unwrap().to_ranges(), vec![0..25]);
+        let evict = lru.push(3, 10..20).unwrap(); // [3: 0..20]
+        assert_eq!(evict.0, 1);
+        assert_eq!(evict.1.to_ranges(), vec![0..25]);
+        assert!(lru.contains(3));
+        assert!(!lru.contains(1));
+        let evict = lru.pop_first().unwrap(); // empty
+        assert_eq!(evict.0, 3);
+        assert_eq!(evict.1.to_ranges(), vec![0..20]);
+        assert!(lru.pop_first().is_none());
+        assert!(lru.is_empty());
+    }
+}
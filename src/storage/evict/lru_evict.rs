@@ -50,6 +50,12 @@ where
     fn pop(&self) -> Option<Self::Item> {
         self.lru.borrow_mut().pop_lru().map(|entry| entry.0)
     }
+
+    /// Remove the item equal to `item` from the container.
+    /// Return `true` if the item existed, otherwise `false`.
+    fn remove(&self, item: &Self::Item) -> bool {
+        self.lru.borrow_mut().pop(item).is_some()
+    }
 }
 
 #[cfg(test)]
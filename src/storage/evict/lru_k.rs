@@ -0,0 +1,234 @@
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Reverse,
+    collections::{HashMap, VecDeque},
+    num::NonZeroUsize,
+    ops::Range,
+};
+
+use crate::storage::BlockId;
+
+use super::{EvictStrategySlice, RangeSet};
+
+/// Eviction order of a block, the least one is evicted first.
+/// A block accessed less than `k` times has no k-th access,
+/// and is evicted before the others, as plain lru does among them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Order {
+    /// time of the k-th most recent access
+    kth_access: Option<u64>,
+    /// time of the most recent access
+    last_access: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    ranges: RangeSet,
+    /// times of the most recent `k` accesses, with the most recent one at back
+    history: VecDeque<u64>,
+}
+
+/// A container with block and its ranges as entries.
+/// This eviction strategy record the slice range size of a block, and maintain a maximum size.
+/// If current size exceeds the maximum size, the block whose k-th most recent update is the least recent
+/// will be evicted, so that the blocks updated only once are evicted before the ones updated repeatedly.
+///
+/// The update history of a block is dropped once it leaves the container.
+#[derive(Debug)]
+pub struct LruKEvict {
+    k: usize,
+    entries: RefCell<HashMap<BlockId, Entry>>,
+    queue: RefCell<priority_queue::PriorityQueue<BlockId, Reverse<Order>>>,
+    clock: Cell<u64>,
+    max_size: usize,
+    cur_size: Cell<usize>,
+}
+
+impl LruKEvict {
+    /// Make a [`LruKEvict`] instance.
+    ///
+    /// # Parameter
+    /// - `k`: number of the most recent updates of a block to track,
+    ///   which is equivalent to [`super::LruBlockEvict`] if `1`
+    /// - `max_size`: max slice size this instance can maintain.
+    pub fn new(k: NonZeroUsize, max_size: NonZeroUsize) -> Self {
+        Self {
+            k: k.get(),
+            entries: Default::default(),
+            queue: Default::default(),
+            clock: Cell::new(0),
+            max_size: max_size.get(),
+            cur_size: Cell::new(0),
+        }
+    }
+
+    fn remove(&self, block_id: BlockId) -> Option<RangeSet> {
+        let entry = self.entries.borrow_mut().remove(&block_id)?;
+        self.queue.borrow_mut().remove(&block_id);
+        self.cur_size.set(self.cur_size.get() - entry.ranges.len());
+        Some(entry.ranges)
+    }
+}
+
+impl EvictStrategySlice for LruKEvict {
+    /// Return `true` if the evict contains a block, otherwise `false`.
+    fn contains(&self, block_id: BlockId) -> bool {
+        self.entries.borrow().contains_key(&block_id)
+    }
+
+    /// Return the current size of the slices stored.
+    fn len(&self) -> usize {
+        self.cur_size.get()
+    }
+
+    /// Return the maximum slice size can store before eviction.
+    fn capacity(&self) -> usize {
+        self.max_size
+    }
+
+    /// Get the slice ranges corresponding to the block.
+    /// This is not regarded as an access to the block.
+    ///
+    /// # Returns
+    /// - [`Some`] with the modified ranges if the block exists
+    /// - [`None`] if the block does not exist
+    fn get(&self, block_id: BlockId) -> Option<RangeSet> {
+        self.entries
+            .borrow()
+            .get(&block_id)
+            .map(|entry| entry.ranges.clone())
+    }
+
+    /// Push a slice range to a block, and record an access to the block.
+    /// If the block already exists, the corresponding slice range will be merged and updated.
+    /// If the block does not exist, a new entry will be inserted.
+    /// This may cause a eviction, and the evicted entry with [`BlockId`] and corresponding ranges will be returned.
+    ///
+    /// # Parameters
+    /// - `block_id`: the id of the block to update
+    /// - `range`: a new range to push
+    ///
+    /// # Return
+    /// - [`Some`] if a block with its range was evicted.
+    /// - [`None`] if no eviction happens
+    fn push(&self, block_id: BlockId, range: Range<usize>) -> Option<(BlockId, RangeSet)> {
+        let now = self.clock.get();
+        self.clock.set(now + 1);
+        let (inc_ranges, order) = {
+            let mut entries = self.entries.borrow_mut();
+            let entry = entries.entry(block_id).or_insert_with(|| Entry {
+                ranges: RangeSet::default(),
+                history: VecDeque::with_capacity(self.k),
+            });
+            if entry.history.len() == self.k {
+                entry.history.pop_front();
+            }
+            entry.history.push_back(now);
+            let order = Order {
+                kth_access: (entry.history.len() == self.k).then(|| entry.history[0]),
+                last_access: now,
+            };
+            (entry.ranges.insert(range), order)
+        };
+        self.queue.borrow_mut().push(block_id, Reverse(order));
+        let inc_size: usize = inc_ranges.iter().map(Range::len).sum();
+        self.cur_size.set(self.cur_size.get() + inc_size);
+        (self.cur_size.get() > self.max_size)
+            .then(|| self.pop_first())
+            .flatten()
+    }
+
+    /// Pop the block whose k-th most recent update is the least recent, with its corresponding ranges.
+    ///
+    /// # Return
+    /// - [`Some`] the block to evict with its corresponding ranges
+    /// - [`None`] if empty
+    fn pop_first(&self) -> Option<(BlockId, RangeSet)> {
+        let block_id = self.peek_first()?;
+        self.remove(block_id).map(|ranges| (block_id, ranges))
+    }
+
+    fn peek_first(&self) -> Option<BlockId> {
+        self.queue.borrow().peek().map(|(&block_id, _)| block_id)
+    }
+
+    /// Pop the block with its corresponding ranges by `block_id`
+    ///
+    /// # Return
+    /// -[`Some`] ranges previously pushed if the block exits
+    /// -[`None`] if the block does not exit
+    fn pop_with_id(&self, block_id: BlockId) -> Option<RangeSet> {
+        self.remove(block_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use crate::storage::evict::{EvictStrategySlice, LruBlockEvict};
+
+    use super::LruKEvict;
+
+    const MAX_SIZE: usize = 40;
+
+    #[test]
+    fn test_evict() {
+        let lru_k = LruKEvict::new(
+            NonZeroUsize::new(2).unwrap(),
+            NonZeroUsize::new(MAX_SIZE).unwrap(),
+        );
+        assert!(lru_k.push(1, 0..10).is_none()); // 1: [0]
+        assert!(lru_k.push(1, 10..20).is_none()); // 1: [0, 1]
+        assert!(lru_k.push(2, 0..10).is_none()); // 1: [0, 1], 2: [2]
+        assert_eq!(lru_k.len(), 30);
+        // block 2 accessed once goes first, although block 1 is accessed earlier
+        assert_eq!(lru_k.peek_first(), Some(2));
+        assert!(lru_k.push(3, 0..5).is_none()); // 1: [0, 1], 2: [2], 3: [3]
+        assert_eq!(lru_k.peek_first(), Some(2));
+        assert!(lru_k.push(2, 10..15).is_none()); // 1: [0, 1], 2: [2, 4], 3: [3]
+        assert_eq!(lru_k.peek_first(), Some(3));
+        let evict = lru_k.push(4, 0..5).unwrap(); // 1: [0, 1], 2: [2, 4], 4: [5]
+        assert_eq!(evict.0, 3);
+        assert_eq!(evict.1.to_ranges(), vec![0..5]);
+        assert_eq!(lru_k.len(), 40);
+        // get is not an access
+        assert_eq!(lru_k.get(4).unwrap().to_ranges(), vec![0..5]);
+        assert_eq!(lru_k.pop_with_id(4).unwrap().to_ranges(), vec![0..5]);
+        // among the blocks accessed k times, the one with the least recent k-th access goes first
+        let evict = lru_k.pop_first().unwrap();
+        assert_eq!(evict.0, 1);
+        assert_eq!(evict.1.to_ranges(), vec![0..20]);
+        assert!(lru_k.contains(2));
+        assert!(!lru_k.contains(1));
+        let evict = lru_k.pop_first().unwrap();
+        assert_eq!(evict.0, 2);
+        assert!(lru_k.pop_first().is_none());
+        assert!(lru_k.is_empty());
+    }
+
+    #[test]
+    fn one_hit_evicted_first() {
+        let lru = LruBlockEvict::with_max_size(NonZeroUsize::new(MAX_SIZE).unwrap());
+        let lru_k = LruKEvict::new(
+            NonZeroUsize::new(2).unwrap(),
+            NonZeroUsize::new(MAX_SIZE).unwrap(),
+        );
+        let pushes = [(1, 0..5), (1, 5..10), (1, 10..20), (2, 0..20), (3, 0..10)];
+        // the hot block 1 is updated before the one-hit block 2
+        let evict = pushes
+            .iter()
+            .cloned()
+            .filter_map(|(block_id, range)| lru.push(block_id, range))
+            .collect::<Vec<_>>();
+        assert_eq!(evict.len(), 1);
+        assert_eq!(evict[0].0, 1);
+        let evict = pushes
+            .iter()
+            .cloned()
+            .filter_map(|(block_id, range)| lru_k.push(block_id, range))
+            .collect::<Vec<_>>();
+        assert_eq!(evict.len(), 1);
+        assert_eq!(evict[0].0, 2);
+    }
+}
@@ -2,18 +2,29 @@ use std::ops::Range;
 
 use super::BlockId;
 
+mod cost_aware_stripe;
+mod fifo;
+mod lru_block;
 mod lru_evict;
+mod lru_k;
 mod most_modified_block;
 mod most_modified_stripe;
 mod non_evict;
 mod range_set;
 
+pub use cost_aware_stripe::CostAwareStripeEvict;
+pub use fifo::FifoEvict;
+pub use lru_block::LruBlockEvict;
 pub use lru_evict::LruEvict;
+pub use lru_k::LruKEvict;
 pub use most_modified_block::MostModifiedBlockEvict;
 pub use most_modified_stripe::MostModifiedStripeEvict;
 pub use non_evict::NonEvict;
 pub use range_set::RangeSet;
 
+/// The blocks with their ranges evicted by a single push, see [`EvictStrategySlice::push_batch()`].
+pub type Evictions = smallvec::SmallVec<[(BlockId, RangeSet); 1]>;
+
 pub trait EvictStrategy {
     type Item;
     /// Return `true` if the evict contains an element equal to `item`, otherwise false
@@ -24,6 +35,9 @@ pub trait EvictStrategy {
     /// Pop an item from the container.
     /// If the container is empty, it returns `None`.
     fn pop(&self) -> Option<Self::Item>;
+    /// Remove the element equal to `item` from the container.
+    /// Return `true` if the element existed, otherwise `false`.
+    fn remove(&self, item: &Self::Item) -> bool;
 }
 
 pub trait EvictStrategySlice: std::fmt::Debug {
@@ -56,6 +70,15 @@ pub trait EvictStrategySlice: std::fmt::Debug {
     /// - [`Some`] if a block with its range was evicted.
     /// - [`None`] if no eviction happens
     fn push(&self, block_id: BlockId, range: Range<usize>) -> Option<(BlockId, RangeSet)>;
+    /// Push a slice range to a block the same as [`EvictStrategySlice::push()`],
+    /// but evict as many blocks as the strategy decides in one call,
+    /// e.g., down to a low watermark, to avoid evicting one block at a time near the capacity.
+    ///
+    /// # Return
+    /// The evicted blocks with their ranges in the order of eviction, empty if no eviction happens
+    fn push_batch(&self, block_id: BlockId, range: Range<usize>) -> Evictions {
+        self.push(block_id, range).into_iter().collect()
+    }
     /// Pop the first block with its corresponding ranges according to the evict strategy.
     ///
     /// # Return
@@ -63,6 +86,13 @@ pub trait EvictStrategySlice: std::fmt::Debug {
     /// - [`None`] if empty
     fn pop_first(&self) -> Option<(BlockId, RangeSet)>;
 
+    /// Get the block to be popped first according to the evict strategy, without removing it.
+    ///
+    /// # Return
+    /// - [`Some`] the block id that [`EvictStrategySlice::pop_first()`] would pop next
+    /// - [`None`] if empty
+    fn peek_first(&self) -> Option<BlockId>;
+
     /// Pop the block with its corresponding ranges by `block_id`
     ///
     /// # Return
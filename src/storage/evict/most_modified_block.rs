@@ -6,7 +6,7 @@ use std::{
 
 use crate::storage::BlockId;
 
-use super::{EvictStrategySlice, RangeSet};
+use super::{EvictStrategySlice, Evictions, RangeSet};
 
 /// Wrapper for [`RangeSet`], whose order is compared by len()
 #[derive(Debug, Default, Eq, Clone)]
@@ -35,12 +35,16 @@ type InnerQueue = RefCell<priority_queue::PriorityQueue<BlockId, RangeSetCmpByLe
 /// A container with block and its ranges as entries.
 /// This eviction strategy record the slice range size of a block, and maintain a maximum size.
 /// If current size exceeds the maximum size, a block with the max slice size will be evicted.
+/// With a low watermark, [`EvictStrategySlice::push_batch()`] keeps evicting
+/// until the current size drops to the low watermark.
 ///
 /// This can be used as the most modified eviction strategy.
 #[derive(Debug)]
 pub struct MostModifiedBlockEvict {
     queue: InnerQueue,
     max_size: usize,
+    /// size to evict down to once `max_size` is exceeded, `max_size` by default
+    low_watermark: usize,
     cur_size: Cell<usize>,
 }
 
@@ -53,10 +57,56 @@ impl MostModifiedBlockEvict {
         let max_size = max_size.get();
         Self {
             max_size,
+            low_watermark: max_size,
             queue: Default::default(),
             cur_size: Cell::new(0),
         }
     }
+
+    /// Make a [`MostModifiedBlockEvict`] instance evicting in batches.
+    ///
+    /// # Parameter
+    /// - `low_watermark`: slice size to evict down to by [`EvictStrategySlice::push_batch()`]
+    /// - `high_watermark`: max slice size this instance can maintain.
+    ///
+    /// # Panics
+    /// - If `low_watermark` is greater than `high_watermark`
+    pub fn with_watermarks(low_watermark: usize, high_watermark: NonZeroUsize) -> Self {
+        assert!(
+            low_watermark <= high_watermark.get(),
+            "low watermark {low_watermark} is greater than high watermark {high_watermark}"
+        );
+        Self {
+            low_watermark,
+            ..Self::with_max_size(high_watermark)
+        }
+    }
+
+    /// Merge a slice range into a block, inserting the block if it does not exist.
+    ///
+    /// # Return
+    /// The size newly covered by the range
+    fn insert(&self, block_id: BlockId, range: Range<usize>) -> usize {
+        let mut queue = self.queue.borrow_mut();
+        let inc_ranges = if queue.get_priority(&block_id).is_some() {
+            let mut inc_range_opt = None::<smallvec::SmallVec<[Range<usize>; 1]>>;
+            let ret = queue.change_priority_by(&block_id, |range_set| {
+                let inc_range = range_set.0.insert(range);
+                inc_range_opt = Some(inc_range)
+            });
+            assert!(ret);
+            inc_range_opt.unwrap()
+        } else {
+            let mut range_set = RangeSet::default();
+            let inc_range = range_set.insert(range.clone());
+            let ret = queue.push(block_id, RangeSetCmpByLen(range_set));
+            debug_assert!(ret.is_none());
+            inc_range
+        };
+        let inc_size: usize = inc_ranges.iter().map(std::ops::Range::len).sum();
+        self.cur_size.set(self.cur_size.get() + inc_size);
+        inc_size
+    }
 }
 
 impl EvictStrategySlice for MostModifiedBlockEvict {
@@ -104,35 +154,21 @@ impl EvictStrategySlice for MostModifiedBlockEvict {
         block_id: crate::storage::BlockId,
         range: std::ops::Range<usize>,
     ) -> Option<(crate::storage::BlockId, super::RangeSet)> {
-        let mut queue = self.queue.borrow_mut();
-        let inc_ranges = if queue.get_priority(&block_id).is_some() {
-            let mut inc_range_opt = None::<smallvec::SmallVec<[Range<usize>; 1]>>;
-            let ret = queue.change_priority_by(&block_id, |range_set| {
-                let inc_range = range_set.0.insert(range);
-                inc_range_opt = Some(inc_range)
-            });
-            assert!(ret);
-            inc_range_opt.unwrap()
-        } else {
-            let mut range_set = RangeSet::default();
-            let inc_range = range_set.insert(range.clone());
-            let ret = queue.push(block_id, RangeSetCmpByLen(range_set));
-            debug_assert!(ret.is_none());
-            inc_range
-        };
-        (!inc_ranges.is_empty())
-            .then(|| {
-                let inc_size: usize = inc_ranges.iter().map(std::ops::Range::len).sum();
-                self.cur_size.set(self.cur_size.get() + inc_size);
-                (self.cur_size.get() > self.max_size).then(|| {
-                    // evict
-                    let (evict_block_id, evict_ranges) = queue.pop().unwrap();
-                    self.cur_size
-                        .set(self.cur_size.get() - evict_ranges.0.len());
-                    (evict_block_id, evict_ranges.0)
-                })
-            })
-            .flatten()
+        (self.insert(block_id, range) > 0 && self.cur_size.get() > self.max_size)
+            .then(|| self.pop_first().unwrap())
+    }
+
+    /// Push a slice range to a block the same as [`EvictStrategySlice::push()`],
+    /// but once the current size exceeds the maximum size,
+    /// the most modified blocks are evicted until it drops to the low watermark.
+    fn push_batch(&self, block_id: BlockId, range: Range<usize>) -> Evictions {
+        let mut evictions = Evictions::new();
+        if self.insert(block_id, range) > 0 && self.cur_size.get() > self.max_size {
+            while self.cur_size.get() > self.low_watermark {
+                evictions.push(self.pop_first().unwrap());
+            }
+        }
+        evictions
     }
 
     /// Pop the first block with its corresponding ranges according to the evict strategy.
@@ -147,6 +183,10 @@ impl EvictStrategySlice for MostModifiedBlockEvict {
         })
     }
 
+    fn peek_first(&self) -> Option<BlockId> {
+        self.queue.borrow().peek().map(|(&block_id, _)| block_id)
+    }
+
     /// Pop the block with its corresponding ranges by `block_id`
     ///
     /// # Return
@@ -167,7 +207,9 @@ impl EvictStrategySlice for MostModifiedBlockEvict {
 mod test {
     use std::num::NonZeroUsize;
 
-    use crate::storage::evict::{most_modified_block::MostModifiedBlockEvict, EvictStrategySlice};
+    use crate::storage::evict::{
+        most_modified_block::MostModifiedBlockEvict, EvictStrategySlice, RangeSet,
+    };
 
     #[test]
     fn test_evict() {
@@ -193,4 +235,51 @@ mod test {
         assert_eq!(evict.1.to_ranges(), vec![0..20, 30..50]);
         assert!(mm.pop_first().is_none());
     }
+
+    #[test]
+    fn batch_evict() {
+        const LOW: usize = 50;
+        const HIGH: usize = 100;
+        let mm = MostModifiedBlockEvict::with_watermarks(LOW, NonZeroUsize::new(HIGH).unwrap());
+        (0..10).for_each(|i| assert!(mm.push_batch(i, 0..10).is_empty())); // 100
+        assert!(mm.push_batch(0, 0..10).is_empty()); // nothing new
+        assert_eq!(mm.len(), HIGH);
+        // exceeds the high watermark, drains to the low watermark
+        let evictions = mm.push_batch(0, 10..20); // 110
+        assert_eq!(evictions.len(), 5);
+        // the most modified block first
+        assert_eq!(evictions[0].0, 0);
+        assert_eq!(evictions[0].1.to_ranges(), vec![0..20]);
+        let evicted = evictions.iter().map(|(_, r)| r.len()).sum::<usize>();
+        assert_eq!(evicted, 110 - LOW);
+        assert_eq!(mm.len(), LOW);
+        assert!(evictions.iter().all(|(id, _)| !mm.contains(*id)));
+        // below the high watermark again
+        assert!(mm.push_batch(20, 0..50).is_empty()); // 100
+        assert_eq!(mm.len(), HIGH);
+
+        // the evicted bytes are exactly the ones pushed but not buffered anymore
+        use rand::Rng;
+        let mm = MostModifiedBlockEvict::with_watermarks(LOW, NonZeroUsize::new(HIGH).unwrap());
+        let mut expect = std::collections::HashMap::<usize, RangeSet>::new();
+        (0..1000).for_each(|_| {
+            let block_id = rand::thread_rng().gen_range(0..20);
+            let start = rand::thread_rng().gen_range(0..30);
+            let len = rand::thread_rng().gen_range(1..=20);
+            expect
+                .entry(block_id)
+                .or_default()
+                .insert(start..start + len);
+            let evictions = mm.push_batch(block_id, start..start + len);
+            if evictions.is_empty() {
+                assert!(mm.len() <= HIGH);
+            } else {
+                assert!(mm.len() <= LOW);
+            }
+            evictions.into_iter().for_each(|(id, ranges)| {
+                assert_eq!(expect.remove(&id).unwrap().to_ranges(), ranges.to_ranges());
+            });
+            assert_eq!(mm.len(), expect.values().map(RangeSet::len).sum::<usize>());
+        });
+    }
 }
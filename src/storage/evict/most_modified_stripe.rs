@@ -6,29 +6,29 @@ use std::{
 
 use crate::storage::{BlockId, StripeId};
 
-use super::{EvictStrategySlice, RangeSet};
+use super::{EvictStrategySlice, Evictions, RangeSet};
 
-type InnerStripeIdx = usize;
+pub(super) type InnerStripeIdx = usize;
 
 #[derive(Debug, Eq)]
-struct StripeRangeSet {
-    len: usize,
-    range_vec: Vec<RangeSet>,
+pub(super) struct StripeRangeSet {
+    pub(super) len: usize,
+    pub(super) range_vec: Vec<RangeSet>,
 }
 
 impl StripeRangeSet {
-    fn with_m(m: usize) -> Self {
+    pub(super) fn with_m(m: usize) -> Self {
         Self {
             len: 0,
             range_vec: vec![RangeSet::default(); m],
         }
     }
 
-    fn get_at(&self, idx: InnerStripeIdx) -> &RangeSet {
+    pub(super) fn get_at(&self, idx: InnerStripeIdx) -> &RangeSet {
         &self.range_vec[idx]
     }
 
-    fn insert_at(
+    pub(super) fn insert_at(
         &mut self,
         idx: InnerStripeIdx,
         range: Range<usize>,
@@ -38,7 +38,7 @@ impl StripeRangeSet {
         inc
     }
 
-    fn take_at(&mut self, idx: InnerStripeIdx) -> RangeSet {
+    pub(super) fn take_at(&mut self, idx: InnerStripeIdx) -> RangeSet {
         let take = std::mem::take(&mut self.range_vec[idx]);
         self.len -= take.len();
         take
@@ -65,10 +65,16 @@ impl PartialOrd for StripeRangeSet {
 
 type InnerQueue = RefCell<priority_queue::PriorityQueue<StripeId, StripeRangeSet>>;
 
+/// This eviction strategy evicts the most modified block in the most modified stripe,
+/// once the current size exceeds the maximum size.
+/// With a low watermark, [`EvictStrategySlice::push_batch()`] keeps evicting
+/// until the current size drops to the low watermark.
 #[derive(Debug)]
 pub struct MostModifiedStripeEvict {
     stripe_m: usize,
     max_size: usize,
+    /// size to evict down to once `max_size` is exceeded, `max_size` by default
+    low_watermark: usize,
     queue: InnerQueue,
     cur_size: Cell<usize>,
 }
@@ -78,10 +84,33 @@ impl MostModifiedStripeEvict {
         MostModifiedStripeEvict {
             stripe_m: stripe_m.get(),
             max_size: max_size.get(),
+            low_watermark: max_size.get(),
             queue: RefCell::new(priority_queue::PriorityQueue::with_capacity(64)),
             cur_size: Cell::new(0),
         }
     }
+
+    /// Make a [`MostModifiedStripeEvict`] instance evicting in batches,
+    /// down to `low_watermark` by [`EvictStrategySlice::push_batch()`]
+    /// once the size exceeds `high_watermark`.
+    ///
+    /// # Panics
+    /// - If `low_watermark` is greater than `high_watermark`
+    pub fn with_watermarks(
+        stripe_m: NonZeroUsize,
+        low_watermark: usize,
+        high_watermark: NonZeroUsize,
+    ) -> Self {
+        assert!(
+            low_watermark <= high_watermark.get(),
+            "low watermark {low_watermark} is greater than high watermark {high_watermark}"
+        );
+        Self {
+            low_watermark,
+            ..Self::new(stripe_m, high_watermark)
+        }
+    }
+
     fn block_id_to_stripe_idx(&self, block_id: BlockId) -> (StripeId, InnerStripeIdx) {
         ((block_id / self.stripe_m).into(), block_id % self.stripe_m)
     }
@@ -89,6 +118,29 @@ impl MostModifiedStripeEvict {
     fn stripe_idx_to_block_to_id(&self, stripe_id: StripeId, idx: InnerStripeIdx) -> BlockId {
         stripe_id.0 * self.stripe_m + idx
     }
+
+    /// Merge a slice range into a block, inserting its stripe if it does not exist.
+    ///
+    /// # Return
+    /// The size newly covered by the range
+    fn insert(&self, block_id: BlockId, range: Range<usize>) -> usize {
+        let (stripe_id, idx) = self.block_id_to_stripe_idx(block_id);
+        let mut queue = self.queue.borrow_mut();
+        if queue.get_priority(&stripe_id).is_none() {
+            let ret = queue.push(stripe_id, StripeRangeSet::with_m(self.stripe_m));
+            debug_assert!(ret.is_none());
+        }
+        let mut inc_range_opt = None::<smallvec::SmallVec<[Range<usize>; 1]>>;
+        let ret = queue.change_priority_by(&stripe_id, |stripe_ranges| {
+            let inc_range = stripe_ranges.insert_at(idx, range);
+            inc_range_opt = Some(inc_range);
+        });
+        assert!(ret);
+        let inc_range = inc_range_opt.unwrap();
+        let inc_size: usize = inc_range.iter().map(std::ops::Range::len).sum();
+        self.cur_size.set(self.cur_size.get() + inc_size);
+        inc_size
+    }
 }
 
 impl EvictStrategySlice for MostModifiedStripeEvict {
@@ -122,49 +174,41 @@ impl EvictStrategySlice for MostModifiedStripeEvict {
         block_id: crate::storage::BlockId,
         range: std::ops::Range<usize>,
     ) -> Option<(crate::storage::BlockId, super::RangeSet)> {
-        let (stripe_id, idx) = self.block_id_to_stripe_idx(block_id);
-        let mut queue = self.queue.borrow_mut();
-        if queue.get_priority(&stripe_id).is_none() {
-            let ret = queue.push(stripe_id, StripeRangeSet::with_m(self.stripe_m));
-            debug_assert!(ret.is_none());
+        (self.insert(block_id, range) > 0 && self.cur_size.get() > self.max_size)
+            .then(|| self.pop_first().unwrap())
+    }
+
+    fn push_batch(&self, block_id: BlockId, range: Range<usize>) -> Evictions {
+        let mut evictions = Evictions::new();
+        if self.insert(block_id, range) > 0 && self.cur_size.get() > self.max_size {
+            while self.cur_size.get() > self.low_watermark {
+                evictions.push(self.pop_first().unwrap());
+            }
         }
-        let mut inc_range_opt = None::<smallvec::SmallVec<[Range<usize>; 1]>>;
-        let ret = queue.change_priority_by(&stripe_id, |stripe_ranges| {
-            let inc_range = stripe_ranges.insert_at(idx, range);
-            inc_range_opt = Some(inc_range);
-        });
-        assert!(ret);
-        let inc_range = inc_range_opt.unwrap();
-        (!inc_range.is_empty())
-            .then(|| {
-                let inc_size: usize = inc_range.iter().map(std::ops::Range::len).sum();
-                self.cur_size.set(self.cur_size.get() + inc_size);
-                drop(queue);
-                (self.cur_size.get() > self.max_size).then(|| self.pop_first().unwrap())
-            })
-            .flatten()
+        evictions
     }
 
     fn pop_first(&self) -> Option<(crate::storage::BlockId, super::RangeSet)> {
         // evict
-        let queue = self.queue.borrow();
-        queue
+        let block_id = self.peek_first()?;
+        let range_set = self.pop_with_id(block_id).unwrap();
+        Some((block_id, range_set))
+    }
+
+    fn peek_first(&self) -> Option<crate::storage::BlockId> {
+        // the block with the most modified bytes in the most modified stripe
+        self.queue
+            .borrow()
             .peek()
-            .map(|(&evict_stripe_id, _)| evict_stripe_id)
-            .map(|evict_stripe_id| {
-                let max_len_block_idx = queue
-                    .get_priority(&evict_stripe_id)
-                    .unwrap()
+            .map(|(&evict_stripe_id, stripe_ranges)| {
+                let max_len_block_idx = stripe_ranges
                     .range_vec
                     .iter()
                     .enumerate()
                     .max_by_key(|(_, item)| item.len())
                     .map(|(idx, _)| idx)
                     .unwrap();
-                let block_id = self.stripe_idx_to_block_to_id(evict_stripe_id, max_len_block_idx);
-                drop(queue);
-                let range_set = self.pop_with_id(block_id).unwrap();
-                (block_id, range_set)
+                self.stripe_idx_to_block_to_id(evict_stripe_id, max_len_block_idx)
             })
     }
 
@@ -253,4 +297,65 @@ mod test {
         assert!(evict.is_none());
         assert!(mms.is_empty());
     }
+
+    #[test]
+    fn peek_first() {
+        use rand::Rng;
+        const MAX_SIZE: usize = 1 << 20;
+        const EC_M: usize = 6;
+        let mms = MostModifiedStripeEvict::new(
+            NonZeroUsize::new(EC_M).unwrap(),
+            NonZeroUsize::new(MAX_SIZE).unwrap(),
+        );
+        assert!(mms.peek_first().is_none());
+        (0..200).for_each(|_| {
+            let block_id = rand::thread_rng().gen_range(0..10 * EC_M);
+            let start = rand::thread_rng().gen_range(0..1000);
+            let len = rand::thread_rng().gen_range(1..100);
+            assert!(mms.push(block_id, start..start + len).is_none());
+        });
+        while !mms.is_empty() {
+            let len = mms.len();
+            let peeked = mms.peek_first().unwrap();
+            // peeking does not change anything
+            assert_eq!(mms.peek_first(), Some(peeked));
+            assert_eq!(mms.len(), len);
+            assert!(mms.contains(peeked));
+            let (popped, _) = mms.pop_first().unwrap();
+            assert_eq!(popped, peeked);
+        }
+        assert!(mms.peek_first().is_none());
+    }
+
+    #[test]
+    fn batch_evict() {
+        const LOW: usize = 40;
+        const HIGH: usize = 100;
+        const EC_M: usize = 4;
+        let mms = MostModifiedStripeEvict::with_watermarks(
+            NonZeroUsize::new(EC_M).unwrap(),
+            LOW,
+            NonZeroUsize::new(HIGH).unwrap(),
+        );
+        // 5 stripes with 2 blocks of 10 bytes each
+        (0..5).for_each(|stripe| {
+            assert!(mms.push_batch(stripe * EC_M, 0..10).is_empty());
+            assert!(mms.push_batch(stripe * EC_M + 1, 0..10).is_empty());
+        });
+        assert_eq!(mms.len(), HIGH);
+        // exceeds the high watermark, drains to the low watermark
+        // the stripe just updated first
+        let evictions = mms.push_batch(10, 0..30); // 130
+        assert_eq!(evictions[0].0, 10);
+        assert_eq!(evictions[0].1.to_ranges(), vec![0..30]);
+        let evicted = evictions.iter().map(|(_, r)| r.len()).sum::<usize>();
+        assert_eq!(evicted, 130 - LOW);
+        assert_eq!(mms.len(), LOW);
+        assert!(evictions.iter().all(|(id, _)| !mms.contains(*id)));
+        // the plain push evicts a single block
+        assert!(mms.push(30, 0..60).is_none()); // 100
+        let evict = mms.push(31, 0..10).unwrap(); // 110
+        assert_eq!(evict.0, 30);
+        assert_eq!(mms.len(), 50);
+    }
 }
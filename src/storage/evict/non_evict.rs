@@ -60,6 +60,10 @@ impl EvictStrategySlice for NonEvict {
         })
     }
 
+    fn peek_first(&self) -> Option<crate::storage::BlockId> {
+        self.map.borrow().keys().next().copied()
+    }
+
     fn pop_with_id(&self, block_id: crate::storage::BlockId) -> Option<RangeSet> {
         self.map
             .borrow_mut()
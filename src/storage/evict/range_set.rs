@@ -62,6 +62,39 @@ impl RangeSet {
             .map(|bound| bound[0]..bound[1])
             .collect()
     }
+
+    /// Get the ranges in `self` but not in `other`.
+    pub fn difference(&self, other: &RangeSet) -> RangeSet {
+        Self::from_inner(self.ranges.difference::<[usize; 2]>(&other.ranges))
+    }
+
+    /// Get the ranges in both `self` and `other`.
+    pub fn intersection(&self, other: &RangeSet) -> RangeSet {
+        Self::from_inner(self.ranges.intersection::<[usize; 2]>(&other.ranges))
+    }
+
+    /// Return `true` if `range` is fully covered by the existing ranges, otherwise `false`.
+    /// An empty `range` is always covered.
+    pub fn contains_range(&self, range: &Range<usize>) -> bool {
+        range.is_empty()
+            || Ranges::from(range.to_owned())
+                .difference::<[usize; 2]>(&self.ranges)
+                .is_empty()
+    }
+
+    /// Make a [`RangeSet`] from the inner ranges, with its length recounted.
+    ///
+    /// # Panics
+    /// - If any range is not bounded.
+    fn from_inner(ranges: Ranges) -> Self {
+        let boundaries = ranges.boundaries();
+        assert!(boundaries.len() % 2 == 0, "unbounded range");
+        let len = boundaries
+            .chunks_exact(2)
+            .map(|bound| bound[1] - bound[0])
+            .sum();
+        Self { ranges, len }
+    }
 }
 
 impl std::ops::Deref for RangeSet {
@@ -126,4 +159,68 @@ mod test {
         let bounds = ranges.to_ranges();
         assert_eq!(bounds, vec![0..1, 2..25]);
     }
+
+    fn single(range: Range<usize>) -> RangeSet {
+        let mut range_set = RangeSet::default();
+        range_set.insert(range);
+        range_set
+    }
+
+    #[test]
+    fn test_difference() {
+        let ranges = RangeSet::from([0..10, 20..30].as_slice());
+        // overlapping
+        let diff = ranges.difference(&single(5..25));
+        assert_eq!(diff.to_ranges(), vec![0..5, 25..30]);
+        assert_eq!(diff.len(), 10);
+        // disjoint
+        let diff = ranges.difference(&RangeSet::from([10..20, 40..50].as_slice()));
+        assert_eq!(diff, ranges);
+        assert_eq!(diff.len(), 20);
+        // adjacent
+        let diff = ranges.difference(&RangeSet::from([0..5, 5..10].as_slice()));
+        assert_eq!(diff.to_ranges(), vec![20..30]);
+        assert_eq!(diff.len(), 10);
+        // covered
+        let diff = ranges.difference(&single(0..30));
+        assert!(diff.is_empty());
+        assert_eq!(diff.len(), 0);
+    }
+
+    #[test]
+    fn test_intersection() {
+        let ranges = RangeSet::from([0..10, 20..30].as_slice());
+        // overlapping
+        let inter = ranges.intersection(&single(5..25));
+        assert_eq!(inter.to_ranges(), vec![5..10, 20..25]);
+        assert_eq!(inter.len(), 10);
+        // disjoint
+        let inter = ranges.intersection(&RangeSet::from([10..20, 40..50].as_slice()));
+        assert!(inter.is_empty());
+        assert_eq!(inter.len(), 0);
+        // adjacent
+        let inter = ranges.intersection(&RangeSet::from([10..15, 15..20].as_slice()));
+        assert!(inter.is_empty());
+        let inter = ranges.intersection(&single(9..21));
+        assert_eq!(inter.to_ranges(), vec![9..10, 20..21]);
+        assert_eq!(inter.len(), 2);
+    }
+
+    #[test]
+    fn test_contains_range() {
+        let ranges = RangeSet::from([0..10, 10..20, 30..40].as_slice());
+        // adjacent ranges are merged
+        assert!(ranges.contains_range(&(5..15)));
+        assert!(ranges.contains_range(&(0..20)));
+        assert!(ranges.contains_range(&(30..40)));
+        // overlapping
+        assert!(!ranges.contains_range(&(15..35)));
+        assert!(!ranges.contains_range(&(35..45)));
+        // disjoint
+        assert!(!ranges.contains_range(&(20..30)));
+        assert!(!ranges.contains_range(&(50..60)));
+        // empty
+        assert!(ranges.contains_range(&(25..25)));
+        assert!(RangeSet::default().contains_range(&(0..0)));
+    }
 }
@@ -1,22 +1,125 @@
 use std::fs::File;
 use std::num::NonZeroUsize;
-use std::os::unix::fs::FileExt;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::{FileExt, OpenOptionsExt};
 use std::path::PathBuf;
 
 use crate::SUError;
 use crate::SUResult;
 
+use super::instrument::{Layer, Op};
 use super::utility::check_slice_range;
-use super::utility::{block_id_to_path, check_block_range};
+use super::utility::{check_block_range, OpLatency, Sharding};
 use super::{BlockId, BlockStorage, SliceStorage};
 
+/// Maximum number of buffers passed to a single `pwritev`/`preadv` call,
+/// i.e. `UIO_MAXIOV` on linux
+const IOV_MAX: usize = 1024;
+
+/// Vectored positional io, i.e. `pwritev` or `preadv`
+type VectoredIoFn =
+    unsafe extern "C" fn(libc::c_int, *const libc::iovec, libc::c_int, libc::off_t) -> isize;
+
+/// Perform the vectored io `op` on `f` over all the `iovecs`, which are contiguous from `offset`.
+/// Partial transfers and interruptions are retried until all the buffers are consumed.
+///
+/// # Error
+/// - [`std::io::ErrorKind::UnexpectedEof`] if the io transfers nothing before all the buffers are consumed
+fn vectored_io_all_at(
+    f: &File,
+    op: VectoredIoFn,
+    iovecs: &mut [libc::iovec],
+    mut offset: u64,
+) -> std::io::Result<()> {
+    // index of the first buffer not fully transferred
+    let mut cur = 0;
+    let mut transferred = 0;
+    loop {
+        // skip the buffers fully transferred, and advance the partially transferred one
+        while let Some(iovec) = iovecs.get_mut(cur) {
+            if transferred < iovec.iov_len {
+                // SAFETY: `transferred` is within the buffer
+                iovec.iov_base = unsafe { iovec.iov_base.add(transferred) };
+                iovec.iov_len -= transferred;
+                break;
+            }
+            transferred -= iovec.iov_len;
+            cur += 1;
+        }
+        if cur == iovecs.len() {
+            break;
+        }
+        let iov_num = (iovecs.len() - cur).min(IOV_MAX);
+        // SAFETY: every iovec points to a live buffer borrowed by the caller,
+        // which is writable when `op` is `preadv`
+        let ret = unsafe {
+            op(
+                f.as_raw_fd(),
+                iovecs[cur..].as_ptr(),
+                libc::c_int::try_from(iov_num).unwrap(),
+                libc::off_t::try_from(offset).unwrap(),
+            )
+        };
+        transferred = match ret {
+            -1 => {
+                let e = std::io::Error::last_os_error();
+                if e.kind() == std::io::ErrorKind::Interrupted {
+                    0
+                } else {
+                    return Err(e);
+                }
+            }
+            0 => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "vectored io transferred no data",
+                ))
+            }
+            n => usize::try_from(n).unwrap(),
+        };
+        offset += u64::try_from(transferred).unwrap();
+    }
+    Ok(())
+}
+
+/// Group the slices into runs, in which each slice starts at the end of the previous one.
+/// The order of the slices is preserved.
+fn contiguous_runs<T>(
+    slices: impl IntoIterator<Item = (usize, T)>,
+    len: impl Fn(&T) -> usize,
+) -> Vec<(usize, Vec<T>)> {
+    let mut runs: Vec<(usize, usize, Vec<T>)> = vec![];
+    slices.into_iter().for_each(|(offset, slice)| {
+        let slice_len = len(&slice);
+        match runs.last_mut() {
+            Some((_, end, run)) if *end == offset => {
+                *end += slice_len;
+                run.push(slice);
+            }
+            _ => runs.push((offset, offset + slice_len, vec![slice])),
+        }
+    });
+    runs.into_iter()
+        .map(|(start, _, run)| (start, run))
+        .collect()
+}
+
 #[derive(Debug)]
 pub struct HDDStorage {
     dev: std::path::PathBuf,
     block_size: usize,
+    op_latency: OpLatency,
+    direct_io: bool,
+    checksum: bool,
+    sharding: Sharding,
 }
 
 impl HDDStorage {
+    /// Alignment of the buffers, offsets and lengths required by the direct io
+    pub const DIRECT_IO_ALIGN: usize = 512;
+    /// Length of the checksum footer appended to each block file
+    pub const CHECKSUM_LEN: usize = std::mem::size_of::<u32>();
+
     /// Connect the [`HDDStorage`] to a device(supposed to be a HDD device) to store the block.
     ///
     /// # Parameter
@@ -40,9 +143,143 @@ impl HDDStorage {
         Ok(Self {
             dev: dev_path,
             block_size,
+            op_latency: OpLatency::default(),
+            direct_io: false,
+            checksum: false,
+            sharding: Sharding::default(),
         })
     }
 
+    /// Connect the [`HDDStorage`] to a device, with the integrity of the blocks checked.
+    /// A CRC32 checksum of the block data is stored as a footer of each block file,
+    /// so the block file is [`HDDStorage::CHECKSUM_LEN`] bytes longer than `block_size`.
+    /// The checksum is verified on every read,
+    /// and a slice update is a read-modify-write of the full block.
+    ///
+    /// # Parameter
+    /// - `dev_path`: path to the HDD device
+    /// - `block_size`: logical size of each block to be created
+    ///
+    /// # Error
+    /// [`SUError::Io(std::io::ErrorKind::NotFound)`] if `dev_path` not existing
+    pub fn connect_to_dev_checked(
+        dev_path: impl Into<PathBuf>,
+        block_size: NonZeroUsize,
+    ) -> SUResult<Self> {
+        let mut hdd_storage = Self::connect_to_dev(dev_path, block_size)?;
+        hdd_storage.checksum = true;
+        Ok(hdd_storage)
+    }
+
+    /// Connect the [`HDDStorage`] to a device, bypassing the page cache.
+    /// The block files are opened with `O_DIRECT`,
+    /// so that the latency of the real device is exposed.
+    ///
+    /// # Note
+    /// The direct io requires the addresses of the buffers, the inner block offsets and the lengths
+    /// of all the blocks and slices to be aligned to [`HDDStorage::DIRECT_IO_ALIGN`].
+    /// The file system of the device should also support `O_DIRECT`.
+    ///
+    /// # Parameter
+    /// - `dev_path`: path to the HDD device
+    /// - `block_size`: size of each block to be created, should be aligned
+    ///
+    /// # Error
+    /// - [`SUError::Io(std::io::ErrorKind::NotFound)`] if `dev_path` not existing
+    /// - [`SUError::InvalidArg`] if `block_size` is not aligned
+    #[cfg(target_os = "linux")]
+    pub fn connect_to_dev_direct(
+        dev_path: impl Into<PathBuf>,
+        block_size: NonZeroUsize,
+    ) -> SUResult<Self> {
+        if block_size.get() % Self::DIRECT_IO_ALIGN != 0 {
+            return Err(SUError::invalid_arg(format!(
+                "block size {block_size} is not aligned to {}",
+                Self::DIRECT_IO_ALIGN
+            )));
+        }
+        let mut hdd_storage = Self::connect_to_dev(dev_path, block_size)?;
+        hdd_storage.direct_io = true;
+        Ok(hdd_storage)
+    }
+
+    /// Inject a latency before each block or slice operation, to model a slower device.
+    pub fn with_op_latency(mut self, op_latency: Option<std::time::Duration>) -> Self {
+        self.op_latency = OpLatency::new(op_latency);
+        self
+    }
+
+    /// Shard the block files under the device root with `sharding`,
+    /// which should be the same as the one the existing blocks are stored with.
+    /// [`Sharding::default`] is used if not set.
+    pub fn with_sharding(mut self, sharding: Sharding) -> Self {
+        self.sharding = sharding;
+        self
+    }
+
+    /// Read the full block from a checked block file, and verify its checksum.
+    ///
+    /// # Error
+    /// - [`SUError::Checksum`] if the checksum does not match the block data
+    fn read_checked_block(&self, f: &File, block_id: BlockId) -> SUResult<Vec<u8>> {
+        let mut buf = vec![0_u8; self.block_size + Self::CHECKSUM_LEN];
+        f.read_exact_at(&mut buf, 0)?;
+        let (block_data, footer) = buf.split_at(self.block_size);
+        let expect = u32::from_le_bytes(footer.try_into().unwrap());
+        let actual = crc32fast::hash(block_data);
+        if expect != actual {
+            return Err(SUError::Checksum(format!(
+                "block {block_id} is corrupted, checksum expected: {expect:#010x}, found: {actual:#010x}"
+            )));
+        }
+        buf.truncate(self.block_size);
+        Ok(buf)
+    }
+
+    /// Write the full block with its checksum footer to a checked block file.
+    fn write_checked_block(&self, f: &File, block_data: &[u8]) -> SUResult<()> {
+        f.write_all_at(block_data, 0)?;
+        f.write_all_at(
+            &crc32fast::hash(block_data).to_le_bytes(),
+            self.block_size.try_into().unwrap(),
+        )?;
+        Ok(())
+    }
+
+    /// Get the custom flags to open the block files.
+    fn open_flags(&self) -> libc::c_int {
+        #[cfg(target_os = "linux")]
+        if self.direct_io {
+            return libc::O_DIRECT;
+        }
+        0
+    }
+
+    /// Check the buffer and the inner block offset are aligned for the direct io.
+    /// Always pass if the direct io is not enabled.
+    ///
+    /// # Error
+    /// - [`SUError::InvalidArg`] if the address or the length of `buf`, or `inner_block_offset` is not aligned
+    fn check_direct_io_align(&self, buf: &[u8], inner_block_offset: usize) -> SUResult<()> {
+        if !self.direct_io {
+            return Ok(());
+        }
+        let align = Self::DIRECT_IO_ALIGN;
+        if buf.as_ptr() as usize % align != 0 || buf.len() % align != 0 {
+            return Err(SUError::invalid_arg(format!(
+                "buffer at {:p} with length {} is not aligned to {align} for direct io",
+                buf.as_ptr(),
+                buf.len()
+            )));
+        }
+        if inner_block_offset % align != 0 {
+            return Err(SUError::invalid_arg(format!(
+                "offset {inner_block_offset} is not aligned to {align} for direct io"
+            )));
+        }
+        Ok(())
+    }
+
     /// Open a block file.
     ///
     /// # Return
@@ -53,7 +290,8 @@ impl HDDStorage {
         match File::options()
             .write(true)
             .read(true)
-            .open(block_id_to_path(self.dev.to_owned(), block_id))
+            .custom_flags(self.open_flags())
+            .open(self.sharding.block_path(self.dev.to_owned(), block_id))
         {
             Ok(f) => Ok(Some(f)),
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
@@ -66,6 +304,55 @@ impl HDDStorage {
         &self.dev
     }
 
+    /// Iterate over the ids of all the blocks stored in the device.
+    /// Any file or directory not created as a block file is skipped.
+    ///
+    /// # Return
+    /// - [`Ok`] with the iterator over block ids, in no specific order
+    /// - [`Err`] on any error occurring
+    ///
+    /// # Error
+    /// - [`SUError::Io`] if the device root cannot be read
+    pub fn iter_block_ids(&self) -> SUResult<Box<dyn Iterator<Item = BlockId>>> {
+        // fail early if the device root is not readable
+        self.dev.read_dir()?;
+        let dev = self.dev.to_owned();
+        let sharding = self.sharding;
+        Ok(Box::new(
+            walkdir::WalkDir::new(self.dev.as_path())
+                .min_depth(sharding.depth() + 1)
+                .max_depth(sharding.depth() + 1)
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter(|entry| entry.file_type().is_file())
+                .filter_map(move |entry| {
+                    let path = entry.path();
+                    let block_id = sharding.try_block_id(path)?;
+                    // the path must be exactly the one made by `Sharding::block_path`
+                    (sharding.block_path(dev.as_path(), block_id) == path).then_some(block_id)
+                }),
+        ))
+    }
+
+    /// Check whether a block is complete, i.e., its block file exists and is of the full size.
+    ///
+    /// # Return
+    /// - [`Ok(true)`] if the block file exists and its length matches the block size
+    /// - [`Ok(false)`] if the block file does not exist or is truncated
+    /// - [`Err`] on any error occurring
+    pub fn is_block_complete(&self, block_id: BlockId) -> SUResult<bool> {
+        let file_len = if self.checksum {
+            self.block_size + Self::CHECKSUM_LEN
+        } else {
+            self.block_size
+        };
+        match std::fs::metadata(self.sharding.block_path(self.dev.to_owned(), block_id)) {
+            Ok(meta) => Ok(meta.is_file() && meta.len() == u64::try_from(file_len).unwrap()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(SUError::Io(e)),
+        }
+    }
+
     /// Create a new block file, guaranteed to be new and with block size
     ///
     /// # Return
@@ -75,17 +362,23 @@ impl HDDStorage {
     /// # Error
     /// - It is an error if the block file already exists
     fn create_block(&self, block_id: BlockId) -> SUResult<File> {
-        let file_path = block_id_to_path(self.dev.to_owned(), block_id);
+        let file_path = self.sharding.block_path(self.dev.to_owned(), block_id);
         let parent_dir = file_path.parent().unwrap();
         std::fs::create_dir_all(parent_dir)?;
         match File::options()
             .write(true)
             .read(true)
             .create_new(true)
+            .custom_flags(self.open_flags())
             .open(file_path)
         {
             Ok(f) => {
-                f.set_len(self.block_size.try_into().unwrap())?;
+                let file_len = if self.checksum {
+                    self.block_size + Self::CHECKSUM_LEN
+                } else {
+                    self.block_size
+                };
+                f.set_len(file_len.try_into().unwrap())?;
                 Ok(f)
             }
             Err(e) => Err(SUError::Io(e)),
@@ -107,6 +400,7 @@ impl BlockStorage for HDDStorage {
     ///
     /// # Error
     /// - [SUError::Range] if `block_data.len()` does not match block size
+    /// - [SUError::InvalidArg] if the direct io is enabled and the buffer or offset is not aligned
     fn put_block(&self, block_id: super::BlockId, block_data: &[u8]) -> crate::SUResult<()> {
         check_block_range(
             file!(),
@@ -115,6 +409,9 @@ impl BlockStorage for HDDStorage {
             block_data.len(),
             self.block_size,
         )?;
+        self.check_direct_io_align(block_data, 0)?;
+        let _op = Op::write(Layer::Hdd, None, block_data.len());
+        self.op_latency.inject();
         let f = match self.open_block(block_id)? {
             Some(f) => f,
             None => {
@@ -122,6 +419,9 @@ impl BlockStorage for HDDStorage {
                 self.create_block(block_id)?
             }
         };
+        if self.checksum {
+            return self.write_checked_block(&f, block_data);
+        }
         f.write_all_at(block_data, 0)?;
         Ok(())
     }
@@ -139,6 +439,8 @@ impl BlockStorage for HDDStorage {
     ///
     /// # Error
     /// - [`SUError::Range`] if `block_data.len()` does not match the block length
+    /// - [SUError::InvalidArg] if the direct io is enabled and the buffer or offset is not aligned
+    /// - [SUError::Checksum] if the integrity is checked and the block is corrupted
     fn get_block(
         &self,
         block_id: super::BlockId,
@@ -151,12 +453,39 @@ impl BlockStorage for HDDStorage {
             block_data.len(),
             self.block_size,
         )?;
+        self.check_direct_io_align(block_data, 0)?;
+        let _op = Op::read(Layer::Hdd, None, block_data.len());
+        self.op_latency.inject();
+        if self.checksum {
+            return self
+                .open_block(block_id)?
+                .map(|f| {
+                    block_data.copy_from_slice(&self.read_checked_block(&f, block_id)?);
+                    Ok(())
+                })
+                .transpose();
+        }
         self.open_block(block_id)?
             .map(|f| f.read_exact_at(block_data, 0))
             .transpose()
             .map_err(SUError::Io)
     }
 
+    /// Remove a block by deleting its block file.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    fn remove_block(&self, block_id: BlockId) -> SUResult<Option<()>> {
+        self.op_latency.inject();
+        match std::fs::remove_file(self.sharding.block_path(self.dev.to_owned(), block_id)) {
+            Ok(()) => Ok(Some(())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(SUError::Io(e)),
+        }
+    }
+
     /// Get size of a block
     fn block_size(&self) -> usize {
         self.block_size
@@ -179,6 +508,8 @@ impl SliceStorage for HDDStorage {
     ///
     /// # Error
     /// - [SUError::Range] if the area specified is out of the block range
+    /// - [SUError::InvalidArg] if the direct io is enabled and the buffer or offset is not aligned
+    /// - [SUError::Checksum] if the integrity is checked and the block is corrupted
     fn put_slice(
         &self,
         block_id: BlockId,
@@ -194,6 +525,19 @@ impl SliceStorage for HDDStorage {
             slice_range.clone(),
             self.block_size(),
         )?;
+        self.check_direct_io_align(slice_data, inner_block_offset)?;
+        let _op = Op::write(Layer::Hdd, Some(1), slice_data.len());
+        self.op_latency.inject();
+        if self.checksum {
+            return self
+                .open_block(block_id)?
+                .map(|f| {
+                    let mut block = self.read_checked_block(&f, block_id)?;
+                    block[slice_range].copy_from_slice(slice_data);
+                    self.write_checked_block(&f, &block)
+                })
+                .transpose();
+        }
         self.open_block(block_id)?
             .map(|f| f.write_all_at(slice_data, slice_range.start.try_into().unwrap()))
             .transpose()
@@ -210,6 +554,8 @@ impl SliceStorage for HDDStorage {
     ///
     /// # Error
     /// - [SUError::Range] if the area specified is out of the block range
+    /// - [SUError::InvalidArg] if the direct io is enabled and the buffer or offset is not aligned
+    /// - [SUError::Checksum] if the integrity is checked and the block is corrupted
     fn get_slice(
         &self,
         block_id: BlockId,
@@ -225,11 +571,168 @@ impl SliceStorage for HDDStorage {
             slice_range.clone(),
             self.block_size(),
         )?;
+        self.check_direct_io_align(slice_data, inner_block_offset)?;
+        let _op = Op::read(Layer::Hdd, Some(1), slice_data.len());
+        self.op_latency.inject();
+        if self.checksum {
+            return self
+                .open_block(block_id)?
+                .map(|f| {
+                    let block = self.read_checked_block(&f, block_id)?;
+                    slice_data.copy_from_slice(&block[slice_range]);
+                    Ok(())
+                })
+                .transpose();
+        }
         self.open_block(block_id)?
             .map(|f| f.read_exact_at(slice_data, slice_range.start.try_into().unwrap()))
             .transpose()
             .map_err(SUError::from)
     }
+
+    /// Storing data from multiple slices to the specific areas of a block.
+    /// Each run of adjacent slices is written with a single `pwritev` call.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    ///
+    /// # Error
+    /// - [SUError::Range] if any area specified is out of the block range,
+    ///   and no slice will be stored
+    /// - [SUError::InvalidArg] if the direct io is enabled and the buffer or offset is not aligned
+    /// - [SUError::Checksum] if the integrity is checked and the block is corrupted
+    fn put_slices(&self, block_id: BlockId, slices: &[(usize, &[u8])]) -> SUResult<Option<()>> {
+        slices
+            .iter()
+            .try_for_each(|(inner_block_offset, slice_data)| {
+                check_slice_range(
+                    file!(),
+                    line!(),
+                    column!(),
+                    *inner_block_offset..inner_block_offset + slice_data.len(),
+                    self.block_size(),
+                )?;
+                self.check_direct_io_align(slice_data, *inner_block_offset)
+            })?;
+        let _op = Op::write(
+            Layer::Hdd,
+            Some(slices.len()),
+            slices.iter().map(|(_, slice_data)| slice_data.len()).sum(),
+        );
+        self.op_latency.inject();
+        if self.checksum {
+            return self
+                .open_block(block_id)?
+                .map(|f| {
+                    let mut block = self.read_checked_block(&f, block_id)?;
+                    slices.iter().for_each(|(inner_block_offset, slice_data)| {
+                        block[*inner_block_offset..*inner_block_offset + slice_data.len()]
+                            .copy_from_slice(slice_data)
+                    });
+                    self.write_checked_block(&f, &block)
+                })
+                .transpose();
+        }
+        self.open_block(block_id)?
+            .map(|f| {
+                contiguous_runs(slices.iter().copied(), |slice_data| slice_data.len())
+                    .into_iter()
+                    .try_for_each(|(start, run)| {
+                        let mut iovecs = run
+                            .into_iter()
+                            .map(|slice_data| libc::iovec {
+                                iov_base: slice_data.as_ptr() as *mut libc::c_void,
+                                iov_len: slice_data.len(),
+                            })
+                            .collect::<Vec<_>>();
+                        vectored_io_all_at(
+                            &f,
+                            libc::pwritev,
+                            &mut iovecs,
+                            start.try_into().unwrap(),
+                        )
+                    })
+            })
+            .transpose()
+            .map_err(SUError::from)
+    }
+
+    /// Retrieving slice data from the specific areas of a block to multiple slice buffers.
+    /// Each run of adjacent slices is read with a single `preadv` call.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success, and all the slice buffers filled with the corresponding data.
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    ///
+    /// # Error
+    /// - [SUError::Range] if any area specified is out of the block range
+    /// - [SUError::InvalidArg] if the direct io is enabled and the buffer or offset is not aligned
+    /// - [SUError::Checksum] if the integrity is checked and the block is corrupted
+    fn get_slices(
+        &self,
+        block_id: BlockId,
+        slices: &mut [(usize, &mut [u8])],
+    ) -> SUResult<Option<()>> {
+        slices
+            .iter()
+            .try_for_each(|(inner_block_offset, slice_data)| {
+                check_slice_range(
+                    file!(),
+                    line!(),
+                    column!(),
+                    *inner_block_offset..inner_block_offset + slice_data.len(),
+                    self.block_size(),
+                )?;
+                self.check_direct_io_align(slice_data, *inner_block_offset)
+            })?;
+        let _op = Op::read(
+            Layer::Hdd,
+            Some(slices.len()),
+            slices.iter().map(|(_, slice_data)| slice_data.len()).sum(),
+        );
+        self.op_latency.inject();
+        if self.checksum {
+            return self
+                .open_block(block_id)?
+                .map(|f| {
+                    let block = self.read_checked_block(&f, block_id)?;
+                    slices
+                        .iter_mut()
+                        .for_each(|(inner_block_offset, slice_data)| {
+                            slice_data.copy_from_slice(
+                                &block[*inner_block_offset..*inner_block_offset + slice_data.len()],
+                            )
+                        });
+                    Ok(())
+                })
+                .transpose();
+        }
+        self.open_block(block_id)?
+            .map(|f| {
+                contiguous_runs(
+                    slices
+                        .iter_mut()
+                        .map(|(inner_block_offset, slice_data)| (*inner_block_offset, slice_data)),
+                    |slice_data| slice_data.len(),
+                )
+                .into_iter()
+                .try_for_each(|(start, run)| {
+                    let mut iovecs = run
+                        .into_iter()
+                        .map(|slice_data| libc::iovec {
+                            iov_base: slice_data.as_mut_ptr() as *mut libc::c_void,
+                            iov_len: slice_data.len(),
+                        })
+                        .collect::<Vec<_>>();
+                    vectored_io_all_at(&f, libc::preadv, &mut iovecs, start.try_into().unwrap())
+                })
+            })
+            .transpose()
+            .map_err(SUError::from)
+    }
 }
 
 #[cfg(test)]
@@ -468,4 +971,303 @@ mod test {
         let e = hdd_store.put_slice(BLOCK_NUM - 1, 0, &data[0..BLOCK_SIZE + 1]);
         assert!(matches!(e, Err(SUError::Range(_))));
     }
+
+    #[test]
+    fn iter_block_ids() {
+        let tempfile = tempfile::TempDir::new().unwrap();
+        let hdd_store = HDDStorage::connect_to_dev(
+            tempfile.path().to_owned(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(hdd_store.iter_block_ids().unwrap().count(), 0);
+        let block_ids = [0, 1, 7, 0xFF, 0x100, 0x1234, 0xFFFF, 0x12345]
+            .into_iter()
+            .collect::<std::collections::BTreeSet<_>>();
+        block_ids
+            .iter()
+            .for_each(|id| hdd_store.put_block(*id, &random_block_data()).unwrap());
+        // non-block files
+        std::fs::write(tempfile.path().join("00").join("not-a-block"), b"").unwrap();
+        std::fs::write(tempfile.path().join("00").join("0g"), b"").unwrap();
+        std::fs::write(tempfile.path().join("top-level"), b"").unwrap();
+        std::fs::create_dir_all(tempfile.path().join("ab").join("CD")).unwrap();
+        let iterated = hdd_store
+            .iter_block_ids()
+            .unwrap()
+            .collect::<std::collections::BTreeSet<_>>();
+        assert_eq!(iterated, block_ids);
+    }
+
+    #[test]
+    fn iter_block_ids_sharded() {
+        use crate::storage::Sharding;
+
+        let tempfile = tempfile::TempDir::new().unwrap();
+        let sharding = Sharding::new(2, 256);
+        let hdd_store = HDDStorage::connect_to_dev(
+            tempfile.path().to_owned(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        )
+        .unwrap()
+        .with_sharding(sharding);
+        let block_ids = [0, 1, 0xFF, 0x1234, 0x12345, 0x1234567]
+            .into_iter()
+            .collect::<std::collections::BTreeSet<_>>();
+        block_ids
+            .iter()
+            .for_each(|id| hdd_store.put_block(*id, &random_block_data()).unwrap());
+        assert!(tempfile.path().join("01").join("23").join("45").is_file());
+        // a block file of the default sharding is not a block of this one
+        std::fs::write(tempfile.path().join("00").join("0A"), b"").unwrap();
+        let iterated = hdd_store
+            .iter_block_ids()
+            .unwrap()
+            .collect::<std::collections::BTreeSet<_>>();
+        assert_eq!(iterated, block_ids);
+        block_ids.iter().for_each(|id| {
+            assert!(hdd_store.is_block_complete(*id).unwrap());
+            hdd_store.remove_block(*id).unwrap().unwrap();
+        });
+        assert_eq!(hdd_store.iter_block_ids().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn remove_block() {
+        let tempfile = tempfile::TempDir::new().unwrap();
+        let hdd_store = HDDStorage::connect_to_dev(
+            tempfile.path().to_owned(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        )
+        .unwrap();
+        hdd_store.put_block(0, &random_block_data()).unwrap();
+        hdd_store.put_block(1, &random_block_data()).unwrap();
+        assert!(hdd_store.remove_block(0).unwrap().is_some());
+        assert!(hdd_store.get_block_owned(0).unwrap().is_none());
+        assert!(hdd_store.get_slice_owned(0, 0..1).unwrap().is_none());
+        assert!(hdd_store.get_block_owned(1).unwrap().is_some());
+        // remove again
+        assert!(hdd_store.remove_block(0).unwrap().is_none());
+    }
+
+    #[test]
+    fn is_block_complete() {
+        let tempfile = tempfile::TempDir::new().unwrap();
+        let hdd_store = HDDStorage::connect_to_dev(
+            tempfile.path().to_owned(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        )
+        .unwrap();
+        assert!(!hdd_store.is_block_complete(0).unwrap());
+        hdd_store.put_block(0, &random_block_data()).unwrap();
+        hdd_store.put_block(1, &random_block_data()).unwrap();
+        assert!(hdd_store.is_block_complete(0).unwrap());
+        // truncated block file
+        std::fs::File::options()
+            .write(true)
+            .open(crate::storage::utility::block_id_to_path(
+                tempfile.path(),
+                1,
+            ))
+            .unwrap()
+            .set_len(BLOCK_SIZE as u64 / 2)
+            .unwrap();
+        assert!(!hdd_store.is_block_complete(1).unwrap());
+    }
+
+    #[test]
+    fn put_get_slices() {
+        let tempfile = tempfile::TempDir::new().unwrap();
+        let hdd_store = HDDStorage::connect_to_dev(
+            tempfile.path().to_owned(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        )
+        .unwrap();
+        let mut block = random_block_data();
+        hdd_store.put_block(0, &block).unwrap();
+        // adjacent, scattered and empty slices
+        let ranges = [
+            0..16,
+            16..100,
+            100..100,
+            512..1024,
+            1024..1025,
+            2048..BLOCK_SIZE,
+        ];
+        let slices = ranges
+            .iter()
+            .map(|range| {
+                rand::thread_rng()
+                    .sample_iter(rand::distributions::Standard)
+                    .take(range.len())
+                    .collect::<Vec<u8>>()
+            })
+            .collect::<Vec<_>>();
+        let put = ranges
+            .iter()
+            .zip(slices.iter())
+            .map(|(range, slice_data)| (range.start, slice_data.as_slice()))
+            .collect::<Vec<_>>();
+        hdd_store.put_slices(0, &put).unwrap().unwrap();
+        ranges
+            .iter()
+            .zip(slices.iter())
+            .for_each(|(range, slice_data)| block[range.clone()].copy_from_slice(slice_data));
+        assert_eq!(hdd_store.get_block_owned(0).unwrap().unwrap(), block);
+        // get
+        let mut get_buf = ranges
+            .iter()
+            .map(|range| vec![0_u8; range.len()])
+            .collect::<Vec<_>>();
+        let mut get = ranges
+            .iter()
+            .zip(get_buf.iter_mut())
+            .map(|(range, slice_data)| (range.start, slice_data.as_mut_slice()))
+            .collect::<Vec<_>>();
+        hdd_store.get_slices(0, &mut get).unwrap().unwrap();
+        assert_eq!(get_buf, slices);
+        // 404
+        assert!(hdd_store.put_slices(1, &put).unwrap().is_none());
+        // out of range, nothing stored
+        let e = hdd_store.put_slices(0, &[(0, &[0_u8; 1]), (BLOCK_SIZE, &[0_u8; 1])]);
+        assert!(matches!(e, Err(SUError::Range(_))));
+        assert_eq!(hdd_store.get_block_owned(0).unwrap().unwrap(), block);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn direct_io() {
+        const ALIGN: usize = HDDStorage::DIRECT_IO_ALIGN;
+        /// Make a buffer with random data, whose address is aligned
+        fn aligned_random_buf(len: usize) -> (Vec<u8>, std::ops::Range<usize>) {
+            let mut buf = vec![0_u8; len + ALIGN];
+            let start = buf.as_ptr().align_offset(ALIGN);
+            rand::thread_rng().fill(&mut buf[start..start + len]);
+            (buf, start..start + len)
+        }
+        let tempfile = tempfile::TempDir::new().unwrap();
+        let e = HDDStorage::connect_to_dev_direct(
+            tempfile.path(),
+            NonZeroUsize::new(BLOCK_SIZE + 1).unwrap(),
+        )
+        .unwrap_err();
+        assert!(matches!(e, SUError::InvalidArg(_)));
+        let hdd_store = HDDStorage::connect_to_dev_direct(
+            tempfile.path(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        )
+        .unwrap();
+        // block round trip
+        let (block, block_range) = aligned_random_buf(BLOCK_SIZE);
+        hdd_store.put_block(0, &block[block_range.clone()]).unwrap();
+        let (mut retrieved, retrieved_range) = aligned_random_buf(BLOCK_SIZE);
+        hdd_store
+            .get_block(0, &mut retrieved[retrieved_range.clone()])
+            .unwrap()
+            .unwrap();
+        assert_eq!(block[block_range.clone()], retrieved[retrieved_range]);
+        // slice round trip
+        let (slice, slice_range) = aligned_random_buf(ALIGN * 2);
+        hdd_store
+            .put_slice(0, ALIGN, &slice[slice_range.clone()])
+            .unwrap()
+            .unwrap();
+        let (mut retrieved, retrieved_range) = aligned_random_buf(ALIGN * 2);
+        hdd_store
+            .get_slice(0, ALIGN, &mut retrieved[retrieved_range.clone()])
+            .unwrap()
+            .unwrap();
+        assert_eq!(slice[slice_range.clone()], retrieved[retrieved_range]);
+        // misaligned offset
+        let e = hdd_store
+            .put_slice(
+                0,
+                ALIGN / 2,
+                &slice[slice_range.start..slice_range.start + ALIGN],
+            )
+            .unwrap_err();
+        assert!(matches!(e, SUError::InvalidArg(_)));
+        // misaligned length
+        let e = hdd_store
+            .put_slice(0, 0, &slice[slice_range.start..slice_range.start + 1])
+            .unwrap_err();
+        assert!(matches!(e, SUError::InvalidArg(_)));
+        // misaligned buffer
+        let e = hdd_store
+            .put_block(0, &block[block_range.start + 1..block_range.end + 1])
+            .unwrap_err();
+        assert!(matches!(e, SUError::InvalidArg(_)));
+    }
+
+    #[test]
+    fn checksum() {
+        let tempfile = tempfile::TempDir::new().unwrap();
+        let hdd_store = HDDStorage::connect_to_dev_checked(
+            tempfile.path(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        )
+        .unwrap();
+        let mut block = random_block_data();
+        hdd_store.put_block(0, &block).unwrap();
+        hdd_store.put_block(1, &random_block_data()).unwrap();
+        let block_path = crate::storage::utility::block_id_to_path(tempfile.path(), 0);
+        assert_eq!(
+            std::fs::metadata(block_path.as_path()).unwrap().len(),
+            u64::try_from(BLOCK_SIZE + HDDStorage::CHECKSUM_LEN).unwrap()
+        );
+        assert_eq!(hdd_store.get_block_owned(0).unwrap().unwrap(), block);
+        // slice update keeps the checksum valid
+        let slice = random_block_data();
+        hdd_store
+            .put_slice(0, 10, &slice[..BLOCK_SIZE / 2])
+            .unwrap()
+            .unwrap();
+        block[10..10 + BLOCK_SIZE / 2].copy_from_slice(&slice[..BLOCK_SIZE / 2]);
+        assert_eq!(hdd_store.get_block_owned(0).unwrap().unwrap(), block);
+        assert_eq!(
+            hdd_store.get_slice_owned(0, 5..100).unwrap().unwrap(),
+            block[5..100]
+        );
+        // flip a byte on disk
+        let mut raw = std::fs::read(block_path.as_path()).unwrap();
+        raw[BLOCK_SIZE / 3] ^= 0xFF;
+        std::fs::write(block_path.as_path(), raw).unwrap();
+        let e = hdd_store.get_block_owned(0).unwrap_err();
+        assert!(matches!(e, SUError::Checksum(_)));
+        let e = hdd_store.get_slice_owned(0, 0..1).unwrap_err();
+        assert!(matches!(e, SUError::Checksum(_)));
+        let e = hdd_store.put_slice(0, 0, &[0_u8]).unwrap_err();
+        assert!(matches!(e, SUError::Checksum(_)));
+        // other blocks are not affected
+        assert!(hdd_store.get_block_owned(1).unwrap().is_some());
+        // a full block put repairs the block
+        hdd_store.put_block(0, &block).unwrap();
+        assert_eq!(hdd_store.get_block_owned(0).unwrap().unwrap(), block);
+    }
+
+    #[test]
+    fn op_latency() {
+        const LATENCY: std::time::Duration = std::time::Duration::from_millis(20);
+        let tempfile = tempfile::TempDir::new().unwrap();
+        let hdd_store = HDDStorage::connect_to_dev(
+            tempfile.path().to_owned(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        )
+        .unwrap()
+        .with_op_latency(Some(LATENCY));
+        let block = random_block_data();
+        let now = std::time::Instant::now();
+        hdd_store.put_block(0, &block).unwrap();
+        assert!(now.elapsed() >= LATENCY);
+        let now = std::time::Instant::now();
+        let mut slice = vec![0_u8; 16];
+        hdd_store.get_slice(0, 0, &mut slice).unwrap().unwrap();
+        assert!(now.elapsed() >= LATENCY);
+        assert_eq!(&slice, &block[0..16]);
+        // no latency on range error
+        let now = std::time::Instant::now();
+        let e = hdd_store.put_block(0, &block[1..]);
+        assert!(matches!(e, Err(SUError::Range(_))));
+        assert!(now.elapsed() < LATENCY);
+    }
 }
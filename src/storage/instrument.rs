@@ -0,0 +1,268 @@
+//! Instrumentation of the storage layers, recorded through the [`metrics`](::metrics) facade
+//! if the `metrics` feature is enabled, and compiled to nothing otherwise.
+//!
+//! Every metric is labeled by the `layer` it is recorded on, i.e. `hdd` or `ssd`.
+//! The operations are counted once their arguments are validated,
+//! including the ones on the blocks not existing.
+#![cfg_attr(not(feature = "metrics"), allow(dead_code))]
+
+const BLOCK_READS: &str = "su_storage_block_reads_total";
+const BLOCK_WRITES: &str = "su_storage_block_writes_total";
+const SLICE_READS: &str = "su_storage_slice_reads_total";
+const SLICE_WRITES: &str = "su_storage_slice_writes_total";
+const READ_BYTES: &str = "su_storage_read_bytes_total";
+const WRITTEN_BYTES: &str = "su_storage_written_bytes_total";
+const EVICTIONS: &str = "su_storage_evictions_total";
+const READ_LATENCY: &str = "su_storage_read_latency_seconds";
+const WRITE_LATENCY: &str = "su_storage_write_latency_seconds";
+
+/// Storage layer a metric is recorded on.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Layer {
+    Hdd,
+    Ssd,
+}
+
+impl Layer {
+    fn label(self) -> &'static str {
+        match self {
+            Layer::Hdd => "hdd",
+            Layer::Ssd => "ssd",
+        }
+    }
+}
+
+/// An operation in progress, whose latency is recorded on drop.
+#[must_use]
+pub(super) struct Op {
+    #[cfg(feature = "metrics")]
+    layer: Layer,
+    #[cfg(feature = "metrics")]
+    latency: &'static str,
+    #[cfg(feature = "metrics")]
+    start: std::time::Instant,
+}
+
+impl Op {
+    /// Start a read of `slice_num` slices in total of `bytes` bytes,
+    /// or a full block if `slice_num` is `None`.
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    #[inline]
+    pub fn read(layer: Layer, slice_num: Option<usize>, bytes: usize) -> Self {
+        #[cfg(feature = "metrics")]
+        {
+            let label = layer.label();
+            match slice_num {
+                Some(n) => ::metrics::counter!(SLICE_READS, "layer" => label).increment(n as u64),
+                None => ::metrics::counter!(BLOCK_READS, "layer" => label).increment(1),
+            }
+            ::metrics::counter!(READ_BYTES, "layer" => label).increment(bytes as u64);
+        }
+        Self {
+            #[cfg(feature = "metrics")]
+            layer,
+            #[cfg(feature = "metrics")]
+            latency: READ_LATENCY,
+            #[cfg(feature = "metrics")]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    /// Start a write of `slice_num` slices in total of `bytes` bytes,
+    /// or a full block if `slice_num` is `None`.
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    #[inline]
+    pub fn write(layer: Layer, slice_num: Option<usize>, bytes: usize) -> Self {
+        #[cfg(feature = "metrics")]
+        {
+            let label = layer.label();
+            match slice_num {
+                Some(n) => ::metrics::counter!(SLICE_WRITES, "layer" => label).increment(n as u64),
+                None => ::metrics::counter!(BLOCK_WRITES, "layer" => label).increment(1),
+            }
+            ::metrics::counter!(WRITTEN_BYTES, "layer" => label).increment(bytes as u64);
+        }
+        Self {
+            #[cfg(feature = "metrics")]
+            layer,
+            #[cfg(feature = "metrics")]
+            latency: WRITE_LATENCY,
+            #[cfg(feature = "metrics")]
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Drop for Op {
+    fn drop(&mut self) {
+        ::metrics::histogram!(self.latency, "layer" => self.layer.label())
+            .record(self.start.elapsed());
+    }
+}
+
+/// Record a block evicted from `layer` to the next storage layer.
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+#[inline]
+pub(super) fn record_eviction(layer: Layer) {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(EVICTIONS, "layer" => layer.label()).increment(1);
+}
+
+/// Register the descriptions of the storage metrics to the installed recorder,
+/// which should be called once after the recorder is installed.
+#[cfg(feature = "metrics")]
+pub fn describe_metrics() {
+    use ::metrics::{describe_counter, describe_histogram, Unit};
+    describe_counter!(BLOCK_READS, Unit::Count, "number of the full block reads");
+    describe_counter!(BLOCK_WRITES, Unit::Count, "number of the full block writes");
+    describe_counter!(SLICE_READS, Unit::Count, "number of the slices read");
+    describe_counter!(SLICE_WRITES, Unit::Count, "number of the slices written");
+    describe_counter!(
+        READ_BYTES,
+        Unit::Bytes,
+        "bytes read by the blocks and slices"
+    );
+    describe_counter!(
+        WRITTEN_BYTES,
+        Unit::Bytes,
+        "bytes written by the blocks and slices"
+    );
+    describe_counter!(
+        EVICTIONS,
+        Unit::Count,
+        "number of the blocks evicted to the next storage layer"
+    );
+    describe_histogram!(READ_LATENCY, Unit::Seconds, "latency of the reads");
+    describe_histogram!(WRITE_LATENCY, Unit::Seconds, "latency of the writes");
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod test {
+    use std::{collections::HashMap, num::NonZeroUsize};
+
+    use metrics_util::{
+        debugging::{DebugValue, DebuggingRecorder},
+        MetricKind,
+    };
+
+    use crate::storage::{BlockStorage, HDDStorage, SSDStorage, SliceStorage};
+
+    use super::{
+        BLOCK_READS, BLOCK_WRITES, EVICTIONS, READ_BYTES, READ_LATENCY, SLICE_READS, SLICE_WRITES,
+        WRITE_LATENCY, WRITTEN_BYTES,
+    };
+
+    const BLOCK_SIZE: usize = 4 << 10;
+
+    /// Counters and number of the histogram samples, by the metric name and the layer label.
+    type Recorded = HashMap<(String, String), u64>;
+
+    fn record(f: impl FnOnce()) -> Recorded {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, f);
+        snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let (kind, key) = key.into_parts();
+                let layer = key
+                    .labels()
+                    .find(|label| label.key() == "layer")
+                    .unwrap()
+                    .value()
+                    .to_string();
+                let value = match value {
+                    DebugValue::Counter(n) => n,
+                    DebugValue::Histogram(samples) => samples.len() as u64,
+                    DebugValue::Gauge(_) => unreachable!("no gauge recorded"),
+                };
+                assert_ne!(kind, MetricKind::Gauge);
+                ((key.name().to_string(), layer), value)
+            })
+            .collect()
+    }
+
+    fn get(recorded: &Recorded, name: &str, layer: &str) -> u64 {
+        recorded
+            .get(&(name.to_string(), layer.to_string()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn hdd_metrics() {
+        let hdd_dev = tempfile::tempdir().unwrap();
+        let recorded = record(|| {
+            let hdd_store =
+                HDDStorage::connect_to_dev(hdd_dev.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap())
+                    .unwrap();
+            let block = vec![1_u8; BLOCK_SIZE];
+            (0..3).for_each(|block_id| hdd_store.put_block(block_id, &block).unwrap());
+            assert!(hdd_store.get_block_owned(0).unwrap().is_some());
+            assert!(hdd_store.get_block_owned(3).unwrap().is_none());
+            hdd_store.put_slice(1, 100, &[0_u8; 10]).unwrap().unwrap();
+            let (mut a, mut b) = ([0_u8; 16], [0_u8; 32]);
+            hdd_store
+                .get_slices(2, &mut [(0, &mut a), (64, &mut b)])
+                .unwrap()
+                .unwrap();
+            // rejected operations are not recorded
+            assert!(hdd_store.put_block(4, &block[1..]).is_err());
+        });
+        assert_eq!(get(&recorded, BLOCK_WRITES, "hdd"), 3);
+        assert_eq!(get(&recorded, BLOCK_READS, "hdd"), 2);
+        assert_eq!(get(&recorded, SLICE_WRITES, "hdd"), 1);
+        assert_eq!(get(&recorded, SLICE_READS, "hdd"), 2);
+        assert_eq!(
+            get(&recorded, WRITTEN_BYTES, "hdd"),
+            3 * BLOCK_SIZE as u64 + 10
+        );
+        assert_eq!(
+            get(&recorded, READ_BYTES, "hdd"),
+            2 * BLOCK_SIZE as u64 + 48
+        );
+        assert_eq!(get(&recorded, WRITE_LATENCY, "hdd"), 4);
+        assert_eq!(get(&recorded, READ_LATENCY, "hdd"), 3);
+        assert_eq!(get(&recorded, EVICTIONS, "hdd"), 0);
+    }
+
+    #[test]
+    fn ssd_metrics() {
+        const SSD_CAP_NUM: usize = 2;
+        let hdd_dev = tempfile::tempdir().unwrap();
+        let ssd_dev = tempfile::tempdir().unwrap();
+        let recorded = record(|| {
+            let hdd_store =
+                HDDStorage::connect_to_dev(hdd_dev.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap())
+                    .unwrap();
+            let ssd_store = SSDStorage::connect_to_dev(
+                ssd_dev.path().to_path_buf(),
+                NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+                NonZeroUsize::new(SSD_CAP_NUM).unwrap(),
+                hdd_store,
+            )
+            .unwrap();
+            let block = vec![1_u8; BLOCK_SIZE];
+            // the third block evicts the first one
+            (0..3).for_each(|block_id| ssd_store.put_block(block_id, &block).unwrap());
+            // fetched from hdd, which evicts the second one
+            assert!(ssd_store.get_block_owned(0).unwrap().is_some());
+            let mut slice = [0_u8; 8];
+            ssd_store.get_slice(2, 8, &mut slice).unwrap().unwrap();
+            // the blocks flushed on drop are not evictions
+            drop(ssd_store);
+        });
+        assert_eq!(get(&recorded, BLOCK_WRITES, "ssd"), 3);
+        assert_eq!(get(&recorded, BLOCK_READS, "ssd"), 1);
+        assert_eq!(get(&recorded, SLICE_READS, "ssd"), 1);
+        assert_eq!(get(&recorded, READ_BYTES, "ssd"), BLOCK_SIZE as u64 + 8);
+        assert_eq!(get(&recorded, EVICTIONS, "ssd"), 2);
+        // every block missing in ssd is looked up in hdd
+        assert_eq!(get(&recorded, BLOCK_READS, "hdd"), 4);
+        // 2 evicted and 2 flushed on drop
+        assert_eq!(get(&recorded, BLOCK_WRITES, "hdd"), 4);
+    }
+}
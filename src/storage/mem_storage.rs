@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::RwLock;
+
+use crate::SUResult;
+
+use super::utility::{check_block_range, check_slice_range};
+use super::{BlockId, BlockStorage, SliceStorage};
+
+/// A volatile storage keeping all the blocks in memory.
+#[derive(Debug)]
+pub struct MemStorage {
+    blocks: RwLock<HashMap<BlockId, Vec<u8>>>,
+    block_size: usize,
+}
+
+impl MemStorage {
+    /// Make an empty [`MemStorage`].
+    ///
+    /// # Parameter
+    /// - `block_size`: size of each block to be created
+    pub fn new(block_size: NonZeroUsize) -> Self {
+        Self {
+            blocks: Default::default(),
+            block_size: block_size.get(),
+        }
+    }
+
+    /// Get the number of blocks stored
+    pub fn len(&self) -> usize {
+        self.blocks.read().unwrap().len()
+    }
+
+    /// Check if no block is stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl BlockStorage for MemStorage {
+    /// Storing data to a block.
+    /// A new block will be created if the block does not exist.
+    ///
+    /// # Parameter
+    /// - `block_id`: id of the block
+    /// - `block_data`: data of the block to store
+    ///
+    /// # Return
+    /// - [`Ok`]: on success
+    /// - [`Err`]: on any error occurring
+    ///
+    /// # Error
+    /// - [SUError::Range] if `block_data.len()` does not match block size
+    fn put_block(&self, block_id: BlockId, block_data: &[u8]) -> SUResult<()> {
+        check_block_range(
+            file!(),
+            line!(),
+            column!(),
+            block_data.len(),
+            self.block_size,
+        )?;
+        self.blocks
+            .write()
+            .unwrap()
+            .insert(block_id, block_data.to_vec());
+        Ok(())
+    }
+
+    /// Retrieving data from a full block.
+    ///
+    /// # Parameter
+    /// - `block_id`: id of the block
+    /// - `block_data`: buffer to get the block data
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success, and the buffer `block_data` filled with the corresponding data
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    ///
+    /// # Error
+    /// - [`SUError::Range`] if `block_data.len()` does not match the block length
+    fn get_block(&self, block_id: BlockId, block_data: &mut [u8]) -> SUResult<Option<()>> {
+        check_block_range(
+            file!(),
+            line!(),
+            column!(),
+            block_data.len(),
+            self.block_size,
+        )?;
+        Ok(self
+            .blocks
+            .read()
+            .unwrap()
+            .get(&block_id)
+            .map(|block| block_data.copy_from_slice(block)))
+    }
+
+    /// Remove a block.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success
+    /// - [`Ok(None)`] on block not existing
+    fn remove_block(&self, block_id: BlockId) -> SUResult<Option<()>> {
+        Ok(self.blocks.write().unwrap().remove(&block_id).map(|_| ()))
+    }
+
+    /// Get size of a block
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+}
+
+impl SliceStorage for MemStorage {
+    /// Storing data from a slice to a specific area of a block.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    ///
+    /// # Error
+    /// - [SUError::Range] if the area specified is out of the block range
+    fn put_slice(
+        &self,
+        block_id: BlockId,
+        inner_block_offset: usize,
+        slice_data: &[u8],
+    ) -> SUResult<Option<()>> {
+        let slice_range = inner_block_offset..inner_block_offset + slice_data.len();
+        // check range
+        check_slice_range(
+            file!(),
+            line!(),
+            column!(),
+            slice_range.clone(),
+            self.block_size(),
+        )?;
+        Ok(self
+            .blocks
+            .write()
+            .unwrap()
+            .get_mut(&block_id)
+            .map(|block| block[slice_range].copy_from_slice(slice_data)))
+    }
+
+    /// Retrieving slice data from a specific area of a block to a slice buffer.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success, and the buffer `slice_data` with be filled with the corresponding data.
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    ///
+    /// # Error
+    /// - [SUError::Range] if the area specified is out of the block range
+    fn get_slice(
+        &self,
+        block_id: BlockId,
+        inner_block_offset: usize,
+        slice_data: &mut [u8],
+    ) -> SUResult<Option<()>> {
+        let slice_range = inner_block_offset..inner_block_offset + slice_data.len();
+        // check range
+        check_slice_range(
+            file!(),
+            line!(),
+            column!(),
+            slice_range.clone(),
+            self.block_size(),
+        )?;
+        Ok(self
+            .blocks
+            .read()
+            .unwrap()
+            .get(&block_id)
+            .map(|block| slice_data.copy_from_slice(&block[slice_range])))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+    use std::num::NonZeroUsize;
+
+    use crate::{
+        storage::{BlockStorage, SliceStorage},
+        SUError,
+    };
+
+    use super::MemStorage;
+    const BLOCK_SIZE: usize = 4 << 10;
+    const BLOCK_NUM: usize = 1 << 10;
+    fn random_block_data() -> Vec<u8> {
+        rand::thread_rng()
+            .sample_iter(rand::distributions::Standard)
+            .take(BLOCK_SIZE)
+            .collect()
+    }
+
+    #[test]
+    fn put_get_block() {
+        let mem_store = MemStorage::new(NonZeroUsize::new(BLOCK_SIZE).unwrap());
+        let blocks = (0..BLOCK_NUM)
+            .map(|_| random_block_data())
+            .collect::<Vec<_>>();
+        // put blocks
+        blocks
+            .iter()
+            .enumerate()
+            .for_each(|(i, block)| mem_store.put_block(i, block).unwrap());
+        assert_eq!(mem_store.len(), BLOCK_NUM);
+        // get blocks
+        blocks.iter().enumerate().for_each(|(i, block)| {
+            let data = mem_store.get_block_owned(i).unwrap().unwrap();
+            assert_eq!(&data, block);
+        });
+        let mut data = vec![0_u8; BLOCK_SIZE];
+        blocks.iter().enumerate().for_each(|(i, block)| {
+            mem_store.get_block(i, &mut data).unwrap().unwrap();
+            assert_eq!(&data, block);
+        });
+        // update
+        let update_blocks = (0..BLOCK_NUM)
+            .step_by(3)
+            .map(|i| (i, random_block_data()))
+            .collect::<Vec<_>>();
+        update_blocks
+            .iter()
+            .for_each(|(i, block)| mem_store.put_block(*i, block).unwrap());
+        update_blocks.iter().for_each(|(i, block)| {
+            let retrieve = mem_store.get_block_owned(*i).unwrap().unwrap();
+            assert_eq!(block, &retrieve);
+        });
+        assert_eq!(mem_store.len(), BLOCK_NUM);
+    }
+
+    #[test]
+    fn block_error_handle() {
+        let mem_store = MemStorage::new(NonZeroUsize::new(BLOCK_SIZE).unwrap());
+        assert!(mem_store.is_empty());
+        // put blocks out of range
+        let out_of_range_data = vec![0_u8; BLOCK_SIZE + 1];
+        let e = mem_store.put_block(0, &out_of_range_data).unwrap_err();
+        assert!(matches!(e, SUError::Range(_)));
+        let out_of_range_data = vec![0_u8; BLOCK_SIZE - 1];
+        let e = mem_store.put_block(0, &out_of_range_data).unwrap_err();
+        assert!(matches!(e, SUError::Range(_)));
+        assert!(mem_store.is_empty());
+
+        // get block out of range
+        let mut out_of_range_data = vec![0_u8; BLOCK_SIZE + 1];
+        let e = mem_store.get_block(0, &mut out_of_range_data).unwrap_err();
+        assert!(matches!(e, SUError::Range(_)));
+        let mut out_of_range_data = vec![0_u8; BLOCK_SIZE - 1];
+        let e = mem_store.get_block(0, &mut out_of_range_data).unwrap_err();
+        assert!(matches!(e, SUError::Range(_)));
+        // get block not exists
+        let mut data = vec![0_u8; BLOCK_SIZE];
+        let ret = mem_store.get_block(0, &mut data).unwrap();
+        assert!(ret.is_none());
+
+        // get block owned not exists
+        let ret = mem_store.get_block_owned(9).unwrap();
+        assert!(ret.is_none());
+    }
+
+    #[test]
+    fn remove_block() {
+        let mem_store = MemStorage::new(NonZeroUsize::new(BLOCK_SIZE).unwrap());
+        mem_store.put_block(0, &random_block_data()).unwrap();
+        mem_store.put_block(1, &random_block_data()).unwrap();
+        assert!(mem_store.remove_block(0).unwrap().is_some());
+        assert!(mem_store.get_block_owned(0).unwrap().is_none());
+        assert!(mem_store.get_block_owned(1).unwrap().is_some());
+        assert_eq!(mem_store.len(), 1);
+        // remove again
+        assert!(mem_store.remove_block(0).unwrap().is_none());
+    }
+
+    #[test]
+    fn put_get_slice() {
+        let mem_store = MemStorage::new(NonZeroUsize::new(BLOCK_SIZE).unwrap());
+        let blocks = (0..BLOCK_NUM)
+            .map(|_| random_block_data())
+            .collect::<Vec<_>>();
+        // put blocks
+        blocks
+            .iter()
+            .enumerate()
+            .for_each(|(i, block)| mem_store.put_block(i, block).unwrap());
+        // get slice
+        fn random_slice_range() -> std::ops::Range<usize> {
+            let start = rand::thread_rng().gen_range(0..BLOCK_SIZE - 1);
+            let end = rand::thread_rng().gen_range(start + 1..BLOCK_SIZE);
+            start..end
+        }
+        fn random_slice_data(range: std::ops::Range<usize>) -> Vec<u8> {
+            rand::thread_rng()
+                .sample_iter(rand::distributions::Standard)
+                .take(range.len())
+                .collect()
+        }
+        (0..blocks.len())
+            .map(|i| {
+                let range = random_slice_range();
+                let owned_data = mem_store
+                    .get_slice_owned(i, range.to_owned())
+                    .unwrap()
+                    .unwrap();
+                let mut data = vec![0_u8; range.len()];
+                mem_store
+                    .get_slice(i, range.start, &mut data)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(&owned_data, &data);
+                (range, owned_data)
+            })
+            .zip(blocks.iter())
+            .for_each(|((range, retrieve), expect)| {
+                assert_eq!(&expect[range.to_owned()], &retrieve)
+            });
+        // update slice
+        let update_slice = (0..BLOCK_NUM)
+            .step_by(2)
+            .map(|i| {
+                let range = random_slice_range();
+                let slice_data = random_slice_data(range.clone());
+                (i, range, slice_data)
+            })
+            .collect::<Vec<_>>();
+        update_slice.iter().for_each(|(i, range, slice_data)| {
+            mem_store
+                .put_slice(*i, range.start, slice_data)
+                .unwrap()
+                .unwrap()
+        });
+        let mut updated_block = blocks.clone();
+        update_slice.iter().for_each(|(i, range, slice_data)| {
+            assert_eq!(
+                &mem_store
+                    .get_slice_owned(*i, range.clone())
+                    .unwrap()
+                    .unwrap(),
+                slice_data
+            );
+            updated_block.get_mut(*i).unwrap()[range.clone()].copy_from_slice(slice_data)
+        });
+        updated_block
+            .iter()
+            .enumerate()
+            .map(|(i, expect)| (expect, mem_store.get_block_owned(i).unwrap().unwrap()))
+            .for_each(|(expect, retrieved)| assert_eq!(expect, &retrieved));
+    }
+
+    #[test]
+    fn slice_error_handle() {
+        let mem_store = MemStorage::new(NonZeroUsize::new(BLOCK_SIZE).unwrap());
+        (0..BLOCK_NUM).for_each(|i| mem_store.put_block(i, &random_block_data()).unwrap());
+        // get 404
+        let e = mem_store.get_slice_owned(BLOCK_NUM, 0..1).unwrap();
+        assert!(e.is_none());
+        // get invalid range
+        let e = mem_store.get_slice_owned(0, 0..BLOCK_SIZE + 1);
+        assert!(matches!(e, Err(SUError::Range(_))));
+        let e = mem_store.get_slice_owned(0, BLOCK_SIZE..BLOCK_SIZE + 1);
+        assert!(matches!(e, Err(SUError::Range(_))));
+        // put 404
+        let data = vec![0_u8; BLOCK_SIZE * 2];
+        let e = mem_store
+            .put_slice(BLOCK_NUM, 0, &data[0..BLOCK_SIZE])
+            .unwrap();
+        assert!(e.is_none());
+        assert_eq!(mem_store.len(), BLOCK_NUM);
+        // put offset out of range
+        let e = mem_store.put_slice(BLOCK_NUM - 1, BLOCK_SIZE, &data[0..1]);
+        assert!(matches!(e, Err(SUError::Range(_))));
+        // put slice len out of range
+        let e = mem_store.put_slice(BLOCK_NUM - 1, BLOCK_SIZE - 1, &data[0..2]);
+        assert!(matches!(e, Err(SUError::Range(_))));
+        let e = mem_store.put_slice(BLOCK_NUM - 1, 0, &data[0..BLOCK_SIZE + 1]);
+        assert!(matches!(e, Err(SUError::Range(_))));
+    }
+}
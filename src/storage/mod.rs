@@ -1,18 +1,42 @@
-use crate::SUResult;
+use crate::{SUError, SUResult};
 
+#[cfg(feature = "tokio")]
+mod async_storage;
+mod coalescing_storage;
+#[cfg(feature = "compression")]
+mod compressed_storage;
 mod evict;
 mod hdd_storage;
+mod instrument;
+mod mem_storage;
 mod slice_buffer;
 mod ssd_storage;
+mod trace_storage;
 mod utility;
 
+#[cfg(feature = "tokio")]
+pub use async_storage::{AsyncBlockStorage, AsyncHDDStorage, BlockingAdapter};
+pub use coalescing_storage::CoalescingStorage;
+#[cfg(feature = "compression")]
+pub use compressed_storage::CompressedStorage;
+pub use evict::CostAwareStripeEvict;
 pub use evict::EvictStrategySlice;
+pub use evict::Evictions;
+pub use evict::FifoEvict;
+pub use evict::LruBlockEvict;
+pub use evict::LruKEvict;
 pub use evict::MostModifiedBlockEvict;
 pub use evict::MostModifiedStripeEvict;
 pub use evict::NonEvict;
 pub use hdd_storage::HDDStorage;
+#[cfg(feature = "metrics")]
+pub use instrument::describe_metrics;
+pub use mem_storage::MemStorage;
 pub use slice_buffer::FixedSizeSliceBuf;
+pub(crate) use slice_buffer::META_EXTENSION;
 pub use ssd_storage::SSDStorage;
+pub use trace_storage::{IoEvent, IoOp, TraceStorage};
+pub use utility::Sharding;
 
 pub type BlockId = usize;
 
@@ -70,6 +94,16 @@ pub trait BlockStorage {
         self.get_block(block_id, &mut data)
             .map(|opt| opt.map(|_| data))
     }
+    /// Remove a block.
+    ///
+    /// # Parameter
+    /// - `block_id`: id of the block
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    fn remove_block(&self, block_id: BlockId) -> SUResult<Option<()>>;
     /// Get size of a block
     fn block_size(&self) -> usize;
 }
@@ -131,6 +165,54 @@ pub trait SliceStorage {
         self.get_slice(block_id, range.start, data.as_mut_slice())
             .map(|opt| opt.map(|_| data))
     }
+    /// Storing data from multiple slices to the specific areas of a block.
+    /// Each slice is given as `(inner_block_offset, slice_data)`,
+    /// and stored as [`SliceStorage::put_slice`] does, in order.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    ///
+    /// # Error
+    /// - [SUError::Range] if any area specified is out of the block range
+    fn put_slices(&self, block_id: BlockId, slices: &[(usize, &[u8])]) -> SUResult<Option<()>> {
+        for (inner_block_offset, slice_data) in slices {
+            if self
+                .put_slice(block_id, *inner_block_offset, slice_data)?
+                .is_none()
+            {
+                return Ok(None);
+            }
+        }
+        Ok(Some(()))
+    }
+    /// Retrieving slice data from the specific areas of a block to multiple slice buffers.
+    /// Each slice buffer is given as `(inner_block_offset, slice_data)`,
+    /// and filled as [`SliceStorage::get_slice`] does, in order.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success, and all the slice buffers filled with the corresponding data.
+    /// - [`Ok(None)`] on block not existing
+    /// - [`Err`] on any error occurring
+    ///
+    /// # Error
+    /// - [SUError::Range] if any area specified is out of the block range
+    fn get_slices(
+        &self,
+        block_id: BlockId,
+        slices: &mut [(usize, &mut [u8])],
+    ) -> SUResult<Option<()>> {
+        for (inner_block_offset, slice_data) in slices {
+            if self
+                .get_slice(block_id, *inner_block_offset, slice_data)?
+                .is_none()
+            {
+                return Ok(None);
+            }
+        }
+        Ok(Some(()))
+    }
 }
 
 pub struct BufferEviction {
@@ -138,6 +220,35 @@ pub struct BufferEviction {
     pub data: PartialBlock,
 }
 
+impl BufferEviction {
+    /// Persist the present slices of the evicted block to `storage`, leaving the absent part untouched.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] if the slices are persisted
+    /// - [`Ok(None)`] if the block does not exist in `storage`
+    /// - [`Err`] on any error occurring
+    pub fn persist_to(&self, storage: &impl SliceStorage) -> SUResult<Option<()>> {
+        let mut cursor = 0;
+        let slices = self
+            .data
+            .slices
+            .iter()
+            .filter_map(|slice| match slice {
+                SliceOpt::Present(data) => {
+                    let offset = cursor;
+                    cursor += data.len();
+                    Some((offset, data.as_ref()))
+                }
+                SliceOpt::Absent(size) => {
+                    cursor += size;
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        storage.put_slices(self.block_id, &slices)
+    }
+}
+
 pub trait SliceBuffer {
     /// Push a slice to the buffer.
     /// The slice is treated as part of a block at range`[inner_block_offset..inner_block_offset + slice_data.len())`
@@ -157,13 +268,57 @@ pub trait SliceBuffer {
         inner_block_offset: usize,
         slice_data: &[u8],
     ) -> SUResult<Option<BufferEviction>>;
+    /// Push a slice to the buffer as [`SliceBuffer::push_slice()`] does,
+    /// but evict in a batch as [`EvictStrategySlice::push_batch()`] does,
+    /// so that an eviction strategy with a low watermark drains the buffer down to it at once.
+    ///
+    /// # Return
+    /// - [`Ok`] with the evictions in the order of eviction, empty if no eviction occurs
+    /// - [`Err`] if any error occurs
+    fn push_slice_batch(
+        &self,
+        block_id: BlockId,
+        inner_block_offset: usize,
+        slice_data: &[u8],
+    ) -> SUResult<Vec<BufferEviction>>;
 
     fn pop(&self) -> Option<BufferEviction>;
     fn pop_one(&self, block_id: BlockId) -> Option<BufferEviction>;
+    /// Get the block to be evicted next by [`SliceBuffer::pop()`], without evicting it.
+    ///
+    /// # Return
+    /// - [`Some`] the id of the block to be evicted next
+    /// - [`None`] if the buffer is empty
+    fn peek_next_evict(&self) -> Option<BlockId>;
     fn len(&self) -> usize;
     fn is_empty(&self) -> bool {
         self.len() == 0
     }
+    /// Evict all the blocks in the buffer one by one in the order of [`SliceBuffer::pop()`],
+    /// and pass each eviction to `f`.
+    fn drain_into<F: FnMut(BufferEviction)>(&self, mut f: F) {
+        while let Some(eviction) = self.pop() {
+            f(eviction);
+        }
+    }
+    /// Evict all the blocks in the buffer, and persist the present slices of each to `storage`
+    /// as [`BufferEviction::persist_to`] does.
+    ///
+    /// # Error
+    /// - [`SUError::Io`] if the block of an eviction does not exist in `storage`,
+    ///   the eviction is dropped and the rest remain in the buffer
+    /// - any error occurring on persisting, with the rest remaining in the buffer
+    fn drain_to_storage(&self, storage: &impl SliceStorage) -> SUResult<()> {
+        while let Some(eviction) = self.pop() {
+            if eviction.persist_to(storage)?.is_none() {
+                return Err(SUError::not_found(format!(
+                    "evicted block {} not found",
+                    eviction.block_id
+                )));
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1,71 +1,386 @@
 use std::{
+    borrow::Cow,
     cell::RefCell,
     collections::{BTreeMap, HashMap},
     io::{Read, Seek, Write},
     num::NonZeroUsize,
-    path::PathBuf,
+    ops::Range,
+    os::unix::fs::FileExt,
+    path::{Path, PathBuf},
 };
 
 use crate::{
-    storage::{utility::block_id_to_path, PartialBlock, SliceOpt},
+    storage::{
+        utility::{block_id_to_path, block_path_to_id, check_slice_range},
+        PartialBlock, SliceOpt,
+    },
     SUError, SUResult,
 };
 
-use super::{evict::RangeSet, BlockId, BufferEviction, EvictStrategySlice, MostModifiedBlockEvict};
+use super::{
+    evict::RangeSet, utility::OpLatency, BlockId, BufferEviction, EvictStrategySlice, Evictions,
+    MostModifiedBlockEvict, NonEvict,
+};
 
 type SegId = usize;
 type RecordIdx = usize;
+/// Default size of a segment, which is the granularity of the slices buffered
 const SEG_SIZE: usize = 4 << 10;
+/// Extension of the sidecar file recording the segment layout of a block file
+pub(crate) const META_EXTENSION: &str = "meta";
+
+/// Get the path of the sidecar metadata file of a block file
+fn meta_path(block_path: &Path) -> PathBuf {
+    block_path.with_extension(META_EXTENSION)
+}
+
+/// Write the segment layout and the modified ranges of a block file to its sidecar metadata file.
+/// The metadata is a sequence of little-endian u64: the number of the segments,
+/// the segment ids ordered by their record index, and then the start and end of each modified range.
+fn write_seg_meta(
+    block_path: &Path,
+    seg_map: &BTreeMap<SegId, RecordIdx>,
+    ranges: &RangeSet,
+) -> SUResult<()> {
+    let mut segs = vec![0_usize; seg_map.len()];
+    seg_map
+        .iter()
+        .for_each(|(seg_id, record_idx)| segs[*record_idx] = *seg_id);
+    let content = std::iter::once(segs.len())
+        .chain(segs)
+        .chain(
+            ranges
+                .to_ranges()
+                .into_iter()
+                .flat_map(|r| [r.start, r.end]),
+        )
+        .flat_map(|n| u64::try_from(n).unwrap().to_le_bytes())
+        .collect::<Vec<_>>();
+    std::fs::write(meta_path(block_path), content)?;
+    Ok(())
+}
 
+/// Read the segment layout and the modified ranges of a block file
+/// from the sidecar metadata file at `meta_path`.
+///
+/// # Error
+/// - [`SUError::Other`] if the metadata file is malformed
+fn read_seg_meta(meta_path: &Path) -> SUResult<(BTreeMap<SegId, RecordIdx>, RangeSet)> {
+    let malformed = || {
+        SUError::other(format!(
+            "malformed slice buffer metadata: {}",
+            meta_path.display()
+        ))
+    };
+    let content = std::fs::read(meta_path)?;
+    if content.len() % std::mem::size_of::<u64>() != 0 {
+        return Err(malformed());
+    }
+    let mut nums = content
+        .chunks_exact(std::mem::size_of::<u64>())
+        .map(|bytes| usize::try_from(u64::from_le_bytes(bytes.try_into().unwrap())).unwrap());
+    let seg_num = nums.next().ok_or_else(malformed)?;
+    let seg_map = nums
+        .by_ref()
+        .take(seg_num)
+        .enumerate()
+        .map(|(record_idx, seg_id)| (seg_id, record_idx))
+        .collect::<BTreeMap<_, _>>();
+    let bounds = nums.collect::<Vec<_>>();
+    if seg_map.len() != seg_num
+        || bounds.len() % 2 != 0
+        || bounds.chunks_exact(2).any(|bound| bound[0] >= bound[1])
+    {
+        return Err(malformed());
+    }
+    let ranges = bounds
+        .chunks_exact(2)
+        .map(|bound| bound[0]..bound[1])
+        .collect::<Vec<_>>();
+    Ok((seg_map, RangeSet::from(ranges.as_slice())))
+}
+
+type SegMap = RefCell<HashMap<BlockId, std::collections::BTreeMap<SegId, RecordIdx>>>;
+
+/// A slice buffer storing the slices of each block in a record file on the dev.
+///
+/// The buffer can be striped over several devs, where each block is stored in the dev
+/// it hashes to, see [`FixedSizeSliceBuf::dev_of`]. The capacity of the eviction strategy
+/// bounds the slices buffered across all the devs.
 #[derive(Debug)]
 pub struct FixedSizeSliceBuf<E = MostModifiedBlockEvict>
 where
     E: std::fmt::Debug,
 {
     evict: E,
-    dev_dir: PathBuf,
+    dev_dirs: Vec<PathBuf>,
     block_size: usize,
-    seg_map: RefCell<HashMap<BlockId, std::collections::BTreeMap<SegId, RecordIdx>>>,
+    seg_size: usize,
+    /// segment maps of the blocks stored in each dev
+    seg_maps: Vec<SegMap>,
+    op_latency: OpLatency,
+    /// evict each slice right after it is pushed, see [`FixedSizeSliceBuf::connect_to_dev_write_through`]
+    write_through: bool,
 }
 
 impl<E> FixedSizeSliceBuf<E>
 where
     E: std::fmt::Debug,
 {
-    pub fn cleanup_dev(&self) -> SUResult<()> {
-        for entry in self.dev_dir.read_dir()?.flatten() {
-            let dir = entry.path();
-            if std::fs::read_dir(dir.as_path())?.count() == 0 {
-                std::fs::remove_dir(dir.as_path())?;
+    /// Remove the empty sharding directories left in the dev directories.
+    /// The block files and their metadata are kept, so that they can be recovered.
+    fn prune_empty_dirs(&self) -> SUResult<()> {
+        for dev_dir in self.dev_dirs.iter() {
+            for entry in dev_dir.read_dir()?.flatten() {
+                let dir = entry.path();
+                if dir.is_dir() && std::fs::read_dir(dir.as_path())?.next().is_none() {
+                    std::fs::remove_dir(dir.as_path())?;
+                }
             }
         }
         Ok(())
     }
+
+    /// Get the index of the dev a block is stored in.
+    fn dev_idx(&self, block_id: BlockId) -> usize {
+        // splitmix64 finalizer, which is stable across runs so that the buffer can be recovered
+        let mut z = u64::try_from(block_id)
+            .unwrap()
+            .wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        usize::try_from(z % u64::try_from(self.dev_dirs.len()).unwrap()).unwrap()
+    }
+
+    /// Get the dev directory a block is stored in, by the hash of `block_id`.
+    pub fn dev_of(&self, block_id: BlockId) -> &Path {
+        self.dev_dirs[self.dev_idx(block_id)].as_path()
+    }
+
+    /// Get the segment map of the dev a block is stored in.
+    fn seg_map_of(&self, block_id: BlockId) -> &SegMap {
+        &self.seg_maps[self.dev_idx(block_id)]
+    }
+
+    /// Get the path of the record file of a block.
+    fn block_path(&self, block_id: BlockId) -> PathBuf {
+        block_id_to_path(self.dev_of(block_id), block_id)
+    }
+
+    /// Inject a latency before each slice push or eviction, to model a slower device.
+    pub fn with_op_latency(mut self, op_latency: Option<std::time::Duration>) -> Self {
+        self.op_latency = OpLatency::new(op_latency);
+        self
+    }
 }
 
 impl<E> FixedSizeSliceBuf<E>
 where
     E: EvictStrategySlice,
 {
+    /// Discard all the slices buffered, and remove everything in the dev directories recursively.
+    /// The dev directories themselves are left intact.
+    ///
+    /// # Error
+    /// - [`SUError::Io`] if any file or directory cannot be removed
+    pub fn cleanup_dev(&self) -> SUResult<()> {
+        while self.evict.pop_first().is_some() {}
+        self.seg_maps
+            .iter()
+            .for_each(|seg_map| seg_map.borrow_mut().clear());
+        for dev_dir in self.dev_dirs.iter() {
+            for entry in dev_dir.read_dir()? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    std::fs::remove_dir_all(path)?;
+                } else {
+                    std::fs::remove_file(path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn connect_to_dev_with_evict(
         dev_root: impl Into<PathBuf>,
         block_size: NonZeroUsize,
         evict: E,
     ) -> SUResult<Self> {
-        let dev_root = dev_root.into();
-        if !dev_root.exists() {
+        Self::connect_to_dev_with_seg_size(
+            dev_root,
+            block_size,
+            NonZeroUsize::new(SEG_SIZE).unwrap(),
+            evict,
+        )
+    }
+
+    /// Connect to the dev with the slices buffered in the granularity of `seg_size`,
+    /// the other constructors use a 4K segment.
+    ///
+    /// # Error
+    /// - [`SUError::Io`] if `dev_root` is not found
+    /// - [`SUError::InvalidArg`] if `seg_size` does not divide `block_size`
+    pub fn connect_to_dev_with_seg_size(
+        dev_root: impl Into<PathBuf>,
+        block_size: NonZeroUsize,
+        seg_size: NonZeroUsize,
+        evict: E,
+    ) -> SUResult<Self> {
+        Self::connect_to_devs_with_seg_size(&[dev_root.into()], block_size, seg_size, evict)
+    }
+
+    /// Connect to several devs with the slices of each block buffered in the dev it hashes to,
+    /// which spreads the writes over the devs. `evict` bounds the slices buffered in all the devs.
+    ///
+    /// # Error
+    /// - [`SUError::Io`] if any of `dev_roots` is not found
+    /// - [`SUError::InvalidArg`] if `dev_roots` is empty
+    pub fn connect_to_devs_with_evict(
+        dev_roots: &[PathBuf],
+        block_size: NonZeroUsize,
+        evict: E,
+    ) -> SUResult<Self> {
+        Self::connect_to_devs_with_seg_size(
+            dev_roots,
+            block_size,
+            NonZeroUsize::new(SEG_SIZE).unwrap(),
+            evict,
+        )
+    }
+
+    /// Connect to several devs with the slices buffered in the granularity of `seg_size`.
+    ///
+    /// # Error
+    /// - [`SUError::Io`] if any of `dev_roots` is not found
+    /// - [`SUError::InvalidArg`] if `dev_roots` is empty, or `seg_size` does not divide `block_size`
+    pub fn connect_to_devs_with_seg_size(
+        dev_roots: &[PathBuf],
+        block_size: NonZeroUsize,
+        seg_size: NonZeroUsize,
+        evict: E,
+    ) -> SUResult<Self> {
+        if dev_roots.is_empty() {
+            return Err(SUError::invalid_arg("no dev to buffer the slices"));
+        }
+        if let Some(dev_root) = dev_roots.iter().find(|dev_root| !dev_root.exists()) {
             return Err(SUError::Io(std::io::Error::new(
                 std::io::ErrorKind::NotFound,
-                "dev path not found",
+                format!("dev path not found: {}", dev_root.display()),
+            )));
+        }
+        if block_size.get() % seg_size.get() != 0 {
+            return Err(SUError::invalid_arg(format!(
+                "segment size {seg_size} does not divide block size {block_size}"
             )));
         }
         Ok(Self {
             evict,
-            dev_dir: dev_root,
+            dev_dirs: dev_roots.to_vec(),
             block_size: block_size.get(),
-            seg_map: Default::default(),
+            seg_size: seg_size.get(),
+            seg_maps: dev_roots.iter().map(|_| Default::default()).collect(),
+            op_latency: OpLatency::default(),
+            write_through: false,
         })
     }
+
+    /// Recover a [`FixedSizeSliceBuf`] from the block files and their sidecar metadata
+    /// left in `dev_root` by a previous instance, e.g. after a crash.
+    /// All the recovered slices are pushed into `evict`, which should be empty.
+    ///
+    /// # Parameters
+    /// - `dev_root`: the dev directory used by the previous instance
+    /// - `block_size`: size of a block, should be the same as the previous instance
+    /// - `evict`: eviction strategy to re-populate
+    ///
+    /// # Error
+    /// - [`SUError::Io`] if `dev_root` is not found, or any io error occurs
+    /// - [`SUError::Other`] if any metadata is malformed or does not match its block file
+    /// - [`SUError::Other`] if the recovered slices exceed the capacity of `evict`
+    pub fn recover_from_dev(
+        dev_root: impl Into<PathBuf>,
+        block_size: NonZeroUsize,
+        evict: E,
+    ) -> SUResult<Self> {
+        Self::recover_from_dev_with_seg_size(
+            dev_root,
+            block_size,
+            NonZeroUsize::new(SEG_SIZE).unwrap(),
+            evict,
+        )
+    }
+
+    /// Recover a [`FixedSizeSliceBuf`] buffering the slices in the granularity of `seg_size`,
+    /// the same as [`FixedSizeSliceBuf::recover_from_dev()`] otherwise.
+    ///
+    /// # Error
+    /// - [`SUError::InvalidArg`] if `seg_size` does not divide `block_size`
+    /// - The same errors as [`FixedSizeSliceBuf::recover_from_dev()`]
+    pub fn recover_from_dev_with_seg_size(
+        dev_root: impl Into<PathBuf>,
+        block_size: NonZeroUsize,
+        seg_size: NonZeroUsize,
+        evict: E,
+    ) -> SUResult<Self> {
+        let buf = Self::connect_to_dev_with_seg_size(dev_root, block_size, seg_size, evict)?;
+        let seg_size = buf.seg_size;
+        let mut recovered = BTreeMap::new();
+        for entry in walkdir::WalkDir::new(buf.dev_dirs[0].as_path()) {
+            let meta_path = entry.map_err(SUError::other)?.into_path();
+            if !meta_path.is_file() || meta_path.extension() != Some(META_EXTENSION.as_ref()) {
+                continue;
+            }
+            let block_path = meta_path.with_extension("");
+            let (seg_map, ranges) = read_seg_meta(meta_path.as_path())?;
+            let record_len = u64::try_from(seg_map.len() * seg_size).unwrap();
+            let f = std::fs::File::options()
+                .write(true)
+                .open(block_path.as_path())?;
+            if f.metadata()?.len() < record_len
+                || seg_map
+                    .keys()
+                    .any(|seg_id| *seg_id >= buf.block_size / seg_size)
+                || ranges.to_ranges().into_iter().any(|range| {
+                    (range.start / seg_size..range.end.div_ceil(seg_size))
+                        .any(|seg_id| !seg_map.contains_key(&seg_id))
+                })
+            {
+                return Err(SUError::other(format!(
+                    "slice buffer metadata does not match block file: {}",
+                    block_path.display()
+                )));
+            }
+            // drop the segments appended but not recorded
+            f.set_len(record_len)?;
+            recovered.insert(block_path_to_id(block_path.as_path()), (seg_map, ranges));
+        }
+        for (block_id, (_, ranges)) in recovered.iter() {
+            for range in ranges.to_ranges() {
+                if buf.evict.push(*block_id, range).is_some() {
+                    return Err(SUError::other(
+                        "recovered slices exceed the capacity of the eviction strategy",
+                    ));
+                }
+            }
+        }
+        buf.seg_maps[0].replace(
+            recovered
+                .into_iter()
+                .map(|(block_id, (seg_map, _))| (block_id, seg_map))
+                .collect(),
+        );
+        Ok(buf)
+    }
+
+    /// Get the modified ranges of a block buffered, without evicting it.
+    ///
+    /// # Return
+    /// - [`Some`] the union of the ranges pushed to the block
+    /// - [`None`] if the block is not buffered
+    pub fn modified_ranges(&self, block_id: BlockId) -> Option<RangeSet> {
+        self.evict.get(block_id)
+    }
 }
 
 impl FixedSizeSliceBuf<MostModifiedBlockEvict> {
@@ -74,18 +389,42 @@ impl FixedSizeSliceBuf<MostModifiedBlockEvict> {
         block_size: NonZeroUsize,
         capacity: NonZeroUsize,
     ) -> SUResult<Self> {
-        let dev_root = dev_root.into();
-        if !dev_root.exists() {
-            return Err(SUError::Io(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "dev path not found",
-            )));
-        }
-        Ok(Self {
-            evict: MostModifiedBlockEvict::with_max_size(capacity),
-            dev_dir: dev_root,
-            block_size: block_size.get(),
-            seg_map: Default::default(),
+        Self::connect_to_dev_with_evict(
+            dev_root,
+            block_size,
+            MostModifiedBlockEvict::with_max_size(capacity),
+        )
+    }
+
+    /// Connect to several devs with a buffer of `capacity` bytes in total,
+    /// see [`FixedSizeSliceBuf::connect_to_devs_with_evict`].
+    pub fn connect_to_devs(
+        dev_roots: &[PathBuf],
+        block_size: NonZeroUsize,
+        capacity: NonZeroUsize,
+    ) -> SUResult<Self> {
+        Self::connect_to_devs_with_evict(
+            dev_roots,
+            block_size,
+            MostModifiedBlockEvict::with_max_size(capacity),
+        )
+    }
+}
+
+impl FixedSizeSliceBuf<NonEvict> {
+    /// Connect to the dev in write-through mode, where nothing is held in the buffer:
+    /// each [`super::SliceBuffer::push_slice`] returns the pushed slice as an eviction at once,
+    /// for the caller to persist it right away. This disables the buffering while keeping the same API.
+    ///
+    /// # Error
+    /// - [`SUError::Io`] if `dev_root` is not found
+    pub fn connect_to_dev_write_through(
+        dev_root: impl Into<PathBuf>,
+        block_size: NonZeroUsize,
+    ) -> SUResult<Self> {
+        Self::connect_to_dev_with_evict(dev_root, block_size, NonEvict::default()).map(|mut buf| {
+            buf.write_through = true;
+            buf
         })
     }
 }
@@ -95,28 +434,49 @@ where
     E: std::fmt::Debug,
 {
     /// Make an eviction from the block id.
-    /// The record file and the log for this block will also be removed.
+    /// The record file and the metadata for this block will also be removed.
+    ///
+    /// Each segment is split into present and absent slices by the modified `ranges`,
+    /// so a fully modified segment is a single present slice.
     ///
     /// # Panics
     /// - Any underlying os error occurs.
     fn make_buffer_eviction(&self, block_id: BlockId, ranges: RangeSet) -> BufferEviction {
-        let seg_map = self.seg_map.borrow_mut().remove(&block_id).unwrap();
-        let path = super::block_id_to_path(self.dev_dir.to_owned(), block_id);
+        self.op_latency.inject();
+        let seg_size = self.seg_size;
+        let seg_map = self
+            .seg_map_of(block_id)
+            .borrow_mut()
+            .remove(&block_id)
+            .unwrap();
+        let path = self.block_path(block_id);
         let mut f = std::fs::File::open(path.as_path()).unwrap();
-        let mut buf = bytes::BytesMut::zeroed(ranges.len());
-        let mut slices: Vec<SliceOpt> =
-            vec![SliceOpt::Absent(SEG_SIZE); self.block_size / SEG_SIZE];
-        let mut segs = seg_map
-            .into_iter()
-            .map(|(id, record_index)| (record_index, id))
-            .collect::<Vec<_>>();
-        segs.sort_unstable_by_key(|(record_index, _)| *record_index);
-        assert!(segs.iter().enumerate().all(|(i, (idx, _))| i == *idx));
-        segs.iter().for_each(|(_record_index, seg_id)| {
-            let mut slice_buf = buf.split_to(SEG_SIZE);
-            f.read_exact(&mut slice_buf).unwrap();
-            slices[*seg_id] = SliceOpt::Present(slice_buf.freeze());
+        let mut buf = bytes::BytesMut::zeroed(seg_map.len() * seg_size);
+        f.read_exact(&mut buf).unwrap();
+        let buf = buf.freeze();
+        let mut slices: Vec<SliceOpt> = Vec::with_capacity(self.block_size / seg_size);
+        (0..self.block_size / seg_size).for_each(|seg_id| {
+            let seg_range = seg_id * seg_size..(seg_id + 1) * seg_size;
+            let mut cursor = seg_range.start;
+            ranges
+                .intersection(&RangeSet::from(&[seg_range.clone()][..]))
+                .to_ranges()
+                .into_iter()
+                .for_each(|range| {
+                    if range.start > cursor {
+                        slices.push(SliceOpt::Absent(range.start - cursor));
+                    }
+                    let record_offset = seg_map[&seg_id] * seg_size + range.start - seg_range.start;
+                    slices.push(SliceOpt::Present(
+                        buf.slice(record_offset..record_offset + range.len()),
+                    ));
+                    cursor = range.end;
+                });
+            if cursor < seg_range.end {
+                slices.push(SliceOpt::Absent(seg_range.end - cursor));
+            }
         });
+        std::fs::remove_file(meta_path(path.as_path())).unwrap();
         std::fs::remove_file(path).unwrap();
         BufferEviction {
             block_id,
@@ -128,48 +488,100 @@ where
     }
 }
 
+impl<E> FixedSizeSliceBuf<E>
+where
+    E: std::fmt::Debug,
+{
+    /// Extend the slice at `slice_range` to the segments it covers.
+    /// The uncovered parts of the head and tail segments are read from the buffered segments,
+    /// or zero-filled if the segments are not buffered, which are never regarded as modified.
+    ///
+    /// # Return
+    /// The data of the covered segments, borrowed if the slice is already aligned
+    fn align_to_segments<'a>(
+        &self,
+        block_id: BlockId,
+        slice_range: Range<usize>,
+        slice_data: &'a [u8],
+    ) -> SUResult<Cow<'a, [u8]>> {
+        let seg_size = self.seg_size;
+        if slice_range.start % seg_size == 0 && slice_range.end % seg_size == 0 {
+            return Ok(Cow::Borrowed(slice_data));
+        }
+        let seg_start = slice_range.start / seg_size;
+        let seg_end = slice_range.end.div_ceil(seg_size);
+        let mut seg_data = vec![0_u8; (seg_end - seg_start) * seg_size];
+        if let Some(seg_map) = self.seg_map_of(block_id).borrow().get(&block_id) {
+            let path = self.block_path(block_id);
+            let f = std::fs::File::open(path.as_path())?;
+            // read-modify-write the partially covered head and tail segments
+            for (seg_id, buf_offset) in [(seg_start, 0), (seg_end - 1, seg_data.len() - seg_size)] {
+                if let Some(record_idx) = seg_map.get(&seg_id) {
+                    f.read_exact_at(
+                        &mut seg_data[buf_offset..buf_offset + seg_size],
+                        u64::try_from(record_idx * seg_size).unwrap(),
+                    )?;
+                }
+            }
+        }
+        let head = slice_range.start - seg_start * seg_size;
+        seg_data[head..head + slice_data.len()].copy_from_slice(slice_data);
+        Ok(Cow::Owned(seg_data))
+    }
+}
+
 impl<E> Drop for FixedSizeSliceBuf<E>
 where
     E: std::fmt::Debug,
 {
     fn drop(&mut self) {
-        self.cleanup_dev().unwrap_or_else(|e| {
-            eprintln!(
-                "fail to clean up dev root:{}, error: {e}",
-                self.dev_dir.display()
-            )
+        self.prune_empty_dirs().unwrap_or_else(|e| {
+            log::error!("fail to clean up dev roots:{:?}, error: {e}", self.dev_dirs)
         });
     }
 }
 
-impl<E> super::SliceBuffer for FixedSizeSliceBuf<E>
+impl<E> FixedSizeSliceBuf<E>
 where
     E: EvictStrategySlice,
 {
-    fn push_slice(
+    /// Push a slice, and evict the blocks chosen by `evict` with the range of the slice.
+    fn push_slice_with(
         &self,
         block_id: BlockId,
         inner_block_offset: usize,
         slice_data: &[u8],
-    ) -> SUResult<Option<super::BufferEviction>> {
-        // assert the slice is aligned with segment size
+        evict: impl FnOnce(&E, BlockId, Range<usize>) -> Evictions,
+    ) -> SUResult<Vec<BufferEviction>> {
         let slice_range = inner_block_offset..inner_block_offset + slice_data.len();
-        let seg_range = slice_range.start / SEG_SIZE..slice_range.end / SEG_SIZE;
-        assert_eq!(slice_range.start % SEG_SIZE, 0);
-        assert_eq!(slice_range.end % SEG_SIZE, 0);
-        let eviction = self.evict.push(block_id, slice_range.clone());
+        if slice_range.is_empty() {
+            return Ok(Vec::new());
+        }
+        check_slice_range(
+            file!(),
+            line!(),
+            column!(),
+            slice_range.clone(),
+            self.block_size,
+        )?;
+        let seg_size = self.seg_size;
+        let seg_range = slice_range.start / seg_size..slice_range.end.div_ceil(seg_size);
+        let slice_data = self.align_to_segments(block_id, slice_range.clone(), slice_data)?;
+        self.op_latency.inject();
+        let evictions = evict(&self.evict, block_id, slice_range.clone());
         // put data
-        let mut update_buf_map = self.seg_map.borrow_mut();
+        let mut update_buf_map = self.seg_map_of(block_id).borrow_mut();
         if cfg!(debug_assertions) {
-            // check map and storage is consistent
+            // check map and storage of the dev is consistent
+            let dev_dir = self.dev_of(block_id);
             let map_path = update_buf_map
-                .iter()
-                .map(|(id, _)| block_id_to_path(self.dev_dir.as_path(), *id))
+                .keys()
+                .map(|id| block_id_to_path(dev_dir, *id))
                 .collect::<std::collections::BTreeSet<_>>();
-            let storage = walkdir::WalkDir::new(self.dev_dir.as_path())
+            let storage = walkdir::WalkDir::new(dev_dir)
                 .into_iter()
                 .map(|p| p.unwrap().path().to_path_buf())
-                .filter(|p| p.is_file())
+                .filter(|p| p.is_file() && p.extension() != Some(META_EXTENSION.as_ref()))
                 .collect::<std::collections::BTreeSet<_>>();
             let diff = map_path
                 .difference(&storage)
@@ -190,7 +602,7 @@ where
                 diff.first().unwrap()
             );
         }
-        let path = super::block_id_to_path(self.dev_dir.to_owned(), block_id);
+        let path = self.block_path(block_id);
         if let Some(map_record) = update_buf_map.get_mut(&block_id) {
             let mut f = std::fs::File::options()
                 .read(true)
@@ -198,13 +610,13 @@ where
                 .open(path.as_path())
                 .unwrap();
             slice_data
-                .chunks_exact(SEG_SIZE)
+                .chunks_exact(seg_size)
                 .zip(seg_range)
                 .try_for_each(|(data, seg_id)| {
                     if let Some(idx) = map_record.get(&seg_id) {
                         // existing segment, update
                         f.seek(std::io::SeekFrom::Start(
-                            u64::try_from(*idx * SEG_SIZE).unwrap(),
+                            u64::try_from(*idx * seg_size).unwrap(),
                         ))
                         .unwrap();
                         f.write_all(data)?;
@@ -232,12 +644,65 @@ where
                 .create_new(true)
                 .write(true)
                 .read(true)
-                .open(path)
+                .open(path.as_path())
                 .unwrap();
-            f.write_all(slice_data)?;
+            f.write_all(&slice_data)?;
         }
+        write_seg_meta(
+            path.as_path(),
+            update_buf_map.get(&block_id).unwrap(),
+            &self.evict.get(block_id).unwrap_or_default(),
+        )?;
         drop(update_buf_map);
-        Ok(eviction.map(|evict| self.make_buffer_eviction(evict.0, evict.1)))
+        if self.write_through {
+            // the block holds nothing but the pushed slice, which is evicted at once
+            debug_assert!(evictions.is_empty());
+            return Ok(self
+                .evict
+                .pop_with_id(block_id)
+                .map(|ranges| self.make_buffer_eviction(block_id, ranges))
+                .into_iter()
+                .collect());
+        }
+        Ok(evictions
+            .into_iter()
+            .map(|(block_id, ranges)| self.make_buffer_eviction(block_id, ranges))
+            .collect())
+    }
+}
+
+impl<E> super::SliceBuffer for FixedSizeSliceBuf<E>
+where
+    E: EvictStrategySlice,
+{
+    fn push_slice(
+        &self,
+        block_id: BlockId,
+        inner_block_offset: usize,
+        slice_data: &[u8],
+    ) -> SUResult<Option<super::BufferEviction>> {
+        let evictions = self.push_slice_with(
+            block_id,
+            inner_block_offset,
+            slice_data,
+            |evict, id, range| evict.push(id, range).into_iter().collect(),
+        )?;
+        debug_assert!(evictions.len() <= 1);
+        Ok(evictions.into_iter().next())
+    }
+
+    fn push_slice_batch(
+        &self,
+        block_id: BlockId,
+        inner_block_offset: usize,
+        slice_data: &[u8],
+    ) -> SUResult<Vec<BufferEviction>> {
+        self.push_slice_with(
+            block_id,
+            inner_block_offset,
+            slice_data,
+            EvictStrategySlice::push_batch,
+        )
     }
 
     fn pop(&self) -> Option<super::BufferEviction> {
@@ -255,6 +720,10 @@ where
             .pop_with_id(block_id)
             .map(|evict| self.make_buffer_eviction(block_id, evict))
     }
+
+    fn peek_next_evict(&self) -> Option<BlockId> {
+        self.evict.peek_first()
+    }
 }
 
 #[cfg(test)]
@@ -263,9 +732,12 @@ mod test {
 
     use rand::Rng;
 
-    use crate::storage::{
-        utility::block_id_to_path, BlockId, BufferEviction, EvictStrategySlice, PartialBlock,
-        SliceBuffer,
+    use crate::{
+        storage::{
+            utility::block_id_to_path, BlockId, BlockStorage, BufferEviction, EvictStrategySlice,
+            MemStorage, MostModifiedBlockEvict, PartialBlock, SliceBuffer, SliceOpt,
+        },
+        SUError,
     };
 
     use super::{FixedSizeSliceBuf, SEG_SIZE};
@@ -350,6 +822,468 @@ mod test {
         assert!(slice_buf.evict.is_empty());
     }
 
+    #[test]
+    fn striped_devs() {
+        const DEV_NUM: usize = 3;
+        let tempdirs = (0..DEV_NUM)
+            .map(|_| tempfile::tempdir().unwrap())
+            .collect::<Vec<_>>();
+        let dev_roots = tempdirs
+            .iter()
+            .map(|dir| dir.path().to_path_buf())
+            .collect::<Vec<_>>();
+        let slice_buf =
+            FixedSizeSliceBuf::connect_to_devs(&dev_roots, BLOCK_SIZE, CAPACITY).unwrap();
+        // each block is stored in the dev it hashes to only
+        let mut blocks = vec![vec![0_u8; BLOCK_SIZE.get()]; BLOCK_NUM];
+        (0..BLOCK_NUM).for_each(|block_id| {
+            let slice_data = vec![u8::try_from(block_id).unwrap() + 1; SLICE_SIZE];
+            blocks[block_id][..SLICE_SIZE].copy_from_slice(&slice_data);
+            assert!(slice_buf
+                .push_slice(block_id, 0, &slice_data)
+                .unwrap()
+                .is_none());
+            let dev = slice_buf.dev_of(block_id);
+            dev_roots.iter().for_each(|dev_root| {
+                assert_eq!(
+                    block_id_to_path(dev_root, block_id).exists(),
+                    dev_root == dev,
+                    "block {block_id}"
+                );
+            });
+        });
+        assert!(dev_roots
+            .iter()
+            .all(|dev_root| (0..BLOCK_NUM).any(|block_id| slice_buf.dev_of(block_id) == dev_root)));
+        // the capacity bounds the slices buffered across the devs
+        assert_eq!(slice_buf.len(), BLOCK_NUM * SLICE_SIZE);
+        let check_evict = |blocks: &Vec<Vec<u8>>, BufferEviction { block_id, data }| {
+            assert!(!block_id_to_path(slice_buf.dev_of(block_id), block_id).exists());
+            let mut offset = 0;
+            data.slices.iter().for_each(|slice| match slice {
+                SliceOpt::Present(slice) => {
+                    assert_eq!(slice[..], blocks[block_id][offset..offset + slice.len()]);
+                    offset += slice.len();
+                }
+                SliceOpt::Absent(size) => offset += size,
+            });
+            assert_eq!(offset, data.size);
+        };
+        let mut evicted = 0;
+        (0..BLOCK_NUM).for_each(|block_id| {
+            let range = SLICE_SIZE..BLOCK_SIZE.get();
+            let slice_data = rand::thread_rng()
+                .sample_iter(rand::distributions::Standard)
+                .take(range.len())
+                .collect::<Vec<u8>>();
+            blocks[block_id][range.clone()].copy_from_slice(&slice_data);
+            if let Some(eviction) = slice_buf
+                .push_slice(block_id, range.start, &slice_data)
+                .unwrap()
+            {
+                check_evict(&blocks, eviction);
+                evicted += 1;
+            }
+        });
+        assert!(evicted > 0);
+        assert!(slice_buf.len() <= CAPACITY.get());
+        slice_buf.drain_into(|eviction| check_evict(&blocks, eviction));
+        assert!(slice_buf.is_empty());
+        // no dev
+        assert!(matches!(
+            FixedSizeSliceBuf::connect_to_devs(&[], BLOCK_SIZE, CAPACITY),
+            Err(SUError::InvalidArg(_))
+        ));
+    }
+
     #[test]
     fn fixed_size_buf_error_handle() {}
+
+    #[test]
+    fn unaligned_slices() {
+        let tempfile = tempfile::tempdir().unwrap();
+        let slice_buf =
+            FixedSizeSliceBuf::connect_to_dev(tempfile.path(), BLOCK_SIZE, CAPACITY).unwrap();
+        let mut expect = vec![None::<u8>; BLOCK_SIZE.get()];
+        [
+            // unaligned start
+            (SEG_SIZE + 100, SEG_SIZE - 100),
+            // unaligned end
+            (4 * SEG_SIZE, SEG_SIZE + 10),
+            // sub-segment
+            (7 * SEG_SIZE + 10, 20),
+            // sub-segment overlapping the buffered ones
+            (SEG_SIZE + 50, 100),
+            (5 * SEG_SIZE, 20),
+            // unaligned both, across segments
+            (9 * SEG_SIZE - 1, 2 * SEG_SIZE + 2),
+        ]
+        .into_iter()
+        .for_each(|(offset, len)| {
+            let slice_data = rand::thread_rng()
+                .sample_iter(rand::distributions::Standard)
+                .take(len)
+                .collect::<Vec<u8>>();
+            assert!(slice_buf
+                .push_slice(0, offset, &slice_data)
+                .unwrap()
+                .is_none());
+            expect[offset..offset + len]
+                .iter_mut()
+                .zip(slice_data)
+                .for_each(|(a, b)| *a = Some(b));
+        });
+        assert_eq!(slice_buf.len(), expect.iter().flatten().count());
+        // out of the block
+        assert!(matches!(
+            slice_buf.push_slice(0, BLOCK_SIZE.get() - 1, &[0, 0]),
+            Err(crate::SUError::Range(_))
+        ));
+        let BufferEviction { block_id, data } = slice_buf.pop().unwrap();
+        assert_eq!(block_id, 0);
+        assert_eq!(data.size, BLOCK_SIZE.get());
+        let mut offset = 0;
+        data.slices.iter().for_each(|slice| match slice {
+            SliceOpt::Present(slice) => {
+                expect[offset..offset + slice.len()]
+                    .iter()
+                    .zip(slice.iter())
+                    .for_each(|(a, b)| assert_eq!(a, &Some(*b), "offset {offset}"));
+                offset += slice.len();
+            }
+            SliceOpt::Absent(size) => {
+                assert!(expect[offset..offset + size].iter().all(Option::is_none));
+                offset += size;
+            }
+        });
+        assert_eq!(offset, BLOCK_SIZE.get());
+    }
+
+    #[test]
+    fn write_through() {
+        let tempfile = tempfile::tempdir().unwrap();
+        let slice_buf =
+            FixedSizeSliceBuf::connect_to_dev_write_through(tempfile.path(), BLOCK_SIZE).unwrap();
+        (0..TEST_LOAD).for_each(|_| {
+            let block_id: BlockId = rand::thread_rng().gen_range(0..BLOCK_NUM);
+            let offset = rand::thread_rng().gen_range(0..BLOCK_SIZE.get());
+            let len = rand::thread_rng().gen_range(1..=BLOCK_SIZE.get() - offset);
+            let slice_data = rand::thread_rng()
+                .sample_iter(rand::distributions::Standard)
+                .take(len)
+                .collect::<Vec<u8>>();
+            let BufferEviction {
+                block_id: evict_id,
+                data,
+            } = slice_buf
+                .push_slice(block_id, offset, &slice_data)
+                .unwrap()
+                .expect("pushed slice not evicted");
+            assert_eq!(evict_id, block_id);
+            assert_eq!(data.size, BLOCK_SIZE.get());
+            // exactly the pushed range is present
+            let mut present = Vec::new();
+            let mut cursor = 0;
+            data.slices.iter().for_each(|slice| match slice {
+                SliceOpt::Present(slice) => {
+                    assert!((offset..offset + len).contains(&cursor));
+                    present.extend_from_slice(slice);
+                    cursor += slice.len();
+                }
+                SliceOpt::Absent(size) => {
+                    assert!(cursor + size <= offset || cursor >= offset + len);
+                    cursor += size;
+                }
+            });
+            assert_eq!(cursor, BLOCK_SIZE.get());
+            assert_eq!(present, slice_data);
+            assert_eq!(slice_buf.len(), 0);
+            assert!(!block_id_to_path(tempfile.path(), block_id).exists());
+        });
+        assert!(slice_buf.pop().is_none());
+    }
+
+    #[test]
+    fn push_slice_batch() {
+        let tempfile = tempfile::tempdir().unwrap();
+        let slice_buf = FixedSizeSliceBuf::connect_to_dev_with_evict(
+            tempfile.path(),
+            BLOCK_SIZE,
+            MostModifiedBlockEvict::with_watermarks(BLOCK_SIZE.get(), CAPACITY),
+        )
+        .unwrap();
+        let block_cap = CAPACITY.get() / BLOCK_SIZE.get();
+        (0..block_cap).for_each(|block_id| {
+            let evictions = slice_buf
+                .push_slice_batch(block_id, 0, &vec![block_id as u8; BLOCK_SIZE.get()])
+                .unwrap();
+            assert!(evictions.is_empty());
+        });
+        assert_eq!(slice_buf.len(), CAPACITY.get());
+        // exceeds the capacity, drains all the full blocks down to the low watermark at once
+        let evictions = slice_buf
+            .push_slice_batch(block_cap, 0, &[0xff; SLICE_SIZE])
+            .unwrap();
+        assert_eq!(evictions.len(), block_cap);
+        assert_eq!(slice_buf.len(), SLICE_SIZE);
+        let mut evicted = evictions
+            .into_iter()
+            .map(|BufferEviction { block_id, data }| {
+                assert!(!block_id_to_path(tempfile.path(), block_id).exists());
+                assert!(data.slices.iter().all(|slice| matches!(
+                    slice,
+                    SliceOpt::Present(data) if data.iter().all(|&b| b == block_id as u8)
+                )));
+                block_id
+            })
+            .collect::<Vec<_>>();
+        evicted.sort_unstable();
+        assert_eq!(evicted, (0..block_cap).collect::<Vec<_>>());
+        assert!(slice_buf.push_slice_batch(0, 0, &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn seg_size() {
+        const SEG_SIZE_8K: NonZeroUsize = NonZeroUsize::new(8 << 10).unwrap();
+        let tempfile = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            FixedSizeSliceBuf::connect_to_dev_with_seg_size(
+                tempfile.path(),
+                BLOCK_SIZE,
+                NonZeroUsize::new(3 << 10).unwrap(),
+                MostModifiedBlockEvict::with_max_size(CAPACITY),
+            ),
+            Err(crate::SUError::InvalidArg(_))
+        ));
+        let slice_buf = FixedSizeSliceBuf::connect_to_dev_with_seg_size(
+            tempfile.path(),
+            BLOCK_SIZE,
+            SEG_SIZE_8K,
+            MostModifiedBlockEvict::with_max_size(CAPACITY),
+        )
+        .unwrap();
+        let seg_size = SEG_SIZE_8K.get();
+        // two full segments, and part of the third segment
+        assert!(slice_buf
+            .push_slice(0, 0, &vec![1_u8; 2 * seg_size])
+            .unwrap()
+            .is_none());
+        assert!(slice_buf
+            .push_slice(0, 2 * seg_size + SEG_SIZE, &[2_u8; 2048])
+            .unwrap()
+            .is_none());
+        let BufferEviction { data, .. } = slice_buf.pop().unwrap();
+        let sizes = data
+            .slices
+            .iter()
+            .map(|slice| match slice {
+                SliceOpt::Present(data) => (true, data.len()),
+                SliceOpt::Absent(size) => (false, *size),
+            })
+            .collect::<Vec<_>>();
+        let mut expect = vec![
+            (true, seg_size),
+            (true, seg_size),
+            (false, SEG_SIZE),
+            (true, 2048),
+            (false, SEG_SIZE - 2048),
+        ];
+        expect.extend(vec![(false, seg_size); BLOCK_SIZE.get() / seg_size - 3]);
+        assert_eq!(sizes, expect);
+    }
+
+    #[test]
+    fn cleanup_dev() {
+        let tempfile = tempfile::tempdir().unwrap();
+        let slice_buf =
+            FixedSizeSliceBuf::connect_to_dev(tempfile.path(), BLOCK_SIZE, CAPACITY).unwrap();
+        // blocks sharing the same sharding directory, and in different ones
+        [0, 1, 2, 0x100, 0x1234].into_iter().for_each(|block_id| {
+            assert!(slice_buf
+                .push_slice(block_id, 0, &[0_u8; SEG_SIZE])
+                .unwrap()
+                .is_none());
+        });
+        assert!(
+            std::fs::read_dir(block_id_to_path(tempfile.path(), 0).parent().unwrap())
+                .unwrap()
+                .count()
+                > 2
+        );
+        // files left by others
+        std::fs::create_dir_all(tempfile.path().join("a").join("b")).unwrap();
+        std::fs::write(tempfile.path().join("a").join("b").join("c"), b"").unwrap();
+        std::fs::write(tempfile.path().join("top-level"), b"").unwrap();
+        slice_buf.cleanup_dev().unwrap();
+        assert!(tempfile.path().is_dir());
+        assert_eq!(std::fs::read_dir(tempfile.path()).unwrap().count(), 0);
+        assert!(slice_buf.is_empty());
+        assert!(slice_buf.pop().is_none());
+        // still usable
+        assert!(slice_buf
+            .push_slice(0, 0, &[0_u8; SEG_SIZE])
+            .unwrap()
+            .is_none());
+        assert_eq!(slice_buf.pop().unwrap().block_id, 0);
+    }
+
+    #[test]
+    fn modified_ranges() {
+        let tempfile = tempfile::tempdir().unwrap();
+        let slice_buf =
+            FixedSizeSliceBuf::connect_to_dev(tempfile.path(), BLOCK_SIZE, CAPACITY).unwrap();
+        assert!(slice_buf.modified_ranges(3).is_none());
+        [(0, 1), (2, 2), (3, 2), (10, 1), (1, 1)]
+            .into_iter()
+            .for_each(|(seg_id, seg_num)| {
+                let slice_data = vec![0_u8; seg_num * SEG_SIZE];
+                assert!(slice_buf
+                    .push_slice(3, seg_id * SEG_SIZE, &slice_data)
+                    .unwrap()
+                    .is_none());
+            });
+        assert_eq!(
+            slice_buf.modified_ranges(3).unwrap().to_ranges(),
+            vec![0..5 * SEG_SIZE, 10 * SEG_SIZE..11 * SEG_SIZE]
+        );
+        assert!(slice_buf.modified_ranges(4).is_none());
+        // not evicted by the query
+        assert_eq!(slice_buf.len(), 6 * SEG_SIZE);
+        assert_eq!(slice_buf.pop().unwrap().block_id, 3);
+        assert!(slice_buf.modified_ranges(3).is_none());
+    }
+
+    #[test]
+    fn fixed_size_buf_recover() {
+        const RECOVER_BLOCK_NUM: usize = 8;
+        let dev = tempfile::tempdir().unwrap();
+        let crash_dev = tempfile::tempdir().unwrap();
+        let slice_buf =
+            FixedSizeSliceBuf::connect_to_dev(dev.path(), BLOCK_SIZE, CAPACITY).unwrap();
+        let crash_buf =
+            FixedSizeSliceBuf::connect_to_dev(crash_dev.path(), BLOCK_SIZE, CAPACITY).unwrap();
+        // the i-th block is modified with i+1 segments in a shuffled order,
+        // so that the eviction order is deterministic
+        (0..RECOVER_BLOCK_NUM)
+            .flat_map(|block_id| {
+                rand::seq::index::sample(
+                    &mut rand::thread_rng(),
+                    BLOCK_SIZE.get() / SEG_SIZE,
+                    block_id + 1,
+                )
+                .into_iter()
+                .map(move |seg_id| (block_id, seg_id))
+            })
+            .for_each(|(block_id, seg_id)| {
+                let slice_data = rand::thread_rng()
+                    .sample_iter(rand::distributions::Standard)
+                    .take(SEG_SIZE)
+                    .collect::<Vec<u8>>();
+                // push twice to update the segment in place
+                (0..2).for_each(|_| {
+                    assert!(slice_buf
+                        .push_slice(block_id, seg_id * SEG_SIZE, &slice_data)
+                        .unwrap()
+                        .is_none());
+                    assert!(crash_buf
+                        .push_slice(block_id, seg_id * SEG_SIZE, &slice_data)
+                        .unwrap()
+                        .is_none());
+                });
+            });
+        // an unaligned slice, recovered with its exact range
+        let slice_data = vec![0xAB_u8; 100];
+        assert!(slice_buf.push_slice(0, 10, &slice_data).unwrap().is_none());
+        assert!(crash_buf.push_slice(0, 10, &slice_data).unwrap().is_none());
+        // drop without popping the buffered slices
+        drop(crash_buf);
+        let recovered_buf = FixedSizeSliceBuf::recover_from_dev(
+            crash_dev.path(),
+            BLOCK_SIZE,
+            MostModifiedBlockEvict::with_max_size(CAPACITY),
+        )
+        .unwrap();
+        assert_eq!(recovered_buf.len(), slice_buf.len());
+        while let Some(expect) = slice_buf.pop() {
+            let recovered = recovered_buf.pop().unwrap();
+            assert_eq!(recovered.block_id, expect.block_id);
+            assert_eq!(recovered.data.size, expect.data.size);
+            recovered
+                .data
+                .slices
+                .iter()
+                .zip(expect.data.slices.iter())
+                .for_each(|(recovered, expect)| match (recovered, expect) {
+                    (SliceOpt::Present(recovered), SliceOpt::Present(expect)) => {
+                        assert_eq!(recovered, expect)
+                    }
+                    (SliceOpt::Absent(recovered), SliceOpt::Absent(expect)) => {
+                        assert_eq!(recovered, expect)
+                    }
+                    _ => panic!("slice presence mismatch"),
+                });
+        }
+        assert!(recovered_buf.pop().is_none());
+    }
+
+    #[test]
+    fn fixed_size_buf_op_latency() {
+        const LATENCY: std::time::Duration = std::time::Duration::from_millis(20);
+        let tempfile = tempfile::tempdir().unwrap();
+        let slice_buf = FixedSizeSliceBuf::connect_to_dev(tempfile.path(), BLOCK_SIZE, CAPACITY)
+            .unwrap()
+            .with_op_latency(Some(LATENCY));
+        let slice = vec![0_u8; SLICE_SIZE];
+        let now = std::time::Instant::now();
+        let evict = slice_buf.push_slice(0, 0, &slice).unwrap();
+        assert!(evict.is_none());
+        assert!(now.elapsed() >= LATENCY);
+        let now = std::time::Instant::now();
+        slice_buf.pop_one(0).unwrap();
+        assert!(now.elapsed() >= LATENCY);
+    }
+
+    #[test]
+    fn drain_to_storage() {
+        const DRAIN_BLOCK_NUM: usize = CAPACITY.get() / BLOCK_SIZE.get();
+        let tempfile = tempfile::tempdir().unwrap();
+        let slice_buf =
+            FixedSizeSliceBuf::connect_to_dev(tempfile.path(), BLOCK_SIZE, CAPACITY).unwrap();
+        let mem_store = MemStorage::new(BLOCK_SIZE);
+        let mut expect = vec![vec![0_u8; BLOCK_SIZE.get()]; DRAIN_BLOCK_NUM];
+        expect.iter().enumerate().for_each(|(block_id, block)| {
+            mem_store.put_block(block_id, block).unwrap();
+        });
+        (0..TEST_LOAD / 4).for_each(|_| {
+            let block_id = rand::thread_rng().gen_range(0..DRAIN_BLOCK_NUM);
+            let offset = rand::thread_rng().gen_range(0..BLOCK_SIZE.get() - SLICE_SIZE);
+            let slice = rand::thread_rng()
+                .sample_iter(rand::distributions::Standard)
+                .take(SLICE_SIZE)
+                .collect::<Vec<u8>>();
+            expect[block_id][offset..offset + SLICE_SIZE].copy_from_slice(&slice);
+            assert!(slice_buf
+                .push_slice(block_id, offset, &slice)
+                .unwrap()
+                .is_none());
+        });
+        slice_buf.drain_to_storage(&mem_store).unwrap();
+        assert!(slice_buf.is_empty());
+        assert!(slice_buf.pop().is_none());
+        expect.iter().enumerate().for_each(|(block_id, block)| {
+            assert_eq!(
+                &mem_store.get_block_owned(block_id).unwrap().unwrap(),
+                block
+            );
+        });
+        // the block of the eviction does not exist
+        slice_buf
+            .push_slice(DRAIN_BLOCK_NUM, 0, &vec![1_u8; SLICE_SIZE])
+            .unwrap();
+        assert!(matches!(
+            slice_buf.drain_to_storage(&mem_store),
+            Err(SUError::Io(_))
+        ));
+        assert!(slice_buf.is_empty());
+    }
 }
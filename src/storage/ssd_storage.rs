@@ -1,9 +1,11 @@
 use std::{
+    collections::HashMap,
     fs::File,
     io::{Read, Seek, Write},
     num::NonZeroUsize,
     os::unix::fs::FileExt,
     path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
 };
 
 use crate::{SUError, SUResult};
@@ -11,18 +13,153 @@ use crate::{SUError, SUResult};
 use super::{
     check_block_range,
     evict::{EvictStrategy, LruEvict},
-    utility::{block_id_to_path, block_path_to_id, check_slice_range},
+    instrument::{self, Layer, Op},
+    utility::{check_slice_range, OpLatency, Sharding},
     BlockId, BlockStorage, HDDStorage, SliceStorage,
 };
 
-pub struct SSDStorage {
+/// An evicted block waiting to be written back, with its eviction sequence number
+type EvictedBlock = (BlockId, u64, Arc<Vec<u8>>);
+/// Evicted blocks not yet written back, with their latest eviction sequence numbers
+type PendingBlocks = Arc<Mutex<HashMap<BlockId, (u64, Arc<Vec<u8>>)>>>;
+
+enum WriteBackMsg {
+    /// Write the blocks back to the next storage layer
+    Batch(Vec<EvictedBlock>),
+    /// Notify when all the previous batches are written back
+    Sync(oneshot::Sender<()>),
+}
+
+/// Write the evicted blocks back to the next storage layer in batches, on a background thread.
+/// The evicted blocks remain readable from `pending` until they are written back.
+struct WriteBack {
+    flush_batch: usize,
+    pending: PendingBlocks,
+    /// evicted blocks not yet sent to the background thread, and the next sequence number
+    batch: Mutex<(Vec<EvictedBlock>, u64)>,
+    sender: Option<mpsc::Sender<WriteBackMsg>>,
+    handle: Option<std::thread::JoinHandle<SUResult<()>>>,
+}
+
+impl WriteBack {
+    fn spawn<N>(next_storage: Arc<N>, flush_batch: NonZeroUsize) -> Self
+    where
+        N: BlockStorage + Send + Sync + 'static,
+    {
+        let pending: PendingBlocks = Default::default();
+        let (sender, receiver) = mpsc::channel();
+        let handle = {
+            let pending = pending.clone();
+            std::thread::spawn(move || {
+                for msg in receiver {
+                    match msg {
+                        WriteBackMsg::Batch(blocks) => {
+                            for (block_id, seq, data) in blocks {
+                                next_storage.put_block(block_id, &data)?;
+                                let mut pending = pending.lock().unwrap();
+                                // the block may be evicted again with newer data
+                                if pending.get(&block_id).is_some_and(|(s, _)| *s == seq) {
+                                    pending.remove(&block_id);
+                                }
+                            }
+                        }
+                        WriteBackMsg::Sync(notify) => {
+                            let _ = notify.send(());
+                        }
+                    }
+                }
+                Ok(())
+            })
+        };
+        Self {
+            flush_batch: flush_batch.get(),
+            pending,
+            batch: Default::default(),
+            sender: Some(sender),
+            handle: Some(handle),
+        }
+    }
+
+    fn send(&self, msg: WriteBackMsg) -> SUResult<()> {
+        self.sender
+            .as_ref()
+            .expect("write back finished")
+            .send(msg)
+            .map_err(|_| SUError::other("write back thread terminated"))
+    }
+
+    /// Send the accumulated blocks to the background thread.
+    fn send_batch(&self, batch: &mut Vec<EvictedBlock>) -> SUResult<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.send(WriteBackMsg::Batch(std::mem::take(batch)))
+    }
+
+    /// Accumulate an evicted block, and send the batch if it is full.
+    fn push(&self, block_id: BlockId, data: Vec<u8>) -> SUResult<()> {
+        let data = Arc::new(data);
+        let mut batch = self.batch.lock().unwrap();
+        let (blocks, seq) = &mut *batch;
+        *seq += 1;
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(block_id, (*seq, data.clone()));
+        blocks.push((block_id, *seq, data));
+        if blocks.len() >= self.flush_batch {
+            self.send_batch(blocks)?;
+        }
+        Ok(())
+    }
+
+    /// Get an evicted block not yet written back.
+    fn get(&self, block_id: BlockId) -> Option<Arc<Vec<u8>>> {
+        self.pending
+            .lock()
+            .unwrap()
+            .get(&block_id)
+            .map(|(_, data)| data.clone())
+    }
+
+    /// Wait until all the evicted blocks are written back.
+    fn sync(&self) -> SUResult<()> {
+        self.send_batch(&mut self.batch.lock().unwrap().0)?;
+        let (notify, wait) = oneshot::channel();
+        self.send(WriteBackMsg::Sync(notify))?;
+        wait.recv()
+            .map_err(|_| SUError::other("write back thread terminated"))
+    }
+
+    /// Write back all the evicted blocks and stop the background thread.
+    fn finish(&mut self) -> SUResult<()> {
+        self.send_batch(&mut self.batch.lock().unwrap().0)?;
+        drop(self.sender.take());
+        self.handle
+            .take()
+            .expect("write back finished")
+            .join()
+            .map_err(|_| SUError::other("write back thread panicked"))?
+    }
+}
+
+/// The eviction state of [`SSDStorage`] is guarded by a mutex,
+/// which is held through the whole block operation,
+/// so that [`SSDStorage`] can be shared among threads.
+///
+/// The blocks evicted from ssd are stored to the next storage layer `N`,
+/// which is an [`HDDStorage`] by default.
+pub struct SSDStorage<N: BlockStorage = HDDStorage> {
     dev: PathBuf,
     block_size: usize,
-    next_storage: HDDStorage,
-    evict: LruEvict<PathBuf>,
+    next_storage: Arc<N>,
+    evict: Mutex<LruEvict<PathBuf>>,
+    op_latency: OpLatency,
+    write_back: Option<WriteBack>,
+    sharding: Sharding,
 }
 
-impl SSDStorage {
+impl<N: BlockStorage> SSDStorage<N> {
     /// Connect the [`SSDStorage`] to a device(supposed to be a SSD device) to store the block.
     /// The number of blocks stored in ssd is bounded,
     /// and some blocks will be evicted to an unbounded storage if the number of block blocks exceeds.
@@ -42,7 +179,7 @@ impl SSDStorage {
         dev_path: PathBuf,
         block_size: NonZeroUsize,
         max_block_num: NonZeroUsize,
-        next_storage: HDDStorage,
+        next_storage: N,
     ) -> SUResult<Self> {
         if !dev_path.exists() {
             return Err(SUError::Io(std::io::Error::new(
@@ -53,11 +190,68 @@ impl SSDStorage {
         Ok(Self {
             dev: dev_path,
             block_size: block_size.get(),
-            next_storage,
-            evict: LruEvict::with_capacity(max_block_num),
+            next_storage: Arc::new(next_storage),
+            evict: Mutex::new(LruEvict::with_capacity(max_block_num)),
+            op_latency: OpLatency::default(),
+            write_back: None,
+            sharding: Sharding::default(),
         })
     }
 
+    /// Connect the [`SSDStorage`] to a device like [`SSDStorage::connect_to_dev`],
+    /// but the evicted blocks are written back to the next storage layer in batches
+    /// on a background thread, instead of inline with the operation causing the eviction.
+    /// The evicted blocks are kept in memory and remain readable until they are written back.
+    ///
+    /// # Parameter
+    /// - `dev_path`: path to the HDD device
+    /// - `block_size`: size of each block to be created
+    /// - `max_block_num`: maximum number of block stored in ssd
+    /// - `next_storage`: the unbounded storage to store the exceeding blocks
+    /// - `flush_batch`: number of the evicted blocks to write back together
+    ///
+    /// # Error
+    /// [`SUError::Io(std::io::ErrorKind::NotFound)`] if `dev_path` not existing
+    pub fn connect_to_dev_batched(
+        dev_path: PathBuf,
+        block_size: NonZeroUsize,
+        max_block_num: NonZeroUsize,
+        next_storage: N,
+        flush_batch: NonZeroUsize,
+    ) -> SUResult<Self>
+    where
+        N: Send + Sync + 'static,
+    {
+        let mut ssd_storage =
+            Self::connect_to_dev(dev_path, block_size, max_block_num, next_storage)?;
+        ssd_storage.write_back = Some(WriteBack::spawn(
+            ssd_storage.next_storage.clone(),
+            flush_batch,
+        ));
+        Ok(ssd_storage)
+    }
+
+    /// Get the next storage layer.
+    /// The evicted blocks pending write back are not stored in it yet if the write back is batched.
+    pub fn next_storage(&self) -> &N {
+        &self.next_storage
+    }
+
+    /// Inject a latency before each block or slice operation on the ssd layer,
+    /// while the latency of the next storage layer is configured on the next storage itself.
+    pub fn with_op_latency(mut self, op_latency: Option<std::time::Duration>) -> Self {
+        self.op_latency = OpLatency::new(op_latency);
+        self
+    }
+
+    /// Shard the block files under the ssd device root with `sharding`,
+    /// [`Sharding::default`] is used if not set.
+    /// The sharding of the next storage layer is configured on the next storage itself.
+    pub fn with_sharding(mut self, sharding: Sharding) -> Self {
+        self.sharding = sharding;
+        self
+    }
+
     /// Open an existing block file.
     /// If the block does not exist in ssd, it will then try to fetch the block in the next storage layer,
     ///
@@ -68,19 +262,29 @@ impl SSDStorage {
     ///
     /// # Note
     /// This method may evict any existing block file to maintain the ssd storage size.
-    fn open_block(&self, block_id: BlockId) -> SUResult<Option<File>> {
-        let block_file_path = block_id_to_path(self.dev.to_owned(), block_id);
-        if self.evict.contains(&block_file_path) {
+    fn open_block(&self, evict: &LruEvict<PathBuf>, block_id: BlockId) -> SUResult<Option<File>> {
+        let block_file_path = self.sharding.block_path(self.dev.to_owned(), block_id);
+        if evict.contains(&block_file_path) {
             let f = File::options()
                 .write(true)
                 .read(true)
                 .open(block_file_path.as_path())?;
             Ok(Some(f))
         } else {
-            // the block does not exist, try to fetch from the next storage layer
+            // the block does not exist, try to fetch from the blocks pending write back,
+            // and then the next storage layer
+            if let Some(block) = self
+                .write_back
+                .as_ref()
+                .and_then(|write_back| write_back.get(block_id))
+            {
+                return self
+                    .make_block_from_data(evict, block_file_path.as_path(), &block)
+                    .map(Some);
+            }
             self.next_storage
                 .get_block_owned(block_id)?
-                .map(|block| self.make_block_from_data(block_file_path.as_path(), &block))
+                .map(|block| self.make_block_from_data(evict, block_file_path.as_path(), &block))
                 .transpose()
         }
     }
@@ -96,13 +300,13 @@ impl SSDStorage {
     ///
     /// # Note
     /// This method may evict any existing block file to maintain the ssd storage size.
-    fn open_or_create_block(&self, block_id: BlockId) -> SUResult<File> {
-        match self.open_block(block_id) {
+    fn open_or_create_block(&self, evict: &LruEvict<PathBuf>, block_id: BlockId) -> SUResult<File> {
+        match self.open_block(evict, block_id) {
             Ok(Some(f)) => Ok(f),
             Ok(None) => {
                 // try to make a new block
-                let block_path = block_id_to_path(self.dev.to_owned(), block_id);
-                self.make_block_zero(block_path.as_path())
+                let block_path = self.sharding.block_path(self.dev.to_owned(), block_id);
+                self.make_block_zero(evict, block_path.as_path())
             }
             Err(e) => Err(e),
         }
@@ -122,7 +326,12 @@ impl SSDStorage {
     ///
     /// # Note
     /// This method may evict existing block file to maintain the ssd size.
-    fn make_block_from_data(&self, block_path: &Path, block_data: &[u8]) -> SUResult<File> {
+    fn make_block_from_data(
+        &self,
+        evict: &LruEvict<PathBuf>,
+        block_path: &Path,
+        block_data: &[u8],
+    ) -> SUResult<File> {
         check_block_range(
             file!(),
             line!(),
@@ -131,7 +340,7 @@ impl SSDStorage {
             self.block_size,
         )?;
         // create a block file and fill it with data
-        let mut f = self.make_block_zero(block_path)?;
+        let mut f = self.make_block_zero(evict, block_path)?;
         f.write_all(block_data)?;
         f.seek(std::io::SeekFrom::Start(0))?;
         Ok(f)
@@ -150,7 +359,7 @@ impl SSDStorage {
     ///
     /// # Note
     /// This method may evict existing block file to maintain the ssd size.
-    fn make_block_zero(&self, block_path: &Path) -> SUResult<File> {
+    fn make_block_zero(&self, evict: &LruEvict<PathBuf>, block_path: &Path) -> SUResult<File> {
         // create a block file
         std::fs::create_dir_all(block_path.parent().unwrap())?;
         let f = File::options()
@@ -161,13 +370,15 @@ impl SSDStorage {
             .unwrap();
         f.set_len(self.block_size.try_into().unwrap())?;
         // evict block file if necessary
-        if let Some(evict) = self.evict.push(block_path.to_owned()) {
-            self.flush_to_next_storage(evict.as_path())?;
+        if let Some(evicted) = evict.push(block_path.to_owned()) {
+            instrument::record_eviction(Layer::Ssd);
+            self.flush_to_next_storage(evicted.as_path())?;
         }
         Ok(f)
     }
 
     /// Remove a block and flush it to the next storage layer.
+    /// If the write back is batched, the block is handed to the background thread instead.
     ///
     /// # Error
     /// - [`SUError::Io`] any io related error when accessing filesystem
@@ -175,8 +386,12 @@ impl SSDStorage {
         let mut evict_file = File::open(path)?;
         let mut evict_data = vec![0_u8; self.block_size];
         evict_file.read_exact(&mut evict_data)?;
-        self.next_storage
-            .put_block(block_path_to_id(path), &evict_data)?;
+        match self.write_back.as_ref() {
+            Some(write_back) => write_back.push(self.sharding.block_id(path), evict_data)?,
+            None => self
+                .next_storage
+                .put_block(self.sharding.block_id(path), &evict_data)?,
+        }
         std::fs::remove_file(path)?;
         Ok(())
     }
@@ -186,8 +401,8 @@ impl SSDStorage {
     ///
     /// # Error
     /// - [`SUError::Io`] any io related error when accessing filesystem
-    fn flush_all_to_next_storage(&self) -> SUResult<()> {
-        while let Some(block_path) = self.evict.pop() {
+    fn flush_all_to_next_storage(&self, evict: &LruEvict<PathBuf>) -> SUResult<()> {
+        while let Some(block_path) = evict.pop() {
             self.flush_to_next_storage(block_path.as_path())?;
         }
         for entry in self.dev.read_dir()?.flatten() {
@@ -200,14 +415,21 @@ impl SSDStorage {
     }
 }
 
-impl Drop for SSDStorage {
+impl<N: BlockStorage> Drop for SSDStorage<N> {
     fn drop(&mut self) {
-        self.flush_all_to_next_storage()
+        // the blocks in ssd are newer than the ones pending write back
+        if let Some(mut write_back) = self.write_back.take() {
+            write_back
+                .finish()
+                .expect("fail to write back the blocks to the next storage");
+        }
+        let evict = self.evict.lock().unwrap();
+        self.flush_all_to_next_storage(&evict)
             .expect("fail to flush the blocks to the next storage");
     }
 }
 
-impl BlockStorage for SSDStorage {
+impl<N: BlockStorage> BlockStorage for SSDStorage<N> {
     /// Storing data to a block.
     /// A new block will be created if the block does not exist.
     ///
@@ -229,7 +451,10 @@ impl BlockStorage for SSDStorage {
             block_data.len(),
             self.block_size,
         )?;
-        let mut f = self.open_or_create_block(block_id)?;
+        let _op = Op::write(Layer::Ssd, None, block_data.len());
+        self.op_latency.inject();
+        let evict = self.evict.lock().unwrap();
+        let mut f = self.open_or_create_block(&evict, block_id)?;
         f.write_all(block_data)?;
         Ok(())
     }
@@ -259,19 +484,44 @@ impl BlockStorage for SSDStorage {
             block_data.len(),
             self.block_size,
         )?;
-        self.open_block(block_id)?
+        let _op = Op::read(Layer::Ssd, None, block_data.len());
+        self.op_latency.inject();
+        let evict = self.evict.lock().unwrap();
+        self.open_block(&evict, block_id)?
             .map(|mut f| f.read_exact(block_data))
             .transpose()
             .map_err(SUError::from)
     }
 
+    /// Remove a block from both the ssd and the next storage layer.
+    ///
+    /// # Return
+    /// - [`Ok(Some)`] on success
+    /// - [`Ok(None)`] on block not existing in either layer
+    /// - [`Err`] on any error occurring
+    fn remove_block(&self, block_id: super::BlockId) -> SUResult<Option<()>> {
+        self.op_latency.inject();
+        let evict = self.evict.lock().unwrap();
+        if let Some(write_back) = self.write_back.as_ref() {
+            // make sure the block is not written back after removal
+            write_back.sync()?;
+        }
+        let block_path = self.sharding.block_path(self.dev.to_owned(), block_id);
+        let in_ssd = evict.remove(&block_path);
+        if in_ssd {
+            std::fs::remove_file(block_path)?;
+        }
+        let in_next = self.next_storage.remove_block(block_id)?.is_some();
+        Ok((in_ssd || in_next).then_some(()))
+    }
+
     /// Get size of a block
     fn block_size(&self) -> usize {
         self.block_size
     }
 }
 
-impl SliceStorage for SSDStorage {
+impl<N: BlockStorage> SliceStorage for SSDStorage<N> {
     fn put_slice(
         &self,
         block_id: super::BlockId,
@@ -287,7 +537,10 @@ impl SliceStorage for SSDStorage {
             slice_range.clone(),
             self.block_size(),
         )?;
-        self.open_block(block_id)?
+        let _op = Op::write(Layer::Ssd, Some(1), slice_data.len());
+        self.op_latency.inject();
+        let evict = self.evict.lock().unwrap();
+        self.open_block(&evict, block_id)?
             .map(|f| f.write_all_at(slice_data, slice_range.start.try_into().unwrap()))
             .transpose()
             .map_err(SUError::from)
@@ -308,7 +561,10 @@ impl SliceStorage for SSDStorage {
             slice_range.clone(),
             self.block_size(),
         )?;
-        self.open_block(block_id)?
+        let _op = Op::read(Layer::Ssd, Some(1), slice_data.len());
+        self.op_latency.inject();
+        let evict = self.evict.lock().unwrap();
+        self.open_block(&evict, block_id)?
             .map(|f| f.read_exact_at(slice_data, slice_range.start.try_into().unwrap()))
             .transpose()
             .map_err(SUError::from)
@@ -321,7 +577,7 @@ mod test {
     use std::num::NonZeroUsize;
 
     use crate::{
-        storage::{BlockStorage, SliceStorage},
+        storage::{BlockStorage, MemStorage, SliceStorage},
         SUError,
     };
 
@@ -585,4 +841,221 @@ mod test {
         let e = ssd_store.put_slice(BLOCK_NUM - 1, 0, &data[0..BLOCK_SIZE + 1]);
         assert!(matches!(e, Err(SUError::Range(_))));
     }
+
+    #[test]
+    fn batched_write_back() {
+        const FLUSH_BATCH: usize = 16;
+        let hdd_dev = tempfile::TempDir::new().unwrap();
+        let ssd_dev = tempfile::TempDir::new().unwrap();
+        let hdd_store = HDDStorage::connect_to_dev(
+            hdd_dev.path().to_path_buf(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        )
+        .unwrap();
+        let ssd_store = SSDStorage::connect_to_dev_batched(
+            ssd_dev.path().to_path_buf(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+            NonZeroUsize::new(SSD_CAP_NUM).unwrap(),
+            hdd_store,
+            NonZeroUsize::new(FLUSH_BATCH).unwrap(),
+        )
+        .unwrap();
+        let mut blocks = (0..BLOCK_NUM)
+            .map(|_| random_block_data())
+            .collect::<Vec<_>>();
+        blocks
+            .iter()
+            .enumerate()
+            .for_each(|(i, block)| ssd_store.put_block(i, block).unwrap());
+        // blocks in ssd, pending write back and in the next storage are all visible
+        blocks.iter().enumerate().for_each(|(i, block)| {
+            assert_eq!(&ssd_store.get_block_owned(i).unwrap().unwrap(), block);
+        });
+        // update
+        (0..BLOCK_NUM).step_by(3).for_each(|i| {
+            blocks[i] = random_block_data();
+            ssd_store.put_block(i, &blocks[i]).unwrap();
+        });
+        (0..BLOCK_NUM).step_by(5).for_each(|i| {
+            rand::thread_rng().fill(&mut blocks[i][1..BLOCK_SIZE / 2]);
+            ssd_store
+                .put_slice(i, 1, &blocks[i][1..BLOCK_SIZE / 2])
+                .unwrap()
+                .unwrap();
+        });
+        // no data loss after the pending blocks are written back on drop
+        drop(ssd_store);
+        let hdd_store = HDDStorage::connect_to_dev(
+            hdd_dev.path().to_path_buf(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        )
+        .unwrap();
+        blocks.iter().enumerate().for_each(|(i, block)| {
+            assert_eq!(&hdd_store.get_block_owned(i).unwrap().unwrap(), block);
+        });
+    }
+
+    #[test]
+    fn remove_block() {
+        let hdd_dev = tempfile::TempDir::new().unwrap();
+        let ssd_dev = tempfile::TempDir::new().unwrap();
+        let hdd_store = HDDStorage::connect_to_dev(
+            hdd_dev.path().to_path_buf(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        )
+        .unwrap();
+        let ssd_store = SSDStorage::connect_to_dev(
+            ssd_dev.path().to_path_buf(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+            NonZeroUsize::new(SSD_CAP_NUM).unwrap(),
+            hdd_store,
+        )
+        .unwrap();
+        // the first blocks are evicted to the next storage
+        (0..SSD_CAP_NUM * 2).for_each(|i| ssd_store.put_block(i, &random_block_data()).unwrap());
+        // remove blocks in ssd and in next storage
+        [0, SSD_CAP_NUM * 2 - 1].into_iter().for_each(|i| {
+            assert!(ssd_store.remove_block(i).unwrap().is_some());
+            assert!(ssd_store.get_block_owned(i).unwrap().is_none());
+            assert!(ssd_store.remove_block(i).unwrap().is_none());
+        });
+        assert!(ssd_store.get_block_owned(1).unwrap().is_some());
+        // removed blocks are not flushed back on drop
+        drop(ssd_store);
+        let hdd_store = HDDStorage::connect_to_dev(
+            hdd_dev.path().to_path_buf(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        )
+        .unwrap();
+        assert!(hdd_store.get_block_owned(0).unwrap().is_none());
+        assert!(hdd_store
+            .get_block_owned(SSD_CAP_NUM * 2 - 1)
+            .unwrap()
+            .is_none());
+        assert!(hdd_store.get_block_owned(1).unwrap().is_some());
+    }
+
+    #[test]
+    fn concurrent_put_get_block() {
+        const THREAD_NUM: usize = 8;
+        const BLOCK_NUM: usize = 64;
+        const SSD_CAP_NUM: usize = 8;
+        fn block_data(block_id: usize) -> Vec<u8> {
+            vec![block_id as u8; BLOCK_SIZE]
+        }
+        let hdd_dev = tempfile::TempDir::new().unwrap();
+        let ssd_dev = tempfile::TempDir::new().unwrap();
+        let hdd_store = HDDStorage::connect_to_dev(
+            hdd_dev.path().to_path_buf(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        )
+        .unwrap();
+        let ssd_store = SSDStorage::connect_to_dev(
+            ssd_dev.path().to_path_buf(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+            NonZeroUsize::new(SSD_CAP_NUM).unwrap(),
+            hdd_store,
+        )
+        .unwrap();
+        // every thread puts and gets all the blocks in a different order
+        std::thread::scope(|s| {
+            (0..THREAD_NUM).for_each(|t| {
+                let ssd_store = &ssd_store;
+                s.spawn(move || {
+                    (0..BLOCK_NUM)
+                        .map(|i| (i * (2 * t + 1) + t) % BLOCK_NUM)
+                        .for_each(|block_id| {
+                            ssd_store
+                                .put_block(block_id, &block_data(block_id))
+                                .unwrap();
+                            let retrieved = ssd_store.get_block_owned(block_id).unwrap().unwrap();
+                            assert_eq!(retrieved, block_data(block_id));
+                        })
+                });
+            })
+        });
+        (0..BLOCK_NUM).for_each(|block_id| {
+            let retrieved = ssd_store.get_block_owned(block_id).unwrap().unwrap();
+            assert_eq!(retrieved, block_data(block_id));
+        });
+        // all blocks are flushed to the next storage on drop
+        drop(ssd_store);
+        let hdd_store = HDDStorage::connect_to_dev(
+            hdd_dev.path().to_path_buf(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+        )
+        .unwrap();
+        (0..BLOCK_NUM).for_each(|block_id| {
+            let retrieved = hdd_store.get_block_owned(block_id).unwrap().unwrap();
+            assert_eq!(retrieved, block_data(block_id));
+        });
+    }
+
+    #[test]
+    fn evict_to_mem_storage() {
+        let ssd_dev = tempfile::TempDir::new().unwrap();
+        let ssd_store = SSDStorage::connect_to_dev(
+            ssd_dev.path().to_path_buf(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+            NonZeroUsize::new(SSD_CAP_NUM).unwrap(),
+            MemStorage::new(NonZeroUsize::new(BLOCK_SIZE).unwrap()),
+        )
+        .unwrap();
+        let blocks = (0..SSD_CAP_NUM * 2)
+            .map(|_| random_block_data())
+            .collect::<Vec<_>>();
+        blocks
+            .iter()
+            .enumerate()
+            .for_each(|(i, block)| ssd_store.put_block(i, block).unwrap());
+        // the least recently used blocks are evicted to the mem layer
+        let mem_store = ssd_store.next_storage();
+        assert_eq!(mem_store.len(), SSD_CAP_NUM);
+        blocks[..SSD_CAP_NUM]
+            .iter()
+            .enumerate()
+            .for_each(|(i, block)| {
+                assert_eq!(&mem_store.get_block_owned(i).unwrap().unwrap(), block);
+            });
+        assert!(mem_store.get_block_owned(SSD_CAP_NUM).unwrap().is_none());
+        // the evicted blocks are fetched back from the mem layer
+        blocks.iter().enumerate().for_each(|(i, block)| {
+            assert_eq!(&ssd_store.get_block_owned(i).unwrap().unwrap(), block);
+        });
+    }
+
+    #[test]
+    fn sharded_eviction() {
+        use crate::storage::Sharding;
+
+        let ssd_dev = tempfile::TempDir::new().unwrap();
+        let hdd_dev = tempfile::TempDir::new().unwrap();
+        let sharding = Sharding::new(2, 16);
+        let hdd_store =
+            HDDStorage::connect_to_dev(hdd_dev.path(), NonZeroUsize::new(BLOCK_SIZE).unwrap())
+                .unwrap()
+                .with_sharding(sharding);
+        let ssd_store = SSDStorage::connect_to_dev(
+            ssd_dev.path().to_path_buf(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+            NonZeroUsize::new(SSD_CAP_NUM).unwrap(),
+            hdd_store,
+        )
+        .unwrap()
+        .with_sharding(sharding);
+        let blocks = (0..SSD_CAP_NUM * 2)
+            .map(|_| random_block_data())
+            .collect::<Vec<_>>();
+        blocks
+            .iter()
+            .enumerate()
+            .for_each(|(i, block)| ssd_store.put_block(i, block).unwrap());
+        assert!(ssd_dev.path().join("0").join("0").is_dir());
+        // the evicted blocks are stored with their ids parsed from the sharded paths
+        let hdd_store = ssd_store.next_storage();
+        assert_eq!(hdd_store.iter_block_ids().unwrap().count(), SSD_CAP_NUM);
+        blocks.iter().enumerate().for_each(|(i, block)| {
+            assert_eq!(&ssd_store.get_block_owned(i).unwrap().unwrap(), block);
+        });
+    }
 }
@@ -0,0 +1,262 @@
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+use crate::SUResult;
+
+use super::utility::{check_block_range, check_slice_range};
+use super::{BlockId, BlockStorage, SliceStorage};
+
+/// Operation of an [`IoEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoOp {
+    Read,
+    Write,
+    Remove,
+}
+
+/// An io issued to a [`TraceStorage`], which accesses `len` bytes at `offset` of a block.
+/// A full block operation accesses the whole block from `0`, and a removal accesses nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoEvent {
+    pub op: IoOp,
+    pub block_id: BlockId,
+    pub offset: usize,
+    pub len: usize,
+}
+
+/// A storage performing no io at all, but recording every io issued to it in order,
+/// to capture the io sequence of a run without touching the disk.
+///
+/// Every block is regarded as existing, and the read buffers are left untouched.
+/// The ios rejected by the range check are not recorded.
+#[derive(Debug)]
+pub struct TraceStorage {
+    block_size: usize,
+    trace: Mutex<Vec<IoEvent>>,
+}
+
+impl TraceStorage {
+    /// Make a [`TraceStorage`] with an empty trace.
+    ///
+    /// # Parameter
+    /// - `block_size`: size of each block
+    pub fn new(block_size: NonZeroUsize) -> Self {
+        Self {
+            block_size: block_size.get(),
+            trace: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Take the ios recorded so far in the order of issue, leaving the trace empty.
+    pub fn take_trace(&self) -> Vec<IoEvent> {
+        std::mem::take(&mut self.trace.lock().unwrap())
+    }
+
+    fn record(&self, op: IoOp, block_id: BlockId, offset: usize, len: usize) {
+        self.trace.lock().unwrap().push(IoEvent {
+            op,
+            block_id,
+            offset,
+            len,
+        });
+    }
+}
+
+impl BlockStorage for TraceStorage {
+    /// Record a write of the full block.
+    ///
+    /// # Error
+    /// - [SUError::Range](crate::SUError::Range) if `block_data.len()` does not match block size
+    fn put_block(&self, block_id: BlockId, block_data: &[u8]) -> SUResult<()> {
+        check_block_range(
+            file!(),
+            line!(),
+            column!(),
+            block_data.len(),
+            self.block_size,
+        )?;
+        self.record(IoOp::Write, block_id, 0, self.block_size);
+        Ok(())
+    }
+
+    /// Record a read of the full block, which always exists.
+    ///
+    /// # Error
+    /// - [SUError::Range](crate::SUError::Range) if `block_data.len()` does not match block size
+    fn get_block(&self, block_id: BlockId, block_data: &mut [u8]) -> SUResult<Option<()>> {
+        check_block_range(
+            file!(),
+            line!(),
+            column!(),
+            block_data.len(),
+            self.block_size,
+        )?;
+        self.record(IoOp::Read, block_id, 0, self.block_size);
+        Ok(Some(()))
+    }
+
+    /// Record a removal of the block, which always exists.
+    fn remove_block(&self, block_id: BlockId) -> SUResult<Option<()>> {
+        self.record(IoOp::Remove, block_id, 0, 0);
+        Ok(Some(()))
+    }
+
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+}
+
+impl SliceStorage for TraceStorage {
+    /// Record a write of the slice.
+    ///
+    /// # Error
+    /// - [SUError::Range](crate::SUError::Range) if the area specified is out of the block range
+    fn put_slice(
+        &self,
+        block_id: BlockId,
+        inner_block_offset: usize,
+        slice_data: &[u8],
+    ) -> SUResult<Option<()>> {
+        check_slice_range(
+            file!(),
+            line!(),
+            column!(),
+            inner_block_offset..inner_block_offset + slice_data.len(),
+            self.block_size,
+        )?;
+        self.record(IoOp::Write, block_id, inner_block_offset, slice_data.len());
+        Ok(Some(()))
+    }
+
+    /// Record a read of the slice, which always exists.
+    ///
+    /// # Error
+    /// - [SUError::Range](crate::SUError::Range) if the area specified is out of the block range
+    fn get_slice(
+        &self,
+        block_id: BlockId,
+        inner_block_offset: usize,
+        slice_data: &mut [u8],
+    ) -> SUResult<Option<()>> {
+        check_slice_range(
+            file!(),
+            line!(),
+            column!(),
+            inner_block_offset..inner_block_offset + slice_data.len(),
+            self.block_size,
+        )?;
+        self.record(IoOp::Read, block_id, inner_block_offset, slice_data.len());
+        Ok(Some(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use crate::storage::{
+        BlockStorage, BufferEviction, FifoEvict, FixedSizeSliceBuf, SliceBuffer, SliceOpt,
+        SliceStorage,
+    };
+
+    use super::{IoEvent, IoOp, TraceStorage};
+
+    const BLOCK_SIZE: usize = 16 << 10;
+    const SEG_SIZE: usize = 4 << 10;
+
+    fn write(block_id: usize, offset: usize, len: usize) -> IoEvent {
+        IoEvent {
+            op: IoOp::Write,
+            block_id,
+            offset,
+            len,
+        }
+    }
+
+    /// Persist the present slices of an eviction, as the worker does.
+    fn persist(store: &TraceStorage, BufferEviction { block_id, data }: BufferEviction) {
+        let mut cursor = 0;
+        data.slices.into_iter().for_each(|slice| match slice {
+            SliceOpt::Present(data) => {
+                store.put_slice(block_id, cursor, &data).unwrap().unwrap();
+                cursor += data.len();
+            }
+            SliceOpt::Absent(size) => cursor += size,
+        });
+    }
+
+    #[test]
+    fn record_io() {
+        let store = TraceStorage::new(NonZeroUsize::new(BLOCK_SIZE).unwrap());
+        store.put_block(0, &[0; BLOCK_SIZE]).unwrap();
+        let mut buf = [0_u8; 8];
+        assert!(store.get_slice(1, 8, &mut buf).unwrap().is_some());
+        store.put_slices(2, &[(0, &[1; 4]), (16, &[1; 8])]).unwrap();
+        assert!(store.remove_block(0).unwrap().is_some());
+        assert!(store.put_slice(0, BLOCK_SIZE - 4, &[1; 8]).is_err());
+        assert_eq!(
+            store.take_trace(),
+            vec![
+                write(0, 0, BLOCK_SIZE),
+                IoEvent {
+                    op: IoOp::Read,
+                    block_id: 1,
+                    offset: 8,
+                    len: 8
+                },
+                write(2, 0, 4),
+                write(2, 16, 8),
+                IoEvent {
+                    op: IoOp::Remove,
+                    block_id: 0,
+                    offset: 0,
+                    len: 0
+                },
+            ]
+        );
+        assert!(store.take_trace().is_empty());
+    }
+
+    #[test]
+    fn slice_buffer_evictions() {
+        let store = TraceStorage::new(NonZeroUsize::new(BLOCK_SIZE).unwrap());
+        let dev = tempfile::tempdir().unwrap();
+        let slice_buf = FixedSizeSliceBuf::connect_to_dev_with_evict(
+            dev.path(),
+            NonZeroUsize::new(BLOCK_SIZE).unwrap(),
+            FifoEvict::with_max_size(NonZeroUsize::new(2 * SEG_SIZE).unwrap()),
+        )
+        .unwrap();
+        [
+            (0, 0, SEG_SIZE),
+            (1, 2 * SEG_SIZE, SEG_SIZE),
+            // overflows the buffer, evicting block 0
+            (2, SEG_SIZE, SEG_SIZE),
+            // overflows the buffer, evicting block 1
+            (2, 3 * SEG_SIZE, SEG_SIZE),
+        ]
+        .into_iter()
+        .for_each(|(block_id, offset, len)| {
+            if let Some(eviction) = slice_buf
+                .push_slice(block_id, offset, &vec![1; len])
+                .unwrap()
+            {
+                persist(&store, eviction);
+            }
+        });
+        assert_eq!(
+            store.take_trace(),
+            vec![write(0, 0, SEG_SIZE), write(1, 2 * SEG_SIZE, SEG_SIZE)]
+        );
+        while let Some(eviction) = slice_buf.pop() {
+            persist(&store, eviction);
+        }
+        assert_eq!(
+            store.take_trace(),
+            vec![
+                write(2, SEG_SIZE, SEG_SIZE),
+                write(2, 3 * SEG_SIZE, SEG_SIZE)
+            ]
+        );
+    }
+}
@@ -4,6 +4,24 @@ use crate::{SUError, SUResult};
 
 use super::BlockId;
 
+/// Latency injected before each operation of a storage, to model a slower device.
+/// No latency is injected if the latency is `None`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpLatency(Option<std::time::Duration>);
+
+impl OpLatency {
+    pub fn new(latency: Option<std::time::Duration>) -> Self {
+        Self(latency)
+    }
+
+    /// Sleep for the latency, if any.
+    pub fn inject(&self) {
+        if let Some(latency) = self.0 {
+            std::thread::sleep(latency);
+        }
+    }
+}
+
 /// Check if the data length matches the block size.
 ///
 /// # Return
@@ -53,45 +71,113 @@ pub fn check_slice_range(
     Ok(())
 }
 
-/// Convert block id to its corresponding block file path
+/// Directory sharding scheme of the block files under a device root.
+///
+/// The block id is formatted in upper hex, zero-padded to at least `depth * width + 2` digits,
+/// where `width` is the number of hex digits per level, i.e., `fanout = 16^width`.
+/// The leading `depth * width` digits are split into `depth` nested directories,
+/// and the remaining digits make the file name.
+///
+/// The default is one level of 256 directories, e.g., block `0x100` is stored at `01/00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sharding {
+    depth: usize,
+    width: usize,
+}
+
+impl Default for Sharding {
+    fn default() -> Self {
+        Self { depth: 1, width: 2 }
+    }
+}
+
+impl Sharding {
+    /// Make a sharding scheme with `depth` levels of directories, each of which has `fanout` children.
+    /// All the block files are stored in the device root directly if `depth` is `0`.
+    ///
+    /// # Panics
+    /// - If `fanout` is not a power of 16 greater than 1
+    pub fn new(depth: usize, fanout: usize) -> Self {
+        assert!(
+            fanout > 1 && fanout.is_power_of_two() && fanout.trailing_zeros() % 4 == 0,
+            "fanout {fanout} is not a power of 16"
+        );
+        Self {
+            depth,
+            width: usize::try_from(fanout.trailing_zeros() / 4).unwrap(),
+        }
+    }
+
+    /// Get the number of the directory levels.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Get the number of the children of each directory.
+    pub fn fanout(&self) -> usize {
+        1 << (4 * self.width)
+    }
+
+    /// Convert block id to its corresponding block file path under `dev_root`.
+    pub fn block_path(&self, dev_root: impl Into<PathBuf>, block_id: BlockId) -> PathBuf {
+        let prefix_len = self.depth * self.width;
+        let s = format!("{:01$X}", block_id, prefix_len + 2);
+        let (prefix, file_name) = s.split_at(prefix_len);
+        let mut path = dev_root.into();
+        (0..self.depth).for_each(|level| {
+            path.push(&prefix[level * self.width..(level + 1) * self.width]);
+        });
+        path.push(file_name);
+        path
+    }
+
+    /// Convert block file path to its corresponding block id.
+    ///
+    /// # Panics
+    /// If the path is not constructed by [`Sharding::block_path`] with the same scheme
+    pub fn block_id(&self, block_path: &Path) -> BlockId {
+        self.try_block_id(block_path).expect("invalid block path")
+    }
+
+    /// Convert block file path to its corresponding block id,
+    /// only the last `depth + 1` components of the path are parsed.
+    ///
+    /// # Return
+    /// - [`Some`] with the block id parsed
+    /// - [`None`] if the path has fewer components or any of them is not in hex
+    pub(crate) fn try_block_id(&self, block_path: &Path) -> Option<BlockId> {
+        let mut components = block_path
+            .iter()
+            .rev()
+            .take(self.depth + 1)
+            .map(|component| component.to_str())
+            .collect::<Option<Vec<_>>>()?;
+        if components.len() != self.depth + 1 {
+            return None;
+        }
+        components.reverse();
+        BlockId::from_str_radix(&components.concat(), 16).ok()
+    }
+}
+
+/// Convert block id to its corresponding block file path, with the default [`Sharding`]
 pub fn block_id_to_path(dev_root: impl Into<PathBuf>, block_id: BlockId) -> PathBuf {
-    let s = format!("{:04X}", block_id);
-    let (a, b) = s.split_at(2);
-    let mut dev_root = dev_root.into();
-    dev_root.push(a);
-    dev_root.push(b);
-    dev_root
+    Sharding::default().block_path(dev_root, block_id)
 }
 
-/// Convert block id to its corresponding block file path
+/// Convert block file path to its corresponding block id, with the default [`Sharding`]
 ///
 /// # Panics
 /// If the path is not constructed by [`block_id_to_path`]
 pub fn block_path_to_id(block_path: &Path) -> BlockId {
-    const ERR_STR: &str = "invalid block path";
-    let mut path = block_path.to_path_buf();
-    let b = path
-        .file_name()
-        .expect(ERR_STR)
-        .to_string_lossy()
-        .to_string();
-    if !path.pop() {
-        panic!("{ERR_STR}");
-    }
-    let a = path
-        .file_name()
-        .expect(ERR_STR)
-        .to_string_lossy()
-        .to_string();
-    let s = a + b.as_str();
-    usize::from_str_radix(&s, 16).expect(ERR_STR)
+    Sharding::default().block_id(block_path)
 }
 
 #[cfg(test)]
 mod test {
     use rand::Rng;
 
-    use crate::storage::utility::{block_id_to_path, block_path_to_id};
+    use crate::storage::utility::{block_id_to_path, block_path_to_id, Sharding};
     use std::str::FromStr;
 
     #[test]
@@ -110,4 +196,42 @@ mod test {
                 assert_eq!(r_id, id);
             })
     }
+
+    #[test]
+    fn sharding_round_trip() {
+        let root = std::path::PathBuf::from_str("./root").unwrap();
+        let sharding = Sharding::new(2, 256);
+        assert_eq!(sharding.depth(), 2);
+        assert_eq!(sharding.fanout(), 256);
+        assert_eq!(
+            sharding.block_path(root.clone(), 0x1_02_03),
+            std::path::PathBuf::from_str("./root/01/02/03").unwrap()
+        );
+        assert_eq!(
+            Sharding::new(0, 16).block_path(root.clone(), 0xA),
+            std::path::PathBuf::from_str("./root/0A").unwrap()
+        );
+        assert_eq!(Sharding::default(), Sharding::new(1, 256));
+        let schemes =
+            [(0, 16), (1, 16), (1, 256), (2, 256), (3, 4096)].map(|(d, f)| Sharding::new(d, f));
+        schemes.iter().for_each(|sharding| {
+            (0..4096)
+                .chain([usize::MAX, usize::MAX >> 4])
+                .chain((0..1000).map(|_| rand::thread_rng().gen::<usize>()))
+                .for_each(|id| {
+                    let path = sharding.block_path(root.clone(), id);
+                    assert_eq!(
+                        path.components().count(),
+                        root.components().count() + sharding.depth() + 1
+                    );
+                    assert_eq!(sharding.block_id(&path), id, "{sharding:?}");
+                })
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn sharding_invalid_fanout() {
+        Sharding::new(2, 100);
+    }
 }
@@ -0,0 +1,122 @@
+use std::io::BufRead;
+
+use crate::SUResult;
+
+use super::{Operation, Trace, TraceParser};
+
+/// Parser of the Alibaba block trace, which is in csv format without header,
+/// and each line is `device_id,opcode,offset,length,timestamp`.
+///
+/// `opcode` is `R` for read and `W` for write, `offset` and `length` are in bytes.
+/// The malformed lines are skipped, and counted by [`AliTraceParser::malformed()`].
+#[derive(Debug)]
+pub struct AliTraceParser<R: BufRead = std::io::BufReader<std::fs::File>> {
+    lines: std::io::Lines<R>,
+    malformed: usize,
+}
+
+impl AliTraceParser {
+    /// Open the trace file at `path`.
+    ///
+    /// # Error
+    /// - [`crate::SUError::Io`] if the file cannot be opened
+    pub fn open(path: impl AsRef<std::path::Path>) -> SUResult<Self> {
+        let f = std::fs::File::open(path)?;
+        Ok(Self::new(std::io::BufReader::new(f)))
+    }
+}
+
+impl<R: BufRead> AliTraceParser<R> {
+    /// Make a parser reading the traces from `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            malformed: 0,
+        }
+    }
+
+    /// Return the number of the malformed lines skipped so far.
+    pub fn malformed(&self) -> usize {
+        self.malformed
+    }
+
+    fn parse_line(line: &str) -> Option<Trace> {
+        let mut fields = line.trim().split(',');
+        let _device_id = fields.next()?;
+        let op = match fields.next()? {
+            "R" => Operation::Read,
+            "W" => Operation::Write,
+            _ => return None,
+        };
+        let offset = fields.next()?.parse().ok()?;
+        let size = fields.next()?.parse().ok()?;
+        let _timestamp = fields.next()?;
+        fields
+            .next()
+            .is_none()
+            .then_some(Trace { offset, size, op })
+    }
+}
+
+impl<R: BufRead> Iterator for AliTraceParser<R> {
+    type Item = Trace;
+
+    /// Yield the next trace, the malformed lines are skipped.
+    /// The iteration ends at the end of the file or at an io error.
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = self.lines.next()?.ok()?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match Self::parse_line(&line) {
+                Some(trace) => return Some(trace),
+                None => self.malformed += 1,
+            }
+        }
+    }
+}
+
+impl<R: BufRead> TraceParser for AliTraceParser<R> {}
+
+#[cfg(test)]
+mod test {
+    use crate::trace_parser::{Operation, Trace};
+
+    use super::AliTraceParser;
+
+    #[test]
+    fn parse() {
+        const TRACE: &str = "\
+0,W,4096,8192,1577808000000000
+0,R,0,4096,1577808000000010
+0,X,0,4096,1577808000000020
+not a trace
+
+1,W,65536,512,1577808000000030
+";
+        let mut parser = AliTraceParser::new(TRACE.as_bytes());
+        let traces = parser.by_ref().collect::<Vec<_>>();
+        assert_eq!(
+            traces,
+            vec![
+                Trace {
+                    offset: 4096,
+                    size: 8192,
+                    op: Operation::Write
+                },
+                Trace {
+                    offset: 0,
+                    size: 4096,
+                    op: Operation::Read
+                },
+                Trace {
+                    offset: 65536,
+                    size: 512,
+                    op: Operation::Write
+                },
+            ]
+        );
+        assert_eq!(parser.malformed(), 2);
+    }
+}
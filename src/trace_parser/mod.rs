@@ -0,0 +1,159 @@
+mod ali_trace;
+mod msr_trace;
+
+pub use ali_trace::AliTraceParser;
+pub use msr_trace::MsrTraceParser;
+
+/// Operation of a block I/O trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Read,
+    Write,
+}
+
+/// A block I/O trace, which accesses `size` bytes at `offset` of a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trace {
+    pub offset: usize,
+    pub size: usize,
+    pub op: Operation,
+}
+
+/// Parser of a trace file, which yields the traces in the order of the file.
+///
+/// The adapters wrap the parser lazily, so they compose without loading the whole trace.
+pub trait TraceParser: Iterator<Item = Trace> {
+    /// Yield only the [`Operation::Write`] traces, in the original order.
+    fn writes_only(self) -> impl TraceParser
+    where
+        Self: Sized,
+    {
+        OpFilter {
+            inner: self,
+            op: Operation::Write,
+        }
+    }
+
+    /// Yield only the [`Operation::Read`] traces, in the original order.
+    fn reads_only(self) -> impl TraceParser
+    where
+        Self: Sized,
+    {
+        OpFilter {
+            inner: self,
+            op: Operation::Read,
+        }
+    }
+
+    /// Multiply the offset and size of each trace by `factor`,
+    /// e.g., to fit the trace to a device of a different size.
+    /// The scaled offset is rounded down, and the scaled size is rounded up.
+    ///
+    /// # Panics
+    /// - If `factor` is not finite or not positive
+    fn scaled(self, factor: f64) -> impl TraceParser
+    where
+        Self: Sized,
+    {
+        assert!(
+            factor.is_finite() && factor > 0.0,
+            "scale factor {factor} is not positive"
+        );
+        Scaled {
+            inner: self,
+            factor,
+        }
+    }
+}
+
+struct OpFilter<P> {
+    inner: P,
+    op: Operation,
+}
+
+impl<P: TraceParser> Iterator for OpFilter<P> {
+    type Item = Trace;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let op = self.op;
+        self.inner.find(|trace| trace.op == op)
+    }
+}
+
+impl<P: TraceParser> TraceParser for OpFilter<P> {}
+
+struct Scaled<P> {
+    inner: P,
+    factor: f64,
+}
+
+impl<P: TraceParser> Iterator for Scaled<P> {
+    type Item = Trace;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|Trace { offset, size, op }| Trace {
+            offset: (offset as f64 * self.factor).floor() as usize,
+            size: (size as f64 * self.factor).ceil() as usize,
+            op,
+        })
+    }
+}
+
+impl<P: TraceParser> TraceParser for Scaled<P> {}
+
+#[cfg(test)]
+mod test {
+    use super::{AliTraceParser, Operation, Trace, TraceParser};
+
+    const TRACE: &str = "\
+0,W,4096,4096,0
+0,R,0,4096,1
+0,W,0,8192,2
+0,R,8192,512,3
+0,W,12288,1000,4
+";
+
+    #[test]
+    fn writes_only() {
+        let offsets = AliTraceParser::new(TRACE.as_bytes())
+            .writes_only()
+            .inspect(|trace| assert_eq!(trace.op, Operation::Write))
+            .map(|trace| trace.offset)
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, vec![4096, 0, 12288]);
+        let offsets = AliTraceParser::new(TRACE.as_bytes())
+            .reads_only()
+            .inspect(|trace| assert_eq!(trace.op, Operation::Read))
+            .map(|trace| trace.offset)
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, vec![0, 8192]);
+    }
+
+    #[test]
+    fn scaled() {
+        let traces = AliTraceParser::new(TRACE.as_bytes())
+            .writes_only()
+            .scaled(0.5)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            traces,
+            vec![
+                Trace {
+                    offset: 2048,
+                    size: 2048,
+                    op: Operation::Write
+                },
+                Trace {
+                    offset: 0,
+                    size: 4096,
+                    op: Operation::Write
+                },
+                Trace {
+                    offset: 6144,
+                    size: 500,
+                    op: Operation::Write
+                },
+            ]
+        );
+    }
+}
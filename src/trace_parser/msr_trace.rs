@@ -0,0 +1,140 @@
+use std::io::BufRead;
+
+use crate::{SUError, SUResult};
+
+use super::{Operation, Trace, TraceParser};
+
+/// Parser of the MSR Cambridge block trace, which is in csv format without header,
+/// and each line is `timestamp,hostname,disk,type,offset,size,latency`.
+///
+/// `type` is `Read` or `Write`, `offset` and `size` are in bytes.
+/// The malformed lines are skipped, and counted by [`MsrTraceParser::malformed()`].
+#[derive(Debug)]
+pub struct MsrTraceParser<R: BufRead = std::io::BufReader<std::fs::File>> {
+    lines: std::io::Lines<R>,
+    malformed: usize,
+}
+
+impl MsrTraceParser {
+    /// Open the trace file at `path`.
+    ///
+    /// # Error
+    /// - [`SUError::Io`] if the file cannot be opened
+    pub fn open(path: impl AsRef<std::path::Path>) -> SUResult<Self> {
+        let f = std::fs::File::open(path)?;
+        Ok(Self::new(std::io::BufReader::new(f)))
+    }
+}
+
+impl<R: BufRead> MsrTraceParser<R> {
+    /// Make a parser reading the traces from `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            malformed: 0,
+        }
+    }
+
+    /// Return the number of the malformed lines skipped so far.
+    pub fn malformed(&self) -> usize {
+        self.malformed
+    }
+
+    /// Parse a line of the trace.
+    ///
+    /// # Error
+    /// - [`SUError::InvalidArg`] if the line is malformed
+    pub fn parse_line(line: &str) -> SUResult<Trace> {
+        let malformed =
+            |field: &str| SUError::invalid_arg(format!("bad {field} in msr trace: {line}"));
+        let fields = line.trim().split(',').collect::<Vec<_>>();
+        let &[_timestamp, _hostname, _disk, op, offset, size, _latency] = fields.as_slice() else {
+            return Err(malformed("field number"));
+        };
+        let op = match op {
+            "Read" => Operation::Read,
+            "Write" => Operation::Write,
+            _ => return Err(malformed("type")),
+        };
+        let offset = offset.parse().map_err(|_| malformed("offset"))?;
+        let size = size.parse().map_err(|_| malformed("size"))?;
+        Ok(Trace { offset, size, op })
+    }
+}
+
+impl<R: BufRead> Iterator for MsrTraceParser<R> {
+    type Item = Trace;
+
+    /// Yield the next trace, the malformed lines are skipped.
+    /// The iteration ends at the end of the file or at an io error.
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = self.lines.next()?.ok()?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match Self::parse_line(&line) {
+                Ok(trace) => return Some(trace),
+                Err(_) => self.malformed += 1,
+            }
+        }
+    }
+}
+
+impl<R: BufRead> TraceParser for MsrTraceParser<R> {}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        trace_parser::{Operation, Trace},
+        SUError,
+    };
+
+    use super::MsrTraceParser;
+
+    #[test]
+    fn parse() {
+        const TRACE: &str = "\
+128166372003061629,hm,1,Read,3216171008,4096,14539
+128166372016382155,hm,1,Write,3965342720,24576,30998
+128166372026382245,prxy,0,Write,8192,512,1079
+128166372036382245,prxy,0,Flush,8192,512,1079
+128166372046382245,prxy,0,Read,-1,512,1079
+128166372056382245,prxy,0,Read,0,512
+
+128166372066382245,src1,2,Read,65536,65536,96215
+";
+        let mut parser = MsrTraceParser::new(TRACE.as_bytes());
+        let traces = parser.by_ref().collect::<Vec<_>>();
+        assert_eq!(
+            traces,
+            vec![
+                Trace {
+                    offset: 3216171008,
+                    size: 4096,
+                    op: Operation::Read
+                },
+                Trace {
+                    offset: 3965342720,
+                    size: 24576,
+                    op: Operation::Write
+                },
+                Trace {
+                    offset: 8192,
+                    size: 512,
+                    op: Operation::Write
+                },
+                Trace {
+                    offset: 65536,
+                    size: 65536,
+                    op: Operation::Read
+                },
+            ]
+        );
+        assert_eq!(parser.malformed(), 3);
+        assert!(matches!(
+            MsrTraceParser::<&[u8]>::parse_line("128166372036382245,prxy,0,Flush,8192,512,1079"),
+            Err(SUError::InvalidArg(_))
+        ));
+    }
+}